@@ -11,9 +11,9 @@ use bevy::{
     input::mouse::MouseMotion,
     log::{Level, LogPlugin},
     prelude::*,
-    window::close_on_esc,
+    window::{close_on_esc, PrimaryWindow},
 };
-use bevy_mod_fbx::FbxPlugin;
+use bevy_mod_fbx::{FbxLoadWarning, FbxPlugin};
 
 use std::f32::consts::TAU;
 
@@ -61,6 +61,7 @@ Controls:
     .add_startup_system(setup)
     .add_system(update_lights)
     .add_system(camera_controller)
+    .add_system(show_import_warnings)
     .add_system(close_on_esc);
 
     app.run();
@@ -100,7 +101,25 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
-const SCALE_STEP: f32 = 0.1;
+/// Count the import warnings in the window title, and log how to fix them.
+fn show_import_warnings(
+    mut warnings: EventReader<FbxLoadWarning>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut count: Local<usize>,
+) {
+    let previous = *count;
+    for warning in warnings.iter() {
+        *count += 1;
+        let object = warning.object.as_deref().unwrap_or("file");
+        let suggestion = warning.suggestion.as_deref().unwrap_or("no suggestion");
+        info!("{:?} warning on {object}: {suggestion}", warning.category);
+    }
+    if *count != previous {
+        if let Ok(mut window) = windows.get_single_mut() {
+            window.title = format!("bevy scene viewer - {} import warnings", *count);
+        }
+    }
+}
 
 fn update_lights(
     key_input: Res<Input<KeyCode>>,
@@ -108,20 +127,6 @@ fn update_lights(
     mut query: Query<(&mut Transform, &mut DirectionalLight)>,
     mut animate_directional_light: Local<bool>,
 ) {
-    let mut projection_adjustment = Vec3::ONE;
-    if key_input.just_pressed(KeyCode::Key5) {
-        projection_adjustment.x -= SCALE_STEP;
-    } else if key_input.just_pressed(KeyCode::Key6) {
-        projection_adjustment.x += SCALE_STEP;
-    } else if key_input.just_pressed(KeyCode::Key7) {
-        projection_adjustment.y -= SCALE_STEP;
-    } else if key_input.just_pressed(KeyCode::Key8) {
-        projection_adjustment.y += SCALE_STEP;
-    } else if key_input.just_pressed(KeyCode::Key9) {
-        projection_adjustment.z -= SCALE_STEP;
-    } else if key_input.just_pressed(KeyCode::Key0) {
-        projection_adjustment.z += SCALE_STEP;
-    }
     for (_, mut light) in query.iter_mut() {
         if key_input.just_pressed(KeyCode::U) {
             light.shadows_enabled = !light.shadows_enabled;
@@ -0,0 +1,60 @@
+//! Additive clips, made by subtracting a reference pose from the keyframes
+//! of a clip, see [`FbxAdditiveClip`].
+//!
+//! [`FbxAdditiveClip`]: crate::FbxAdditiveClip
+
+use bevy::prelude::{Transform, Vec3};
+
+use super::{player::apply_curve, FbxAnimationClip, FbxEntityPath, FbxKeyframes};
+
+/// The transform of the entity at `path` in `clip`, at `time`.
+fn sample(clip: &FbxAnimationClip, path: &FbxEntityPath, time: f32) -> Option<Transform> {
+    let curves = clip.curves.get(path)?;
+    let mut transform = Transform::IDENTITY;
+    for curve in curves {
+        apply_curve(curve, time, &mut transform);
+    }
+    Some(transform)
+}
+
+/// The additive version of `clip`, named `name`, relative to the pose of
+/// `reference` at `time`, or of `clip` at `time` without `reference`.
+///
+/// Entities missing from `reference` are relative to the first frame of `clip`.
+pub(crate) fn additive_clip(
+    clip: &FbxAnimationClip,
+    reference: Option<&FbxAnimationClip>,
+    time: f32,
+    name: String,
+) -> FbxAnimationClip {
+    let mut additive = FbxAnimationClip {
+        name: Some(name),
+        additive: true,
+        ..clip.clone()
+    };
+    for (path, curves) in &mut additive.curves {
+        let pose = match reference {
+            Some(reference) => sample(reference, path, 0.0),
+            None => sample(clip, path, time),
+        };
+        let pose = pose.or_else(|| sample(clip, path, 0.0)).unwrap_or_default();
+        for curve in curves {
+            match &mut curve.keyframes {
+                FbxKeyframes::Translation(translations) => {
+                    translations.iter_mut().for_each(|t| *t -= pose.translation);
+                }
+                FbxKeyframes::Rotation(rotations) => {
+                    let inverse = pose.rotation.inverse();
+                    rotations.iter_mut().for_each(|r| *r = inverse * *r);
+                }
+                FbxKeyframes::Scale(scales) => {
+                    // Axes flattened in the reference pose can't be scaled back.
+                    let inverse =
+                        Vec3::select(pose.scale.cmpeq(Vec3::ZERO), Vec3::ONE, pose.scale.recip());
+                    scales.iter_mut().for_each(|s| *s *= inverse);
+                }
+            }
+        }
+    }
+    additive
+}
@@ -0,0 +1,406 @@
+//! Sampling of FBX animation curves into [`FbxAnimationClip`]s.
+
+use bevy::{
+    prelude::{Name, Quat, Transform, Vec3},
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use super::{
+    blend::{blend_layers, blend_user_property},
+    constraint::Constraint,
+    curve::{Stack, TransformProperty},
+    event::clip_events,
+    pose::Pose,
+    reduce::reduce,
+    stored_pose::StoredPose,
+    FbxAnimationClip, FbxCurve, FbxEntityPath, FbxKeyframes, KTIME_PER_SECOND,
+};
+use crate::{
+    data::{entity_paths, FbxObject},
+    fbx_transform::{FbxNodeTransformInfo, SHEAR_TOLERANCE},
+    progress::Progress,
+    FbxLoaderSettings, FbxPropertyCurve, FbxTakeSlice, FbxWarningCategory,
+};
+
+/// The animated user-defined properties of each node, by property name.
+pub(crate) type PropertyCurves = HashMap<ObjectId, HashMap<String, FbxPropertyCurve>>;
+
+/// The sampled local transforms of a node.
+#[derive(Default)]
+struct Track {
+    path: FbxEntityPath,
+    translations: Vec<Vec3>,
+    rotations: Vec<Quat>,
+    scales: Vec<Vec3>,
+}
+impl Track {
+    fn push(&mut self, transform: Transform) {
+        // Keep successive quaternions in the same hemisphere,
+        // otherwise interpolating between them takes the long way around.
+        let rotation = match self.rotations.last() {
+            Some(previous) if previous.dot(transform.rotation) < 0.0 => -transform.rotation,
+            _ => transform.rotation,
+        };
+        self.translations.push(transform.translation);
+        self.rotations.push(rotation);
+        self.scales.push(transform.scale);
+    }
+}
+
+/// Computes the animated bevy transforms of the nodes of an FBX scene.
+pub(crate) struct Baker<'a> {
+    hierarchy: &'a HashMap<ObjectId, FbxObject>,
+    roots: &'a [ObjectId],
+    infos: &'a HashMap<ObjectId, FbxNodeTransformInfo>,
+    /// The parent in the file of the roots frozen by
+    /// [`FbxLoaderSettings::frozen_nodes`].
+    detached: &'a HashMap<ObjectId, ObjectId>,
+    frame_rate: f64,
+    /// Constraints applied after sampling the curves, at each frame.
+    constraints: Vec<Constraint>,
+    settings: &'a FbxLoaderSettings,
+}
+impl<'a> Baker<'a> {
+    pub(crate) fn new(
+        hierarchy: &'a HashMap<ObjectId, FbxObject>,
+        infos: &'a HashMap<ObjectId, FbxNodeTransformInfo>,
+        roots: &'a [ObjectId],
+        detached: &'a HashMap<ObjectId, ObjectId>,
+        frame_rate: f64,
+        constraints: Vec<Constraint>,
+        settings: &'a FbxLoaderSettings,
+    ) -> Self {
+        Baker {
+            hierarchy,
+            roots,
+            infos,
+            detached,
+            frame_rate,
+            constraints,
+            settings,
+        }
+    }
+
+    /// The nodes affected by `stack`, that is, the animated and constrained
+    /// nodes and their descendants, with their path from the scene root.
+    fn animated_tracks(&self, stack: &Stack) -> HashMap<ObjectId, Track> {
+        fn collect(
+            baker: &Baker,
+            stack: &Stack,
+            node: ObjectId,
+            parent: Option<&FbxEntityPath>,
+            parent_animated: bool,
+            tracks: &mut HashMap<ObjectId, Track>,
+        ) {
+            let object = match baker.hierarchy.get(&node) {
+                Some(object) => object,
+                None => return,
+            };
+            // Unnamed nodes can't be targeted by a path.
+            let name = match &object.name {
+                Some(name) => Name::new(name.clone()),
+                None => return,
+            };
+            let mut path = parent.cloned().unwrap_or_default();
+            path.parts.push(name);
+
+            let properties = [
+                TransformProperty::Translation,
+                TransformProperty::Rotation,
+                TransformProperty::Scaling,
+            ];
+            let is_animated = parent_animated
+                || baker.constraints.iter().any(|c| c.affected() == node)
+                || stack.active_layers().any(|layer| {
+                    properties
+                        .iter()
+                        .any(|property| layer.animates(node, *property))
+                });
+            for child in &object.children {
+                collect(baker, stack, *child, Some(&path), is_animated, tracks);
+            }
+            if is_animated {
+                let track = Track {
+                    path,
+                    ..Default::default()
+                };
+                tracks.insert(node, track);
+            }
+        }
+        let mut tracks = HashMap::default();
+        // Frozen roots come after their former ancestors, and follow them.
+        for root in self.roots {
+            let parent_animated = (self.detached.get(root)).is_some_and(|p| tracks.contains_key(p));
+            collect(self, stack, *root, None, parent_animated, &mut tracks);
+        }
+        tracks
+    }
+
+    /// Insert `node` and its descendants in `pose`, `animate` computes
+    /// the transform info of a node from its rest transform info.
+    fn sample_rec(
+        &self,
+        node: ObjectId,
+        parent: Option<ObjectId>,
+        animate: &dyn Fn(ObjectId, &FbxNodeTransformInfo) -> FbxNodeTransformInfo,
+        pose: &mut Pose,
+    ) {
+        let (object, info) = match (self.hierarchy.get(&node), self.infos.get(&node)) {
+            (Some(object), Some(info)) => (object, info),
+            _ => return,
+        };
+        match self.detached.get(&node) {
+            Some(parent) => pose.insert_detached(node, *parent, animate(node, info)),
+            None => pose.insert(node, parent, animate(node, info)),
+        }
+        for child in &object.children {
+            self.sample_rec(*child, Some(node), animate, pose);
+        }
+    }
+
+    /// The transforms of the nodes of the scene at `time`.
+    fn sample(&self, time: i64, stack: &Stack) -> Pose<'a> {
+        let animate = |node, info: &FbxNodeTransformInfo| {
+            let rest = info.rest_local();
+            let local = blend_layers(stack, node, time, &rest, info.rotation_order());
+            info.animated(&local)
+        };
+        let mut pose = Pose::new(self.hierarchy);
+        for root in self.roots {
+            self.sample_rec(*root, None, &animate, &mut pose);
+        }
+        for constraint in &self.constraints {
+            constraint.apply(&mut pose);
+        }
+        pose
+    }
+
+    /// A clip with a single keyframe setting the nodes of `stored` to their pose.
+    pub(crate) fn bake_pose(&self, stored: &StoredPose) -> FbxAnimationClip {
+        let mut pose = Pose::new(self.hierarchy);
+        for root in self.roots {
+            self.sample_rec(*root, None, &|_, info| info.clone(), &mut pose);
+        }
+        // Parents first, so that children are posed relative to their posed parent.
+        let mut posed = Vec::new();
+        let mut stack: Vec<ObjectId> = self.roots.to_vec();
+        while let Some(node) = stack.pop() {
+            if let Some(global) = stored.globals.get(&node) {
+                pose.set_global(node, *global);
+                posed.push(node);
+            }
+            if let Some(object) = self.hierarchy.get(&node) {
+                stack.extend(&object.children);
+            }
+        }
+        let mut clip = FbxAnimationClip {
+            name: stored.name.clone(),
+            frame_rate: self.frame_rate,
+            ..Default::default()
+        };
+        let paths = entity_paths(self.hierarchy, self.roots);
+        for node in posed {
+            let (path, transform) = match (paths.get(&node), pose.local(node)) {
+                (Some(path), Some(transform)) => (path, transform),
+                _ => continue,
+            };
+            let keyframes = [
+                FbxKeyframes::Translation(vec![transform.translation]),
+                FbxKeyframes::Rotation(vec![transform.rotation]),
+                FbxKeyframes::Scale(vec![transform.scale]),
+            ];
+            for keyframes in keyframes {
+                let curve = FbxCurve {
+                    keyframe_timestamps: vec![0.0],
+                    keyframes,
+                };
+                clip.add_curve_to_path(path.clone(), curve);
+            }
+        }
+        clip
+    }
+
+    /// Sample the animation of `stack` at each frame, the resulting clip
+    /// starts at zero with [`FbxLoaderSettings::normalize_clip_start`].
+    pub(crate) fn bake(
+        &self,
+        stack: &Stack,
+        progress: &Progress,
+    ) -> (FbxAnimationClip, PropertyCurves) {
+        match stack.time_span() {
+            Some(span) => {
+                let origin = if self.settings.normalize_clip_start {
+                    span.0
+                } else {
+                    0
+                };
+                self.bake_span(stack, stack.name.clone(), span, origin, progress)
+            }
+            None => {
+                let clip = FbxAnimationClip {
+                    name: stack.name.clone(),
+                    frame_rate: self.frame_rate,
+                    ..Default::default()
+                };
+                (clip, PropertyCurves::default())
+            }
+        }
+    }
+
+    /// Sample the frames `slice.start..=slice.end` of `stack`,
+    /// the resulting clip starts at zero.
+    pub(crate) fn bake_slice(
+        &self,
+        stack: &Stack,
+        slice: &FbxTakeSlice,
+        progress: &Progress,
+    ) -> (FbxAnimationClip, PropertyCurves) {
+        let start = self.frame_to_ktime(slice.start);
+        let stop = self.frame_to_ktime(slice.end);
+        self.bake_span(
+            stack,
+            Some(slice.clip_name()),
+            (start, stop),
+            start,
+            progress,
+        )
+    }
+
+    fn frame_to_ktime(&self, frame: i64) -> i64 {
+        (frame as f64 * KTIME_PER_SECOND as f64 / self.frame_rate).round() as i64
+    }
+
+    /// Sample `stack` at each frame between `start` and `stop`,
+    /// timestamps of the clip are relative to `origin`.
+    fn bake_span(
+        &self,
+        stack: &Stack,
+        name: Option<String>,
+        (start, stop): (i64, i64),
+        origin: i64,
+        progress: &Progress,
+    ) -> (FbxAnimationClip, PropertyCurves) {
+        let mut clip = FbxAnimationClip {
+            name,
+            frame_rate: self.frame_rate,
+            ..Default::default()
+        };
+        if start > stop || stack.active_layers().next().is_none() {
+            return (clip, PropertyCurves::default());
+        }
+        let sample_rate = self.settings.sample_rate.filter(|rate| *rate > 0.0);
+        let step = KTIME_PER_SECOND as f64 / sample_rate.unwrap_or(self.frame_rate);
+        // Round the sample count to absorb the errors of the KTime conversions,
+        // a sample rate that doesn't divide the span gets a last sample at `stop`.
+        let sample_count = ((stop - start) as f64 / step - 1e-6).ceil().max(0.0) as usize + 1;
+        let mut times: Vec<i64> = (0..sample_count)
+            .map(|frame| (start + (frame as f64 * step).round() as i64).min(stop))
+            .collect();
+        if self.settings.keep_original_keys {
+            let keys = stack.key_times().into_iter();
+            times.extend(keys.filter(|time| (start..=stop).contains(time)));
+            times.sort_unstable();
+        }
+        times.dedup();
+
+        let mut tracks = self.animated_tracks(stack);
+        let mut sheared = HashMap::<ObjectId, f32>::default();
+        for &time in &times {
+            let pose = self.sample(time, stack);
+            for (node, track) in tracks.iter_mut() {
+                if let Some((transform, shear)) = pose.local_decomposed(*node) {
+                    track.push(transform);
+                    if shear > SHEAR_TOLERANCE {
+                        let max_shear = sheared.entry(*node).or_default();
+                        *max_shear = max_shear.max(shear);
+                    }
+                }
+            }
+        }
+        for (node, shear) in sheared {
+            let name = self.hierarchy.get(&node).and_then(|o| o.name.as_deref());
+            progress.warn(
+                FbxWarningCategory::Transform,
+                name,
+                format!(
+                    "Node {} is sheared in clip {}, up to {:.1}% of its scale, \
+                     approximated with the closest rotation and scale",
+                    name.unwrap_or("<unnamed>"),
+                    clip.name.as_deref().unwrap_or("<unnamed>"),
+                    shear * 100.0
+                ),
+                Some("Avoid non-uniform scale on parents of rotated nodes, or bake the animation"),
+            );
+        }
+        let timestamps: Vec<f32> = times
+            .iter()
+            .map(|time| super::ktime_to_seconds(*time - origin) as f32)
+            .collect();
+        for track in tracks.into_values() {
+            let keyframes = [
+                FbxKeyframes::Translation(track.translations),
+                FbxKeyframes::Rotation(track.rotations),
+                FbxKeyframes::Scale(track.scales),
+            ];
+            for keyframes in keyframes {
+                let curve = FbxCurve {
+                    keyframe_timestamps: timestamps.clone(),
+                    keyframes,
+                };
+                clip.add_curve_to_path(track.path.clone(), curve);
+            }
+        }
+        let properties = self.bake_user_properties(stack, &times, &timestamps);
+        if !properties.is_empty() {
+            // The clip may only animate user-defined properties.
+            let last = timestamps.last().copied().unwrap_or_default();
+            clip.duration = clip.duration.max(last);
+        }
+        if self.settings.tolerance > 0.0 {
+            for curves in clip.curves.values_mut() {
+                curves
+                    .iter_mut()
+                    .for_each(|curve| reduce(curve, self.settings.tolerance));
+            }
+        }
+        if let Some(pattern) = &self.settings.event_pattern {
+            clip.events = clip_events(stack, self.hierarchy, pattern, (start, stop), origin);
+        }
+        (clip, properties)
+    }
+
+    /// Sample the user-defined properties animated by `stack` at `times`.
+    fn bake_user_properties(
+        &self,
+        stack: &Stack,
+        times: &[i64],
+        timestamps: &[f32],
+    ) -> PropertyCurves {
+        let mut curves = PropertyCurves::default();
+        let animated = stack
+            .active_layers()
+            .flat_map(|layer| layer.user_properties.keys());
+        for (node, name) in animated {
+            let extras = match self.hierarchy.get(node) {
+                Some(object) => &object.extras,
+                None => continue,
+            };
+            let node_curves: &mut HashMap<_, _> = curves.entry(*node).or_default();
+            if node_curves.contains_key(name) {
+                continue;
+            }
+            let channels: Option<Vec<_>> = times
+                .iter()
+                .map(|time| blend_user_property(stack, *node, name, *time))
+                .collect();
+            let curve =
+                channels.and_then(|channels| extras.curve(name, timestamps.to_vec(), &channels));
+            if let Some(curve) = curve {
+                node_curves.insert(name.clone(), curve);
+            }
+        }
+        curves.retain(|_, node_curves| !node_curves.is_empty());
+        curves
+    }
+}
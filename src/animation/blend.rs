@@ -0,0 +1,139 @@
+//! Combination of the values of the layers of an animation stack.
+//!
+//! FBX layers are evaluated from bottom to top, each layer is either added
+//! to the result of the layers below it, or overrides it, proportionally
+//! to the layer's weight.
+
+use bevy::math::{DQuat, DVec3, EulerRot};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use super::curve::{
+    BlendMode, Layer, RotationAccumulation, ScaleAccumulation, Stack, TransformProperty,
+};
+use crate::fbx_transform::AnimatedLocal;
+
+/// Convert FBX euler angles in degrees to a quaternion.
+pub(crate) fn to_quat(order: EulerRot, degrees: DVec3) -> DQuat {
+    let DVec3 { x, y, z } = -degrees * (std::f64::consts::TAU / 360.0);
+    DQuat::from_euler(order, x, y, z).inverse()
+}
+/// Convert a quaternion to FBX euler angles in degrees.
+fn to_euler(order: EulerRot, rotation: DQuat) -> DVec3 {
+    let (x, y, z) = rotation.inverse().to_euler(order);
+    -DVec3::new(x, y, z) * (360.0 / std::f64::consts::TAU)
+}
+
+/// Combine the value of `layer` with `below`, the result of the layers below it.
+fn blend_rotation(layer: &Layer, order: EulerRot, below: DVec3, value: DVec3) -> DVec3 {
+    let weight = layer.weight;
+    match (layer.blend_mode, layer.rotation_accumulation) {
+        (BlendMode::Additive, RotationAccumulation::ByChannel) => below + value * weight,
+        (_, RotationAccumulation::ByChannel) => below.lerp(value, weight),
+        (BlendMode::Additive, RotationAccumulation::ByLayer) => {
+            let added = DQuat::IDENTITY.slerp(to_quat(order, value), weight);
+            to_euler(order, to_quat(order, below) * added)
+        }
+        (_, RotationAccumulation::ByLayer) => {
+            let (below, value) = (to_quat(order, below), to_quat(order, value));
+            to_euler(order, below.slerp(value, weight))
+        }
+    }
+}
+fn blend_scale(layer: &Layer, below: DVec3, value: DVec3) -> DVec3 {
+    let weight = layer.weight;
+    match (layer.blend_mode, layer.scale_accumulation) {
+        (BlendMode::Additive, ScaleAccumulation::Multiply) => {
+            below * DVec3::ONE.lerp(value, weight)
+        }
+        (BlendMode::Additive, ScaleAccumulation::Additive) => below + (value - 1.0) * weight,
+        _ => below.lerp(value, weight),
+    }
+}
+fn blend_translation(layer: &Layer, below: DVec3, value: DVec3) -> DVec3 {
+    match layer.blend_mode {
+        BlendMode::Additive => below + value * layer.weight,
+        BlendMode::Override | BlendMode::OverridePassthrough => below.lerp(value, layer.weight),
+    }
+}
+
+/// The value of the transform properties of `node` at `time`,
+/// combining all the active layers of `stack`.
+///
+/// `rest` holds the static values of the properties, used for the properties
+/// not animated by the bottom layer. Properties animated by no layers
+/// are `None` in the returned value.
+pub(crate) fn blend_layers(
+    stack: &Stack,
+    node: ObjectId,
+    time: i64,
+    rest: &AnimatedLocal,
+    order: EulerRot,
+) -> AnimatedLocal {
+    let blend = |property, rest: Option<DVec3>| {
+        let mut value: Option<DVec3> = None;
+        for (i, layer) in stack.active_layers().enumerate() {
+            let curve_node = match layer.properties.get(&(node, property)) {
+                Some(curve_node) => curve_node,
+                None => continue,
+            };
+            let sample = curve_node.sample(time);
+            // The bottom layer always overrides the static value.
+            let below = match value.or(rest) {
+                Some(below) if i != 0 => below,
+                _ => {
+                    value = Some(sample);
+                    continue;
+                }
+            };
+            value = Some(match property {
+                TransformProperty::Translation => blend_translation(layer, below, sample),
+                TransformProperty::Rotation => blend_rotation(layer, order, below, sample),
+                TransformProperty::Scaling => blend_scale(layer, below, sample),
+            });
+        }
+        value
+    };
+    AnimatedLocal {
+        translation: blend(TransformProperty::Translation, rest.translation),
+        rotation: blend(TransformProperty::Rotation, rest.rotation),
+        scale: blend(TransformProperty::Scaling, rest.scale),
+    }
+}
+
+/// The value of each component of the user-defined property `name` of `node`
+/// at `time`, combining all the active layers of `stack`.
+///
+/// Returns `None` if no layer animates the property.
+pub(crate) fn blend_user_property(
+    stack: &Stack,
+    node: ObjectId,
+    name: &str,
+    time: i64,
+) -> Option<Vec<f64>> {
+    let mut value: Option<Vec<f64>> = None;
+    for layer in stack.active_layers() {
+        let curve_node = match layer.user_properties.get(&(node, name.to_owned())) {
+            Some(curve_node) => curve_node,
+            None => continue,
+        };
+        let sample = curve_node.sample_channels(time);
+        let below = match &value {
+            Some(below) => below,
+            None => {
+                value = Some(sample);
+                continue;
+            }
+        };
+        let blended = below.iter().zip(&sample).map(|(below, sample)| {
+            let weight = layer.weight;
+            match layer.blend_mode {
+                BlendMode::Additive => below + sample * weight,
+                BlendMode::Override | BlendMode::OverridePassthrough => {
+                    below + (sample - below) * weight
+                }
+            }
+        });
+        value = Some(blended.collect());
+    }
+    value
+}
@@ -0,0 +1,434 @@
+//! Evaluation of FBX constraints, so that their effect can be baked
+//! into the animation clips.
+//!
+//! Supported constraints are the position, rotation, scale, parent
+//! and aim constraints, and single chain IK over two bones.
+//! Constraints are evaluated in the order they appear in the file,
+//! using the static value of their properties.
+
+use bevy::{
+    math::{BVec3, DVec3, EulerRot},
+    prelude::{Mat4, Quat, Vec3},
+};
+use fbxcel_dom::v7400::{
+    object::{ObjectHandle, ObjectId},
+    Document,
+};
+
+use super::{blend::to_quat, pose::Pose};
+use crate::{progress::Progress, utils::fbx_extend::ObjectPropertiesExt, FbxWarningCategory};
+
+/// Smallest distance considered when solving IK chains.
+const IK_EPSILON: f32 = 1e-4;
+
+/// What the up vector of an aim constraint points at, the `WorldUpType` property.
+#[derive(Clone, Debug)]
+enum WorldUp {
+    Scene,
+    /// Toward the position of an object.
+    Object(ObjectId),
+    /// The given vector, in the space of an object.
+    ObjectRotation(ObjectId, Vec3),
+    Vector(Vec3),
+    None,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Position {
+        offset: Vec3,
+        affect: [bool; 3],
+    },
+    Rotation {
+        offset: Quat,
+    },
+    Scale {
+        offset: Vec3,
+        affect: [bool; 3],
+    },
+    /// The offset of the constrained object relative to each source.
+    Parent {
+        offsets: Vec<Mat4>,
+    },
+    Aim {
+        aim: Vec3,
+        up: Vec3,
+        world_up: WorldUp,
+        offset: Quat,
+    },
+    /// The constrained object is the first joint of the chain,
+    /// the source is the effector.
+    SingleChainIk {
+        end: ObjectId,
+    },
+}
+
+/// An FBX `Constraint` object.
+#[derive(Clone, Debug)]
+pub(crate) struct Constraint {
+    constrained: ObjectId,
+    /// Objects the constrained object follows, with their weight.
+    sources: Vec<(ObjectId, f32)>,
+    /// The `Weight` of the constraint, between 0 and 1.
+    weight: f32,
+    kind: Kind,
+}
+
+fn to_rotation(degrees: DVec3) -> Quat {
+    to_quat(EulerRot::XYZ, degrees).as_f32()
+}
+/// `to` on the affected axes, `from` on the others.
+fn select_axes(from: Vec3, to: Vec3, [x, y, z]: [bool; 3]) -> Vec3 {
+    Vec3::select(BVec3::new(x, y, z), to, from)
+}
+fn translation_of(global: Mat4) -> Vec3 {
+    global.w_axis.truncate()
+}
+
+impl Constraint {
+    fn from_object(object: ObjectHandle, progress: &Progress) -> Option<Self> {
+        let kind = match object.node().first_child_by_name("Type") {
+            Some(node) => node.attributes().first()?.get_string()?.to_owned(),
+            None => object.subclass().to_owned(),
+        };
+        let typename = match kind.as_str() {
+            "Position From Positions" => "FbxConstraintPosition",
+            "Rotation From Rotations" => "FbxConstraintRotation",
+            "Scale From Scales" => "FbxConstraintScale",
+            "Parent-Child" => "FbxConstraintParent",
+            "Aim" => "FbxConstraintAim",
+            "Single Chain IK" => "FbxConstraintSingleChainIK",
+            _ => {
+                progress.warn(
+                    FbxWarningCategory::Animation,
+                    object.name(),
+                    format!("Unsupported FBX constraint type: {kind}"),
+                    Some("Bake the constraint into the animation before exporting"),
+                );
+                return None;
+            }
+        };
+        let p = object.properties_by_native_typename(typename);
+        if !p.get::<bool>("Active").unwrap_or(true) {
+            return None;
+        }
+        let mut constrained = None;
+        let mut end_joint = None;
+        let mut world_up_object = None;
+        let mut sources = Vec::new();
+        for connected in object.source_objects() {
+            let source = match connected.object_handle() {
+                Some(source) if source.node().name() == "Model" => source,
+                _ => continue,
+            };
+            let label = connected.label().unwrap_or_default();
+            if label.starts_with("Constrained") || label == "First Joint" {
+                constrained = Some(source.object_id());
+            } else if label == "End Joint" {
+                end_joint = Some(source.object_id());
+            } else if label == "World Up Object" {
+                world_up_object = Some(source.object_id());
+            } else if label.starts_with("Source") || label == "Aim At Object" || label == "Effector"
+            {
+                let name = source.name().unwrap_or_default();
+                let weight = p.get::<f64>(&format!("{name}.Weight")).unwrap_or(100.0);
+                sources.push((source.object_id(), weight as f32 / 100.0));
+            }
+        }
+        let affect = |prefix: &str| {
+            ["X", "Y", "Z"].map(|axis| p.get::<bool>(&format!("{prefix}{axis}")).unwrap_or(true))
+        };
+        let vector = |name: &str, default| p.get::<DVec3>(name).map_or(default, |v| v.as_vec3());
+        let rotation = |name: &str| to_rotation(p.get(name).unwrap_or_default());
+        let kind = match typename {
+            "FbxConstraintPosition" => Kind::Position {
+                offset: vector("Translation", Vec3::ZERO),
+                affect: affect("Affect"),
+            },
+            "FbxConstraintRotation" => Kind::Rotation {
+                offset: rotation("Rotation"),
+            },
+            "FbxConstraintScale" => Kind::Scale {
+                offset: vector("Scaling", Vec3::ONE),
+                affect: affect("Affect"),
+            },
+            "FbxConstraintParent" => {
+                let offsets = sources.iter().map(|(source, _)| {
+                    let name = source
+                        .to_object_handle(object.document())
+                        .and_then(|obj| obj.name())
+                        .unwrap_or_default();
+                    let translation = vector(&format!("{name}.Offset T"), Vec3::ZERO);
+                    let rotation = rotation(&format!("{name}.Offset R"));
+                    Mat4::from_rotation_translation(rotation, translation)
+                });
+                Kind::Parent {
+                    offsets: offsets.collect(),
+                }
+            }
+            "FbxConstraintAim" => {
+                let world_up_vector = vector("WorldUpVector", Vec3::Y);
+                let world_up = match (p.get::<i32>("WorldUpType"), world_up_object) {
+                    (Some(1), Some(object)) => WorldUp::Object(object),
+                    (Some(2), Some(object)) => WorldUp::ObjectRotation(object, world_up_vector),
+                    (Some(3), _) => WorldUp::Vector(world_up_vector),
+                    (Some(4), _) => WorldUp::None,
+                    _ => WorldUp::Scene,
+                };
+                Kind::Aim {
+                    aim: vector("AimVector", Vec3::X).normalize_or_zero(),
+                    up: vector("UpVector", Vec3::Y).normalize_or_zero(),
+                    world_up,
+                    offset: rotation("RotationOffset"),
+                }
+            }
+            _ => Kind::SingleChainIk { end: end_joint? },
+        };
+        Some(Constraint {
+            constrained: constrained?,
+            sources,
+            weight: p.get::<f64>("Weight").unwrap_or(100.0).clamp(0.0, 100.0) as f32 / 100.0,
+            kind,
+        })
+    }
+
+    /// All the active constraints of `doc`.
+    pub(crate) fn all(doc: &Document, progress: &Progress) -> Vec<Self> {
+        doc.objects()
+            .filter(|obj| obj.node().name() == "Constraint")
+            .filter_map(|obj| Constraint::from_object(obj, progress))
+            .collect()
+    }
+
+    /// The node transformed by this constraint, its descendants are affected too.
+    pub(crate) fn affected(&self) -> ObjectId {
+        self.constrained
+    }
+
+    /// The global transforms of the sources, with their normalized weight.
+    fn weighted_sources(&self, pose: &Pose) -> Vec<(Mat4, f32)> {
+        let sources: Vec<_> = self
+            .sources
+            .iter()
+            .filter_map(|(source, weight)| Some((pose.global(*source)?, *weight)))
+            .collect();
+        let total: f32 = sources.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+        sources
+            .into_iter()
+            .map(|(global, weight)| (global, weight / total))
+            .collect()
+    }
+
+    /// Apply the constraint to `pose`.
+    pub(crate) fn apply(&self, pose: &mut Pose) {
+        if let Kind::SingleChainIk { end } = self.kind {
+            self.solve_ik(end, pose);
+            return;
+        }
+        let original = match pose.global(self.constrained) {
+            Some(global) => global,
+            None => return,
+        };
+        let sources = self.weighted_sources(pose);
+        if sources.is_empty() {
+            return;
+        }
+        let (scale, rotation, translation) = original.to_scale_rotation_translation();
+        let average = |value: fn(Mat4) -> Vec3| -> Vec3 {
+            sources.iter().map(|(g, weight)| value(*g) * *weight).sum()
+        };
+        let constrained = match &self.kind {
+            Kind::Position { offset, affect } => {
+                let position = average(translation_of) + *offset;
+                let translation = select_axes(translation, position, *affect);
+                Mat4::from_scale_rotation_translation(scale, rotation, translation)
+            }
+            Kind::Rotation { offset } => {
+                let rotations = sources
+                    .iter()
+                    .map(|(g, weight)| (g.to_scale_rotation_translation().1, *weight));
+                let average = average_rotation(rotations) * *offset;
+                Mat4::from_scale_rotation_translation(scale, average, translation)
+            }
+            Kind::Scale { offset, affect } => {
+                let source_scale = |g: Mat4| g.to_scale_rotation_translation().0;
+                let average = average(source_scale) * *offset;
+                let scale = select_axes(scale, average, *affect);
+                Mat4::from_scale_rotation_translation(scale, rotation, translation)
+            }
+            Kind::Parent { offsets } => {
+                let parented: Vec<_> = self
+                    .sources
+                    .iter()
+                    .zip(offsets)
+                    .filter_map(|((source, weight), offset)| {
+                        Some((pose.global(*source)? * *offset, *weight))
+                    })
+                    .collect();
+                let total: f32 = parented.iter().map(|(_, weight)| weight).sum();
+                let position = parented
+                    .iter()
+                    .map(|(g, weight)| translation_of(*g) * *weight / total)
+                    .sum();
+                let rotations = parented
+                    .iter()
+                    .map(|(g, weight)| (g.to_scale_rotation_translation().1, *weight / total));
+                Mat4::from_scale_rotation_translation(scale, average_rotation(rotations), position)
+            }
+            Kind::Aim {
+                aim,
+                up,
+                world_up,
+                offset,
+            } => {
+                let direction = (average(translation_of) - translation).normalize_or_zero();
+                if direction == Vec3::ZERO {
+                    return;
+                }
+                let aimed = Quat::from_rotation_arc(*aim, direction);
+                let world_up = match world_up {
+                    WorldUp::Scene => Some(Vec3::Y),
+                    WorldUp::Object(object) => pose
+                        .global(*object)
+                        .map(|g| translation_of(g) - translation),
+                    WorldUp::ObjectRotation(object, up) => {
+                        pose.global(*object).map(|g| g.transform_vector3(*up))
+                    }
+                    WorldUp::Vector(up) => Some(*up),
+                    WorldUp::None => None,
+                };
+                let on_plane = |v: Vec3| (v - direction * v.dot(direction)).normalize_or_zero();
+                let twist = match world_up.map(on_plane) {
+                    Some(world_up) if world_up != Vec3::ZERO => {
+                        let current_up = on_plane(aimed * *up);
+                        if current_up == Vec3::ZERO {
+                            Quat::IDENTITY
+                        } else {
+                            Quat::from_rotation_arc(current_up, world_up)
+                        }
+                    }
+                    _ => Quat::IDENTITY,
+                };
+                let rotation = twist * aimed * *offset;
+                Mat4::from_scale_rotation_translation(scale, rotation, translation)
+            }
+            Kind::SingleChainIk { .. } => unreachable!(),
+        };
+        pose.set_global(self.constrained, blend(original, constrained, self.weight));
+    }
+
+    /// Rotate the first and middle joints of a two bones chain so that
+    /// its end reaches the effector, keeping the current bending plane.
+    fn solve_ik(&self, end: ObjectId, pose: &mut Pose) {
+        let first = self.constrained;
+        let middle = match pose.parent(end) {
+            Some(middle) if pose.parent(middle) == Some(first) => middle,
+            _ => return,
+        };
+        let effector = match self.sources.first().and_then(|(s, _)| pose.global(*s)) {
+            Some(effector) => translation_of(effector),
+            None => return,
+        };
+        let position = |pose: &Pose, node| pose.global(node).map(translation_of);
+        let (a, b, c) = match (
+            position(pose, first),
+            position(pose, middle),
+            position(pose, end),
+        ) {
+            (Some(a), Some(b), Some(c)) => (a, b, c),
+            _ => return,
+        };
+        let (ab, cb) = (a.distance(b), c.distance(b));
+        if ab < IK_EPSILON || cb < IK_EPSILON {
+            return;
+        }
+        let at = a.distance(effector).clamp(IK_EPSILON, ab + cb - IK_EPSILON);
+        let law_of_cosines = |adjacent: f32, other: f32, opposite: f32| -> f32 {
+            let cos = (adjacent * adjacent + other * other - opposite * opposite)
+                / (2.0 * adjacent * other);
+            cos.clamp(-1.0, 1.0).acos()
+        };
+        let angle_at_first = (c - a).angle_between(b - a);
+        let angle_at_middle = (a - b).angle_between(c - b);
+        let bend_axis = match (c - a).cross(b - a).try_normalize() {
+            Some(axis) => axis,
+            None => (c - a).any_orthonormal_vector(),
+        };
+        let rotate_around = |pose: &mut Pose, node, pivot: Vec3, rotation: Quat| {
+            if let Some(global) = pose.global(node) {
+                let around = Mat4::from_translation(pivot)
+                    * Mat4::from_quat(rotation)
+                    * Mat4::from_translation(-pivot);
+                pose.set_global(node, around * global);
+            }
+        };
+        let weight = self.weight;
+        let first_delta = law_of_cosines(ab, at, cb) - angle_at_first;
+        let middle_delta = law_of_cosines(ab, cb, at) - angle_at_middle;
+        rotate_around(
+            pose,
+            first,
+            a,
+            Quat::from_axis_angle(bend_axis, first_delta * weight),
+        );
+        let b = match position(pose, middle) {
+            Some(b) => b,
+            None => return,
+        };
+        rotate_around(
+            pose,
+            middle,
+            b,
+            Quat::from_axis_angle(bend_axis, middle_delta * weight),
+        );
+        let c = match position(pose, end) {
+            Some(c) => c,
+            None => return,
+        };
+        let (current, target) = (
+            (c - a).normalize_or_zero(),
+            (effector - a).normalize_or_zero(),
+        );
+        if current != Vec3::ZERO && target != Vec3::ZERO {
+            let swing = Quat::IDENTITY.slerp(Quat::from_rotation_arc(current, target), weight);
+            rotate_around(pose, first, a, swing);
+        }
+    }
+}
+
+/// Weighted average of `rotations`, weights should sum to 1.
+fn average_rotation(rotations: impl Iterator<Item = (Quat, f32)>) -> Quat {
+    let mut average = Quat::from_xyzw(0.0, 0.0, 0.0, 0.0);
+    for (rotation, weight) in rotations {
+        // Quaternions q and -q are the same rotation,
+        // keep them in the same hemisphere before summing them.
+        let rotation = if average.dot(rotation) < 0.0 {
+            -rotation
+        } else {
+            rotation
+        };
+        average = average + rotation * weight;
+    }
+    if average.length_squared() > 0.0 {
+        average.normalize()
+    } else {
+        Quat::IDENTITY
+    }
+}
+
+/// Interpolate between two global transforms.
+fn blend(from: Mat4, to: Mat4, weight: f32) -> Mat4 {
+    if weight >= 1.0 {
+        return to;
+    }
+    let (from_scale, from_rotation, from_translation) = from.to_scale_rotation_translation();
+    let (to_scale, to_rotation, to_translation) = to.to_scale_rotation_translation();
+    Mat4::from_scale_rotation_translation(
+        from_scale.lerp(to_scale, weight),
+        from_rotation.slerp(to_rotation, weight),
+        from_translation.lerp(to_translation, weight),
+    )
+}
@@ -0,0 +1,440 @@
+//! Reading of the FBX animation objects.
+//!
+//! fbxcel_dom doesn't have handles for animation objects,
+//! so we read them from the raw FBX node tree:
+//!
+//! - `AnimationStack`: a "take", the equivalent of a bevy clip
+//! - `AnimationLayer`: connected to its stack, a stack can have several layers
+//! - `AnimationCurveNode`: connected to a layer and to the animated property
+//!   of an object (for example `Lcl Translation` of a `Model`)
+//! - `AnimationCurve`: connected to a curve node, one per component
+//!   of the property (`d|X`, `d|Y`, `d|Z`).
+
+use bevy::{math::DVec3, utils::HashMap};
+use fbxcel_dom::v7400::{
+    object::{ObjectHandle, ObjectId},
+    Document,
+};
+
+use crate::{
+    animation::{ktime_to_seconds, take::FbxTakeMetadata},
+    extras::is_user_property,
+    utils::fbx_extend::ObjectPropertiesExt,
+};
+
+/// The animated transform properties of a `Model`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum TransformProperty {
+    Translation,
+    Rotation,
+    Scaling,
+}
+impl TransformProperty {
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "Lcl Translation" => Some(Self::Translation),
+            "Lcl Rotation" => Some(Self::Rotation),
+            "Lcl Scaling" => Some(Self::Scaling),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Interpolation {
+    Constant,
+    Linear,
+    /// Cubic Hermite interpolation, with the slopes of the tangents
+    /// leaving the key and entering the next key, in value per second.
+    Cubic {
+        right_slope: f32,
+        next_left_slope: f32,
+    },
+}
+impl Interpolation {
+    const CONSTANT_FLAG: i32 = 0x02;
+    const CUBIC_FLAG: i32 = 0x08;
+
+    /// The interpolation of the keys with `flags`, `data` being their
+    /// `KeyAttrDataFloat`: right slope, next left slope, weights and velocity.
+    fn from_flags(flags: i32, data: Option<&[f32]>) -> Self {
+        if flags & Self::CONSTANT_FLAG != 0 {
+            Interpolation::Constant
+        } else if let (true, Some([right_slope, next_left_slope, ..])) =
+            (flags & Self::CUBIC_FLAG != 0, data)
+        {
+            // Tangent weights are ignored, weighted tangents are
+            // approximated by regular Hermite tangents.
+            Interpolation::Cubic {
+                right_slope: *right_slope,
+                next_left_slope: *next_left_slope,
+            }
+        } else {
+            Interpolation::Linear
+        }
+    }
+}
+
+/// An FBX `AnimationCurve`, the keys of a single float channel.
+#[derive(Clone, Debug)]
+pub(crate) struct Curve {
+    /// Key times in KTime.
+    times: Vec<i64>,
+    values: Vec<f32>,
+    /// Interpolation between a key and the next one.
+    interpolations: Vec<Interpolation>,
+}
+impl Curve {
+    fn from_object(object: ObjectHandle) -> Option<Self> {
+        let node = object.node();
+        let attributes = |name| node.first_child_by_name(name)?.attributes().first();
+        let times = attributes("KeyTime")?.get_arr_i64()?.to_vec();
+        let values = match attributes("KeyValueFloat")? {
+            value if value.get_arr_f32().is_some() => value.get_arr_f32()?.to_vec(),
+            value => value.get_arr_f64()?.iter().map(|v| *v as f32).collect(),
+        };
+        if times.len() != values.len() {
+            return None;
+        }
+        // Flags are run-length encoded: each flag applies to `ref_count` keys.
+        let flags = attributes("KeyAttrFlags").and_then(|a| a.get_arr_i32());
+        let ref_counts = attributes("KeyAttrRefCount").and_then(|a| a.get_arr_i32());
+        let data = attributes("KeyAttrDataFloat").and_then(|a| a.get_arr_f32());
+        let mut interpolations = Vec::with_capacity(times.len());
+        if let (Some(flags), Some(ref_counts)) = (flags, ref_counts) {
+            for (i, (flags, count)) in flags.iter().zip(ref_counts).enumerate() {
+                let count = (*count).max(0) as usize;
+                let data = data.and_then(|data| data.get(i * 4..i * 4 + 4));
+                let interpolation = Interpolation::from_flags(*flags, data);
+                interpolations.extend(std::iter::repeat(interpolation).take(count));
+            }
+        }
+        interpolations.resize(times.len(), Interpolation::Linear);
+        Some(Curve {
+            times,
+            values,
+            interpolations,
+        })
+    }
+    /// The value of the curve at `time` (in KTime).
+    pub(crate) fn sample(&self, time: i64) -> Option<f32> {
+        let next = self.times.partition_point(|t| *t <= time);
+        if next == 0 {
+            return self.values.first().copied();
+        }
+        let current = next - 1;
+        let (next_time, next_value) = match (self.times.get(next), self.values.get(next)) {
+            (Some(time), Some(value)) => (*time, *value),
+            _ => return self.values.last().copied(),
+        };
+        let (current_time, current_value) = (self.times[current], self.values[current]);
+        match self.interpolations[current] {
+            Interpolation::Constant => Some(current_value),
+            Interpolation::Linear => {
+                let ratio = (time - current_time) as f64 / (next_time - current_time) as f64;
+                Some(current_value + (next_value - current_value) * ratio as f32)
+            }
+            Interpolation::Cubic {
+                right_slope,
+                next_left_slope,
+            } => {
+                let t = (time - current_time) as f64 / (next_time - current_time) as f64;
+                let duration = ktime_to_seconds(next_time - current_time);
+                let (t2, t3) = (t * t, t * t * t);
+                let value = (2.0 * t3 - 3.0 * t2 + 1.0) * current_value as f64
+                    + (t3 - 2.0 * t2 + t) * duration * right_slope as f64
+                    + (-2.0 * t3 + 3.0 * t2) * next_value as f64
+                    + (t3 - t2) * duration * next_left_slope as f64;
+                Some(value as f32)
+            }
+        }
+    }
+    /// The first and last key time of this curve.
+    pub(crate) fn time_span(&self) -> Option<(i64, i64)> {
+        Some((*self.times.first()?, *self.times.last()?))
+    }
+    /// The time (in KTime) and value of each key.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = (i64, f32)> + '_ {
+        self.times.iter().copied().zip(self.values.iter().copied())
+    }
+}
+
+/// A component of an animated property, such as `d|X`.
+#[derive(Clone, Debug)]
+struct Channel {
+    name: String,
+    /// Value of the channel when it is not animated.
+    default: f64,
+    curve: Option<Curve>,
+}
+
+/// An FBX `AnimationCurveNode`, a property animated by one [`Curve`]
+/// per component.
+///
+/// Vector properties have the `d|X`, `d|Y` and `d|Z` components,
+/// number properties a single component named after the property.
+#[derive(Clone, Debug)]
+pub(crate) struct CurveNode {
+    channels: Vec<Channel>,
+}
+impl CurveNode {
+    fn from_object(object: ObjectHandle) -> Self {
+        let p = object.properties_by_native_typename("AnimationCurveNode");
+        let properties = object
+            .node()
+            .first_child_by_name("Properties70")
+            .into_iter()
+            .flat_map(|node| node.children_by_name("P"));
+        let mut channels: Vec<_> = properties
+            .filter_map(|property| {
+                let name = property.attributes().first()?.get_string()?;
+                name.starts_with("d|").then(|| Channel {
+                    name: name.to_owned(),
+                    default: p.get(name).unwrap_or_default(),
+                    curve: None,
+                })
+            })
+            .collect();
+        for source in object.source_objects() {
+            let channel = source
+                .label()
+                .and_then(|label| channels.iter_mut().find(|c| c.name == label));
+            let channel = match channel {
+                Some(channel) => channel,
+                None => continue,
+            };
+            channel.curve = source
+                .object_handle()
+                .filter(|obj| obj.node().name() == "AnimationCurve")
+                .and_then(Curve::from_object);
+        }
+        CurveNode { channels }
+    }
+    /// The value of each component of the property at `time` (in KTime).
+    pub(crate) fn sample_channels(&self, time: i64) -> Vec<f64> {
+        let sample = |channel: &Channel| {
+            let sample = channel.curve.as_ref().and_then(|c| c.sample(time));
+            sample.map_or(channel.default, f64::from)
+        };
+        self.channels.iter().map(sample).collect()
+    }
+    /// The value of the vector property at `time` (in KTime).
+    pub(crate) fn sample(&self, time: i64) -> DVec3 {
+        let mut value = DVec3::ZERO;
+        for (i, axis) in ["d|X", "d|Y", "d|Z"].iter().enumerate() {
+            if let Some(channel) = self.channels.iter().find(|c| c.name == *axis) {
+                let sample = channel.curve.as_ref().and_then(|c| c.sample(time));
+                value[i] = sample.map_or(channel.default, f64::from);
+            }
+        }
+        value
+    }
+    /// The keys of all the components of the property.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = (i64, f32)> + '_ {
+        let curves = self.channels.iter().filter_map(|c| c.curve.as_ref());
+        curves.flat_map(Curve::keys)
+    }
+    /// The first and last key time of this curve node.
+    pub(crate) fn time_span(&self) -> Option<(i64, i64)> {
+        self.channels
+            .iter()
+            .filter_map(|channel| channel.curve.as_ref()?.time_span())
+            .reduce(|(min, max), (start, end)| (min.min(start), max.max(end)))
+    }
+}
+
+/// How a layer is combined with the layers below it, the `BlendMode` property.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum BlendMode {
+    /// The layer values are added to the result of the layers below.
+    #[default]
+    Additive,
+    /// The layer values replace the result of the layers below,
+    /// proportionally to the layer weight.
+    Override,
+    /// Like `Override`, but the weight only applies to the properties
+    /// animated by the layer.
+    OverridePassthrough,
+}
+impl BlendMode {
+    fn from_i32(value: i32) -> Self {
+        match value {
+            1 => BlendMode::Override,
+            2 => BlendMode::OverridePassthrough,
+            _ => BlendMode::Additive,
+        }
+    }
+}
+
+/// How rotations are combined, the `RotationAccumulationMode` property.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum RotationAccumulation {
+    /// Rotations are combined as a whole, as quaternions.
+    #[default]
+    ByLayer,
+    /// Each euler angle is combined independently.
+    ByChannel,
+}
+
+/// How scales are combined by additive layers, the `ScaleAccumulationMode` property.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ScaleAccumulation {
+    #[default]
+    Multiply,
+    Additive,
+}
+
+/// The curves of an `AnimationLayer`, for each animated property of each `Model`.
+#[derive(Clone, Debug)]
+pub(crate) struct Layer {
+    pub(crate) properties: HashMap<(ObjectId, TransformProperty), CurveNode>,
+    /// Animated user-defined properties, by object and property name.
+    pub(crate) user_properties: HashMap<(ObjectId, String), CurveNode>,
+    /// The `Weight` of the layer, between 0 and 1.
+    pub(crate) weight: f64,
+    pub(crate) mute: bool,
+    pub(crate) solo: bool,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) rotation_accumulation: RotationAccumulation,
+    pub(crate) scale_accumulation: ScaleAccumulation,
+}
+impl Layer {
+    fn from_object(object: ObjectHandle) -> Self {
+        let mut properties = HashMap::default();
+        let mut user_properties = HashMap::default();
+        let curve_nodes = object
+            .source_objects()
+            .filter_map(|obj| obj.object_handle())
+            .filter(|obj| obj.node().name() == "AnimationCurveNode");
+        for curve_node in curve_nodes {
+            for target in curve_node.destination_objects() {
+                let label = match target.label() {
+                    Some(label) => label,
+                    None => continue,
+                };
+                let key = (target.object_id(), label.to_owned());
+                match TransformProperty::from_label(label) {
+                    Some(property) => {
+                        let key = (target.object_id(), property);
+                        properties.insert(key, CurveNode::from_object(curve_node));
+                    }
+                    None if target
+                        .object_handle()
+                        .is_some_and(|obj| is_user_property(obj, label)) =>
+                    {
+                        user_properties.insert(key, CurveNode::from_object(curve_node));
+                    }
+                    None => {}
+                }
+            }
+        }
+        let p = object.properties_by_native_typename("FbxAnimLayer");
+        let flag = |name| p.get::<bool>(name).unwrap_or(false);
+        let rotation_accumulation = match p.get::<i32>("RotationAccumulationMode") {
+            Some(1) => RotationAccumulation::ByChannel,
+            _ => RotationAccumulation::ByLayer,
+        };
+        let scale_accumulation = match p.get::<i32>("ScaleAccumulationMode") {
+            Some(1) => ScaleAccumulation::Additive,
+            _ => ScaleAccumulation::Multiply,
+        };
+        Layer {
+            properties,
+            user_properties,
+            weight: p.get::<f64>("Weight").unwrap_or(100.0).clamp(0.0, 100.0) / 100.0,
+            mute: flag("Mute"),
+            solo: flag("Solo"),
+            blend_mode: BlendMode::from_i32(p.get("BlendMode").unwrap_or_default()),
+            rotation_accumulation,
+            scale_accumulation,
+        }
+    }
+    /// Whether this layer animates `property` of `node`.
+    pub(crate) fn animates(&self, node: ObjectId, property: TransformProperty) -> bool {
+        self.properties.contains_key(&(node, property))
+    }
+    /// The first and last key time of this layer.
+    pub(crate) fn time_span(&self) -> Option<(i64, i64)> {
+        self.properties
+            .values()
+            .chain(self.user_properties.values())
+            .filter_map(CurveNode::time_span)
+            .reduce(|(min, max), (start, end)| (min.min(start), max.max(end)))
+    }
+}
+
+/// An FBX `AnimationStack`, also called "take".
+#[derive(Clone, Debug)]
+pub(crate) struct Stack {
+    pub(crate) id: ObjectId,
+    pub(crate) name: Option<String>,
+    /// The `LocalStart` and `LocalStop` properties, in KTime.
+    pub(crate) local_span: Option<(i64, i64)>,
+    pub(crate) layers: Vec<Layer>,
+    pub(crate) metadata: FbxTakeMetadata,
+}
+impl Stack {
+    fn from_object(object: ObjectHandle) -> Self {
+        let p = object.properties_by_native_typename("FbxAnimStack");
+        let start = p.get::<i64>("LocalStart");
+        let stop = p.get::<i64>("LocalStop");
+        let local_span = start.zip(stop).filter(|(start, stop)| start < stop);
+        let layers = object
+            .source_objects()
+            .filter_map(|obj| obj.object_handle())
+            .filter(|obj| obj.node().name() == "AnimationLayer")
+            .map(Layer::from_object)
+            .collect();
+        Stack {
+            id: object.object_id(),
+            name: object.name().filter(|n| !n.is_empty()).map(Into::into),
+            local_span,
+            layers,
+            metadata: FbxTakeMetadata::from_stack(object),
+        }
+    }
+    /// All the animation stacks of `doc`.
+    pub(crate) fn all(doc: &Document) -> Vec<Self> {
+        doc.objects()
+            .filter(|obj| obj.node().name() == "AnimationStack")
+            .map(Stack::from_object)
+            .collect()
+    }
+    /// The layers that contribute to the animation, from bottom to top.
+    ///
+    /// Muted layers are skipped, and if any layer is soloed,
+    /// only the soloed layers are kept.
+    pub(crate) fn active_layers(&self) -> impl Iterator<Item = &Layer> {
+        let any_solo = self.layers.iter().any(|layer| layer.solo);
+        self.layers
+            .iter()
+            .filter(move |layer| !layer.mute && (!any_solo || layer.solo))
+    }
+    /// The times of all the keys of the active layers, in KTime, sorted.
+    pub(crate) fn key_times(&self) -> Vec<i64> {
+        let curve_nodes = self.active_layers().flat_map(|layer| {
+            layer
+                .properties
+                .values()
+                .chain(layer.user_properties.values())
+        });
+        let mut times: Vec<i64> = curve_nodes
+            .flat_map(|curves| curves.keys().map(|(time, _)| time))
+            .collect();
+        times.sort_unstable();
+        times.dedup();
+        times
+    }
+    /// The time span of the stack, in KTime.
+    ///
+    /// This is `LocalStart` and `LocalStop` if they are defined,
+    /// otherwise the first and last key of the stack.
+    pub(crate) fn time_span(&self) -> Option<(i64, i64)> {
+        let keys_span = || {
+            self.active_layers()
+                .filter_map(Layer::time_span)
+                .reduce(|(min, max), (start, end)| (min.min(start), max.max(end)))
+        };
+        self.local_span.or_else(keys_span)
+    }
+}
@@ -0,0 +1,85 @@
+//! Animation events authored in the DCC, such as footsteps or impacts,
+//! see [`FbxLoaderSettings::event_pattern`].
+//!
+//! [`FbxLoaderSettings::event_pattern`]: crate::FbxLoaderSettings::event_pattern
+
+use bevy::{
+    prelude::{Entity, Handle},
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use super::{curve::Stack, ktime_to_seconds, FbxAnimationClip};
+use crate::{data::FbxObject, names};
+
+/// A named event at a time of an [`FbxAnimationClip`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FbxClipEvent {
+    /// The name of the node or property the event comes from, without
+    /// the text around the wildcards of
+    /// [`FbxLoaderSettings::event_pattern`].
+    ///
+    /// [`FbxLoaderSettings::event_pattern`]: crate::FbxLoaderSettings::event_pattern
+    pub name: String,
+    /// Time of the event in the clip, in seconds.
+    pub time: f32,
+}
+
+/// Event sent when an [`FbxAnimationPlayer`] reaches an [`FbxClipEvent`]
+/// of the clip it plays.
+///
+/// [`FbxAnimationPlayer`]: crate::FbxAnimationPlayer
+#[derive(Clone, Debug)]
+pub struct FbxAnimationEvent {
+    /// The entity holding the [`FbxAnimationPlayer`].
+    ///
+    /// [`FbxAnimationPlayer`]: crate::FbxAnimationPlayer
+    pub player: Entity,
+    pub clip: Handle<FbxAnimationClip>,
+    pub name: String,
+    /// Time of the event in the clip, in seconds.
+    pub time: f32,
+}
+
+/// The events of `stack` between `start` and `stop` (in KTime),
+/// with times relative to `origin`.
+///
+/// Each key of the nodes matching `pattern` is an event, as well as each
+/// non-zero key of the user-defined properties matching `pattern`,
+/// which may be keyed back to zero between events.
+pub(crate) fn clip_events(
+    stack: &Stack,
+    hierarchy: &HashMap<ObjectId, FbxObject>,
+    pattern: &str,
+    (start, stop): (i64, i64),
+    origin: i64,
+) -> Vec<FbxClipEvent> {
+    let matching = |name: &str| names::glob_match(pattern, name);
+    let mut events = Vec::new();
+    for layer in stack.active_layers() {
+        let node_keys = layer.properties.iter().filter_map(|((node, _), curves)| {
+            let name = hierarchy.get(node)?.name.as_deref()?;
+            Some((name, curves.keys().collect::<Vec<_>>()))
+        });
+        let property_keys = layer.user_properties.iter().map(|((_, name), curves)| {
+            let keys = curves.keys().filter(|(_, value)| *value != 0.0).collect();
+            (name.as_str(), keys)
+        });
+        for (name, keys) in node_keys.chain(property_keys) {
+            if !matching(name) {
+                continue;
+            }
+            let name = names::wildcard_match(pattern, name);
+            let keys = keys
+                .into_iter()
+                .filter(|(time, _)| (start..=stop).contains(time));
+            events.extend(keys.map(|(time, _)| FbxClipEvent {
+                name: name.to_owned(),
+                time: ktime_to_seconds(time - origin) as f32,
+            }));
+        }
+    }
+    events.sort_by(|a, b| a.time.total_cmp(&b.time).then_with(|| a.name.cmp(&b.name)));
+    events.dedup();
+    events
+}
@@ -0,0 +1,78 @@
+//! Mirrored clips, with the curves of each side moved to the other side,
+//! see [`FbxMirroredClip`].
+//!
+//! [`FbxMirroredClip`]: crate::FbxMirroredClip
+
+use bevy::{
+    prelude::{Name, Quat, Vec3},
+    utils::HashMap,
+};
+
+use super::{FbxAnimationClip, FbxEntityPath, FbxKeyframes};
+use crate::{FbxMirrorAxis, FbxMirroredClip};
+
+/// `translation` mirrored across the plane perpendicular to `axis`.
+fn mirror_translation(translation: Vec3, axis: FbxMirrorAxis) -> Vec3 {
+    let mut translation = translation;
+    match axis {
+        FbxMirrorAxis::X => translation.x = -translation.x,
+        FbxMirrorAxis::Y => translation.y = -translation.y,
+        FbxMirrorAxis::Z => translation.z = -translation.z,
+    }
+    translation
+}
+
+/// `rotation` mirrored across the plane perpendicular to `axis`: rotations
+/// around `axis` are kept, rotations around the two other axes reversed.
+fn mirror_rotation(rotation: Quat, axis: FbxMirrorAxis) -> Quat {
+    let [x, y, z, w] = rotation.to_array();
+    match axis {
+        FbxMirrorAxis::X => Quat::from_xyzw(x, -y, -z, w),
+        FbxMirrorAxis::Y => Quat::from_xyzw(-x, y, -z, w),
+        FbxMirrorAxis::Z => Quat::from_xyzw(-x, -y, z, w),
+    }
+}
+
+/// The mirrored version of `clip`, according to `mirror`.
+pub(crate) fn mirrored_clip(clip: &FbxAnimationClip, mirror: &FbxMirroredClip) -> FbxAnimationClip {
+    let mirror_path = |path: &FbxEntityPath| FbxEntityPath {
+        parts: (path.parts.iter())
+            .map(|part| match mirror.mirrored_name(part) {
+                Some(mirrored) => Name::new(mirrored),
+                None => part.clone(),
+            })
+            .collect(),
+    };
+    let mut curves = HashMap::default();
+    for (path, path_curves) in &clip.curves {
+        let mut path_curves = path_curves.clone();
+        for curve in &mut path_curves {
+            match &mut curve.keyframes {
+                FbxKeyframes::Translation(translations) => {
+                    let mirror = |t: &mut Vec3| *t = mirror_translation(*t, mirror.axis);
+                    translations.iter_mut().for_each(mirror);
+                }
+                FbxKeyframes::Rotation(rotations) => {
+                    let mirror = |r: &mut Quat| *r = mirror_rotation(*r, mirror.axis);
+                    rotations.iter_mut().for_each(mirror);
+                }
+                FbxKeyframes::Scale(_) => {}
+            }
+        }
+        curves.insert(mirror_path(path), path_curves);
+    }
+    let mut events = clip.events.clone();
+    for event in &mut events {
+        if let Some(mirrored) = mirror.mirrored_name(&event.name) {
+            event.name = mirrored;
+        }
+    }
+    FbxAnimationClip {
+        name: Some(mirror.clip_name()),
+        frame_rate: clip.frame_rate,
+        duration: clip.duration,
+        curves,
+        events,
+        additive: clip.additive,
+    }
+}
@@ -0,0 +1,140 @@
+//! FBX animation stacks ("takes") imported as [`FbxAnimationClip`]s.
+//!
+//! FBX animations are curves over the properties of the nodes,
+//! since FBX transforms do not map directly to bevy's [`Transform`],
+//! we sample the curves at the authored frame rate and compute
+//! the resulting bevy [`Transform`] of each animated node at each frame.
+//!
+//! [`Transform`]: bevy::prelude::Transform
+
+use std::cmp::Reverse;
+
+use bevy::{
+    prelude::{debug, Name, Quat, Vec3},
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+
+pub(crate) mod additive;
+pub(crate) mod bake;
+pub(crate) mod blend;
+pub(crate) mod constraint;
+pub(crate) mod curve;
+pub(crate) mod event;
+pub(crate) mod mirror;
+pub(crate) mod player;
+pub(crate) mod pose;
+pub(crate) mod reduce;
+pub(crate) mod stored_pose;
+pub(crate) mod take;
+
+use crate::{FbxClipEvent, FbxScene};
+
+/// Number of FBX time units ("KTime") in a second.
+pub(crate) const KTIME_PER_SECOND: i64 = 46_186_158_000;
+
+/// Convert a FBX KTime into seconds.
+pub(crate) fn ktime_to_seconds(ktime: i64) -> f64 {
+    ktime as f64 / KTIME_PER_SECOND as f64
+}
+
+/// Path to an entity, relative to the FBX scene root entity,
+/// made of the [`Name`]s of all the entities on the way.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct FbxEntityPath {
+    pub parts: Vec<Name>,
+}
+
+/// The values of a [`FbxCurve`] at each keyframe.
+#[derive(Clone, Debug)]
+pub enum FbxKeyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+/// An animated property of an entity.
+#[derive(Clone, Debug)]
+pub struct FbxCurve {
+    /// Timestamp of each keyframe, in seconds.
+    pub keyframe_timestamps: Vec<f32>,
+    pub keyframes: FbxKeyframes,
+}
+
+/// An animation imported from an FBX animation stack (also called "take").
+///
+/// This mirrors bevy's `AnimationClip`, the curves target entities by their
+/// [`FbxEntityPath`] relative to the scene root.
+#[derive(Clone, Debug, Default, TypeUuid)]
+#[uuid = "4a3bd5e6-b49b-4d43-a4ac-6b2f3ff0a512"]
+pub struct FbxAnimationClip {
+    pub name: Option<String>,
+    /// The frame rate the animation was authored at, in frames per second.
+    ///
+    /// This is the FBX scene's `TimeMode`, the curves are sampled at this rate
+    /// unless [`FbxLoaderSettings::sample_rate`] is set.
+    ///
+    /// [`FbxLoaderSettings::sample_rate`]: crate::FbxLoaderSettings::sample_rate
+    pub frame_rate: f64,
+    /// Duration of the clip, in seconds.
+    pub duration: f32,
+    pub curves: HashMap<FbxEntityPath, Vec<FbxCurve>>,
+    /// The events of the clip, by time, see
+    /// [`FbxLoaderSettings::event_pattern`].
+    ///
+    /// [`FbxLoaderSettings::event_pattern`]: crate::FbxLoaderSettings::event_pattern
+    pub events: Vec<FbxClipEvent>,
+    /// Whether the keyframes are offsets from a reference pose, to layer
+    /// on top of another animation, see [`FbxAdditiveClip`].
+    ///
+    /// [`FbxAdditiveClip`]: crate::FbxAdditiveClip
+    pub additive: bool,
+}
+impl FbxAnimationClip {
+    /// Add a [`FbxCurve`] to the entity at `path`.
+    pub fn add_curve_to_path(&mut self, path: FbxEntityPath, curve: FbxCurve) {
+        if let Some(&last) = curve.keyframe_timestamps.last() {
+            self.duration = self.duration.max(last);
+        }
+        self.curves.entry(path).or_default().push(curve);
+    }
+    /// Number of frames in this clip at its authored frame rate.
+    pub fn frame_count(&self) -> usize {
+        (self.duration as f64 * self.frame_rate).round() as usize + 1
+    }
+    /// A copy of this clip targeting the nodes of `skeleton`.
+    ///
+    /// Use this to play clips from animation-only FBX files (such as
+    /// `run.fbx` or `idle.fbx`) on a character loaded from another file
+    /// (such as `hero.fbx`), when the hierarchy above the joints differs.
+    ///
+    /// Each path is matched to the path of `skeleton` ending with the same
+    /// node name that shares the most parent names with it.
+    /// Curves targeting nodes that are not in `skeleton` are dropped.
+    pub fn retarget(&self, skeleton: &FbxScene) -> FbxAnimationClip {
+        let targets: Vec<FbxEntityPath> = skeleton.entity_paths().into_values().collect();
+        let shared_suffix = |a: &FbxEntityPath, b: &FbxEntityPath| {
+            let pairs = a.parts.iter().rev().zip(b.parts.iter().rev());
+            pairs.take_while(|(a, b)| a == b).count()
+        };
+        let mut clip = FbxAnimationClip {
+            curves: HashMap::default(),
+            ..self.clone()
+        };
+        for (path, curves) in &self.curves {
+            let target = targets
+                .iter()
+                .map(|target| (shared_suffix(path, target), target))
+                .filter(|(shared, _)| *shared > 0)
+                .max_by_key(|(shared, target)| (*shared, Reverse(target.parts.len())));
+            match target {
+                Some((_, target)) => {
+                    let target_curves = clip.curves.entry(target.clone()).or_default();
+                    target_curves.extend(curves.iter().cloned());
+                }
+                None => debug!("Dropping curves of {path:?}: no such node in skeleton"),
+            }
+        }
+        clip
+    }
+}
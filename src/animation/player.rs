@@ -0,0 +1,215 @@
+//! A minimal player for [`FbxAnimationClip`]s, similar to bevy's `AnimationPlayer`.
+
+use bevy::{
+    prelude::{
+        Assets, Children, Component, Entity, EventWriter, Handle, Name, Query, ReflectComponent,
+        Res, Time, Transform,
+    },
+    reflect::Reflect,
+};
+
+use super::{
+    event::{FbxAnimationEvent, FbxClipEvent},
+    FbxAnimationClip, FbxCurve, FbxEntityPath, FbxKeyframes,
+};
+
+/// Animation controls, plays a [`FbxAnimationClip`] on the entity's descendants.
+///
+/// The [`FbxEntityPath`]s of the clip are relative to the entity
+/// holding this component, which is the FBX scene root
+/// when spawned by the `FbxLoader`.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct FbxAnimationPlayer {
+    paused: bool,
+    repeat: bool,
+    speed: f32,
+    elapsed: f32,
+    animation_clip: Handle<FbxAnimationClip>,
+}
+impl Default for FbxAnimationPlayer {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            repeat: false,
+            speed: 1.0,
+            elapsed: 0.0,
+            animation_clip: Default::default(),
+        }
+    }
+}
+impl FbxAnimationPlayer {
+    /// Start playing an animation, resetting state of the player.
+    pub fn play(&mut self, handle: Handle<FbxAnimationClip>) -> &mut Self {
+        *self = Self {
+            animation_clip: handle,
+            ..Default::default()
+        };
+        self
+    }
+    /// Set the animation to repeat.
+    pub fn repeat(&mut self) -> &mut Self {
+        self.repeat = true;
+        self
+    }
+    /// Stop the animation from repeating.
+    pub fn stop_repeating(&mut self) -> &mut Self {
+        self.repeat = false;
+        self
+    }
+    /// Pause the animation.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    /// Unpause the animation.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    /// Is the animation paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// Speed of the animation playback.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+    /// Set the speed of the animation playback.
+    pub fn set_speed(&mut self, speed: f32) -> &mut Self {
+        self.speed = speed;
+        self
+    }
+    /// Time elapsed playing the animation, in seconds.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+    /// Seek to a specific time in the animation.
+    pub fn set_elapsed(&mut self, elapsed: f32) -> &mut Self {
+        self.elapsed = elapsed;
+        self
+    }
+    /// The clip being played.
+    pub fn clip(&self) -> &Handle<FbxAnimationClip> {
+        &self.animation_clip
+    }
+    /// The current time in `clip`, in seconds.
+    ///
+    /// This is [`Self::elapsed`], wrapped around the clip duration when repeating.
+    pub fn clip_time(&self, clip: &FbxAnimationClip) -> f32 {
+        if self.repeat && clip.duration > 0.0 {
+            self.elapsed.rem_euclid(clip.duration)
+        } else {
+            self.elapsed
+        }
+    }
+}
+
+/// The entity at `path`, starting from the children of `root`.
+fn find_entity(
+    root: Entity,
+    path: &FbxEntityPath,
+    children: &Query<&Children>,
+    names: &Query<&Name>,
+) -> Option<Entity> {
+    let mut current = root;
+    for part in &path.parts {
+        current = children
+            .get(current)
+            .ok()?
+            .iter()
+            .copied()
+            .find(|child| names.get(*child).is_ok_and(|name| name == part))?;
+    }
+    Some(current)
+}
+
+/// Apply the value of `curve` at `time` to `transform`.
+pub(crate) fn apply_curve(curve: &FbxCurve, time: f32, transform: &mut Transform) {
+    let timestamps = &curve.keyframe_timestamps;
+    if timestamps.is_empty() {
+        return;
+    }
+    let next = timestamps.partition_point(|t| *t <= time);
+    let (current, next, lerp) = match next {
+        0 => (0, 0, 0.0),
+        next if next == timestamps.len() => (next - 1, next - 1, 0.0),
+        next => {
+            let (start, end) = (timestamps[next - 1], timestamps[next]);
+            (next - 1, next, (time - start) / (end - start))
+        }
+    };
+    match &curve.keyframes {
+        FbxKeyframes::Translation(keyframes) => {
+            transform.translation = keyframes[current].lerp(keyframes[next], lerp);
+        }
+        FbxKeyframes::Rotation(keyframes) => {
+            transform.rotation = keyframes[current].slerp(keyframes[next], lerp);
+        }
+        FbxKeyframes::Scale(keyframes) => {
+            transform.scale = keyframes[current].lerp(keyframes[next], lerp);
+        }
+    }
+}
+
+/// The events of `clip` reached when playing it from `from` to `to`,
+/// wrapping around the end of the clip if `to` is before `from`.
+fn reached_events(
+    clip: &FbxAnimationClip,
+    from: f32,
+    to: f32,
+) -> impl Iterator<Item = &FbxClipEvent> {
+    clip.events.iter().filter(move |event| {
+        if from <= to {
+            from <= event.time && event.time < to
+        } else {
+            from <= event.time || event.time < to
+        }
+    })
+}
+
+/// System that advances the [`FbxAnimationPlayer`]s, updates
+/// the [`Transform`]s of the animated entities and sends the
+/// [`FbxAnimationEvent`]s of the clips.
+pub fn animation_player(
+    time: Res<Time>,
+    clips: Res<Assets<FbxAnimationClip>>,
+    mut players: Query<(Entity, &mut FbxAnimationPlayer)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    mut transforms: Query<&mut Transform>,
+    mut events: EventWriter<FbxAnimationEvent>,
+) {
+    for (root, mut player) in &mut players {
+        let clip = match clips.get(&player.animation_clip) {
+            Some(clip) => clip,
+            None => continue,
+        };
+        let previous = player.clip_time(clip);
+        let delta = time.delta_seconds() * player.speed;
+        if !player.paused {
+            player.elapsed += delta;
+        }
+        let elapsed = player.clip_time(clip);
+        // Events are only sent when playing forward.
+        if !player.paused && delta > 0.0 {
+            events.send_batch(reached_events(clip, previous, elapsed).map(|event| {
+                FbxAnimationEvent {
+                    player: root,
+                    clip: player.animation_clip.clone_weak(),
+                    name: event.name.clone(),
+                    time: event.time,
+                }
+            }));
+        }
+        for (path, curves) in &clip.curves {
+            let target = match find_entity(root, path, &children, &names) {
+                Some(target) => target,
+                None => continue,
+            };
+            if let Ok(mut transform) = transforms.get_mut(target) {
+                for curve in curves {
+                    apply_curve(curve, elapsed, &mut transform);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! The transforms of the nodes of an FBX scene at a given time.
+
+use bevy::{
+    prelude::{Mat4, Transform},
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use crate::{
+    data::FbxObject,
+    fbx_transform::{FbxNodeTransformInfo, FbxTransform},
+};
+
+struct PoseNode {
+    parent: Option<ObjectId>,
+    info: FbxNodeTransformInfo,
+    transform: FbxTransform,
+    /// The global transform relative to the parent's, once overwritten.
+    relative_override: Option<Mat4>,
+}
+
+/// The FBX global transforms of the nodes of a scene.
+///
+/// Global transforms can be overwritten, for example by constraints,
+/// descendants of the modified node follow it. An overwritten node
+/// stays rigidly attached to its parent if the parent is modified later.
+pub(crate) struct Pose<'a> {
+    hierarchy: &'a HashMap<ObjectId, FbxObject>,
+    nodes: HashMap<ObjectId, PoseNode>,
+}
+impl<'a> Pose<'a> {
+    pub(crate) fn new(hierarchy: &'a HashMap<ObjectId, FbxObject>) -> Self {
+        Pose {
+            hierarchy,
+            nodes: HashMap::default(),
+        }
+    }
+    /// Add `node` to the pose, its parent must already be in the pose.
+    pub(crate) fn insert(
+        &mut self,
+        node: ObjectId,
+        parent: Option<ObjectId>,
+        info: FbxNodeTransformInfo,
+    ) {
+        let parent_transform = parent.and_then(|parent| Some(self.nodes.get(&parent)?.transform));
+        let transform = FbxTransform::from_fbxtrans(info.clone(), parent_transform);
+        let pose_node = PoseNode {
+            parent,
+            info,
+            transform,
+            relative_override: None,
+        };
+        self.nodes.insert(node, pose_node);
+    }
+    /// Add `node` to the pose as a root, placed under its `parent` in the file,
+    /// see [`FbxLoaderSettings::frozen_nodes`]. `parent` must already be in the pose.
+    ///
+    /// [`FbxLoaderSettings::frozen_nodes`]: crate::FbxLoaderSettings::frozen_nodes
+    pub(crate) fn insert_detached(
+        &mut self,
+        node: ObjectId,
+        parent: ObjectId,
+        info: FbxNodeTransformInfo,
+    ) {
+        self.insert(node, Some(parent), info);
+        if let Some(pose_node) = self.nodes.get_mut(&node) {
+            pose_node.parent = None;
+        }
+    }
+    pub(crate) fn parent(&self, node: ObjectId) -> Option<ObjectId> {
+        self.nodes.get(&node)?.parent
+    }
+    pub(crate) fn global(&self, node: ObjectId) -> Option<Mat4> {
+        Some(self.nodes.get(&node)?.transform.global)
+    }
+    /// The bevy local transform of `node`.
+    pub(crate) fn local(&self, node: ObjectId) -> Option<Transform> {
+        Some(self.local_decomposed(node)?.0)
+    }
+    /// The bevy local transform of `node`, with the shear it drops.
+    pub(crate) fn local_decomposed(&self, node: ObjectId) -> Option<(Transform, f32)> {
+        let pose_node = self.nodes.get(&node)?;
+        let parent = pose_node.parent.and_then(|parent| self.global(parent));
+        Some(pose_node.transform.as_local_decomposed(parent))
+    }
+    /// Overwrite the global transform of `node`, updating its descendants.
+    pub(crate) fn set_global(&mut self, node: ObjectId, global: Mat4) {
+        let parent = self.parent(node).and_then(|parent| self.global(parent));
+        if let Some(pose_node) = self.nodes.get_mut(&node) {
+            pose_node.transform.global = global;
+            pose_node.relative_override = Some(match parent {
+                Some(parent) => parent.inverse() * global,
+                None => global,
+            });
+            self.update_children(node);
+        }
+    }
+    fn update_children(&mut self, node: ObjectId) {
+        let parent_transform = match self.nodes.get(&node) {
+            Some(pose_node) => pose_node.transform,
+            None => return,
+        };
+        let children = match self.hierarchy.get(&node) {
+            Some(object) => &object.children,
+            None => return,
+        };
+        for child in children {
+            if let Some(pose_node) = self.nodes.get_mut(child) {
+                match pose_node.relative_override {
+                    Some(relative) => {
+                        pose_node.transform.global = parent_transform.global * relative
+                    }
+                    None => {
+                        let info = pose_node.info.clone();
+                        pose_node.transform =
+                            FbxTransform::from_fbxtrans(info, Some(parent_transform));
+                    }
+                }
+                self.update_children(*child);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Keyframe reduction of sampled curves, see [`FbxLoaderSettings::tolerance`].
+//!
+//! [`FbxLoaderSettings::tolerance`]: crate::FbxLoaderSettings::tolerance
+
+use super::{FbxCurve, FbxKeyframes};
+
+/// Remove the keyframes of `curve` that interpolating between the kept
+/// keyframes around them reproduces within `tolerance`.
+///
+/// The first and last keyframes are always kept.
+pub(crate) fn reduce(curve: &mut FbxCurve, tolerance: f32) {
+    let times = &curve.keyframe_timestamps;
+    // The error at keyframe `i`, when interpolating between `from` and `to`.
+    let error = |from: usize, to: usize, i: usize| {
+        let ratio = (times[i] - times[from]) / (times[to] - times[from]);
+        match &curve.keyframes {
+            FbxKeyframes::Translation(values) | FbxKeyframes::Scale(values) => {
+                values[from].lerp(values[to], ratio).distance(values[i])
+            }
+            FbxKeyframes::Rotation(values) => values[from]
+                .slerp(values[to], ratio)
+                .angle_between(values[i]),
+        }
+    };
+    let len = times.len();
+    let mut kept = Vec::with_capacity(len);
+    if len > 0 {
+        kept.push(0);
+    }
+    let mut last_kept = 0;
+    for i in 1..len.saturating_sub(1) {
+        let removable = (last_kept + 1..=i).all(|k| error(last_kept, i + 1, k) <= tolerance);
+        if !removable {
+            kept.push(i);
+            last_kept = i;
+        }
+    }
+    if len > 1 {
+        kept.push(len - 1);
+    }
+    if kept.len() == len {
+        return;
+    }
+    curve.keyframe_timestamps = kept.iter().map(|i| curve.keyframe_timestamps[*i]).collect();
+    match &mut curve.keyframes {
+        FbxKeyframes::Translation(values) | FbxKeyframes::Scale(values) => {
+            *values = kept.iter().map(|i| values[*i]).collect();
+        }
+        FbxKeyframes::Rotation(values) => {
+            *values = kept.iter().map(|i| values[*i]).collect();
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! FBX `Pose` objects, snapshots of the global transforms of some nodes.
+//!
+//! Bind poses (the transforms of the joints when a mesh was bound to them)
+//! are used for skinning, other poses are stored character poses
+//! that artists saved in their DCC tool.
+
+use bevy::{math::DVec3, prelude::Mat4, utils::HashMap};
+use fbxcel_dom::v7400::{
+    object::{ObjectHandle, ObjectId},
+    Document,
+};
+
+/// An FBX `Pose`.
+#[derive(Clone, Debug)]
+pub(crate) struct StoredPose {
+    pub(crate) id: ObjectId,
+    pub(crate) name: Option<String>,
+    /// Whether this is a bind pose.
+    pub(crate) bind: bool,
+    /// The FBX global transform of each node of the pose.
+    pub(crate) globals: HashMap<ObjectId, Mat4>,
+}
+impl StoredPose {
+    /// `ids` maps raw ids to the objects of the document.
+    fn from_object(object: ObjectHandle, ids: &HashMap<i64, ObjectId>) -> Option<Self> {
+        let node = object.node();
+        let kind = node.first_child_by_name("Type")?.attributes().first()?;
+        let bind = kind.get_string()? == "BindPose";
+        let globals = node
+            .children_by_name("PoseNode")
+            .filter_map(|pose_node| {
+                let attribute = |name| pose_node.first_child_by_name(name)?.attributes().first();
+                let id = *ids.get(&attribute("Node")?.get_i64()?)?;
+                let matrix = attribute("Matrix")?.get_arr_f64()?;
+                let matrix: [f64; 16] = matrix.try_into().ok()?;
+                let matrix = Mat4::from_cols_array(&matrix.map(|v| v as f32));
+                Some((id, matrix))
+            })
+            .collect();
+        Some(StoredPose {
+            id: object.object_id(),
+            name: object.name().filter(|n| !n.is_empty()).map(Into::into),
+            bind,
+            globals,
+        })
+    }
+    /// Move the pose by `-origin`, see [`FbxLoaderSettings::recenter`].
+    ///
+    /// [`FbxLoaderSettings::recenter`]: crate::FbxLoaderSettings::recenter
+    pub(crate) fn recenter(&mut self, origin: DVec3) {
+        if origin == DVec3::ZERO {
+            return;
+        }
+        for global in self.globals.values_mut() {
+            let mut moved = global.as_dmat4();
+            moved.w_axis -= origin.extend(0.0);
+            *global = moved.as_mat4();
+        }
+    }
+    /// A single pose holding the nodes of all the bind poses of `doc`.
+    ///
+    /// There is usually one bind pose per skinned mesh, they share
+    /// the joints bound to several meshes.
+    pub(crate) fn merge_bind_poses(poses: &[Self]) -> Option<Self> {
+        let mut bind_poses = poses.iter().filter(|pose| pose.bind);
+        let mut merged = bind_poses.next()?.clone();
+        for pose in bind_poses {
+            for (node, global) in &pose.globals {
+                merged.globals.entry(*node).or_insert(*global);
+            }
+        }
+        Some(merged)
+    }
+    pub(crate) fn all(doc: &Document) -> Vec<Self> {
+        let ids = doc
+            .objects()
+            .map(|obj| (obj.object_id().raw(), obj.object_id()))
+            .collect();
+        doc.objects()
+            .filter(|obj| obj.node().name() == "Pose")
+            .filter_map(|obj| StoredPose::from_object(obj, &ids))
+            .collect()
+    }
+}
@@ -0,0 +1,92 @@
+//! Metadata of the FBX takes, see [`FbxScene::take_metadata`].
+//!
+//! [`FbxScene::take_metadata`]: crate::FbxScene::take_metadata
+
+use fbxcel_dom::{fbxcel::tree::v7400::NodeHandle, v7400::object::ObjectHandle};
+
+use super::ktime_to_seconds;
+use crate::utils::fbx_extend::ObjectPropertiesExt;
+
+/// Metadata of the take (animation stack) an [`FbxAnimationClip`] comes from,
+/// for tools to configure playback and show the animator's notes.
+///
+/// [`FbxAnimationClip`]: crate::FbxAnimationClip
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FbxTakeMetadata {
+    /// The part of the timeline played by the take, in seconds.
+    ///
+    /// For clips sliced with [`FbxLoaderSettings::takes`], the slice.
+    ///
+    /// [`FbxLoaderSettings::takes`]: crate::FbxLoaderSettings::takes
+    pub local_span: Option<(f32, f32)>,
+    /// The part of the timeline the clip was sampled from, in seconds.
+    ///
+    /// Clip times start at zero with
+    /// [`FbxLoaderSettings::normalize_clip_start`], time zero of the clip
+    /// then being the start of this span.
+    ///
+    /// [`FbxLoaderSettings::normalize_clip_start`]: crate::FbxLoaderSettings::normalize_clip_start
+    pub original_span: Option<(f32, f32)>,
+    /// The span of the source material the take was recorded from or
+    /// edited out of, in seconds.
+    pub reference_span: Option<(f32, f32)>,
+    /// The `Description` of the take, or its `Comments` in the `Takes` section.
+    pub comments: Option<String>,
+    /// Whether the take should loop, from a `Loop` or `Looping` custom
+    /// property of the take. `None` if the file doesn't tell.
+    pub looping: Option<bool>,
+}
+impl FbxTakeMetadata {
+    /// The metadata of the `AnimationStack` `stack`, completed with
+    /// its entry in the legacy `Takes` section of the file.
+    pub(crate) fn from_stack(stack: ObjectHandle) -> Self {
+        let p = stack.properties_by_native_typename("FbxAnimStack");
+        let seconds = |start, stop| {
+            let span = p.get::<i64>(start).zip(p.get::<i64>(stop));
+            span.filter(|(start, stop)| start < stop)
+                .map(|(start, stop)| (ktime_to_seconds(start), ktime_to_seconds(stop)))
+                .map(|(start, stop)| (start as f32, stop as f32))
+        };
+        let description = p.get_property("Description").and_then(|property| {
+            let value = property.value_part().first()?.get_string()?;
+            Some(value.to_owned()).filter(|s| !s.is_empty())
+        });
+        let take = stack.name().and_then(|name| take_node(stack, name));
+        let take_span = |name| {
+            let [start, stop, ..] = take?.first_child_by_name(name)?.attributes() else {
+                return None;
+            };
+            let (start, stop) = (start.get_i64()?, stop.get_i64()?);
+            let span = (
+                ktime_to_seconds(start) as f32,
+                ktime_to_seconds(stop) as f32,
+            );
+            Some(span).filter(|(start, stop)| start < stop)
+        };
+        let comments = take.and_then(|take| {
+            let value = take.first_child_by_name("Comments")?.attributes().first()?;
+            Some(value.get_string()?.to_owned()).filter(|s| !s.is_empty())
+        });
+        FbxTakeMetadata {
+            local_span: seconds("LocalStart", "LocalStop").or_else(|| take_span("LocalTime")),
+            reference_span: seconds("ReferenceStart", "ReferenceStop")
+                .or_else(|| take_span("ReferenceTime")),
+            original_span: None,
+            comments: description.or(comments),
+            looping: p.get::<bool>("Loop").or_else(|| p.get::<bool>("Looping")),
+        }
+    }
+}
+
+/// The `Take` named `name` in the `Takes` section of the document of `stack`.
+fn take_node<'a>(stack: ObjectHandle<'a>, name: &str) -> Option<NodeHandle<'a>> {
+    let takes = stack
+        .document()
+        .tree()
+        .root()
+        .first_child_by_name("Takes")?;
+    takes.children_by_name("Take").find(|take| {
+        let take_name = take.attributes().first().and_then(|a| a.get_string());
+        take_name == Some(name)
+    })
+}
@@ -0,0 +1,556 @@
+//! Conversion of a spawned FBX [`Scene`] to a glTF 2.0 document.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use bevy::{
+    pbr::{AlphaMode, StandardMaterial},
+    prelude::{
+        warn, Assets, Children, Entity, Handle, Image, Mesh, Name, Parent, Quat, Transform, Vec3,
+        World,
+    },
+    render::{
+        mesh::{
+            skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+            Indices, VertexAttributeValues,
+        },
+        render_resource::{PrimitiveTopology, TextureFormat},
+    },
+    scene::Scene,
+    utils::HashMap,
+};
+use bevy_mod_fbx::{
+    FbxAnimationClip, FbxEntityPath, FbxExtras, FbxKeyframes, FbxPropertyValue, FbxScene,
+};
+use serde_json::{json, Map, Value};
+
+use crate::png;
+
+const UNSIGNED_SHORT: u32 = 5123;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// The assets referenced by the spawned scene.
+pub struct Sources<'a> {
+    pub meshes: &'a Assets<Mesh>,
+    pub materials: &'a Assets<StandardMaterial>,
+    pub images: &'a Assets<Image>,
+    pub clips: &'a Assets<FbxAnimationClip>,
+    pub inverse_bindposes: &'a Assets<SkinnedMeshInverseBindposes>,
+}
+
+/// A glTF document being built, with a single binary buffer.
+#[derive(Default)]
+pub struct Gltf {
+    nodes: Vec<Value>,
+    meshes: Vec<Value>,
+    materials: Vec<Value>,
+    textures: Vec<Value>,
+    images: Vec<Value>,
+    accessors: Vec<Value>,
+    buffer_views: Vec<Value>,
+    animations: Vec<Value>,
+    skins: Vec<Value>,
+    scene_nodes: Vec<usize>,
+    extensions_used: Vec<&'static str>,
+    /// The content of the binary buffer.
+    pub bin: Vec<u8>,
+
+    mesh_indices: HashMap<(Handle<Mesh>, Option<Handle<StandardMaterial>>), usize>,
+    /// The node of each entity, and the skinned mesh entities and their node.
+    entity_nodes: HashMap<Entity, usize>,
+    skinned: Vec<(usize, Entity)>,
+    material_indices: HashMap<Handle<StandardMaterial>, usize>,
+    texture_indices: HashMap<Handle<Image>, Option<usize>>,
+    /// Accessors by hash of their content, to share identical data,
+    /// such as the vertices of the meshes of each material.
+    accessor_cache: HashMap<u64, Vec<(usize, std::ops::Range<usize>)>>,
+}
+
+impl Gltf {
+    /// Convert `scene`, the [`Scene`] spawned for `fbx_scene`.
+    pub fn from_scene(scene: &Scene, fbx_scene: &FbxScene, sources: &Sources) -> Gltf {
+        let mut gltf = Gltf::default();
+        let world = &scene.world;
+        let mut paths = HashMap::default();
+        let roots: Vec<Entity> = world
+            .iter_entities()
+            .filter(|entity| !entity.contains::<Parent>())
+            .map(|entity| entity.id())
+            .collect();
+        for root in roots {
+            let node = gltf.add_node(world, root, None, &mut paths, sources);
+            gltf.scene_nodes.push(node);
+        }
+        for (node, entity) in std::mem::take(&mut gltf.skinned) {
+            let skinned = world.get::<SkinnedMesh>(entity).unwrap();
+            if let Some(skin) = gltf.add_skin(skinned, sources) {
+                gltf.nodes[node]["skin"] = skin.into();
+            }
+        }
+        for clip in &fbx_scene.animations {
+            if let Some(clip) = sources.clips.get(clip) {
+                gltf.add_animation(clip, &paths);
+            }
+        }
+        gltf
+    }
+
+    /// Add `entity` and its descendants, `path` is its [`FbxEntityPath`],
+    /// `None` for the scene root entities.
+    fn add_node(
+        &mut self,
+        world: &World,
+        entity: Entity,
+        path: Option<FbxEntityPath>,
+        paths: &mut HashMap<FbxEntityPath, usize>,
+        sources: &Sources,
+    ) -> usize {
+        let entity = world.entity(entity);
+        let index = self.nodes.len();
+        self.nodes.push(json!({}));
+        self.entity_nodes.insert(entity.id(), index);
+        if let Some(path) = &path {
+            paths.entry(path.clone()).or_insert(index);
+        }
+
+        let mut node = json!({});
+        if let Some(name) = entity.get::<Name>() {
+            node["name"] = name.as_str().into();
+        }
+        if let Some(transform) = entity.get::<Transform>() {
+            if transform.translation != Vec3::ZERO {
+                node["translation"] = json!(transform.translation.to_array());
+            }
+            if transform.rotation != Quat::IDENTITY {
+                node["rotation"] = json!(transform.rotation.to_array());
+            }
+            if transform.scale != Vec3::ONE {
+                node["scale"] = json!(transform.scale.to_array());
+            }
+        }
+        if let Some(mesh) = entity.get::<Handle<Mesh>>() {
+            let material = entity.get::<Handle<StandardMaterial>>();
+            if let Some(mesh) = self.add_mesh(mesh, material, sources) {
+                node["mesh"] = mesh.into();
+            }
+            if entity.contains::<SkinnedMesh>() {
+                self.skinned.push((index, entity.id()));
+            }
+        }
+        if let Some(extras) = entity.get::<FbxExtras>() {
+            node["extras"] = properties(extras);
+        }
+        let children: Vec<usize> = entity
+            .get::<Children>()
+            .map(|children| children.to_vec())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| {
+                let name = world.entity(child).get::<Name>().cloned();
+                let child_path = match (&path, name) {
+                    (Some(path), Some(name)) => {
+                        let mut path = path.clone();
+                        path.parts.push(name);
+                        Some(path)
+                    }
+                    (None, Some(name)) => Some(FbxEntityPath { parts: vec![name] }),
+                    (_, None) => None,
+                };
+                self.add_node(world, child, child_path, paths, sources)
+            })
+            .collect();
+        if !children.is_empty() {
+            node["children"] = children.into();
+        }
+        self.nodes[index] = node;
+        index
+    }
+
+    fn add_mesh(
+        &mut self,
+        handle: &Handle<Mesh>,
+        material: Option<&Handle<StandardMaterial>>,
+        sources: &Sources,
+    ) -> Option<usize> {
+        let key = (handle.clone_weak(), material.map(Handle::clone_weak));
+        if let Some(index) = self.mesh_indices.get(&key) {
+            return Some(*index);
+        }
+        let mesh = sources.meshes.get(handle)?;
+        let mode: u32 = match mesh.primitive_topology() {
+            PrimitiveTopology::PointList => 0,
+            PrimitiveTopology::LineList => 1,
+            PrimitiveTopology::LineStrip => 3,
+            PrimitiveTopology::TriangleList => 4,
+            PrimitiveTopology::TriangleStrip => 5,
+        };
+        let mut attributes = Map::new();
+        let named_attributes = [
+            ("POSITION", Mesh::ATTRIBUTE_POSITION),
+            ("NORMAL", Mesh::ATTRIBUTE_NORMAL),
+            ("TEXCOORD_0", Mesh::ATTRIBUTE_UV_0),
+            ("TANGENT", Mesh::ATTRIBUTE_TANGENT),
+            ("COLOR_0", Mesh::ATTRIBUTE_COLOR),
+            ("JOINTS_0", Mesh::ATTRIBUTE_JOINT_INDEX),
+            ("WEIGHTS_0", Mesh::ATTRIBUTE_JOINT_WEIGHT),
+        ];
+        for (name, attribute) in named_attributes {
+            let Some(values) = mesh.attribute(attribute.id) else {
+                continue;
+            };
+            let (component_type, kind) = match values {
+                VertexAttributeValues::Float32x2(_) => (FLOAT, "VEC2"),
+                VertexAttributeValues::Float32x3(_) => (FLOAT, "VEC3"),
+                VertexAttributeValues::Float32x4(_) => (FLOAT, "VEC4"),
+                VertexAttributeValues::Uint16x4(_) => (UNSIGNED_SHORT, "VEC4"),
+                _ => {
+                    warn!("Skipping unsupported {name} attribute format");
+                    continue;
+                }
+            };
+            let bounds = match values {
+                VertexAttributeValues::Float32x3(positions) if name == "POSITION" => {
+                    let (min, max) = positions.iter().fold(
+                        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                        |(min, max), p| (min.min(Vec3::from(*p)), max.max(Vec3::from(*p))),
+                    );
+                    Some((min.to_array().to_vec(), max.to_array().to_vec()))
+                }
+                _ => None,
+            };
+            let accessor = self.add_accessor(
+                values.get_bytes(),
+                component_type,
+                values.len(),
+                kind,
+                bounds,
+                Some(ARRAY_BUFFER),
+            );
+            attributes.insert(name.to_owned(), accessor.into());
+        }
+        let mut primitive = json!({ "attributes": attributes, "mode": mode });
+        if let Some(indices) = mesh.indices() {
+            let indices: Vec<u32> = match indices {
+                Indices::U16(indices) => indices.iter().map(|i| u32::from(*i)).collect(),
+                Indices::U32(indices) => indices.clone(),
+            };
+            let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+            let accessor = self.add_accessor(
+                &bytes,
+                UNSIGNED_INT,
+                indices.len(),
+                "SCALAR",
+                None,
+                Some(ELEMENT_ARRAY_BUFFER),
+            );
+            primitive["indices"] = accessor.into();
+        }
+        if let Some(material) = material.and_then(|m| self.add_material(m, sources)) {
+            primitive["material"] = material.into();
+        }
+
+        let index = self.meshes.len();
+        self.meshes.push(json!({ "primitives": [primitive] }));
+        self.mesh_indices.insert(key, index);
+        Some(index)
+    }
+
+    fn add_material(
+        &mut self,
+        handle: &Handle<StandardMaterial>,
+        sources: &Sources,
+    ) -> Option<usize> {
+        if let Some(index) = self.material_indices.get(handle) {
+            return Some(*index);
+        }
+        let material = sources.materials.get(handle)?;
+        let mut texture = |texture: &Option<Handle<Image>>| {
+            let index = self.add_texture(texture.as_ref()?, sources)?;
+            Some(json!({ "index": index }))
+        };
+
+        let mut pbr = json!({
+            "baseColorFactor": material.base_color.as_linear_rgba_f32(),
+            "metallicFactor": material.metallic,
+            "roughnessFactor": material.perceptual_roughness,
+        });
+        if let Some(texture) = texture(&material.base_color_texture) {
+            pbr["baseColorTexture"] = texture;
+        }
+        if let Some(texture) = texture(&material.metallic_roughness_texture) {
+            pbr["metallicRoughnessTexture"] = texture;
+        }
+        let mut json = json!({ "pbrMetallicRoughness": pbr });
+        let emissive = material.emissive.as_linear_rgba_f32();
+        json["emissiveFactor"] = json!(emissive[..3]);
+        if let Some(texture) = texture(&material.emissive_texture) {
+            json["emissiveTexture"] = texture;
+        }
+        if let Some(texture) = texture(&material.normal_map_texture) {
+            json["normalTexture"] = texture;
+        }
+        if let Some(texture) = texture(&material.occlusion_texture) {
+            json["occlusionTexture"] = texture;
+        }
+        match material.alpha_mode {
+            AlphaMode::Opaque => {}
+            AlphaMode::Mask(cutoff) => {
+                json["alphaMode"] = "MASK".into();
+                json["alphaCutoff"] = cutoff.into();
+            }
+            _ => json["alphaMode"] = "BLEND".into(),
+        }
+        if material.double_sided || material.cull_mode.is_none() {
+            json["doubleSided"] = true.into();
+        }
+        if material.unlit {
+            if !self.extensions_used.contains(&"KHR_materials_unlit") {
+                self.extensions_used.push("KHR_materials_unlit");
+            }
+            json["extensions"] = json!({ "KHR_materials_unlit": {} });
+        }
+
+        let index = self.materials.len();
+        self.materials.push(json);
+        self.material_indices.insert(handle.clone_weak(), index);
+        Some(index)
+    }
+
+    /// Add a texture for `handle`, `None` if the image can't be converted to PNG.
+    fn add_texture(&mut self, handle: &Handle<Image>, sources: &Sources) -> Option<usize> {
+        if let Some(index) = self.texture_indices.get(handle) {
+            return *index;
+        }
+        let image = sources.images.get(handle);
+        let rgba = image.and_then(|image| match image.texture_descriptor.format {
+            TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => Some(image.clone()),
+            _ => image.convert(TextureFormat::Rgba8UnormSrgb),
+        });
+        let index = match rgba {
+            Some(image) => {
+                let size = image.texture_descriptor.size;
+                let png = png::encode(size.width, size.height, &image.data);
+                let view = self.add_buffer_view(&png, None);
+                self.images
+                    .push(json!({ "bufferView": view, "mimeType": "image/png" }));
+                self.textures
+                    .push(json!({ "source": self.images.len() - 1 }));
+                Some(self.textures.len() - 1)
+            }
+            None => {
+                warn!("Skipping texture with an unsupported format");
+                None
+            }
+        };
+        self.texture_indices.insert(handle.clone_weak(), index);
+        index
+    }
+
+    fn add_animation(&mut self, clip: &FbxAnimationClip, paths: &HashMap<FbxEntityPath, usize>) {
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+        for (path, curves) in &clip.curves {
+            let Some(node) = paths.get(path) else {
+                warn!("Skipping curves of {path:?}: no such node");
+                continue;
+            };
+            for curve in curves {
+                let timestamps = &curve.keyframe_timestamps;
+                let (Some(first), Some(last)) = (timestamps.first(), timestamps.last()) else {
+                    continue;
+                };
+                let bytes: Vec<u8> = timestamps.iter().flat_map(|t| t.to_le_bytes()).collect();
+                let bounds = Some((vec![*first], vec![*last]));
+                let input =
+                    self.add_accessor(&bytes, FLOAT, timestamps.len(), "SCALAR", bounds, None);
+                let (values, kind, property): (Vec<f32>, _, _) = match &curve.keyframes {
+                    FbxKeyframes::Translation(values) => {
+                        (flatten(values, Vec3::to_array), "VEC3", "translation")
+                    }
+                    FbxKeyframes::Rotation(values) => {
+                        (flatten(values, Quat::to_array), "VEC4", "rotation")
+                    }
+                    FbxKeyframes::Scale(values) => {
+                        (flatten(values, Vec3::to_array), "VEC3", "scale")
+                    }
+                };
+                let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+                let output = self.add_accessor(&bytes, FLOAT, timestamps.len(), kind, None, None);
+                channels.push(json!({
+                    "sampler": samplers.len(),
+                    "target": { "node": node, "path": property },
+                }));
+                samplers.push(json!({
+                    "input": input,
+                    "output": output,
+                    "interpolation": "LINEAR",
+                }));
+            }
+        }
+        if channels.is_empty() {
+            return;
+        }
+        let mut animation = json!({ "channels": channels, "samplers": samplers });
+        if let Some(name) = &clip.name {
+            animation["name"] = name.as_str().into();
+        }
+        self.animations.push(animation);
+    }
+
+    /// Add the skin of a skinned mesh entity, `None` if some of its joints
+    /// or its inverse bind poses are missing.
+    ///
+    /// Meshes bound at rest to their node only, whose instance has no copy
+    /// of the joints, are left unskinned: glTF joints must be unique, and
+    /// the node places them the same.
+    fn add_skin(&mut self, skinned: &SkinnedMesh, sources: &Sources) -> Option<usize> {
+        if skinned
+            .joints
+            .iter()
+            .skip(1)
+            .all(|j| *j == skinned.joints[0])
+        {
+            return None;
+        }
+        let joints: Option<Vec<usize>> = (skinned.joints.iter())
+            .map(|joint| self.entity_nodes.get(joint).copied())
+            .collect();
+        let inverse_bindposes = sources.inverse_bindposes.get(&skinned.inverse_bindposes);
+        let (Some(joints), Some(inverse_bindposes)) = (joints, inverse_bindposes) else {
+            warn!("Skipping skin with missing joints or inverse bind poses");
+            return None;
+        };
+        let matrices: Vec<u8> = (inverse_bindposes.iter())
+            .take(joints.len())
+            .flat_map(|matrix| matrix.to_cols_array())
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let count = matrices.len() / 64;
+        let inverse_bind_matrices = self.add_accessor(&matrices, FLOAT, count, "MAT4", None, None);
+        self.skins.push(json!({
+            "joints": joints,
+            "inverseBindMatrices": inverse_bind_matrices,
+        }));
+        Some(self.skins.len() - 1)
+    }
+
+    fn add_buffer_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = target.into();
+        }
+        self.bin.extend_from_slice(bytes);
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    fn add_accessor(
+        &mut self,
+        bytes: &[u8],
+        component_type: u32,
+        count: usize,
+        kind: &str,
+        bounds: Option<(Vec<f32>, Vec<f32>)>,
+        target: Option<u32>,
+    ) -> usize {
+        let mut hasher = DefaultHasher::new();
+        (bytes, component_type, kind).hash(&mut hasher);
+        let hash = hasher.finish();
+        let cached = self.accessor_cache.get(&hash).and_then(|accessors| {
+            let same = |(_, range): &&(usize, std::ops::Range<usize>)| {
+                self.bin.get(range.clone()) == Some(bytes)
+            };
+            accessors.iter().find(same).map(|(index, _)| *index)
+        });
+        if let Some(index) = cached {
+            return index;
+        }
+
+        let view = self.add_buffer_view(bytes, target);
+        let start = self.bin.len() - bytes.len();
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": component_type,
+            "count": count,
+            "type": kind,
+        });
+        if let Some((min, max)) = bounds {
+            accessor["min"] = min.into();
+            accessor["max"] = max.into();
+        }
+        self.accessors.push(accessor);
+        let index = self.accessors.len() - 1;
+        let cache = self.accessor_cache.entry(hash).or_default();
+        cache.push((index, start..self.bin.len()));
+        index
+    }
+
+    /// The glTF JSON document, `uri` is the file of the binary buffer,
+    /// `None` when it is embedded in a `.glb` file.
+    pub fn document(&self, uri: Option<&str>) -> Value {
+        let mut buffer = json!({ "byteLength": self.bin.len() });
+        if let Some(uri) = uri {
+            buffer["uri"] = uri.into();
+        }
+        let mut document = json!({
+            "asset": { "version": "2.0", "generator": "bevy_mod_fbx fbx2gltf" },
+            "scene": 0,
+            "scenes": [{ "nodes": self.scene_nodes }],
+        });
+        if !self.extensions_used.is_empty() {
+            document["extensionsUsed"] = self.extensions_used.clone().into();
+        }
+        // glTF forbids empty arrays.
+        let arrays = [
+            ("nodes", &self.nodes),
+            ("meshes", &self.meshes),
+            ("materials", &self.materials),
+            ("textures", &self.textures),
+            ("images", &self.images),
+            ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+            ("animations", &self.animations),
+            ("skins", &self.skins),
+        ];
+        for (name, values) in arrays {
+            if !values.is_empty() {
+                document[name] = Value::Array(values.clone());
+            }
+        }
+        if !self.bin.is_empty() {
+            document["buffers"] = json!([buffer]);
+        }
+        document
+    }
+}
+
+fn flatten<T, const N: usize>(values: &[T], to_array: fn(&T) -> [f32; N]) -> Vec<f32> {
+    values.iter().flat_map(to_array).collect()
+}
+
+/// The static user-defined properties of a node, as glTF extras.
+fn properties(extras: &FbxExtras) -> Value {
+    let fields = extras.properties.iter().map(|(name, value)| {
+        let value = match value {
+            FbxPropertyValue::Bool(value) => json!(value),
+            FbxPropertyValue::Int(value) => json!(value),
+            FbxPropertyValue::Float(value) => json!(value),
+            FbxPropertyValue::Vector(value) => json!(value.to_array()),
+            FbxPropertyValue::String(value) => json!(value),
+        };
+        (name.clone(), value)
+    });
+    Value::Object(fields.collect())
+}
@@ -0,0 +1,223 @@
+//! Convert FBX files to glTF 2.0.
+//!
+//! ```text
+//! fbx2gltf <input.fbx> [output.glb|output.gltf]
+//! fbx2gltf --validate <input.fbx>...
+//! fbx2gltf --extract-media <input.fbx>...
+//! ```
+//!
+//! The FBX file is loaded with the `FbxLoader`, with the default
+//! [`FbxLoaderSettings`], and the spawned scene is written as glTF:
+//! the node hierarchy with the user-defined properties as `extras`,
+//! meshes, materials and their textures (as PNG), skins and animations.
+//!
+//! The output defaults to the input path with the `.glb` extension.
+//! With a `.gltf` extension, the binary buffer is written next to it,
+//! in a `.bin` file.
+//!
+//! Decoding textures needs the matching image format features of bevy,
+//! such as `png` or `jpeg`.
+//!
+//! With `--validate`, the files are only loaded, with
+//! [`FbxLoader::validate_bytes`], and their warnings and errors printed.
+//! The exit code is 1 if any of them failed to load, for content CI.
+//!
+//! With `--extract-media`, the media embedded in the files are moved to
+//! their `.fbm` folder with [`FbxLoader::extract_embedded_media`], and the
+//! files are rewritten to reference them.
+
+use std::{fs, path::PathBuf, thread, time::Duration};
+
+use anyhow::{bail, Context};
+use bevy::{
+    asset::{AssetPlugin, LoadState},
+    pbr::StandardMaterial,
+    prelude::{AddAsset, App, AssetServer, Assets, Image, Mesh, MinimalPlugins},
+    render::mesh::skinning::SkinnedMeshInverseBindposes,
+    scene::Scene,
+};
+use bevy_mod_fbx::{FbxAnimationClip, FbxLoader, FbxPlugin, FbxScene};
+
+mod gltf;
+mod png;
+
+use gltf::{Gltf, Sources};
+
+const USAGE: &str =
+    "usage: fbx2gltf <input.fbx> [output.glb|output.gltf]\n       fbx2gltf --validate <input.fbx>...\n       fbx2gltf --extract-media <input.fbx>...";
+
+fn main() {
+    if std::env::args_os()
+        .nth(1)
+        .is_some_and(|arg| arg == "--validate")
+    {
+        match validate() {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("fbx2gltf: {err:?}");
+                std::process::exit(2);
+            }
+        }
+    }
+    if std::env::args_os()
+        .nth(1)
+        .is_some_and(|arg| arg == "--extract-media")
+    {
+        if let Err(err) = extract_media() {
+            eprintln!("fbx2gltf: {err:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Err(err) = run() {
+        eprintln!("fbx2gltf: {err:?}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let mut args = std::env::args_os().skip(1);
+    let input = PathBuf::from(args.next().context(USAGE)?);
+    let output = args
+        .next()
+        .map_or_else(|| input.with_extension("glb"), PathBuf::from);
+    if args.next().is_some() {
+        bail!(USAGE);
+    }
+    let input = input
+        .canonicalize()
+        .with_context(|| format!("Failed to find {}", input.display()))?;
+    let (Some(folder), Some(file_name)) = (input.parent(), input.file_name()) else {
+        bail!("{} is not a file", input.display());
+    };
+    let file_name = file_name.to_string_lossy();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin {
+            asset_folder: folder.to_string_lossy().into_owned(),
+            watch_for_changes: false,
+        })
+        .add_asset::<Mesh>()
+        .add_asset::<Image>()
+        .add_asset::<StandardMaterial>()
+        .add_asset::<Scene>()
+        .add_asset::<SkinnedMeshInverseBindposes>()
+        .add_plugin(FbxPlugin);
+
+    let server = app.world.resource::<AssetServer>().clone();
+    let fbx_scene = server.load::<FbxScene, _>(format!("{file_name}#FbxScene"));
+    loop {
+        app.update();
+        match server.get_load_state(&fbx_scene) {
+            LoadState::Loaded => break,
+            LoadState::Failed => bail!("Failed to load {}", input.display()),
+            _ => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+    let scene = server.get_handle::<Scene, _>(format!("{file_name}#Scene"));
+    let scene = app
+        .world
+        .resource_mut::<Assets<Scene>>()
+        .remove(scene)
+        .context("The FBX scene has no spawned scene")?;
+
+    let world = &app.world;
+    let fbx_scene = world.resource::<Assets<FbxScene>>().get(&fbx_scene);
+    let fbx_scene = fbx_scene.context("Failed to load the FBX scene")?;
+    let sources = Sources {
+        meshes: world.resource::<Assets<Mesh>>(),
+        materials: world.resource::<Assets<StandardMaterial>>(),
+        images: world.resource::<Assets<Image>>(),
+        clips: world.resource::<Assets<FbxAnimationClip>>(),
+        inverse_bindposes: world.resource::<Assets<SkinnedMeshInverseBindposes>>(),
+    };
+    let gltf = Gltf::from_scene(&scene, fbx_scene, &sources);
+    write(&gltf, &output).with_context(|| format!("Failed to write {}", output.display()))
+}
+
+/// Load the files of the arguments without converting them,
+/// printing their report. Whether they all loaded.
+fn validate() -> anyhow::Result<bool> {
+    let inputs: Vec<PathBuf> = std::env::args_os().skip(2).map(PathBuf::from).collect();
+    if inputs.is_empty() {
+        bail!(USAGE);
+    }
+    let mut passed = true;
+    for input in &inputs {
+        let bytes =
+            fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+        let report = FbxLoader::validate_bytes(&bytes, Default::default());
+        println!(
+            "{}: {}",
+            input.display(),
+            report.to_string().replace('\n', "\n  ")
+        );
+        passed &= report.passed();
+    }
+    Ok(passed)
+}
+
+/// Move the embedded media of the files of the arguments to their
+/// `.fbm` folder, printing the extracted files.
+fn extract_media() -> anyhow::Result<()> {
+    let inputs: Vec<PathBuf> = std::env::args_os().skip(2).map(PathBuf::from).collect();
+    if inputs.is_empty() {
+        bail!(USAGE);
+    }
+    for input in &inputs {
+        let bytes =
+            fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+        let media = FbxLoader::extract_embedded_media(&bytes, input)
+            .with_context(|| format!("Failed to extract the media of {}", input.display()))?;
+        media
+            .write(input)
+            .with_context(|| format!("Failed to write the media of {}", input.display()))?;
+        println!("{}: {} files", input.display(), media.files.len());
+        for (path, _) in &media.files {
+            println!("  {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Write `gltf` to `output`, as `.gltf` and `.bin` files
+/// if the extension of `output` is `gltf`, as a binary `.glb` file otherwise.
+fn write(gltf: &Gltf, output: &std::path::Path) -> anyhow::Result<()> {
+    let is_gltf = output.extension().is_some_and(|ext| ext == "gltf");
+    if is_gltf {
+        let bin = output.with_extension("bin");
+        let uri = bin.file_name().map(|name| name.to_string_lossy());
+        fs::write(output, gltf.document(uri.as_deref()).to_string())?;
+        fs::write(&bin, &gltf.bin)?;
+        return Ok(());
+    }
+
+    let pad = |bytes: &mut Vec<u8>, padding: u8| {
+        while bytes.len() % 4 != 0 {
+            bytes.push(padding);
+        }
+    };
+    let mut json = gltf.document(None).to_string().into_bytes();
+    pad(&mut json, b' ');
+    let mut bin = gltf.bin.clone();
+    pad(&mut bin, 0);
+
+    let mut chunks = vec![(*b"JSON", json)];
+    if !bin.is_empty() {
+        chunks.push((*b"BIN\0", bin));
+    }
+    let length = 12 + chunks.iter().map(|(_, c)| 8 + c.len()).sum::<usize>();
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    for (kind, chunk) in chunks {
+        glb.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&kind);
+        glb.extend_from_slice(&chunk);
+    }
+    fs::write(output, glb)?;
+    Ok(())
+}
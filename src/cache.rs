@@ -0,0 +1,813 @@
+//! On-disk cache of the expensive parts of FBX conversion.
+//!
+//! Converted meshes, decoded textures and baked animations are stored in a
+//! binary file named after the hash of the FBX file content and of the
+//! loader settings changing them, see [`FbxLoaderSettings::cache_folder`].
+//!
+//! A cache file is written once, after a load that found no cache file,
+//! and only read afterward. Entries that can't be stored (such as compressed
+//! textures) are converted again on each load.
+//!
+//! The FBX file is still parsed, and its hierarchy, materials and scenes
+//! built, on each load: only the conversion of the cached entries is skipped.
+//!
+//! [`FbxLoaderSettings::cache_folder`]: crate::FbxLoaderSettings::cache_folder
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use bevy::{
+    prelude::{debug, Image, Mesh, Name, Quat, Vec3},
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    progress::Progress, utils::mesh::ATTRIBUTES, FbxAnimationClip, FbxClipEvent, FbxCurve,
+    FbxEntityPath, FbxKeyframes, FbxLoaderSettings, FbxPropertyCurve, FbxPropertyValue,
+    FbxWarningCategory,
+};
+
+/// Bump when the layout of cache files changes.
+const FORMAT_VERSION: u32 = 2;
+const MAGIC: &[u8; 8] = b"FBXCACHE";
+
+/// Texture formats of decoded images that can be cached.
+const IMAGE_FORMATS: [TextureFormat; 8] = [
+    TextureFormat::Rgba8UnormSrgb,
+    TextureFormat::Rgba8Unorm,
+    TextureFormat::R8Unorm,
+    TextureFormat::Rg8Unorm,
+    TextureFormat::R16Uint,
+    TextureFormat::Rg16Uint,
+    TextureFormat::Rgba16Uint,
+    TextureFormat::Rgba32Float,
+];
+
+/// A FBX geometry converted to a bevy mesh, before it is split per material.
+#[derive(Clone)]
+pub(crate) struct ConvertedMesh {
+    pub(crate) mesh: Mesh,
+    /// The indices of each material.
+    pub(crate) all_indices: Vec<Vec<u32>>,
+    /// The control point of each vertex, empty if the geometry was subdivided.
+    pub(crate) vertex_control_points: Vec<u32>,
+}
+
+/// A baked clip, with the curves of the user-defined properties
+/// of each node, by raw object id.
+#[derive(Clone)]
+pub(crate) struct BakedClip {
+    pub(crate) clip: FbxAnimationClip,
+    pub(crate) properties: HashMap<i64, HashMap<String, FbxPropertyCurve>>,
+}
+
+/// The settings changing the cached meshes and clips.
+///
+/// The entries of maps are sorted, their iteration order changes each run.
+fn settings_key(settings: &FbxLoaderSettings) -> String {
+    fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        entries
+    }
+    let s = settings;
+    let meshes = (
+        s.flip_uv_v,
+        s.subdivision_level,
+        &s.simplification,
+        s.max_mesh_vertices,
+        s.optimize_meshes,
+        &s.vertex_formats,
+        sorted(&s.mesh_vertex_formats),
+        s.vertex_colors,
+        sorted(&s.material_vertex_colors),
+        sorted(&s.material_overrides),
+    );
+    let nodes = (
+        s.strip_namespaces,
+        sorted(&s.namespace_remap),
+        &s.root_name,
+        &s.naming_conventions,
+        &s.socket_pattern,
+        &s.include_nodes,
+        &s.exclude_nodes,
+        &s.frozen_nodes,
+        s.strict_connections,
+        s.static_scene,
+        s.recenter,
+        s.scale,
+    );
+    let clips = (
+        &s.takes,
+        s.bake_constraints,
+        s.sample_rate,
+        s.keep_original_keys,
+        s.tolerance,
+        s.normalize_clip_start,
+        &s.event_pattern,
+    );
+    format!("{meshes:?}{nodes:?}{clips:?}")
+}
+
+/// The cache of a FBX file.
+pub(crate) struct FbxCache {
+    path: PathBuf,
+    meshes: HashMap<String, ConvertedMesh>,
+    images: HashMap<u64, Image>,
+    clips: HashMap<String, BakedClip>,
+    /// The entries to write, `None` if the cache file already exists.
+    writer: Option<Writer>,
+}
+
+impl FbxCache {
+    /// Open the cache of the FBX file `bytes` loaded with `settings`, in `folder`.
+    ///
+    /// The cache is empty if there is no cache file yet, or if it can't be read.
+    pub(crate) fn open(
+        folder: &Path,
+        bytes: &[u8],
+        settings: &FbxLoaderSettings,
+        progress: &Progress,
+    ) -> Self {
+        let key = format!(
+            "{}{FORMAT_VERSION}{}",
+            env!("CARGO_PKG_VERSION"),
+            settings_key(settings)
+        );
+        let hash = content_hash(bytes) ^ content_hash(key.as_bytes()).rotate_left(1);
+        let mut cache = FbxCache::new(folder.join(format!("{hash:016x}.fbxcache")));
+        match fs::read(&cache.path) {
+            Ok(content) => match cache.read(&content) {
+                Ok(()) => debug!("Read FBX cache {}", cache.path.display()),
+                Err(err) => {
+                    progress.warn(
+                        FbxWarningCategory::Cache,
+                        None,
+                        format!(
+                            "Ignoring invalid FBX cache {}: {err:?}",
+                            cache.path.display()
+                        ),
+                        Some("Delete the cache file, it is written again on the next load"),
+                    );
+                    cache.meshes.clear();
+                    cache.images.clear();
+                    cache.clips.clear();
+                }
+            },
+            Err(_) => cache.writer = Some(Writer::new()),
+        }
+        cache
+    }
+
+    fn new(path: PathBuf) -> Self {
+        FbxCache {
+            path,
+            meshes: HashMap::default(),
+            images: HashMap::default(),
+            clips: HashMap::default(),
+            writer: None,
+        }
+    }
+
+    /// Write the cache file, if it didn't exist.
+    pub(crate) fn save(&self) -> anyhow::Result<()> {
+        let Some(writer) = &self.writer else {
+            return Ok(());
+        };
+        if let Some(folder) = self.path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        fs::write(&self.path, &writer.0)?;
+        debug!("Wrote FBX cache {}", self.path.display());
+        Ok(())
+    }
+
+    pub(crate) fn mesh(&self, key: &str) -> Option<ConvertedMesh> {
+        self.meshes.get(key).cloned()
+    }
+    pub(crate) fn insert_mesh(&mut self, key: &str, mesh: &ConvertedMesh) {
+        if let Some(writer) = &mut self.writer {
+            writer.entry(Entry::Mesh, |w| {
+                w.str(key);
+                w.mesh(mesh)
+            });
+        }
+    }
+
+    /// The decoded image of the file `bytes`.
+    pub(crate) fn image(&self, bytes: &[u8]) -> Option<Image> {
+        self.images.get(&content_hash(bytes)).cloned()
+    }
+    pub(crate) fn insert_image(&mut self, bytes: &[u8], image: &Image) {
+        if let Some(writer) = &mut self.writer {
+            writer.entry(Entry::Image, |w| {
+                w.u64(content_hash(bytes));
+                w.image(image)
+            });
+        }
+    }
+
+    pub(crate) fn clip(&self, label: &str) -> Option<BakedClip> {
+        self.clips.get(label).cloned()
+    }
+    pub(crate) fn insert_clip(&mut self, label: &str, clip: &BakedClip) {
+        if let Some(writer) = &mut self.writer {
+            writer.entry(Entry::Clip, |w| {
+                w.str(label);
+                w.clip(clip);
+                Some(())
+            });
+        }
+    }
+
+    fn read(&mut self, content: &[u8]) -> anyhow::Result<()> {
+        let mut reader = Reader(content);
+        if reader.bytes(MAGIC.len())? != MAGIC || reader.u32()? != FORMAT_VERSION {
+            bail!("Not a FBX cache file of this version");
+        }
+        while !reader.0.is_empty() {
+            match reader.u8()? {
+                tag if tag == Entry::Mesh as u8 => {
+                    let key = reader.string()?;
+                    self.meshes.insert(key, reader.mesh()?);
+                }
+                tag if tag == Entry::Image as u8 => {
+                    let key = reader.u64()?;
+                    self.images.insert(key, reader.image()?);
+                }
+                tag if tag == Entry::Clip as u8 => {
+                    let key = reader.string()?;
+                    self.clips.insert(key, reader.clip()?);
+                }
+                tag => bail!("Unknown entry {tag}"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A 64 bits hash of `bytes`, stable across runs and platforms.
+///
+/// FNV-1a over eight bytes at a time, rotated so that the high bits of each
+/// word reach the low bits of the hash, the whole FBX file is hashed on
+/// each load.
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mix = |hash: u64, value: u64| {
+        (hash ^ value)
+            .wrapping_mul(0x0100_0000_01b3)
+            .rotate_left(29)
+    };
+    let mut words = bytes.chunks_exact(8);
+    let hash = words.by_ref().fold(0xcbf2_9ce4_8422_2325, |hash, word| {
+        mix(hash, u64::from_le_bytes(word.try_into().unwrap()))
+    });
+    let hash = words
+        .remainder()
+        .iter()
+        .fold(hash, |hash, byte| mix(hash, u64::from(*byte)));
+    mix(hash, bytes.len() as u64)
+}
+
+#[derive(Clone, Copy)]
+enum Entry {
+    Mesh = 1,
+    Image = 2,
+    Clip = 3,
+}
+
+struct Writer(Vec<u8>);
+impl Writer {
+    fn new() -> Self {
+        let mut writer = Writer(MAGIC.to_vec());
+        writer.u32(FORMAT_VERSION);
+        writer
+    }
+    /// Write an entry, unless `write` returns `None` because
+    /// the value can't be cached.
+    fn entry(&mut self, entry: Entry, write: impl FnOnce(&mut Writer) -> Option<()>) {
+        let start = self.0.len();
+        self.u8(entry as u8);
+        if write(self).is_none() {
+            self.0.truncate(start);
+        }
+    }
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn f64(&mut self, value: f64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn len(&mut self, len: usize) {
+        self.u64(len as u64);
+    }
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.0.extend_from_slice(bytes);
+    }
+    fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+    fn u32s(&mut self, values: &[u32]) {
+        self.len(values.len());
+        values.iter().for_each(|v| self.u32(*v));
+    }
+    fn f32s(&mut self, values: &[f32]) {
+        self.len(values.len());
+        values.iter().for_each(|v| self.f32(*v));
+    }
+
+    fn mesh(&mut self, converted: &ConvertedMesh) -> Option<()> {
+        let mesh = &converted.mesh;
+        let Some(Indices::U32(indices)) = mesh.indices() else {
+            return None;
+        };
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let attributes: Vec<_> = ATTRIBUTES
+            .iter()
+            .enumerate()
+            .filter_map(|(i, a)| Some((i, mesh.attribute(a.id)?)))
+            .collect();
+        if attributes.len() != mesh.attributes().count() {
+            return None;
+        }
+        self.len(attributes.len());
+        for (i, values) in attributes {
+            let components = match values {
+                VertexAttributeValues::Float32x2(_) => 2,
+                VertexAttributeValues::Float32x3(_) => 3,
+                VertexAttributeValues::Float32x4(_) => 4,
+                _ => return None,
+            };
+            self.u8(i as u8);
+            self.u8(components);
+            self.bytes(values.get_bytes());
+        }
+        self.u32s(indices);
+        self.len(converted.all_indices.len());
+        converted.all_indices.iter().for_each(|i| self.u32s(i));
+        self.u32s(&converted.vertex_control_points);
+        Some(())
+    }
+
+    fn image(&mut self, image: &Image) -> Option<()> {
+        let descriptor = &image.texture_descriptor;
+        let format = IMAGE_FORMATS.iter().position(|f| *f == descriptor.format)?;
+        let is_plain = descriptor.dimension == TextureDimension::D2
+            && descriptor.mip_level_count == 1
+            && descriptor.size.depth_or_array_layers == 1
+            && image.texture_view_descriptor.is_none();
+        if !is_plain {
+            return None;
+        }
+        self.u8(format as u8);
+        self.u32(descriptor.size.width);
+        self.u32(descriptor.size.height);
+        self.bytes(&image.data);
+        Some(())
+    }
+
+    fn clip(&mut self, baked: &BakedClip) {
+        let clip = &baked.clip;
+        self.str(clip.name.as_deref().unwrap_or_default());
+        self.u8(clip.name.is_some() as u8);
+        self.f64(clip.frame_rate);
+        self.f32(clip.duration);
+        self.len(clip.curves.len());
+        for (path, curves) in &clip.curves {
+            self.len(path.parts.len());
+            path.parts.iter().for_each(|part| self.str(part));
+            self.len(curves.len());
+            for curve in curves {
+                self.f32s(&curve.keyframe_timestamps);
+                let (kind, values): (u8, Vec<f32>) = match &curve.keyframes {
+                    FbxKeyframes::Translation(v) => {
+                        (0, v.iter().flat_map(Vec3::to_array).collect())
+                    }
+                    FbxKeyframes::Rotation(v) => (1, v.iter().flat_map(Quat::to_array).collect()),
+                    FbxKeyframes::Scale(v) => (2, v.iter().flat_map(Vec3::to_array).collect()),
+                };
+                self.u8(kind);
+                self.f32s(&values);
+            }
+        }
+        self.len(clip.events.len());
+        for event in &clip.events {
+            self.str(&event.name);
+            self.f32(event.time);
+        }
+        self.len(baked.properties.len());
+        for (node, properties) in &baked.properties {
+            self.u64(*node as u64);
+            self.len(properties.len());
+            for (name, curve) in properties {
+                self.str(name);
+                self.f32s(&curve.keyframe_timestamps);
+                self.len(curve.values.len());
+                curve.values.iter().for_each(|v| self.property_value(v));
+            }
+        }
+    }
+
+    fn property_value(&mut self, value: &FbxPropertyValue) {
+        match value {
+            FbxPropertyValue::Bool(value) => {
+                self.u8(0);
+                self.u8(*value as u8);
+            }
+            FbxPropertyValue::Int(value) => {
+                self.u8(1);
+                self.u32(*value as u32);
+            }
+            FbxPropertyValue::Float(value) => {
+                self.u8(2);
+                self.f32(*value);
+            }
+            FbxPropertyValue::Vector(value) => {
+                self.u8(3);
+                value.to_array().iter().for_each(|v| self.f32(*v));
+            }
+            FbxPropertyValue::String(value) => {
+                self.u8(4);
+                self.str(value);
+            }
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let bytes = self.bytes(N)?;
+        Ok(bytes.try_into().unwrap())
+    }
+    fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("Unexpected end of file");
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+    fn f32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+    fn f64(&mut self) -> anyhow::Result<f64> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+    fn len(&mut self) -> anyhow::Result<usize> {
+        let len = self.u64()? as usize;
+        // Every element takes at least one byte, this rejects corrupted lengths
+        // before allocating for them.
+        if len > self.0.len() {
+            bail!("Invalid length {len}");
+        }
+        Ok(len)
+    }
+    fn sized_bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.len()?;
+        self.bytes(len)
+    }
+    fn string(&mut self) -> anyhow::Result<String> {
+        let bytes = self.sized_bytes()?;
+        Ok(std::str::from_utf8(bytes)?.to_owned())
+    }
+    fn u32s(&mut self) -> anyhow::Result<Vec<u32>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.u32()).collect()
+    }
+    fn f32s(&mut self) -> anyhow::Result<Vec<f32>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.f32()).collect()
+    }
+
+    fn mesh(&mut self) -> anyhow::Result<ConvertedMesh> {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        for _ in 0..self.len()? {
+            let attribute = ATTRIBUTES
+                .get(self.u8()? as usize)
+                .context("Unknown mesh attribute")?
+                .clone();
+            let components = self.u8()?;
+            let floats: Vec<f32> = self
+                .sized_bytes()?
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+            fn group<const N: usize>(floats: &[f32]) -> Vec<[f32; N]> {
+                floats
+                    .chunks_exact(N)
+                    .map(|c| c.try_into().unwrap())
+                    .collect()
+            }
+            let values = match components {
+                2 => VertexAttributeValues::Float32x2(group(&floats)),
+                3 => VertexAttributeValues::Float32x3(group(&floats)),
+                4 => VertexAttributeValues::Float32x4(group(&floats)),
+                _ => bail!("Invalid mesh attribute format"),
+            };
+            mesh.insert_attribute(attribute, values);
+        }
+        mesh.set_indices(Some(Indices::U32(self.u32s()?)));
+        let all_indices = (0..self.len()?)
+            .map(|_| self.u32s())
+            .collect::<anyhow::Result<_>>()?;
+        Ok(ConvertedMesh {
+            mesh,
+            all_indices,
+            vertex_control_points: self.u32s()?,
+        })
+    }
+
+    fn image(&mut self) -> anyhow::Result<Image> {
+        let format = *IMAGE_FORMATS
+            .get(self.u8()? as usize)
+            .context("Unknown image format")?;
+        let size = Extent3d {
+            width: self.u32()?,
+            height: self.u32()?,
+            depth_or_array_layers: 1,
+        };
+        let data = self.sized_bytes()?.to_vec();
+        let expected = format.describe().block_size as usize * (size.width * size.height) as usize;
+        if data.len() != expected {
+            bail!("Invalid image size");
+        }
+        Ok(Image::new(size, TextureDimension::D2, data, format))
+    }
+
+    fn clip(&mut self) -> anyhow::Result<BakedClip> {
+        let name = self.string()?;
+        let name = (self.u8()? != 0).then_some(name);
+        let mut clip = FbxAnimationClip {
+            name,
+            frame_rate: self.f64()?,
+            duration: self.f32()?,
+            curves: HashMap::default(),
+            events: Vec::new(),
+            additive: false,
+        };
+        for _ in 0..self.len()? {
+            let parts = (0..self.len()?)
+                .map(|_| Ok(Name::new(self.string()?)))
+                .collect::<anyhow::Result<_>>()?;
+            let mut curves = Vec::new();
+            for _ in 0..self.len()? {
+                let keyframe_timestamps = self.f32s()?;
+                let kind = self.u8()?;
+                let values = self.f32s()?;
+                let vec3s = || values.chunks_exact(3).map(Vec3::from_slice).collect();
+                let keyframes = match kind {
+                    0 => FbxKeyframes::Translation(vec3s()),
+                    1 => FbxKeyframes::Rotation(
+                        values.chunks_exact(4).map(Quat::from_slice).collect(),
+                    ),
+                    2 => FbxKeyframes::Scale(vec3s()),
+                    _ => bail!("Invalid keyframes"),
+                };
+                curves.push(FbxCurve {
+                    keyframe_timestamps,
+                    keyframes,
+                });
+            }
+            clip.curves.insert(FbxEntityPath { parts }, curves);
+        }
+        for _ in 0..self.len()? {
+            let name = self.string()?;
+            let time = self.f32()?;
+            clip.events.push(FbxClipEvent { name, time });
+        }
+        let mut properties = HashMap::default();
+        for _ in 0..self.len()? {
+            let node = self.u64()? as i64;
+            let mut curves = HashMap::default();
+            for _ in 0..self.len()? {
+                let name = self.string()?;
+                let keyframe_timestamps = self.f32s()?;
+                let values = (0..self.len()?)
+                    .map(|_| self.property_value())
+                    .collect::<anyhow::Result<_>>()?;
+                let curve = FbxPropertyCurve {
+                    keyframe_timestamps,
+                    values,
+                };
+                curves.insert(name, curve);
+            }
+            properties.insert(node, curves);
+        }
+        Ok(BakedClip { clip, properties })
+    }
+
+    fn property_value(&mut self) -> anyhow::Result<FbxPropertyValue> {
+        Ok(match self.u8()? {
+            0 => FbxPropertyValue::Bool(self.u8()? != 0),
+            1 => FbxPropertyValue::Int(self.u32()? as i32),
+            2 => FbxPropertyValue::Float(self.f32()?),
+            3 => FbxPropertyValue::Vector(Vec3::new(self.f32()?, self.f32()?, self.f32()?)),
+            4 => FbxPropertyValue::String(self.string()?),
+            _ => bail!("Invalid property value"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache holding the entries written to `writer`.
+    fn reread(writer: Writer) -> FbxCache {
+        let mut cache = FbxCache::new(PathBuf::new());
+        cache.read(&writer.0).unwrap();
+        cache
+    }
+
+    #[test]
+    fn settings_key() {
+        let remap = |pairs: &[(&str, &str)]| {
+            let pairs = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+            FbxLoaderSettings {
+                namespace_remap: pairs.collect(),
+                ..Default::default()
+            }
+        };
+        let pairs: Vec<_> = (0..32)
+            .map(|i| (format!("a{i}"), format!("b{i}")))
+            .collect();
+        let pairs: Vec<_> = pairs.iter().map(|(k, v)| (&k[..], &v[..])).collect();
+        let reversed: Vec<_> = pairs.iter().rev().copied().collect();
+        let key = super::settings_key(&remap(&pairs));
+        assert_eq!(key, super::settings_key(&remap(&reversed)));
+        assert_ne!(key, super::settings_key(&remap(&pairs[1..])));
+
+        let unrelated = FbxLoaderSettings {
+            cache_folder: Some(PathBuf::from("cache")),
+            light_exposure: 2.0,
+            ..remap(&pairs)
+        };
+        assert_eq!(key, super::settings_key(&unrelated));
+    }
+
+    #[test]
+    fn mesh_round_trip() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions = vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+        ];
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 1.0]; 4]);
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 2, 1, 3])));
+        let converted = ConvertedMesh {
+            mesh,
+            all_indices: vec![vec![0, 1, 2], vec![2, 1, 3]],
+            vertex_control_points: vec![0, 1, 2, 3],
+        };
+        let mut writer = Writer::new();
+        writer.entry(Entry::Mesh, |w| {
+            w.str("Mesh0");
+            w.mesh(&converted)
+        });
+
+        let read = reread(writer).mesh("Mesh0").unwrap();
+        for attribute in [Mesh::ATTRIBUTE_POSITION, Mesh::ATTRIBUTE_UV_0] {
+            let bytes = |mesh: &Mesh| mesh.attribute(attribute.id).unwrap().get_bytes().to_vec();
+            assert_eq!(bytes(&read.mesh), bytes(&converted.mesh));
+        }
+        assert_eq!(read.mesh.attributes().count(), 2);
+        let Some(Indices::U32(indices)) = read.mesh.indices() else {
+            panic!("the indices should be u32");
+        };
+        assert_eq!(indices, &[0, 1, 2, 2, 1, 3]);
+        assert_eq!(read.all_indices, converted.all_indices);
+        assert_eq!(read.vertex_control_points, converted.vertex_control_points);
+    }
+
+    #[test]
+    fn image_round_trip() {
+        let size = Extent3d {
+            width: 2,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        let data = vec![255, 0, 0, 255, 0, 0, 255, 128];
+        let image = Image::new(
+            size,
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        let mut writer = Writer::new();
+        writer.entry(Entry::Image, |w| {
+            w.u64(content_hash(b"texture.png"));
+            w.image(&image)
+        });
+
+        let cache = reread(writer);
+        let read = cache.image(b"texture.png").unwrap();
+        assert_eq!(read.data, image.data);
+        assert_eq!(read.texture_descriptor.size, size);
+        assert_eq!(
+            read.texture_descriptor.format,
+            TextureFormat::Rgba8UnormSrgb
+        );
+        assert!(cache.image(b"other.png").is_none());
+    }
+
+    #[test]
+    fn clip_round_trip() {
+        let mut clip = FbxAnimationClip {
+            name: Some("Walk".to_owned()),
+            frame_rate: 30.0,
+            events: vec![FbxClipEvent {
+                name: "Step".to_owned(),
+                time: 0.5,
+            }],
+            ..Default::default()
+        };
+        let path = FbxEntityPath {
+            parts: vec![Name::new("Root"), Name::new("Leg")],
+        };
+        let curves = [
+            FbxKeyframes::Translation(vec![Vec3::ZERO, Vec3::X]),
+            FbxKeyframes::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_z(1.0)]),
+            FbxKeyframes::Scale(vec![Vec3::ONE, Vec3::splat(2.0)]),
+        ];
+        for keyframes in curves {
+            let curve = FbxCurve {
+                keyframe_timestamps: vec![0.0, 1.0],
+                keyframes,
+            };
+            clip.add_curve_to_path(path.clone(), curve);
+        }
+        let property = FbxPropertyCurve {
+            keyframe_timestamps: vec![0.0, 1.0],
+            values: vec![FbxPropertyValue::Float(0.5), FbxPropertyValue::Bool(true)],
+        };
+        let properties =
+            HashMap::from_iter([(42, HashMap::from_iter([("Intensity".to_owned(), property)]))]);
+        let baked = BakedClip { clip, properties };
+        let mut writer = Writer::new();
+        writer.entry(Entry::Clip, |w| {
+            w.str("Clip0");
+            w.clip(&baked);
+            Some(())
+        });
+
+        let read = reread(writer).clip("Clip0").unwrap();
+        assert_eq!(read.clip.name, baked.clip.name);
+        assert_eq!(read.clip.frame_rate, 30.0);
+        assert_eq!(read.clip.duration, 1.0);
+        assert_eq!(read.clip.events, baked.clip.events);
+        // The keyframes aren't comparable, their debug output is.
+        assert_eq!(
+            format!("{:?}", read.clip.curves[&path]),
+            format!("{:?}", baked.clip.curves[&path])
+        );
+        let property = &read.properties[&42]["Intensity"];
+        assert_eq!(property.keyframe_timestamps, [0.0, 1.0]);
+        assert_eq!(
+            property.values,
+            [FbxPropertyValue::Float(0.5), FbxPropertyValue::Bool(true)]
+        );
+    }
+
+    #[test]
+    fn content_hash_sees_every_byte() {
+        let bytes: Vec<u8> = (0..=20).collect();
+        let hash = content_hash(&bytes);
+        for i in 0..bytes.len() {
+            let mut changed = bytes.clone();
+            changed[i] ^= 0x80;
+            assert_ne!(content_hash(&changed), hash, "byte {i}");
+        }
+        assert_ne!(content_hash(&bytes[..20]), hash);
+    }
+}
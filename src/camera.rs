@@ -0,0 +1,233 @@
+//! Translation of FBX cameras into bevy cameras.
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::{
+        Component, Entity, FromWorld, OrthographicProjection, PerspectiveProjection, Projection,
+        Quat, Reflect, ReflectComponent, World,
+    },
+    render::camera::ScalingMode,
+};
+use fbxcel_dom::v7400::object::{
+    model::ModelHandle, nodeattribute::TypedNodeAttributeHandle, property::ObjectProperties,
+    ObjectHandle, ObjectId,
+};
+
+use crate::utils::fbx_extend::{ApertureMode, ModelHandleExt, ObjectPropertiesExt};
+
+/// FBX film sizes are in inches, focal lengths in millimeters.
+const INCH_TO_MM: f64 = 25.4;
+
+/// The depth of field and exposure settings of a FBX camera.
+///
+/// Bevy cameras have no depth of field, and a fixed exposure, so this
+/// component is added to each camera for post-processing effects to use.
+/// Properties missing from the file are `None`.
+#[derive(Component, Debug, Clone, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct FbxCameraExtras {
+    /// Whether the artist enabled depth of field on this camera.
+    pub depth_of_field: bool,
+    /// Distance of the plane in focus, in bevy units.
+    pub focus_distance: Option<f32>,
+    /// Aperture of the lens, as its f-number.
+    pub f_stop: Option<f32>,
+    /// Focal length of the lens, in millimeters.
+    pub focal_length: f32,
+    /// Height of the film back (sensor), in millimeters.
+    pub sensor_height: f32,
+    /// Shutter angle, in degrees, 180 being a shutter open for half a frame.
+    pub shutter_angle: Option<f32>,
+    /// Exposure compensation, in stops.
+    pub exposure: Option<f32>,
+}
+impl FbxCameraExtras {
+    fn from_properties(p: ObjectProperties, unit_scale: f32) -> Self {
+        let any = |names: &[&str]| {
+            let value = names.iter().find_map(|name| p.get::<f64>(name));
+            value.map(|value| value as f32)
+        };
+        FbxCameraExtras {
+            depth_of_field: p.get::<bool>("UseDepthOfField").unwrap_or(false),
+            focus_distance: any(&["FocusDistance"]).map(|d| d * unit_scale),
+            f_stop: any(&["FStop", "fStop", "FNumber"]),
+            focal_length: any(&["FocalLength"]).unwrap_or(35.0),
+            sensor_height: (p.get::<f64>("FilmHeight").unwrap_or(0.612) * INCH_TO_MM) as f32,
+            shutter_angle: any(&["ShutterAngle", "shutterAngle"]),
+            exposure: any(&["Exposure", "aiExposure"]),
+        }
+    }
+}
+
+/// Which camera of a stereo rig a [`FbxStereoCamera`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum FbxStereoEye {
+    /// The camera holding the `CameraStereo` attribute of the rig.
+    #[default]
+    Center,
+    Left,
+    Right,
+}
+
+/// A camera of a FBX stereo rig.
+///
+/// The center, left and right cameras of a rig each get this component,
+/// with the same `rig`. The left and right cameras are children of the
+/// center node in the FBX file, so they keep their offset when the rig moves.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct FbxStereoCamera {
+    pub eye: FbxStereoEye,
+    /// The entity of the center camera of the rig.
+    pub rig: Entity,
+    /// Distance between the left and right cameras, in bevy units.
+    pub interaxial_separation: f32,
+    /// Distance of the plane where both eyes converge, in bevy units.
+    pub zero_parallax: f32,
+}
+impl FromWorld for FbxStereoCamera {
+    fn from_world(_world: &mut World) -> Self {
+        FbxStereoCamera {
+            eye: FbxStereoEye::Center,
+            rig: Entity::PLACEHOLDER,
+            interaxial_separation: 0.0,
+            zero_parallax: 0.0,
+        }
+    }
+}
+impl MapEntities for FbxStereoCamera {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.rig = entity_map.get(self.rig)?;
+        Ok(())
+    }
+}
+
+/// The `CameraStereo` attribute of the center node of a stereo rig.
+#[derive(Debug, Clone)]
+pub(crate) struct StereoRig {
+    /// The nodes of the left and right cameras.
+    pub(crate) left: Option<ObjectId>,
+    pub(crate) right: Option<ObjectId>,
+    pub(crate) interaxial_separation: f32,
+    pub(crate) zero_parallax: f32,
+}
+impl StereoRig {
+    fn from_attribute(attribute: &ObjectHandle, p: ObjectProperties, unit_scale: f32) -> Self {
+        // The rig references the attributes of its cameras,
+        // or sometimes their nodes.
+        let eye_node = |label: &str| {
+            let source = attribute
+                .source_objects()
+                .find(|obj| obj.label() == Some(label))?;
+            let source = source.object_handle()?;
+            if source.class() == "Model" {
+                return Some(source.object_id());
+            }
+            source
+                .destination_objects()
+                .filter(|obj| obj.label().is_none())
+                .filter_map(|obj| obj.object_handle())
+                .find(|obj| obj.class() == "Model")
+                .map(|obj| obj.object_id())
+        };
+        let get = |name: &str, default: f64| p.get::<f64>(name).unwrap_or(default) as f32;
+        StereoRig {
+            left: eye_node("LeftCamera"),
+            right: eye_node("RightCamera"),
+            interaxial_separation: get("InteraxialSeparation", 6.35) * unit_scale,
+            zero_parallax: get("ZeroParallax", 100.0) * unit_scale,
+        }
+    }
+
+    /// The component of the camera `eye` of the rig.
+    pub(crate) fn component(&self, eye: FbxStereoEye, rig: Entity) -> FbxStereoCamera {
+        FbxStereoCamera {
+            eye,
+            rig,
+            interaxial_separation: self.interaxial_separation,
+            zero_parallax: self.zero_parallax,
+        }
+    }
+}
+
+/// A camera attached to an FBX node.
+#[derive(Debug, Clone)]
+pub(crate) struct FbxCamera {
+    pub(crate) projection: Projection,
+    pub(crate) extras: FbxCameraExtras,
+    /// The stereo rig of the camera, if it is the center of one.
+    pub(crate) stereo: Option<StereoRig>,
+}
+impl FbxCamera {
+    /// Read the camera properties of the `NodeAttribute` of `node`.
+    ///
+    /// `unit_scale` converts FBX file units into bevy units,
+    /// it is used for the near and far clipping planes and the focus distance.
+    pub(crate) fn from_node(node: ModelHandle, unit_scale: f32) -> Option<Self> {
+        let (attribute, native_typename) = match node.node_attribute()? {
+            TypedNodeAttributeHandle::Camera(camera) => (**camera, "FbxCamera"),
+            TypedNodeAttributeHandle::Unknown(attribute)
+                if attribute.subclass() == "CameraStereo" =>
+            {
+                (*attribute, "FbxCameraStereo")
+            }
+            _ => return None,
+        };
+        let p = attribute.properties_by_native_typename(native_typename);
+        let near = p.get::<f64>("NearPlane").unwrap_or(10.0) as f32 * unit_scale;
+        let far = p.get::<f64>("FarPlane").unwrap_or(4000.0) as f32 * unit_scale;
+        let aspect_width = p.get::<f64>("AspectWidth").unwrap_or(320.0);
+        let aspect_height = p.get::<f64>("AspectHeight").unwrap_or(200.0);
+        let aspect_ratio = aspect_width / aspect_height;
+
+        let projection = if p.get::<i32>("CameraProjectionType") == Some(1) {
+            Projection::Orthographic(OrthographicProjection {
+                near,
+                far,
+                scale: p.get::<f64>("OrthoZoom").unwrap_or(1.0) as f32,
+                scaling_mode: ScalingMode::FixedVertical(2.0),
+                ..Default::default()
+            })
+        } else {
+            let to_vertical = |horizontal: f64| {
+                2.0 * ((horizontal.to_radians() / 2.0).tan() / aspect_ratio).atan()
+            };
+            let fov = match p.get("ApertureMode").unwrap_or_default() {
+                ApertureMode::Horizontal => to_vertical(p.get("FieldOfView").unwrap_or(40.0)),
+                ApertureMode::Vertical => p.get::<f64>("FieldOfView").unwrap_or(25.0).to_radians(),
+                ApertureMode::HorizAndVert => {
+                    p.get::<f64>("FieldOfViewY").unwrap_or(25.0).to_radians()
+                }
+                ApertureMode::FocalLength => {
+                    let film_height = p.get::<f64>("FilmHeight").unwrap_or(0.612) * INCH_TO_MM;
+                    let focal_length = p.get::<f64>("FocalLength").unwrap_or(35.0);
+                    2.0 * (film_height / (2.0 * focal_length)).atan()
+                }
+            };
+            Projection::Perspective(PerspectiveProjection {
+                fov: fov as f32,
+                aspect_ratio: aspect_ratio as f32,
+                near,
+                far,
+            })
+        };
+        let extras = FbxCameraExtras::from_properties(p, unit_scale);
+        let stereo = (native_typename == "FbxCameraStereo")
+            .then(|| StereoRig::from_attribute(&attribute, p, unit_scale));
+        Some(FbxCamera {
+            projection,
+            extras,
+            stereo,
+        })
+    }
+}
+
+/// FBX cameras look along their node's +X axis, bevy cameras along -Z.
+pub(crate) fn rest_rotation() -> Quat {
+    Quat::from_rotation_y(-FRAC_PI_2)
+}
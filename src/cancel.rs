@@ -0,0 +1,68 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{prelude::Resource, utils::HashMap};
+
+/// Resource to cancel FBX files being loaded, for example when
+/// switching levels before the previous level finished loading.
+///
+/// The `FbxLoader` checks for cancellation between the conversion of
+/// each object and between the loading stages, a cancelled load stops
+/// there, without adding any asset.
+#[derive(Resource, Clone, Default)]
+pub struct FbxLoadCancellation {
+    /// Whether each file being loaded was cancelled, by asset path.
+    in_flight: Arc<Mutex<HashMap<PathBuf, bool>>>,
+}
+impl FbxLoadCancellation {
+    /// Cancel the load of the FBX file at `path`, relative to the asset folder.
+    ///
+    /// Returns `false` if the file isn't being loaded, files loaded later
+    /// are not affected.
+    pub fn cancel(&self, path: impl AsRef<Path>) -> bool {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match in_flight.get_mut(path.as_ref()) {
+            Some(cancelled) => {
+                *cancelled = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the file at `path` is being loaded.
+    pub fn is_loading(&self, path: impl AsRef<Path>) -> bool {
+        self.in_flight.lock().unwrap().contains_key(path.as_ref())
+    }
+
+    pub(crate) fn start(&self, path: &Path) {
+        self.in_flight
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), false);
+    }
+
+    pub(crate) fn finish(&self, path: &Path) {
+        self.in_flight.lock().unwrap().remove(path);
+    }
+
+    pub(crate) fn check(&self, path: &Path) -> Result<(), LoadCancelled> {
+        match self.in_flight.lock().unwrap().get(path) {
+            Some(true) => Err(LoadCancelled),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Error of a load stopped by [`FbxLoadCancellation::cancel`].
+#[derive(Debug)]
+pub(crate) struct LoadCancelled;
+impl fmt::Display for LoadCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the load was cancelled")
+    }
+}
+impl std::error::Error for LoadCancelled {}
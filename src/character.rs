@@ -0,0 +1,49 @@
+//! HumanIK character definitions.
+//!
+//! A `Character` object maps the joints of a skeleton to the standard
+//! HumanIK slots (`Hips`, `LeftUpLeg`, `Head`...), each joint is connected
+//! to the `<slot>Link` property of the character.
+
+use bevy::utils::HashMap;
+use fbxcel_dom::v7400::{
+    object::{ObjectHandle, ObjectId},
+    Document,
+};
+
+use crate::{FbxEntityPath, FbxScene};
+
+/// A HumanIK character definition, the joint of the skeleton
+/// in each character slot.
+#[derive(Clone, Debug, Default)]
+pub struct FbxCharacter {
+    pub name: Option<String>,
+    /// The joint of each slot, by slot name (such as `Hips` or `LeftForeArm`).
+    pub joints: HashMap<String, ObjectId>,
+}
+impl FbxCharacter {
+    fn from_object(object: ObjectHandle) -> Self {
+        let joints = object
+            .source_objects()
+            .filter_map(|obj| {
+                let slot = obj.label()?.strip_suffix("Link")?;
+                Some((slot.to_owned(), obj.object_id()))
+            })
+            .collect();
+        FbxCharacter {
+            name: object.name().filter(|n| !n.is_empty()).map(Into::into),
+            joints,
+        }
+    }
+    /// All the character definitions of `doc`.
+    pub(crate) fn all(doc: &Document) -> Vec<Self> {
+        doc.objects()
+            .filter(|obj| obj.node().name() == "Character")
+            .map(FbxCharacter::from_object)
+            .collect()
+    }
+    /// The path to the entity of the joint in `slot`, relative to the scene root.
+    pub fn joint_path(&self, slot: &str, scene: &FbxScene) -> Option<FbxEntityPath> {
+        let joint = self.joints.get(slot)?;
+        scene.entity_paths().remove(joint)
+    }
+}
@@ -0,0 +1,138 @@
+//! Naming conventions of game pipelines,
+//! see [`FbxLoaderSettings::naming_conventions`].
+//!
+//! [`FbxLoaderSettings::naming_conventions`]: crate::FbxLoaderSettings::naming_conventions
+
+use bevy::{
+    prelude::{Component, Reflect, ReflectComponent},
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use crate::{data::FbxObject, FbxNamingConventions};
+
+/// The shape of a collision mesh, given by the prefix of its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum FbxCollisionShape {
+    /// A convex hull, Unreal's `UCX_` prefix.
+    #[default]
+    Convex,
+    /// A box, Unreal's `UBX_` prefix.
+    Box,
+    /// A sphere, Unreal's `USP_` prefix.
+    Sphere,
+    /// A capsule, Unreal's `UCP_` prefix.
+    Capsule,
+}
+
+/// Added to the nodes named as collision meshes,
+/// see [`FbxNamingConventions::collision_prefixes`].
+///
+/// The collision mesh is the mesh of the node, approximating the shape.
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct FbxCollisionMesh {
+    pub shape: FbxCollisionShape,
+    /// The name of the render mesh the collision mesh belongs to, without the
+    /// prefix and the index of the collision mesh: `UCX_Rock_01` is for `Rock`.
+    pub mesh: String,
+}
+
+/// Added to the nodes named as a level of detail of a mesh,
+/// see [`FbxNamingConventions::lod_suffix`].
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct FbxLod {
+    /// The name of the node without the suffix, shared by all the levels
+    /// of the mesh: `Rock_LOD2` is in the group `Rock`.
+    pub group: String,
+    /// The level of detail, 0 being the most detailed.
+    pub level: u32,
+}
+
+/// Added to the nodes named as static or skeletal meshes,
+/// see [`FbxNamingConventions::static_mesh_prefix`].
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum FbxMeshKind {
+    #[default]
+    Static,
+    Skeletal,
+}
+
+/// The components of a node following the naming conventions.
+#[derive(Debug, Clone)]
+pub(crate) struct ConventionTags {
+    pub(crate) collision: Option<FbxCollisionMesh>,
+    pub(crate) lod: Option<FbxLod>,
+    pub(crate) kind: Option<FbxMeshKind>,
+    /// Whether the node is spawned hidden.
+    pub(crate) hidden: bool,
+}
+impl ConventionTags {
+    /// The tags of the node `name`, `None` if it follows no convention.
+    fn new(name: &str, conventions: &FbxNamingConventions) -> Option<Self> {
+        let collision = (conventions.collision_prefixes.iter())
+            .find(|(prefix, _)| name.starts_with(prefix.as_str()))
+            .map(|(prefix, shape)| {
+                let mesh = &name[prefix.len()..];
+                // Several collision meshes of a mesh are numbered `_01`, `_02`...
+                let mesh = match mesh.rsplit_once('_') {
+                    Some((mesh, index)) if is_number(index) => mesh,
+                    _ => mesh,
+                };
+                FbxCollisionMesh {
+                    shape: *shape,
+                    mesh: mesh.to_owned(),
+                }
+            });
+        let lod = conventions.lod_suffix.as_ref().and_then(|suffix| {
+            let (group, level) = name.rsplit_once(suffix.as_str())?;
+            if !is_number(level) {
+                return None;
+            }
+            Some(FbxLod {
+                group: group.to_owned(),
+                level: level.parse().ok()?,
+            })
+        });
+        let has_prefix =
+            |prefix: &Option<String>| prefix.as_ref().is_some_and(|p| name.starts_with(p));
+        let kind = if has_prefix(&conventions.static_mesh_prefix) {
+            Some(FbxMeshKind::Static)
+        } else if has_prefix(&conventions.skeletal_mesh_prefix) {
+            Some(FbxMeshKind::Skeletal)
+        } else {
+            None
+        };
+        let hidden = (collision.is_some() && conventions.hide_collision_meshes)
+            || lod
+                .as_ref()
+                .is_some_and(|lod| lod.level > 0 && conventions.hide_lods);
+        let tags = ConventionTags {
+            collision,
+            lod,
+            kind,
+            hidden,
+        };
+        let is_tagged = tags.collision.is_some() || tags.lod.is_some() || tags.kind.is_some();
+        is_tagged.then_some(tags)
+    }
+}
+
+/// The tags of the nodes of `hierarchy` following `conventions`.
+pub(crate) fn tag_hierarchy(
+    hierarchy: &HashMap<ObjectId, FbxObject>,
+    conventions: &FbxNamingConventions,
+) -> HashMap<ObjectId, ConventionTags> {
+    (hierarchy.iter())
+        .filter_map(|(id, object)| {
+            let tags = ConventionTags::new(object.name.as_ref()?, conventions)?;
+            Some((*id, tags))
+        })
+        .collect()
+}
+
+fn is_number(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|c| c.is_ascii_digit())
+}
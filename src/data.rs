@@ -1,18 +1,136 @@
 use bevy::{
-    prelude::{Handle, Image, Mesh, StandardMaterial, Transform},
+    math::DVec3,
+    prelude::{Color, Handle, Image, Mesh, Name, Scene, StandardMaterial, Transform, Vec3, World},
     reflect::TypeUuid,
+    render::{
+        mesh::{skinning::SkinnedMeshInverseBindposes, MeshVertexAttribute},
+        primitives::Aabb,
+        render_resource::VertexFormat,
+    },
     utils::HashMap,
 };
 use fbxcel_dom::v7400::object::ObjectId;
 
+use crate::{
+    diff, FbxAnimationClip, FbxCharacter, FbxEntityPath, FbxExternalReference, FbxExtras,
+    FbxMaterialExtras, FbxMediaRef, FbxMemoryUsage, FbxSceneDiff, FbxSkeleton, FbxStreamedTexture,
+    FbxSubdivisionData, FbxTakeMetadata, FbxVertexCache, FbxVertexCacheReference,
+};
+
 #[derive(Debug, Clone, TypeUuid)]
 #[uuid = "966d55c0-515b-4141-97a1-de30ac8ee44c"]
 pub struct FbxMesh {
     pub name: Option<String>,
+    /// The bevy meshes, one per material, or several per material for meshes
+    /// split at import, see [`FbxLoaderSettings::max_mesh_vertices`].
+    ///
+    /// Nodes instancing the same FBX geometry share their bevy meshes, unless
+    /// they are converted differently, for example with another material count.
+    ///
+    /// [`FbxLoaderSettings::max_mesh_vertices`]: crate::FbxLoaderSettings::max_mesh_vertices
     pub bevy_mesh_handles: Vec<Handle<Mesh>>,
+    /// The bounding box of each of the bevy meshes, in the space of their node
+    /// moved by [`FbxMesh::offset`].
+    pub bevy_mesh_aabbs: Vec<Option<Aabb>>,
+    /// The bounding box of the meshes, in the space of their node
+    /// moved by [`FbxMesh::offset`].
+    pub aabb: Option<Aabb>,
+    /// The translation of the bevy meshes from their node, where the vertices
+    /// of meshes far from their node were moved from with
+    /// [`FbxLoaderSettings::recenter`]. Zero for the other meshes.
+    ///
+    /// The mesh entities are spawned with this translation.
+    ///
+    /// [`FbxLoaderSettings::recenter`]: crate::FbxLoaderSettings::recenter
+    pub offset: Vec3,
+    /// The material of each of the bevy meshes.
     pub materials: Vec<Handle<StandardMaterial>>,
+    /// Each of the bevy meshes with its material and the FBX material it
+    /// comes from, for custom spawners replacing materials or merging meshes.
+    pub primitives: Vec<FbxPrimitive>,
+    /// The cache file of the vertex cache deformer of the mesh, if any.
+    pub vertex_cache_reference: Option<FbxVertexCacheReference>,
+    /// The loaded vertex cache, only `.pc2` caches are supported.
+    pub vertex_cache: Option<Handle<FbxVertexCache>>,
+    /// The creases and subdivision levels of the mesh, if any.
+    pub subdivision: Option<FbxSubdivisionData>,
+    /// The skin of the mesh, if its geometry has `Skin` deformers.
+    ///
+    /// Subdivided meshes, and meshes moved by more joints than a
+    /// [`SkinnedMesh`] supports, are loaded unskinned.
+    ///
+    /// [`SkinnedMesh`]: bevy::render::mesh::skinning::SkinnedMesh
+    pub skin: Option<FbxMeshSkin>,
+    /// A [`LineList`] mesh of the polygon edges, without the triangulation edges,
+    /// if [`FbxLoaderSettings::wireframe_meshes`] is enabled.
+    ///
+    /// [`LineList`]: bevy::render::render_resource::PrimitiveTopology::LineList
+    /// [`FbxLoaderSettings::wireframe_meshes`]: crate::FbxLoaderSettings::wireframe_meshes
+    pub wireframe: Option<Handle<Mesh>>,
+    /// The `Casts Shadows` property of the node or geometry, spawned mesh
+    /// entities get a [`NotShadowCaster`] when it is disabled.
+    ///
+    /// [`NotShadowCaster`]: bevy::pbr::NotShadowCaster
+    pub casts_shadows: bool,
+    /// The `Receive Shadows` property of the node or geometry, spawned mesh
+    /// entities get a [`NotShadowReceiver`] when it is disabled.
+    ///
+    /// [`NotShadowReceiver`]: bevy::pbr::NotShadowReceiver
+    pub receives_shadows: bool,
+}
+impl FbxMesh {
+    /// The vertex colors of meshes imported with [`FbxVertexColors::Data`].
+    ///
+    /// [`FbxVertexColors::Data`]: crate::FbxVertexColors::Data
+    pub const ATTRIBUTE_VERTEX_COLOR: MeshVertexAttribute =
+        MeshVertexAttribute::new("FbxVertexColor", 1_237_615_309, VertexFormat::Float32x4);
 }
 
+/// The inverse bind poses of a skinned [`FbxMesh`].
+///
+/// Its mesh entities are spawned with a [`SkinnedMesh`] moved by the joints
+/// of [`FbxScene::skin_joints`], followed by the mesh node, which moves the
+/// vertices without weights.
+///
+/// [`SkinnedMesh`]: bevy::render::mesh::skinning::SkinnedMesh
+#[derive(Debug, Clone)]
+pub struct FbxMeshSkin {
+    /// The joint of each cluster of the skin, in the rig of the file.
+    pub joints: Vec<ObjectId>,
+    /// The inverse bind pose of each joint, then of the mesh node.
+    pub inverse_bindposes: Handle<SkinnedMeshInverseBindposes>,
+    /// Inverse bind poses moving all the vertices with the mesh node,
+    /// for the instances of the mesh without a copy of the rig.
+    pub rest_bindposes: Handle<SkinnedMeshInverseBindposes>,
+}
+
+/// A bevy mesh of a [`FbxMesh`], with its material.
+#[derive(Debug, Clone)]
+pub struct FbxPrimitive {
+    /// The bevy mesh, from [`FbxMesh::bevy_mesh_handles`].
+    pub mesh: Handle<Mesh>,
+    /// The material of the mesh, from [`FbxMesh::materials`].
+    pub material: Handle<StandardMaterial>,
+    /// The index of the material in the materials of the FBX mesh node,
+    /// which the polygons of the FBX mesh refer to. `None` for nodes without
+    /// materials, which are drawn with the default material.
+    pub material_index: Option<usize>,
+    /// The name of the material in the file, if any.
+    pub material_name: Option<String>,
+    /// The bounding box of the mesh, from [`FbxMesh::bevy_mesh_aabbs`].
+    pub aabb: Option<Aabb>,
+}
+
+/// Every bevy mesh of a FBX file, without the scene hierarchy, labeled
+/// `Meshes` (`file.fbx#Meshes`).
+///
+/// Meshes are sorted by the object id of their node, and meshes shared by
+/// several nodes instancing the same geometry appear once. Node transforms
+/// and materials are not applied, see [`FbxScene`] for those.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "6d1d22fe-4cf9-4da6-b963-df547310719e"]
+pub struct FbxMeshes(pub Vec<Handle<Mesh>>);
+
 /// The data loaded from a FBX scene.
 ///
 /// Note that the loader spawns a [`Scene`], with all the
@@ -29,11 +147,194 @@ pub struct FbxMesh {
 pub struct FbxScene {
     pub name: Option<String>,
     pub bevy_meshes: HashMap<Handle<Mesh>, String>,
+    /// The materials, by label.
+    ///
+    /// Materials are labeled `FbxMaterial@{name}` with their FBX name, or
+    /// `FbxMaterial{id}` with their object id if they have no name.
+    /// When several materials have the same name, the one with the lowest
+    /// object id keeps it, the others get a `.1`, `.2`... suffix.
     pub materials: HashMap<String, Handle<StandardMaterial>>,
+    /// The materials, sorted by object id, which is stable for a given file.
+    pub ordered_materials: Vec<Handle<StandardMaterial>>,
+    /// The material parameters bevy's `StandardMaterial` can't represent,
+    /// by material label.
+    pub material_extras: HashMap<String, FbxMaterialExtras>,
     pub textures: HashMap<String, Handle<Image>>,
+    /// The full resolution of the textures loaded as proxies, labeled
+    /// `{texture label}/Full`, by texture label,
+    /// see [`FbxLoaderSettings::texture_streaming`].
+    ///
+    /// [`FbxLoaderSettings::texture_streaming`]: crate::FbxLoaderSettings::texture_streaming
+    pub streamed_textures: HashMap<String, Handle<FbxStreamedTexture>>,
     pub meshes: HashMap<ObjectId, Handle<FbxMesh>>,
     pub hierarchy: HashMap<ObjectId, FbxObject>,
     pub roots: Vec<ObjectId>,
+    /// The bevy [`Scene`] of each document of the file, in file order,
+    /// labeled `Scene{index}`.
+    ///
+    /// Most files have a single document. The `Scene` label is the
+    /// scene of the first document.
+    ///
+    /// [`Scene`]: bevy::scene::Scene
+    pub scenes: Vec<Handle<Scene>>,
+    /// The nodes of [`FbxScene::hierarchy`] as assets, sorted by object id,
+    /// the node at `index` being labeled `FbxNode{index}`.
+    pub nodes: Vec<Handle<FbxNode>>,
+    /// The nodes with a name. When several nodes have the same name,
+    /// this is the one with the lowest object id.
+    pub named_nodes: HashMap<String, Handle<FbxNode>>,
+    /// The `AmbientColor` of the FBX `GlobalSettings`.
+    pub ambient_color: Option<Color>,
+    /// All the animation stacks (takes) of the file, in file order.
+    pub animations: Vec<Handle<FbxAnimationClip>>,
+    /// The named animation stacks of the file.
+    pub named_animations: HashMap<String, Handle<FbxAnimationClip>>,
+    /// The metadata of the take each clip of [`FbxScene::animations`]
+    /// comes from, such as its comments and whether it loops.
+    pub take_metadata: HashMap<Handle<FbxAnimationClip>, FbxTakeMetadata>,
+    /// The stored poses of the file (excluding bind poses),
+    /// as single keyframe clips, by name.
+    pub poses: HashMap<String, Handle<FbxAnimationClip>>,
+    /// The bind pose of the skeletons of the file, as a single keyframe clip.
+    ///
+    /// This merges all the bind poses of the file, usually one per skinned mesh.
+    pub bind_pose: Option<Handle<FbxAnimationClip>>,
+    /// The joints of the skeletons of the file, if it has any.
+    pub skeleton: Option<Handle<FbxSkeleton>>,
+    /// The HumanIK character definitions of the file.
+    pub characters: Vec<FbxCharacter>,
+    /// The bounding box of all the meshes of the scene at rest,
+    /// in the space of the spawned [`Scene`].
+    ///
+    /// [`Scene`]: bevy::scene::Scene
+    pub bounds: Option<Aabb>,
+    /// The bounding box of each mesh of [`FbxScene::skin_joints`] over the
+    /// clips of [`FbxScene::animations`], by mesh node, in the space of its
+    /// mesh entities like [`FbxMesh::aabb`].
+    ///
+    /// The mesh entities of skinned meshes are spawned with these bounds,
+    /// so that they aren't culled when their limbs move out of their bounds
+    /// at rest. Meshes loaded unskinned, see [`FbxMesh::skin`], keep their
+    /// bounds at rest. They cover the vertices moved by the `Skin` deformers of the
+    /// file, but not blend shapes or vertex caches.
+    pub skinned_aabbs: HashMap<ObjectId, Aabb>,
+    /// The joints moving each skinned mesh, by mesh node, one per cluster
+    /// of its `Skin` deformers, see [`FbxMesh::skin`].
+    ///
+    /// Nodes instancing a skinned geometry are bound to their own copy of
+    /// the rig: the joints with the same names at the same place under
+    /// their ancestors. Instances without a copy of the rig are left out,
+    /// and spawned at rest.
+    pub skin_joints: HashMap<ObjectId, Vec<ObjectId>>,
+    /// The audio clips and video files referenced by the file.
+    pub media: Vec<FbxMediaRef>,
+    /// The external FBX documents referenced by the file, unresolved unless
+    /// [`FbxLoaderSettings::resolve_references`] is set.
+    ///
+    /// [`FbxLoaderSettings::resolve_references`]: crate::FbxLoaderSettings::resolve_references
+    pub external_references: Vec<FbxExternalReference>,
+    /// The preview thumbnail embedded in the file, labeled `Thumbnail`.
+    ///
+    /// Asset browsers can get it without converting the scene with
+    /// [`FbxLoader::thumbnail_from_bytes`].
+    ///
+    /// [`FbxLoader::thumbnail_from_bytes`]: crate::FbxLoader::thumbnail_from_bytes
+    pub thumbnail: Option<Handle<Image>>,
+    /// The position of the origin of the spawned scene in the file, in the
+    /// units of the file, before the conversion to Bevy's coordinate system.
+    ///
+    /// This is the center of the meshes of the file with
+    /// [`FbxLoaderSettings::recenter`], and zero otherwise.
+    ///
+    /// [`FbxLoaderSettings::recenter`]: crate::FbxLoaderSettings::recenter
+    pub origin: DVec3,
+    /// The memory used by the load of the file, by stage and by asset.
+    pub memory_usage: FbxMemoryUsage,
+}
+
+impl FbxScene {
+    /// The material with the FBX name `name`, see [`FbxScene::materials`]
+    /// for materials sharing the same name.
+    pub fn material_by_name(&self, name: &str) -> Option<&Handle<StandardMaterial>> {
+        self.materials.get(&format!("FbxMaterial@{name}"))
+    }
+
+    /// The node named `name`, see [`FbxScene::named_nodes`].
+    pub fn node_by_name(&self, name: &str) -> Option<&Handle<FbxNode>> {
+        self.named_nodes.get(name)
+    }
+
+    /// The material at `index` in [`FbxScene::ordered_materials`].
+    pub fn material_by_index(&self, index: usize) -> Option<&Handle<StandardMaterial>> {
+        self.ordered_materials.get(index)
+    }
+
+    /// The [`FbxEntityPath`] of each node that can be targeted by animations,
+    /// that is, nodes whose ancestors and themselves all have a name.
+    pub fn entity_paths(&self) -> HashMap<ObjectId, FbxEntityPath> {
+        entity_paths(&self.hierarchy, &self.roots)
+    }
+
+    /// The nodes, meshes and materials added, removed or changed from this
+    /// scene to `other`, such as a new export of the same file, for tools
+    /// showing artists what changed.
+    ///
+    /// The meshes, materials and textures of both scenes are read from the
+    /// `Assets` resources of `world`. Use [`FbxLoadedScene::diff`] for scenes
+    /// loaded without an `AssetServer`.
+    ///
+    /// [`FbxLoadedScene::diff`]: crate::FbxLoadedScene::diff
+    pub fn diff(&self, other: &FbxScene, world: &World) -> FbxSceneDiff {
+        diff::diff(self, world, other, world)
+    }
+}
+
+/// See [`FbxScene::entity_paths`].
+pub(crate) fn entity_paths(
+    hierarchy: &HashMap<ObjectId, FbxObject>,
+    roots: &[ObjectId],
+) -> HashMap<ObjectId, FbxEntityPath> {
+    fn collect(
+        hierarchy: &HashMap<ObjectId, FbxObject>,
+        node: ObjectId,
+        parent: &FbxEntityPath,
+        paths: &mut HashMap<ObjectId, FbxEntityPath>,
+    ) {
+        let object = match hierarchy.get(&node) {
+            Some(object) => object,
+            None => return,
+        };
+        let name = match &object.name {
+            Some(name) => Name::new(name.clone()),
+            None => return,
+        };
+        let mut path = parent.clone();
+        path.parts.push(name);
+        for child in &object.children {
+            collect(hierarchy, *child, &path, paths);
+        }
+        paths.insert(node, path);
+    }
+    let mut paths = HashMap::default();
+    for root in roots {
+        collect(hierarchy, *root, &FbxEntityPath::default(), &mut paths);
+    }
+    paths
+}
+
+/// A node of a FBX scene, to place parts of the file without
+/// spawning the whole scene, see [`FbxScene::nodes`].
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "5dbf1017-8c77-40b5-89a5-91dbe61f17ef"]
+pub struct FbxNode {
+    pub name: Option<String>,
+    /// The transform of the node, relative to its parent.
+    pub transform: Transform,
+    /// The mesh of the node, if any.
+    pub mesh: Option<Handle<FbxMesh>>,
+    pub children: Vec<Handle<FbxNode>>,
+    /// The user-defined properties of this node.
+    pub extras: FbxExtras,
 }
 
 /// An FBX object in the scene tree.
@@ -51,4 +352,6 @@ pub struct FbxObject {
     /// Meaning that you won't find the `ObjectId` in `hierarchy` or `meshes`
     /// `HashMap`s of the [`FbxScene`] structure.
     pub children: Vec<ObjectId>,
+    /// The user-defined properties of this node.
+    pub extras: FbxExtras,
 }
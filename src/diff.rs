@@ -0,0 +1,266 @@
+//! Differences between two FBX scenes, see [`FbxScene::diff`].
+//!
+//! [`FbxScene::diff`]: crate::FbxScene::diff
+
+use bevy::{
+    asset::Asset,
+    prelude::{Assets, Handle, Image, Mesh, StandardMaterial, World},
+    reflect::Struct,
+    render::mesh::{Indices, MeshVertexAttributeId},
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use crate::{FbxEntityPath, FbxLoadedScene, FbxMesh, FbxScene};
+
+/// What changed between two FBX scenes, such as two exports of the same
+/// file, see [`FbxScene::diff`].
+///
+/// Nodes and meshes are matched by the [`FbxEntityPath`] of their node,
+/// or by object id for the nodes without one, and are listed by their
+/// object id in the scene they are in. Materials are matched by label,
+/// see [`FbxScene::materials`]. All the lists are sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FbxSceneDiff {
+    /// The nodes only in the new scene.
+    pub added_nodes: Vec<ObjectId>,
+    /// The nodes only in the old scene.
+    pub removed_nodes: Vec<ObjectId>,
+    /// The nodes whose transform or user-defined properties changed,
+    /// in the old and the new scene.
+    pub changed_nodes: Vec<(ObjectId, ObjectId)>,
+    /// The mesh nodes only in the new scene, or whose node wasn't a mesh.
+    pub added_meshes: Vec<ObjectId>,
+    /// The mesh nodes only in the old scene, or whose node isn't a mesh anymore.
+    pub removed_meshes: Vec<ObjectId>,
+    /// The mesh nodes whose vertices, indices or materials changed,
+    /// in the old and the new scene.
+    pub changed_meshes: Vec<(ObjectId, ObjectId)>,
+    /// The labels of the materials only in the new scene.
+    pub added_materials: Vec<String>,
+    /// The labels of the materials only in the old scene.
+    pub removed_materials: Vec<String>,
+    /// The labels of the materials whose parameters or textures changed.
+    pub changed_materials: Vec<String>,
+}
+impl FbxSceneDiff {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        *self == FbxSceneDiff::default()
+    }
+}
+
+/// Where the assets of a scene are read from.
+pub(crate) trait AssetSource {
+    fn asset<T: Asset>(&self, handle: &Handle<T>) -> Option<&T>;
+}
+impl AssetSource for World {
+    fn asset<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        self.get_resource::<Assets<T>>()?.get(handle)
+    }
+}
+impl AssetSource for FbxLoadedScene {
+    fn asset<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        self.get(handle)
+    }
+}
+
+/// How a node is matched between two scenes.
+#[derive(Hash, PartialEq, Eq)]
+enum NodeKey {
+    Path(FbxEntityPath),
+    Id(ObjectId),
+}
+
+/// A scene and where to read its assets.
+struct Side<'a, S> {
+    scene: &'a FbxScene,
+    assets: &'a S,
+    /// The label of each material.
+    material_labels: HashMap<&'a Handle<StandardMaterial>, &'a str>,
+}
+impl<'a, S: AssetSource> Side<'a, S> {
+    fn new(scene: &'a FbxScene, assets: &'a S) -> Self {
+        let material_labels = (scene.materials.iter())
+            .map(|(label, handle)| (handle, label.as_str()))
+            .collect();
+        Side {
+            scene,
+            assets,
+            material_labels,
+        }
+    }
+
+    /// The nodes of the scene, by key.
+    fn nodes(&self) -> HashMap<NodeKey, ObjectId> {
+        let mut paths = self.scene.entity_paths();
+        (self.scene.hierarchy.keys())
+            .map(|id| match paths.remove(id) {
+                Some(path) => (NodeKey::Path(path), *id),
+                None => (NodeKey::Id(*id), *id),
+            })
+            .collect()
+    }
+
+    fn mesh(&self, node: ObjectId) -> Option<&'a FbxMesh> {
+        let assets: &'a S = self.assets;
+        assets.asset(self.scene.meshes.get(&node)?)
+    }
+
+    fn material_label(&self, material: &Handle<StandardMaterial>) -> Option<&'a str> {
+        self.material_labels.get(material).copied()
+    }
+}
+
+/// The differences from the scene `old` to the scene `new`.
+pub(crate) fn diff<S: AssetSource, T: AssetSource>(
+    old: &FbxScene,
+    old_assets: &S,
+    new: &FbxScene,
+    new_assets: &T,
+) -> FbxSceneDiff {
+    let old = Side::new(old, old_assets);
+    let new = Side::new(new, new_assets);
+    let mut diff = FbxSceneDiff::default();
+
+    let old_nodes = old.nodes();
+    let mut new_nodes = new.nodes();
+    for (key, old_id) in old_nodes {
+        let Some(new_id) = new_nodes.remove(&key) else {
+            diff.removed_nodes.push(old_id);
+            if old.scene.meshes.contains_key(&old_id) {
+                diff.removed_meshes.push(old_id);
+            }
+            continue;
+        };
+        let (old_node, new_node) = (&old.scene.hierarchy[&old_id], &new.scene.hierarchy[&new_id]);
+        let properties = (&old_node.extras.properties, &new_node.extras.properties);
+        if old_node.transform != new_node.transform || properties.0 != properties.1 {
+            diff.changed_nodes.push((old_id, new_id));
+        }
+        match (old.mesh(old_id), new.mesh(new_id)) {
+            (Some(old_mesh), Some(new_mesh)) => {
+                if !meshes_equal(&old, old_mesh, &new, new_mesh) {
+                    diff.changed_meshes.push((old_id, new_id));
+                }
+            }
+            (Some(_), None) => diff.removed_meshes.push(old_id),
+            (None, Some(_)) => diff.added_meshes.push(new_id),
+            (None, None) => {}
+        }
+    }
+    for new_id in new_nodes.into_values() {
+        diff.added_nodes.push(new_id);
+        if new.scene.meshes.contains_key(&new_id) {
+            diff.added_meshes.push(new_id);
+        }
+    }
+
+    for (label, old_material) in &old.scene.materials {
+        let Some(new_material) = new.scene.materials.get(label) else {
+            diff.removed_materials.push(label.clone());
+            continue;
+        };
+        let materials = (
+            old.assets.asset(old_material),
+            new.assets.asset(new_material),
+        );
+        let equal = match materials {
+            (Some(a), Some(b)) => materials_equal(old.assets, a, new.assets, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        if !equal {
+            diff.changed_materials.push(label.clone());
+        }
+    }
+    diff.added_materials = (new.scene.materials.keys())
+        .filter(|label| !old.scene.materials.contains_key(*label))
+        .cloned()
+        .collect();
+
+    let by_raw = |id: &ObjectId| id.raw();
+    diff.added_nodes.sort_by_key(by_raw);
+    diff.removed_nodes.sort_by_key(by_raw);
+    diff.changed_nodes.sort_by_key(|(id, _)| id.raw());
+    diff.added_meshes.sort_by_key(by_raw);
+    diff.removed_meshes.sort_by_key(by_raw);
+    diff.changed_meshes.sort_by_key(|(id, _)| id.raw());
+    diff.added_materials.sort();
+    diff.removed_materials.sort();
+    diff.changed_materials.sort();
+    diff
+}
+
+/// Whether the bevy meshes of two [`FbxMesh`] have the same vertices,
+/// indices and material labels.
+fn meshes_equal<S: AssetSource, T: AssetSource>(
+    old: &Side<S>,
+    a: &FbxMesh,
+    new: &Side<T>,
+    b: &FbxMesh,
+) -> bool {
+    if a.bevy_mesh_handles.len() != b.bevy_mesh_handles.len() || a.offset != b.offset {
+        return false;
+    }
+    let materials = a.materials.iter().zip(&b.materials);
+    let same_materials = a.materials.len() == b.materials.len()
+        && materials.into_iter().all(|(a, b)| {
+            let (a, b) = (old.material_label(a), new.material_label(b));
+            a == b
+        });
+    let handles = a.bevy_mesh_handles.iter().zip(&b.bevy_mesh_handles);
+    same_materials
+        && handles
+            .into_iter()
+            .all(|(a, b)| match (old.assets.asset(a), new.assets.asset(b)) {
+                (Some(a), Some(b)) => mesh_data_equal(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            })
+}
+
+fn mesh_data_equal(a: &Mesh, b: &Mesh) -> bool {
+    fn attributes(mesh: &Mesh) -> Vec<(MeshVertexAttributeId, &[u8])> {
+        let mut attributes: Vec<_> = (mesh.attributes())
+            .map(|(id, values)| (id, values.get_bytes()))
+            .collect();
+        attributes.sort_by_key(|(id, _)| *id);
+        attributes
+    }
+    let indices = |mesh: &Mesh| match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|i| *i as u32).collect(),
+        Some(Indices::U32(indices)) => indices.clone(),
+        None => Vec::new(),
+    };
+    a.primitive_topology() == b.primitive_topology()
+        && indices(a) == indices(b)
+        && attributes(a) == attributes(b)
+}
+
+/// Whether two materials have the same parameters and textures.
+///
+/// Textures are compared by their pixels, the textures of two files
+/// being different assets even when they are the same image.
+fn materials_equal<S: AssetSource, T: AssetSource>(
+    old: &S,
+    a: &StandardMaterial,
+    new: &T,
+    b: &StandardMaterial,
+) -> bool {
+    (0..a.field_len()).all(|i| {
+        let (Some(field_a), Some(field_b)) = (a.field_at(i), b.field_at(i)) else {
+            return false;
+        };
+        let textures = (field_a.downcast_ref::<Option<Handle<Image>>>())
+            .zip(field_b.downcast_ref::<Option<Handle<Image>>>());
+        match textures {
+            Some((Some(a), Some(b))) => match (old.asset(a), new.asset(b)) {
+                (Some(a), Some(b)) => {
+                    a.data == b.data && a.texture_descriptor.size == b.texture_descriptor.size
+                }
+                _ => a == b,
+            },
+            Some((a, b)) => a.is_none() && b.is_none(),
+            None => field_a.reflect_partial_eq(field_b).unwrap_or(false),
+        }
+    })
+}
@@ -0,0 +1,228 @@
+//! Moving the media embedded in FBX files to files next to them,
+//! see [`FbxLoader::extract_embedded_media`].
+//!
+//! [`FbxLoader::extract_embedded_media`]: crate::FbxLoader::extract_embedded_media
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use bevy::utils::HashMap;
+use fbxcel::{
+    low::{v7400::AttributeValue, FbxVersion},
+    tree::{
+        any::AnyTree,
+        v7400::{NodeId, Tree},
+    },
+    writer::v7400::binary::{FbxFooter, Writer},
+};
+
+/// The FBX file and media files of [`FbxLoader::extract_embedded_media`].
+///
+/// [`FbxLoader::extract_embedded_media`]: crate::FbxLoader::extract_embedded_media
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FbxExtractedMedia {
+    /// The FBX file without the embedded media, its `Video` objects and
+    /// their textures referencing the extracted files instead.
+    pub fbx: Vec<u8>,
+    /// The extracted files, by path relative to the FBX file,
+    /// in the `.fbm` folder of the FBX file.
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+}
+impl FbxExtractedMedia {
+    /// Whether the file had no embedded media.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Write the extracted files next to the FBX file `fbx_path`, then
+    /// replace it with [`FbxExtractedMedia::fbx`].
+    ///
+    /// Existing files are overwritten, nothing is written if
+    /// [`FbxExtractedMedia::is_empty`].
+    pub fn write(&self, fbx_path: &Path) -> io::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let folder = fbx_path.parent().unwrap_or(Path::new(""));
+        for (path, content) in &self.files {
+            let path = folder.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        fs::write(fbx_path, &self.fbx)
+    }
+}
+
+/// Move the media embedded in the FBX file `bytes` to the `media_folder`
+/// folder, relative to the FBX file.
+pub(crate) fn extract(bytes: &[u8], media_folder: &Path) -> anyhow::Result<FbxExtractedMedia> {
+    let (version, mut tree, footer) = match AnyTree::from_seekable_reader(io::Cursor::new(bytes))? {
+        AnyTree::V7400(version, tree, footer) => (version, tree, footer),
+        _ => bail!("Unsupported FBX version, only 7.4 and 7.5 are supported"),
+    };
+    let Some(objects) = tree.root().first_child_by_name("Objects") else {
+        return Ok(FbxExtractedMedia::default());
+    };
+    let objects = objects.node_id();
+
+    // The new path of each extracted video, by its original paths.
+    let mut renamed = HashMap::default();
+    let mut files: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+    let videos: Vec<NodeId> = (objects.to_handle(&tree).children_by_name("Video"))
+        .map(|video| video.node_id())
+        .collect();
+    for video in videos {
+        let handle = video.to_handle(&tree);
+        let Some(content) = handle.first_child_by_name("Content") else {
+            continue;
+        };
+        let Some(bytes) = content.attributes().first().and_then(|a| a.get_binary()) else {
+            continue;
+        };
+        if bytes.is_empty() {
+            continue;
+        }
+        let (content, bytes) = (content.node_id(), bytes.to_vec());
+        let paths = ["RelativeFilename", "Filename"].map(|name| child_string(&tree, video, name));
+        let name = (paths.iter().flatten())
+            .find_map(|path| file_name(path))
+            .unwrap_or_else(|| format!("Video{}", object_id(&tree, video)));
+        let path = match files.iter().find(|(_, existing)| *existing == bytes) {
+            Some((path, _)) => path.clone(),
+            None => {
+                let path = unique_path(&files, media_folder, &name);
+                files.push((path.clone(), bytes));
+                path
+            }
+        };
+        let new_path = path.to_string_lossy().replace('\\', "/");
+        for old_path in paths.into_iter().flatten() {
+            renamed.insert(old_path, new_path.clone());
+        }
+        tree.detach(content);
+        for name in ["RelativeFilename", "Filename"] {
+            set_child_string(&mut tree, video, name, &new_path);
+        }
+        for name in ["RelativeFilename", "Path"] {
+            set_property_string(&mut tree, video, name, &new_path);
+        }
+    }
+    if files.is_empty() {
+        return Ok(FbxExtractedMedia::default());
+    }
+
+    // Textures repeat the paths of their video.
+    let textures: Vec<NodeId> = (objects.to_handle(&tree).children_by_name("Texture"))
+        .map(|texture| texture.node_id())
+        .collect();
+    for texture in textures {
+        for name in ["RelativeFilename", "FileName"] {
+            let new_path = child_string(&tree, texture, name).and_then(|path| renamed.get(&path));
+            if let Some(new_path) = new_path.cloned() {
+                set_child_string(&mut tree, texture, name, &new_path);
+            }
+        }
+        for name in ["RelativeFilename", "FileName"] {
+            let new_path = property_string(&tree, texture, name).and_then(|p| renamed.get(&p));
+            if let Some(new_path) = new_path.cloned() {
+                set_property_string(&mut tree, texture, name, &new_path);
+            }
+        }
+    }
+
+    let unknown1 = footer.as_ref().ok().map(|footer| &footer.unknown1);
+    let fbx = write(&tree, version, unknown1)
+        .map_err(|err| anyhow!("Failed to write the FBX file: {err}"))?;
+    Ok(FbxExtractedMedia { fbx, files })
+}
+
+/// Write `tree` as a binary FBX file.
+fn write(
+    tree: &Tree,
+    version: FbxVersion,
+    unknown1: Option<&[u8; 16]>,
+) -> Result<Vec<u8>, fbxcel::writer::v7400::binary::Error> {
+    let mut writer = Writer::new(io::Cursor::new(Vec::new()), version)?;
+    writer.write_tree(tree)?;
+    let footer = FbxFooter {
+        unknown1,
+        ..Default::default()
+    };
+    Ok(writer.finalize_and_flush(&footer)?.into_inner())
+}
+
+/// The object id of the object node `node`.
+fn object_id(tree: &Tree, node: NodeId) -> i64 {
+    let attributes = node.to_handle(tree).attributes();
+    attributes.first().and_then(|a| a.get_i64()).unwrap_or(0)
+}
+
+/// The file name of the path `path`, written on any platform.
+fn file_name(path: &str) -> Option<String> {
+    let name = path.rsplit(['/', '\\']).next()?;
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+/// A path for the file `name` in `folder`, not used by `files`.
+fn unique_path(files: &[(PathBuf, Vec<u8>)], folder: &Path, name: &str) -> PathBuf {
+    let path = Path::new(name);
+    let (stem, extension) = (path.file_stem(), path.extension());
+    let stem = stem.map_or("media".into(), |stem| stem.to_string_lossy());
+    let mut candidate = folder.join(name);
+    let mut i = 1;
+    while files.iter().any(|(path, _)| *path == candidate) {
+        candidate = folder.join(match extension {
+            Some(extension) => format!("{stem}.{i}.{}", extension.to_string_lossy()),
+            None => format!("{stem}.{i}"),
+        });
+        i += 1;
+    }
+    candidate
+}
+
+/// The string of the child `name` of `node`.
+fn child_string(tree: &Tree, node: NodeId, name: &str) -> Option<String> {
+    let child = node.to_handle(tree).first_child_by_name(name)?;
+    let value = child.attributes().first()?.get_string()?;
+    Some(value.to_owned()).filter(|value| !value.is_empty())
+}
+
+fn set_child_string(tree: &mut Tree, node: NodeId, name: &str, value: &str) {
+    let Some(child) = node.to_handle(tree).first_child_by_name(name) else {
+        return;
+    };
+    let child = child.node_id();
+    if let Some(attribute) = tree.get_attribute_mut(child, 0) {
+        *attribute = AttributeValue::String(value.to_owned());
+    }
+}
+
+/// The `P` node of the property `name` of `node`, in its `Properties70`.
+fn property(tree: &Tree, node: NodeId, name: &str) -> Option<NodeId> {
+    let properties = node.to_handle(tree).first_child_by_name("Properties70")?;
+    let mut nodes = properties.children_by_name("P");
+    let property =
+        nodes.find(|p| p.attributes().first().and_then(|a| a.get_string()) == Some(name));
+    property.map(|property| property.node_id())
+}
+
+/// The string value of the property `name` of `node`.
+fn property_string(tree: &Tree, node: NodeId, name: &str) -> Option<String> {
+    let property = property(tree, node, name)?.to_handle(tree);
+    let value = property.attributes().get(4)?.get_string()?;
+    Some(value.to_owned()).filter(|value| !value.is_empty())
+}
+
+fn set_property_string(tree: &mut Tree, node: NodeId, name: &str, value: &str) {
+    let Some(property) = property(tree, node, name) else {
+        return;
+    };
+    if let Some(attribute) = tree.get_attribute_mut(property, 4) {
+        *attribute = AttributeValue::String(value.to_owned());
+    }
+}
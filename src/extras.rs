@@ -0,0 +1,228 @@
+//! User-defined ("custom") properties of FBX nodes.
+//!
+//! Artists can add their own properties to nodes in their DCC tool,
+//! those are flagged with `U` in the FBX `Properties70` and may be animated.
+//! They are exposed on the node's entity as a [`FbxExtras`] component.
+
+use bevy::{
+    asset::HandleId,
+    prelude::{Component, Handle, ReflectComponent, Vec3},
+    reflect::{FromReflect, Reflect},
+    utils::HashMap,
+};
+use fbxcel_dom::{fbxcel::tree::v7400::NodeHandle, v7400::object::ObjectHandle};
+use serde::Deserialize;
+
+use crate::FbxAnimationClip;
+
+/// The value of a user-defined property.
+#[derive(Clone, Debug, PartialEq, Reflect, FromReflect, Deserialize)]
+pub enum FbxPropertyValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Vector(Vec3),
+    String(String),
+}
+impl FbxPropertyValue {
+    /// Read the value of a `P` node of a `Properties70`, given its type name.
+    fn from_node(type_name: &str, node: &NodeHandle) -> Option<Self> {
+        let values = node.attributes().get(4..)?;
+        let float = |i: usize| -> Option<f32> {
+            let value = values.get(i)?;
+            value
+                .get_f64()
+                .map(|v| v as f32)
+                .or_else(|| value.get_f32())
+                .or_else(|| value.get_i32().map(|v| v as f32))
+                .or_else(|| value.get_i64().map(|v| v as f32))
+        };
+        let int = || -> Option<i32> {
+            let value = values.first()?;
+            value
+                .get_i32()
+                .or_else(|| value.get_i64().map(|v| v as i32))
+        };
+        let value = match type_name {
+            "bool" | "Bool" => FbxPropertyValue::Bool(int()? != 0),
+            "int" | "Integer" | "enum" => FbxPropertyValue::Int(int()?),
+            "double" | "Number" | "float" | "Float" => FbxPropertyValue::Float(float(0)?),
+            "Vector" | "Vector3D" | "Color" | "ColorRGB" => {
+                FbxPropertyValue::Vector(Vec3::new(float(0)?, float(1)?, float(2)?))
+            }
+            "KString" => FbxPropertyValue::String(values.first()?.get_string()?.to_owned()),
+            _ => return None,
+        };
+        Some(value)
+    }
+    /// Build a value of the same type as `self` from the channels
+    /// of an animated property.
+    fn with_channels(&self, channels: &[f64]) -> Option<Self> {
+        let channel = |i: usize| channels.get(i).map(|v| *v as f32);
+        let value = match self {
+            FbxPropertyValue::Bool(_) => FbxPropertyValue::Bool(channel(0)? != 0.0),
+            FbxPropertyValue::Int(_) => FbxPropertyValue::Int(channel(0)?.round() as i32),
+            FbxPropertyValue::Float(_) => FbxPropertyValue::Float(channel(0)?),
+            FbxPropertyValue::Vector(_) => {
+                FbxPropertyValue::Vector(Vec3::new(channel(0)?, channel(1)?, channel(2)?))
+            }
+            FbxPropertyValue::String(_) => return None,
+        };
+        Some(value)
+    }
+    /// The value at `ratio` between `self` and `other`.
+    ///
+    /// Only floats and vectors are interpolated,
+    /// other values change at the next keyframe.
+    fn lerp(&self, other: &Self, ratio: f32) -> Self {
+        match (self, other) {
+            (FbxPropertyValue::Float(a), FbxPropertyValue::Float(b)) => {
+                FbxPropertyValue::Float(a + (b - a) * ratio)
+            }
+            (FbxPropertyValue::Vector(a), FbxPropertyValue::Vector(b)) => {
+                FbxPropertyValue::Vector(a.lerp(*b, ratio))
+            }
+            _ if ratio < 1.0 => self.clone(),
+            _ => other.clone(),
+        }
+    }
+}
+
+/// The animation of a user-defined property in a clip.
+#[derive(Clone, Debug, Default, Reflect, FromReflect)]
+pub struct FbxPropertyCurve {
+    /// Timestamp of each keyframe, in seconds.
+    pub keyframe_timestamps: Vec<f32>,
+    pub values: Vec<FbxPropertyValue>,
+}
+impl FbxPropertyCurve {
+    /// The value of the property at `time` seconds.
+    ///
+    /// Before the first and after the last keyframe,
+    /// the value of the closest keyframe is returned.
+    pub fn sample(&self, time: f32) -> Option<FbxPropertyValue> {
+        let timestamps = &self.keyframe_timestamps;
+        let next = timestamps.partition_point(|t| *t <= time);
+        match next {
+            0 => self.values.first().cloned(),
+            next if next >= self.values.len() => self.values.last().cloned(),
+            next => {
+                let (start, end) = (timestamps[next - 1], timestamps[next]);
+                let ratio = (time - start) / (end - start);
+                Some(self.values[next - 1].lerp(&self.values[next], ratio))
+            }
+        }
+    }
+}
+
+/// The user-defined properties of a FBX node.
+///
+/// Added to the node's entity when it has at least one user-defined property.
+/// Animated properties are sampled at each frame of each clip,
+/// use [`FbxExtras::sample`] to read them at runtime, for example
+/// at the current time of a [`FbxAnimationPlayer`]:
+///
+/// ```ignore
+/// let clip = clips.get(player.clip()).unwrap();
+/// let open = extras.sample(player.clip(), "DoorOpen", player.clip_time(clip));
+/// ```
+///
+/// [`FbxAnimationPlayer`]: crate::FbxAnimationPlayer
+#[derive(Component, Clone, Debug, Default, Reflect, FromReflect)]
+#[reflect(Component)]
+pub struct FbxExtras {
+    /// The static value of each property.
+    pub properties: HashMap<String, FbxPropertyValue>,
+    /// The animated properties, for the id of each clip animating them.
+    ///
+    /// Clips are identified by their [`HandleId`] rather than their handle,
+    /// so that the component can be serialized in a [`DynamicScene`].
+    ///
+    /// [`DynamicScene`]: bevy::scene::DynamicScene
+    pub animations: HashMap<HandleId, HashMap<String, FbxPropertyCurve>>,
+    /// The name of the node in the FBX file, if the loader renamed it,
+    /// see [`FbxLoaderSettings::strip_namespaces`].
+    ///
+    /// [`FbxLoaderSettings::strip_namespaces`]: crate::FbxLoaderSettings::strip_namespaces
+    pub original_name: Option<String>,
+}
+impl FbxExtras {
+    /// Read the user-defined properties of `object`.
+    pub(crate) fn from_object(object: &ObjectHandle) -> Self {
+        let properties = user_properties(object)
+            .filter_map(|node| {
+                let attributes = node.attributes();
+                let name = attributes.first()?.get_string()?;
+                let type_name = attributes.get(1)?.get_string()?;
+                let value = FbxPropertyValue::from_node(type_name, &node)?;
+                Some((name.to_owned(), value))
+            })
+            .collect();
+        FbxExtras {
+            properties,
+            animations: HashMap::default(),
+            original_name: None,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty() && self.animations.is_empty() && self.original_name.is_none()
+    }
+    /// The value of `property` at `time` seconds in `clip`.
+    ///
+    /// This is the static value of the property if `clip` doesn't animate it,
+    /// and `None` if the node has no such property.
+    pub fn sample(
+        &self,
+        clip: &Handle<FbxAnimationClip>,
+        property: &str,
+        time: f32,
+    ) -> Option<FbxPropertyValue> {
+        let curve = self
+            .animations
+            .get(&clip.id())
+            .and_then(|c| c.get(property));
+        match curve {
+            Some(curve) => curve.sample(time),
+            None => self.properties.get(property).cloned(),
+        }
+    }
+    /// Convert the sampled channels of `property` into values
+    /// of the type of the property.
+    pub(crate) fn curve(
+        &self,
+        property: &str,
+        keyframe_timestamps: Vec<f32>,
+        channels: &[Vec<f64>],
+    ) -> Option<FbxPropertyCurve> {
+        let value = self.properties.get(property)?;
+        let values = channels
+            .iter()
+            .map(|channels| value.with_channels(channels))
+            .collect::<Option<_>>()?;
+        Some(FbxPropertyCurve {
+            keyframe_timestamps,
+            values,
+        })
+    }
+}
+
+/// The `P` nodes of the user-defined properties of `object`.
+fn user_properties<'a>(object: &ObjectHandle<'a>) -> impl Iterator<Item = NodeHandle<'a>> {
+    object
+        .node()
+        .first_child_by_name("Properties70")
+        .into_iter()
+        .flat_map(|node| node.children_by_name("P"))
+        .filter(|node| {
+            let flags = node.attributes().get(3).and_then(|a| a.get_string());
+            flags.is_some_and(|flags| flags.contains('U'))
+        })
+}
+
+/// Whether `name` is a user-defined property of `object`.
+pub(crate) fn is_user_property(object: ObjectHandle, name: &str) -> bool {
+    user_properties(&object).any(|node| {
+        let property = node.attributes().first().and_then(|a| a.get_string());
+        property == Some(name)
+    })
+}
@@ -22,10 +22,10 @@ use std::f32::consts::TAU;
 
 use anyhow::Result;
 use bevy::math::{DVec3, EulerRot};
-use bevy::prelude::{Mat4, Transform, Vec3};
+use bevy::prelude::{Mat3, Mat4, Quat, Transform, Vec3};
 use fbxcel_dom::v7400::object::{model::ModelHandle, property::ObjectProperties, ObjectHandle};
 
-use crate::utils::fbx_extend::{InheritType, Loadable};
+use crate::utils::fbx_extend::{InheritType, Loadable, ObjectPropertiesExt};
 
 #[derive(Copy, Clone, Debug)]
 struct Translation(Vec3);
@@ -87,18 +87,31 @@ struct NodeRotation {
 /// where we set the local transform infered
 /// from the computed FBX global position.
 #[derive(Clone, Debug)]
-struct FbxNodeTransformInfo {
+pub(crate) struct FbxNodeTransformInfo {
     rotation: NodeRotation,
     translation: Translation,
     scale: NodeScale,
     inherit_type: InheritType,
+    /// The position removed from `Lcl Translation`, see [`FbxNodeTransformInfo::recentered`].
+    origin: DVec3,
 }
 impl FbxNodeTransformInfo {
     // if you were wondering: "Lcl" stands for "Local"
-    // FIXME: Non-zero {Rotation,Scaling}{Pivot,Offset} is untested.
+    // FIXME: Non-zero {Rotation,Scaling}Offset is untested,
+    // pivots are covered by `tests/coordinates.rs`.
     // TODO: Geometric{Translation,Scaling,Rotation}
     // (see docs.autodesk.com and stackoverflow.com links at top of this file)
-    fn from_object(object: ObjectHandle) -> Result<Self> {
+    pub(crate) fn from_object(object: ObjectHandle) -> Result<Self> {
+        Self::recentered(object, DVec3::ZERO)
+    }
+    /// The transform of the root node `object`, relative to `origin` rather
+    /// than to the origin of the file, see [`FbxLoaderSettings::recenter`].
+    ///
+    /// `origin` is removed from the translation of the node in `f64`, before
+    /// the translation loses the precision of huge values in `f32`.
+    ///
+    /// [`FbxLoaderSettings::recenter`]: crate::FbxLoaderSettings::recenter
+    pub(crate) fn recentered(object: ObjectHandle, origin: DVec3) -> Result<Self> {
         fn load<T: Loadable>(p: ObjectProperties, attribute: &str) -> Result<T> {
             T::get_property(p, attribute)
         }
@@ -112,30 +125,89 @@ impl FbxNodeTransformInfo {
                 pre: Rotation::from_euler(e, load(p, "PreRotation")?),
                 post: Rotation::from_euler(e, load(p, "PostRotation")?),
             },
-            translation: Translation::from_double(load(p, "Lcl Translation")?),
+            translation: Translation::from_double(load::<DVec3>(p, "Lcl Translation")? - origin),
             scale: NodeScale {
                 pivot: Translation::from_double(load(p, "ScalingPivot")?),
                 offset: Translation::from_double(load(p, "ScalingOffset")?),
                 local: Scale::from_double(load(p, "Lcl Scaling")?),
             },
-            inherit_type: load(p, "InheritType")?,
+            // Files without a `FbxNode` property template may leave out the default,
+            // unknown values are reported by the loader, see `unknown_inherit_type`.
+            inherit_type: p.get("InheritType").unwrap_or_default(),
+            origin,
         })
     }
+    /// The static `Lcl {Translation,Rotation,Scaling}` properties of the node,
+    /// rotation in degrees.
+    pub(crate) fn rest_local(&self) -> AnimatedLocal {
+        AnimatedLocal {
+            translation: Some(self.translation.0.as_dvec3() + self.origin),
+            rotation: Some(self.rotation.local.0.as_dvec3() * -(360.0 / std::f64::consts::TAU)),
+            scale: Some(self.scale.local.0.as_dvec3()),
+        }
+    }
+    /// The order in which the euler angles of `Lcl Rotation` are applied.
+    pub(crate) fn rotation_order(&self) -> EulerRot {
+        self.rotation.local.1
+    }
+    /// This transform with the `Lcl {Translation,Rotation,Scaling}`
+    /// properties replaced by their animated values.
+    pub(crate) fn animated(&self, local: &AnimatedLocal) -> Self {
+        let mut animated = self.clone();
+        if let Some(translation) = local.translation {
+            animated.translation = Translation::from_double(translation - self.origin);
+        }
+        if let Some(rotation) = local.rotation {
+            animated.rotation.local = Rotation::from_euler(self.rotation.local.1, rotation);
+        }
+        if let Some(scale) = local.scale {
+            animated.scale.local = Scale::from_double(scale);
+        }
+        animated
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub(crate) struct LocalScale(Scale);
+/// The value of the `InheritType` property of `object`, if it is not one of
+/// the [`InheritType`] modes, which [`FbxNodeTransformInfo`] replaces by the
+/// default [`InheritType::RrSs`].
+pub(crate) fn unknown_inherit_type(object: ObjectHandle) -> Option<String> {
+    let p = object.properties_by_native_typename("FbxNode");
+    let property = p.get_property("InheritType")?;
+    if p.get::<InheritType>("InheritType").is_some() {
+        return None;
+    }
+    Some(format!("{:?}", property.value_part().first()?))
+}
+
+/// The animated values of the `Lcl {Translation,Rotation,Scaling}` properties
+/// of a node at a given time, `None` when the property is not animated.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AnimatedLocal {
+    pub(crate) translation: Option<DVec3>,
+    pub(crate) rotation: Option<DVec3>,
+    pub(crate) scale: Option<DVec3>,
+}
 
 // This is similar to mat.to_scale_rotation_translation()
 // but takes into account shear operations (meaning: rotation followed by non-uniform scale)
 // The implementation is the one used in the Autodesk scene translation example file.
+// The rotation is the closest one to `mat` (see `decompose`), so that a sheared parent,
+// such as the parent of a segment scale compensated joint under a non-uniformly scaled
+// limb, leaves its exact local scale in the returned shear scale.
 fn get_reverse_transform(mat: Mat4) -> (Mat4, Mat4, Mat4) {
     let mat_q = Mat4::from_quat;
     let mat_t = Mat4::from_translation;
-    let (_, rotation, translation) = mat.to_scale_rotation_translation();
+    let (
+        Transform {
+            rotation,
+            translation,
+            ..
+        },
+        _,
+    ) = decompose(mat);
     let rotation = mat_q(rotation);
     let translation = mat_t(translation);
-    let shear_scale = mat * rotation.inverse() * translation.inverse();
+    let shear_scale = rotation.inverse() * translation.inverse() * mat;
     (shear_scale, rotation, translation)
 }
 
@@ -154,8 +226,7 @@ fn global_transform(node: FbxNodeTransformInfo, parent: Option<FbxTransform>) ->
         local_scale: local_parent_scale,
     } = parent.unwrap_or_default();
 
-    let (parent_shear_scale, parent_rotation, parent_translation) =
-        get_reverse_transform(parent_transform);
+    let (parent_shear_scale, parent_rotation, _) = get_reverse_transform(parent_transform);
     let parent_nonlocal_scale = parent_shear_scale * local_parent_scale.mat().inverse();
 
     let inherited_rot_scale = match node.inherit_type {
@@ -170,11 +241,7 @@ fn global_transform(node: FbxNodeTransformInfo, parent: Option<FbxTransform>) ->
         * with_off_piv(rot.offset, rot.pivot, rotation)
         * with_off_piv(scale.offset, scale.pivot, scale.local.mat());
     let translation = translation.to_scale_rotation_translation().2;
-    // NOTE: this is unlike the Autodesk resource provided on top, it seems
-    // we need to remove the scale component from the parent's global matrix
-    // we multiply the translation with. Absolutely no idea why, but it works.
-    let parent_non_scale_transform = parent_translation * parent_rotation;
-    let global_translation = parent_non_scale_transform.transform_vector3(translation);
+    let global_translation = parent_transform.transform_point3(translation);
     mat_t(global_translation) * inherited_rot_scale
 }
 
@@ -198,7 +265,7 @@ impl FbxTransform {
         let transform = FbxNodeTransformInfo::from_object(*node).unwrap();
         FbxTransform::from_fbxtrans(transform, parent)
     }
-    fn from_fbxtrans(trans: FbxNodeTransformInfo, parent: Option<FbxTransform>) -> Self {
+    pub(crate) fn from_fbxtrans(trans: FbxNodeTransformInfo, parent: Option<FbxTransform>) -> Self {
         FbxTransform {
             local_scale: trans.scale.local,
             global: global_transform(trans, parent),
@@ -213,13 +280,121 @@ impl FbxTransform {
     // 4. We need to find the child(Transform):
     //    - from bevy's transform mat: child(GlobalTransform) = parent(GlobalTransform) * child(Transform)
     //    - We have: child(GlobalTransform) and parent(GlobalTransform)
-    //    - child(Transform) = child(GlobalTransform) * parent(GlobalTransform)¯¹
+    //    - child(Transform) = parent(GlobalTransform)¯¹ * child(GlobalTransform)
     pub(crate) fn as_local_transform(&self, parent: Option<Mat4>) -> Transform {
+        self.as_local_decomposed(parent).0
+    }
+    /// [`Self::as_local_transform`], with the shear of the local transform
+    /// it drops, see [`decompose`].
+    pub(crate) fn as_local_decomposed(&self, parent: Option<Mat4>) -> (Transform, f32) {
         let mat = if let Some(parent) = parent {
-            self.global * parent.inverse()
+            parent.inverse() * self.global
         } else {
             self.global
         };
-        Transform::from_matrix(mat)
+        decompose(mat)
+    }
+}
+
+/// Shear above which a transform is reported as sheared, see [`decompose`].
+pub(crate) const SHEAR_TOLERANCE: f32 = 1e-3;
+
+/// Decompose `mat` into a bevy [`Transform`], and the amount of shear of
+/// `mat`, relative to its scale, which a `Transform` can't represent.
+///
+/// Non-uniform scales combined with rotations down the hierarchy, as in
+/// squash and stretch rigs, shear the transforms. [`Transform::from_matrix`]
+/// then takes a skewed rotation from the normalized axes of `mat`, here the
+/// rotation is the closest one to `mat`, from its polar decomposition,
+/// and the scale is what remains along the rotated axes.
+pub(crate) fn decompose(mat: Mat4) -> (Transform, f32) {
+    let mut linear = Mat3::from_mat4(mat);
+    let determinant = linear.determinant();
+    if !determinant.is_normal() {
+        // Flattened or degenerate, there is no rotation to recover.
+        return (Transform::from_matrix(mat), 0.0);
+    }
+    // Mirrored transforms: decompose the unmirrored matrix, mirror the scale.
+    let mirrored = determinant < 0.0;
+    if mirrored {
+        linear.x_axis = -linear.x_axis;
+    }
+    // Newton iterations converging to the orthogonal factor of `linear`.
+    let mut rotation = linear;
+    for _ in 0..32 {
+        let next = (rotation + rotation.inverse().transpose()) * 0.5;
+        let converged = next.abs_diff_eq(rotation, 1e-7);
+        rotation = next;
+        if converged {
+            break;
+        }
+    }
+    let stretch = rotation.transpose() * linear;
+    let mut scale = Vec3::new(stretch.x_axis.x, stretch.y_axis.y, stretch.z_axis.z);
+    let off_diagonal = stretch - Mat3::from_diagonal(scale);
+    let norm = |m: Mat3| {
+        (m.x_axis.length_squared() + m.y_axis.length_squared() + m.z_axis.length_squared()).sqrt()
+    };
+    let shear = norm(off_diagonal) / scale.length();
+    if mirrored {
+        scale.x = -scale.x;
+    }
+    let transform = Transform {
+        translation: mat.w_axis.truncate(),
+        rotation: Quat::from_mat3(&rotation).normalize(),
+        scale,
+    };
+    (transform, shear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A node without pivots, offsets or pre/post rotations.
+    fn node(translation: Vec3, rotation: Vec3, scale: Vec3) -> FbxNodeTransformInfo {
+        let zero = Translation(Vec3::ZERO);
+        let none = Rotation(Vec3::ZERO, EulerRot::XYZ);
+        FbxNodeTransformInfo {
+            rotation: NodeRotation {
+                pivot: zero,
+                offset: zero,
+                local: Rotation::from_euler(EulerRot::XYZ, rotation.as_dvec3()),
+                pre: none,
+                post: none,
+            },
+            translation: Translation(translation),
+            scale: NodeScale {
+                pivot: zero,
+                offset: zero,
+                local: Scale(scale),
+            },
+            inherit_type: InheritType::RrSs,
+            origin: DVec3::ZERO,
+        }
+    }
+
+    /// Children of translated, rotated and scaled parents are placed by the
+    /// whole parent transform, and keep their own local transform in bevy.
+    #[test]
+    fn child_of_transformed_parent() {
+        let parent = node(
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 90.0, 0.0),
+            Vec3::splat(2.0),
+        );
+        let parent = FbxTransform::from_fbxtrans(parent, None);
+        let translation = Vec3::new(1.0, 2.0, 3.0);
+        let child = node(translation, Vec3::ZERO, Vec3::ONE);
+        let child = FbxTransform::from_fbxtrans(child, Some(parent));
+
+        let expected = parent.global.transform_point3(translation);
+        let actual = child.global.transform_point3(Vec3::ZERO);
+        assert!(actual.abs_diff_eq(expected, 1e-5), "{actual} != {expected}");
+
+        let local = child.as_local_transform(Some(parent.global));
+        assert!(local.translation.abs_diff_eq(translation, 1e-5));
+        assert!(local.rotation.abs_diff_eq(Quat::IDENTITY, 1e-5));
+        assert!(local.scale.abs_diff_eq(Vec3::ONE, 1e-5));
     }
 }
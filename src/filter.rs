@@ -0,0 +1,109 @@
+//! Nodes dropped at import, see [`FbxLoaderSettings::exclude_nodes`].
+//!
+//! [`FbxLoaderSettings::exclude_nodes`]: crate::FbxLoaderSettings::exclude_nodes
+
+use bevy::{
+    prelude::Mat4,
+    utils::{HashMap, HashSet},
+};
+use fbxcel_dom::v7400::object::ObjectId;
+use serde::Deserialize;
+
+use crate::{data::FbxObject, names, FbxLoaderSettings, FbxPropertyValue};
+
+/// Which nodes [`FbxLoaderSettings::include_nodes`] and
+/// [`FbxLoaderSettings::exclude_nodes`] select.
+///
+/// [`FbxLoaderSettings::include_nodes`]: crate::FbxLoaderSettings::include_nodes
+/// [`FbxLoaderSettings::exclude_nodes`]: crate::FbxLoaderSettings::exclude_nodes
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum FbxNodeFilter {
+    /// Nodes whose name matches this glob pattern, `*` matching any
+    /// characters and `?` one character. Matching is case-sensitive, and done
+    /// after [`FbxLoaderSettings::strip_namespaces`] and
+    /// [`FbxLoaderSettings::namespace_remap`] are applied.
+    ///
+    /// [`FbxLoaderSettings::strip_namespaces`]: crate::FbxLoaderSettings::strip_namespaces
+    /// [`FbxLoaderSettings::namespace_remap`]: crate::FbxLoaderSettings::namespace_remap
+    Name(String),
+    /// Nodes with a user-defined property of this value, see [`FbxExtras`].
+    ///
+    /// [`FbxExtras`]: crate::FbxExtras
+    Property {
+        name: String,
+        value: FbxPropertyValue,
+    },
+}
+impl FbxNodeFilter {
+    pub(crate) fn matches(&self, node: &FbxObject, settings: &FbxLoaderSettings) -> bool {
+        match self {
+            FbxNodeFilter::Name(pattern) => node
+                .name
+                .as_ref()
+                .is_some_and(|name| names::glob_match(pattern, &names::sanitize(name, settings))),
+            FbxNodeFilter::Property { name, value } => {
+                node.extras.properties.get(name) == Some(value)
+            }
+        }
+    }
+}
+
+/// Remove the nodes dropped by the include and exclude filters of `settings`
+/// from `hierarchy`, `globals` and `roots`.
+///
+/// Returns the removed nodes.
+pub(crate) fn filter_hierarchy(
+    settings: &FbxLoaderSettings,
+    hierarchy: &mut HashMap<ObjectId, FbxObject>,
+    globals: &mut HashMap<ObjectId, Mat4>,
+    roots: &mut Vec<ObjectId>,
+) -> HashSet<ObjectId> {
+    /// Collect the kept nodes under `node`, `included` is whether an
+    /// ancestor matches the include filters. Returns whether `node` is kept.
+    fn visit(
+        settings: &FbxLoaderSettings,
+        hierarchy: &HashMap<ObjectId, FbxObject>,
+        node: ObjectId,
+        included: bool,
+        kept: &mut HashSet<ObjectId>,
+    ) -> bool {
+        let Some(object) = hierarchy.get(&node) else {
+            return false;
+        };
+        let matches = |filters: &[FbxNodeFilter]| {
+            filters
+                .iter()
+                .any(|filter| filter.matches(object, settings))
+        };
+        if matches(&settings.exclude_nodes) {
+            return false;
+        }
+        let included = included || matches(&settings.include_nodes);
+        let mut has_kept_child = false;
+        for child in &object.children {
+            has_kept_child |= visit(settings, hierarchy, *child, included, kept);
+        }
+        let is_kept = included || has_kept_child;
+        if is_kept {
+            kept.insert(node);
+        }
+        is_kept
+    }
+    if settings.include_nodes.is_empty() && settings.exclude_nodes.is_empty() {
+        return HashSet::new();
+    }
+    let included = settings.include_nodes.is_empty();
+    let mut kept = HashSet::new();
+    for root in roots.iter() {
+        visit(settings, hierarchy, *root, included, &mut kept);
+    }
+    let removed: HashSet<ObjectId> = hierarchy
+        .keys()
+        .filter(|node| !kept.contains(*node))
+        .copied()
+        .collect();
+    hierarchy.retain(|node, _| kept.contains(node));
+    globals.retain(|node, _| kept.contains(node));
+    roots.retain(|root| kept.contains(root));
+    removed
+}
@@ -0,0 +1,77 @@
+//! Subtrees detached from their ancestors at import, see
+//! [`FbxLoaderSettings::frozen_nodes`].
+//!
+//! [`FbxLoaderSettings::frozen_nodes`]: crate::FbxLoaderSettings::frozen_nodes
+
+use bevy::{prelude::Mat4, utils::HashMap};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use crate::{data::FbxObject, fbx_transform::decompose, FbxLoaderSettings};
+
+/// A node matching [`FbxLoaderSettings::frozen_nodes`], moved to the roots.
+///
+/// [`FbxLoaderSettings::frozen_nodes`]: crate::FbxLoaderSettings::frozen_nodes
+pub(crate) struct FrozenNode {
+    pub(crate) node: ObjectId,
+    /// The parent of the node in the file.
+    pub(crate) parent: ObjectId,
+    /// The root the node was under in the file.
+    pub(crate) root: ObjectId,
+}
+
+/// Move the nodes of `hierarchy` matching [`FbxLoaderSettings::frozen_nodes`]
+/// to `roots`, with their transform set to their global transform in `globals`.
+///
+/// Returns the moved nodes, parents before their descendants.
+///
+/// [`FbxLoaderSettings::frozen_nodes`]: crate::FbxLoaderSettings::frozen_nodes
+pub(crate) fn freeze_hierarchy(
+    settings: &FbxLoaderSettings,
+    hierarchy: &mut HashMap<ObjectId, FbxObject>,
+    globals: &HashMap<ObjectId, Mat4>,
+    roots: &mut Vec<ObjectId>,
+) -> Vec<FrozenNode> {
+    /// Collect the frozen nodes under `node`, which is under `root`.
+    fn visit(
+        settings: &FbxLoaderSettings,
+        hierarchy: &HashMap<ObjectId, FbxObject>,
+        node: ObjectId,
+        root: ObjectId,
+        frozen: &mut Vec<FrozenNode>,
+    ) {
+        let Some(object) = hierarchy.get(&node) else {
+            return;
+        };
+        for child in &object.children {
+            let is_frozen = hierarchy.get(child).is_some_and(|child| {
+                (settings.frozen_nodes.iter()).any(|filter| filter.matches(child, settings))
+            });
+            if is_frozen {
+                frozen.push(FrozenNode {
+                    node: *child,
+                    parent: node,
+                    root,
+                });
+            }
+            visit(settings, hierarchy, *child, root, frozen);
+        }
+    }
+    if settings.frozen_nodes.is_empty() {
+        return Vec::new();
+    }
+    // Roots have no ancestors to detach from.
+    let mut frozen = Vec::new();
+    for root in roots.iter() {
+        visit(settings, hierarchy, *root, *root, &mut frozen);
+    }
+    for FrozenNode { node, parent, .. } in &frozen {
+        if let Some(parent) = hierarchy.get_mut(parent) {
+            parent.children.retain(|child| child != node);
+        }
+        if let (Some(object), Some(global)) = (hierarchy.get_mut(node), globals.get(node)) {
+            object.transform = decompose(*global).0;
+        }
+        roots.push(*node);
+    }
+    frozen
+}
@@ -0,0 +1,261 @@
+//! Spawning FBX scenes over several frames, see [`FbxIncrementalSpawn`].
+
+use std::{any::TypeId, ops::Range};
+
+use bevy::{
+    ecs::{entity::EntityMap, reflect::ReflectMapEntities},
+    prelude::{
+        warn, AppTypeRegistry, Assets, BuildWorldChildren, Children, Component, Entity, Handle,
+        Mut, Parent, ReflectComponent, World,
+    },
+    scene::Scene,
+    utils::HashMap,
+};
+
+/// Spawn the scene `scene` as children of this entity over several frames,
+/// `entities_per_frame` entities at a time, instead of the whole scene in
+/// the frame it is loaded as with a `SceneBundle`.
+///
+/// ```ignore
+/// commands.spawn((
+///     SpatialBundle::default(),
+///     FbxIncrementalSpawn::new(asset_server.load("city.fbx#Scene"), 500),
+/// ));
+/// ```
+///
+/// Parents are spawned before their children, so the scene grows from
+/// its root. A [`FbxSceneSpawned`] event is sent once the whole hierarchy
+/// is spawned. Changing the scene of a spawn in progress has no effect.
+#[derive(Component, Debug, Clone)]
+pub struct FbxIncrementalSpawn {
+    pub scene: Handle<Scene>,
+    /// Maximum number of entities spawned each frame, at least one.
+    pub entities_per_frame: usize,
+}
+impl FbxIncrementalSpawn {
+    pub fn new(scene: Handle<Scene>, entities_per_frame: usize) -> Self {
+        FbxIncrementalSpawn {
+            scene,
+            entities_per_frame,
+        }
+    }
+}
+
+/// Event sent when the scene of a [`FbxIncrementalSpawn`] is fully spawned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FbxSceneSpawned {
+    /// The entity with the [`FbxIncrementalSpawn`].
+    pub entity: Entity,
+}
+
+/// The progress of a [`FbxIncrementalSpawn`], on the same entity.
+#[derive(Component)]
+pub(crate) struct IncrementalSpawnProgress {
+    template: SceneTemplate,
+    /// The entity in the world of each entity of the scene, all reserved
+    /// upfront so that components can refer to entities not spawned yet.
+    entity_map: EntityMap,
+    entities: Vec<Entity>,
+    /// The number of entities of the scene already spawned.
+    spawned: usize,
+}
+impl IncrementalSpawnProgress {
+    /// Reserve the entities of `scene`, in `world`.
+    fn new(scene: &World, world: &mut World) -> Self {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let template = SceneTemplate::new(scene, &registry);
+        let (entity_map, entities) = template.reserve(world);
+        IncrementalSpawnProgress {
+            template,
+            entity_map,
+            entities,
+            spawned: 0,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.spawned == self.entities.len()
+    }
+
+    /// Copy the next `count` entities of `scene` to `world`, the entities
+    /// without parent as children of `root`.
+    fn spawn(&mut self, scene: &World, world: &mut World, root: Entity, count: usize) {
+        let end = (self.spawned + count.max(1)).min(self.entities.len());
+        let batch = self.spawned..end;
+        self.template
+            .copy(scene, world, root, &self.entity_map, &self.entities, batch);
+        self.spawned = end;
+    }
+}
+
+/// How to copy the entities of a scene to a world, looked up once per scene.
+///
+/// Used by [`FbxIncrementalSpawn`] and [`FbxSceneInstance`].
+///
+/// [`FbxSceneInstance`]: crate::FbxSceneInstance
+pub(crate) struct SceneTemplate {
+    /// The entities of the scene, parents first, with the index of their
+    /// parent and the components to copy.
+    entities: Vec<(Entity, Option<usize>, Vec<ReflectComponent>)>,
+    /// The components of the scene referring to other entities, by type name.
+    map_entities: Vec<(&'static str, ReflectMapEntities)>,
+}
+impl SceneTemplate {
+    /// The template of `scene`, warning once about each component type
+    /// that isn't registered and so can't be copied.
+    pub(crate) fn new(scene: &World, registry: &AppTypeRegistry) -> Self {
+        let registry = registry.read();
+        // The hierarchy is rebuilt as entities are copied.
+        let hierarchy = [TypeId::of::<Parent>(), TypeId::of::<Children>()];
+        let order = hierarchy_order(scene);
+        let indices: HashMap<Entity, usize> = (order.iter().enumerate())
+            .map(|(i, entity)| (*entity, i))
+            .collect();
+        let mut types = Vec::new();
+        let mut unregistered = Vec::new();
+        let entities = (order.iter())
+            .map(|scene_entity| {
+                let source = scene.entity(*scene_entity);
+                let parent = source.get::<Parent>().map(|parent| indices[&parent.get()]);
+                let components = (source.archetype().components())
+                    .filter_map(|component| {
+                        let info = scene.components().get_info(component)?;
+                        let type_id = info.type_id().filter(|id| !hierarchy.contains(id))?;
+                        let registration = registry.get(type_id);
+                        let reflect = registration.and_then(|r| r.data::<ReflectComponent>());
+                        match reflect {
+                            Some(_) => types.push(type_id),
+                            None => unregistered.push(info.name().to_owned()),
+                        }
+                        reflect.cloned()
+                    })
+                    .collect();
+                (*scene_entity, parent, components)
+            })
+            .collect();
+        unregistered.sort_unstable();
+        unregistered.dedup();
+        for name in unregistered {
+            warn!("Can't spawn unregistered component {name}");
+        }
+        types.sort_unstable();
+        types.dedup();
+        let map_entities = (types.iter())
+            .filter_map(|type_id| {
+                let registration = registry.get(*type_id)?;
+                let map_entities = registration.data::<ReflectMapEntities>()?;
+                Some((registration.type_name(), map_entities.clone()))
+            })
+            .collect();
+        SceneTemplate {
+            entities,
+            map_entities,
+        }
+    }
+
+    /// Reserve the entities of a copy of the scene in `world`, returning
+    /// the entity of each entity of the scene, in the template order.
+    pub(crate) fn reserve(&self, world: &mut World) -> (EntityMap, Vec<Entity>) {
+        let mut entity_map = EntityMap::default();
+        let entities = (self.entities.iter())
+            .map(|(scene_entity, ..)| {
+                let entity = world.spawn_empty().id();
+                entity_map.insert(*scene_entity, entity);
+                entity
+            })
+            .collect();
+        (entity_map, entities)
+    }
+
+    /// Copy the entities `batch` of `scene`, in the template order, to their
+    /// reserved `entities` of `world`, the entities without parent as
+    /// children of `root`.
+    ///
+    /// Parents must be copied before or with their children.
+    pub(crate) fn copy(
+        &self,
+        scene: &World,
+        world: &mut World,
+        root: Entity,
+        entity_map: &EntityMap,
+        entities: &[Entity],
+        batch: Range<usize>,
+    ) {
+        for i in batch.clone() {
+            let (scene_entity, parent, components) = &self.entities[i];
+            for component in components {
+                component.copy(scene, world, *scene_entity, entities[i]);
+            }
+            let parent = parent.map_or(root, |parent| entities[parent]);
+            world.entity_mut(parent).add_child(entities[i]);
+        }
+        for (name, map_entities) in &self.map_entities {
+            if let Err(err) =
+                map_entities.map_specific_entities(world, entity_map, &entities[batch.clone()])
+            {
+                warn!("Can't map the entities of {name}: {err}");
+            }
+        }
+    }
+
+    /// Copy all the entities of `scene` to `world`, the entities without
+    /// parent as children of `root`.
+    pub(crate) fn spawn(&self, scene: &World, world: &mut World, root: Entity) {
+        let (entity_map, entities) = self.reserve(world);
+        self.copy(
+            scene,
+            world,
+            root,
+            &entity_map,
+            &entities,
+            0..entities.len(),
+        );
+    }
+}
+
+/// The entities of the scene world `scene`, parents before their children.
+fn hierarchy_order(scene: &World) -> Vec<Entity> {
+    let mut order: Vec<Entity> = (scene.iter_entities())
+        .filter(|entity| !entity.contains::<Parent>())
+        .map(|entity| entity.id())
+        .collect();
+    let mut i = 0;
+    while i < order.len() {
+        if let Some(children) = scene.get::<Children>(order[i]) {
+            order.extend(children.iter());
+        }
+        i += 1;
+    }
+    order
+}
+
+/// Spawn the next entities of the [`FbxIncrementalSpawn`] scenes.
+pub(crate) fn spawn_incrementally(world: &mut World) {
+    let mut query = world.query::<(Entity, &FbxIncrementalSpawn)>();
+    let spawns: Vec<_> = (query.iter(world))
+        .map(|(entity, spawn)| (entity, spawn.clone()))
+        .collect();
+    if spawns.is_empty() {
+        return;
+    }
+    world.resource_scope(|world, scenes: Mut<Assets<Scene>>| {
+        for (entity, spawn) in spawns {
+            let progress = world.get::<IncrementalSpawnProgress>(entity);
+            if progress.is_some_and(IncrementalSpawnProgress::is_done) {
+                continue;
+            }
+            let Some(scene) = scenes.get(&spawn.scene) else {
+                continue;
+            };
+            let mut progress = match world.entity_mut(entity).take::<IncrementalSpawnProgress>() {
+                Some(progress) => progress,
+                None => IncrementalSpawnProgress::new(&scene.world, world),
+            };
+            progress.spawn(&scene.world, world, entity, spawn.entities_per_frame);
+            if progress.is_done() {
+                world.send_event(FbxSceneSpawned { entity });
+            }
+            world.entity_mut(entity).insert(progress);
+        }
+    });
+}
@@ -0,0 +1,76 @@
+//! Spawning many instances of the same FBX scene, see [`FbxSceneInstance`].
+
+use bevy::{
+    asset::HandleId,
+    ecs::event::ManualEventReader,
+    prelude::{
+        AppTypeRegistry, AssetEvent, Assets, Component, Entity, Events, Handle, Local, Mut,
+        Without, World,
+    },
+    scene::Scene,
+    utils::HashMap,
+};
+
+use crate::incremental::SceneTemplate;
+
+/// Spawn an instance of the scene as children of this entity, like a
+/// `SceneBundle`, for scenes spawned many times such as the trees of a forest.
+///
+/// ```ignore
+/// let tree = asset_server.load("tree.fbx#Scene");
+/// for position in tree_positions {
+///     commands.spawn((
+///         SpatialBundle::from_transform(Transform::from_translation(position)),
+///         FbxSceneInstance(tree.clone()),
+///     ));
+/// }
+/// ```
+///
+/// The hierarchy of the scene and the reflection data of its components
+/// are looked up once, when its first instance is spawned. All instances
+/// share the meshes, materials and other assets of the scene.
+/// Instances are not updated when the scene is reloaded, only later ones.
+#[derive(Component, Debug, Clone, Default)]
+pub struct FbxSceneInstance(pub Handle<Scene>);
+
+/// Marks the [`FbxSceneInstance`] already spawned.
+#[derive(Component)]
+pub(crate) struct InstanceSpawned;
+
+/// The templates of the scenes of [`FbxSceneInstance`]s.
+#[derive(Default)]
+pub(crate) struct SceneTemplates {
+    templates: HashMap<HandleId, SceneTemplate>,
+    scene_events: ManualEventReader<AssetEvent<Scene>>,
+}
+
+/// Spawn the new [`FbxSceneInstance`]s whose scene is loaded.
+pub(crate) fn spawn_scene_instances(world: &mut World, mut templates: Local<SceneTemplates>) {
+    let templates = &mut *templates;
+    let events = world.resource::<Events<AssetEvent<Scene>>>();
+    for event in templates.scene_events.iter(events) {
+        if let AssetEvent::Modified { handle } | AssetEvent::Removed { handle } = event {
+            templates.templates.remove(&handle.id());
+        }
+    }
+    let mut query = world.query_filtered::<(Entity, &FbxSceneInstance), Without<InstanceSpawned>>();
+    let instances: Vec<_> = (query.iter(world))
+        .map(|(entity, instance)| (entity, instance.0.clone_weak()))
+        .collect();
+    if instances.is_empty() {
+        return;
+    }
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    world.resource_scope(|world, scenes: Mut<Assets<Scene>>| {
+        for (entity, scene) in instances {
+            let Some(scene_world) = scenes.get(&scene).map(|scene| &scene.world) else {
+                continue;
+            };
+            let template = (templates.templates)
+                .entry(scene.id())
+                .or_insert_with(|| SceneTemplate::new(scene_world, &registry));
+            template.spawn(scene_world, world, entity);
+            world.entity_mut(entity).insert(InstanceSpawned);
+        }
+    });
+}
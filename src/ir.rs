@@ -0,0 +1,324 @@
+//! Engine-agnostic representation of FBX scenes.
+//!
+//! The types of this module only hold plain data (arrays, strings and indices),
+//! so that tools can reuse the FBX conversion of this crate, triangulation,
+//! coordinate conversion and material loading included, without handling
+//! bevy assets. Load an [`IrScene`] with [`IrScene::load`], or convert
+//! a [`FbxLoadedScene`] with [`IrScene::from_loaded`].
+//!
+//! Nodes, meshes and materials reference each other by index in the [`IrScene`].
+use anyhow::Context;
+use bevy::{
+    asset::HandleId,
+    pbr::AlphaMode,
+    prelude::{Handle, Image, Mesh, Parent, StandardMaterial, Transform},
+    render::{
+        mesh::Indices,
+        render_resource::{PrimitiveTopology, TextureFormat},
+    },
+    scene::Scene,
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use crate::{quantize, FbxLoadedScene, FbxLoader, FbxLoaderSettings, FbxPropertyValue};
+
+/// A FBX scene, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct IrScene {
+    pub name: Option<String>,
+    pub nodes: Vec<IrNode>,
+    /// The nodes without parent.
+    pub roots: Vec<usize>,
+    pub meshes: Vec<IrMesh>,
+    pub materials: Vec<IrMaterial>,
+    pub textures: Vec<IrTexture>,
+    /// Uniform scale of the scene, converting FBX units to meters.
+    pub unit_scale: f32,
+}
+
+/// A node of the scene tree.
+#[derive(Debug, Clone, Default)]
+pub struct IrNode {
+    /// The id of the FBX object of this node.
+    pub id: i64,
+    pub name: Option<String>,
+    pub children: Vec<usize>,
+    pub translation: [f32; 3],
+    /// Rotation quaternion, as `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    pub mesh: Option<usize>,
+    /// The user-defined properties of the node.
+    pub properties: HashMap<String, FbxPropertyValue>,
+}
+
+/// A mesh, with one primitive per material.
+#[derive(Debug, Clone, Default)]
+pub struct IrMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<IrPrimitive>,
+}
+
+/// Triangles sharing a material.
+///
+/// Vertex attributes are either empty or have one value per position.
+#[derive(Debug, Clone, Default)]
+pub struct IrPrimitive {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub tangents: Vec<[f32; 4]>,
+    /// The vertex colors, whether they tint the material or not,
+    /// see [`FbxVertexColors`](crate::FbxVertexColors).
+    pub colors: Vec<[f32; 4]>,
+    /// Triangle list indices.
+    pub indices: Vec<u32>,
+    pub material: Option<usize>,
+}
+
+/// How the alpha channel of an [`IrMaterial`] is used.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IrAlphaMode {
+    #[default]
+    Opaque,
+    /// Fragments with an alpha lower than the cutoff are discarded.
+    Mask(f32),
+    Blend,
+}
+
+/// A metallic-roughness material, colors are linear RGBA.
+#[derive(Debug, Clone, Default)]
+pub struct IrMaterial {
+    /// The FBX name of the material, or its asset label if it has no name.
+    pub name: String,
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<usize>,
+    pub emissive: [f32; 4],
+    pub emissive_texture: Option<usize>,
+    pub metallic: f32,
+    pub perceptual_roughness: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_map_texture: Option<usize>,
+    pub occlusion_texture: Option<usize>,
+    pub alpha_mode: IrAlphaMode,
+    pub double_sided: bool,
+    pub unlit: bool,
+}
+
+/// A texture, as 8 bits sRGB RGBA pixels, row by row.
+#[derive(Debug, Clone, Default)]
+pub struct IrTexture {
+    /// The asset label of the texture.
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl IrScene {
+    /// Load the FBX file `bytes`, see [`FbxLoader::load_from_bytes`].
+    pub fn load(bytes: &[u8], settings: FbxLoaderSettings) -> anyhow::Result<IrScene> {
+        let loaded = FbxLoader::load_from_bytes(bytes, settings)?;
+        IrScene::from_loaded(&loaded)
+    }
+
+    /// Convert a scene loaded with [`FbxLoader::load_from_bytes`].
+    pub fn from_loaded(loaded: &FbxLoadedScene) -> anyhow::Result<IrScene> {
+        let scene = &loaded.scene;
+        let spawned: &Scene = loaded
+            .get_labeled("Scene")
+            .context("The loaded scene has no spawned Scene")?;
+        let unit_scale = spawned
+            .world
+            .iter_entities()
+            .filter(|entity| !entity.contains::<Parent>())
+            .find_map(|entity| entity.get::<Transform>())
+            .map_or(1.0, |transform| transform.scale.x);
+
+        let mut builder = Builder {
+            loaded,
+            ir: IrScene {
+                name: scene.name.clone(),
+                unit_scale,
+                ..Default::default()
+            },
+            materials: HashMap::default(),
+            textures: HashMap::default(),
+        };
+        let mut ids: Vec<ObjectId> = scene.hierarchy.keys().copied().collect();
+        ids.sort_by_key(|id| id.raw());
+        let indices: HashMap<ObjectId, usize> =
+            ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        for id in &ids {
+            let object = &scene.hierarchy[id];
+            let mesh = scene.meshes.get(id).and_then(|mesh| builder.add_mesh(mesh));
+            let Transform {
+                translation,
+                rotation,
+                scale,
+            } = object.transform;
+            builder.ir.nodes.push(IrNode {
+                id: id.raw(),
+                name: object.name.clone(),
+                children: object
+                    .children
+                    .iter()
+                    .filter_map(|child| indices.get(child).copied())
+                    .collect(),
+                translation: translation.into(),
+                rotation: rotation.into(),
+                scale: scale.into(),
+                mesh,
+                properties: object.extras.properties.clone(),
+            });
+        }
+        builder.ir.roots = scene
+            .roots
+            .iter()
+            .filter_map(|root| indices.get(root).copied())
+            .collect();
+        Ok(builder.ir)
+    }
+}
+
+struct Builder<'a> {
+    loaded: &'a FbxLoadedScene,
+    ir: IrScene,
+    materials: HashMap<HandleId, usize>,
+    textures: HashMap<HandleId, usize>,
+}
+impl Builder<'_> {
+    fn add_mesh(&mut self, handle: &Handle<crate::FbxMesh>) -> Option<usize> {
+        let fbx_mesh = self.loaded.get(handle)?;
+        let mut mesh = IrMesh {
+            name: fbx_mesh.name.clone(),
+            primitives: Vec::with_capacity(fbx_mesh.bevy_mesh_handles.len()),
+        };
+        for (handle, material) in fbx_mesh.bevy_mesh_handles.iter().zip(&fbx_mesh.materials) {
+            let Some(bevy_mesh) = self.loaded.get(handle) else {
+                continue;
+            };
+            if let Some(mut primitive) = primitive(bevy_mesh) {
+                // The IR has no mesh entities to move the vertices back with.
+                let offset = fbx_mesh.offset.to_array();
+                for position in &mut primitive.positions {
+                    position.iter_mut().zip(offset).for_each(|(v, o)| *v += o);
+                }
+                primitive.material = self.add_material(material);
+                mesh.primitives.push(primitive);
+            }
+        }
+        self.ir.meshes.push(mesh);
+        Some(self.ir.meshes.len() - 1)
+    }
+
+    fn add_material(&mut self, handle: &Handle<StandardMaterial>) -> Option<usize> {
+        if let Some(index) = self.materials.get(&handle.id()) {
+            return Some(*index);
+        }
+        let material = self.loaded.get(handle)?;
+        let name = self
+            .loaded
+            .scene
+            .materials
+            .iter()
+            .find(|(_, material)| material == &handle)
+            .map_or_else(String::new, |(label, _)| {
+                let name = label.strip_prefix("FbxMaterial@");
+                name.unwrap_or(label).to_owned()
+            });
+        let mut texture = |texture: &Option<Handle<Image>>| self.add_texture(texture.as_ref()?);
+        let ir_material = IrMaterial {
+            name,
+            base_color: material.base_color.as_linear_rgba_f32(),
+            base_color_texture: texture(&material.base_color_texture),
+            emissive: material.emissive.as_linear_rgba_f32(),
+            emissive_texture: texture(&material.emissive_texture),
+            metallic: material.metallic,
+            perceptual_roughness: material.perceptual_roughness,
+            metallic_roughness_texture: texture(&material.metallic_roughness_texture),
+            normal_map_texture: texture(&material.normal_map_texture),
+            occlusion_texture: texture(&material.occlusion_texture),
+            alpha_mode: match material.alpha_mode {
+                AlphaMode::Opaque => IrAlphaMode::Opaque,
+                AlphaMode::Mask(cutoff) => IrAlphaMode::Mask(cutoff),
+                _ => IrAlphaMode::Blend,
+            },
+            double_sided: material.double_sided || material.cull_mode.is_none(),
+            unlit: material.unlit,
+        };
+        self.ir.materials.push(ir_material);
+        let index = self.ir.materials.len() - 1;
+        self.materials.insert(handle.id(), index);
+        Some(index)
+    }
+
+    fn add_texture(&mut self, handle: &Handle<Image>) -> Option<usize> {
+        if let Some(index) = self.textures.get(&handle.id()) {
+            return Some(*index);
+        }
+        let image = self.loaded.get(handle)?;
+        let image = match image.texture_descriptor.format {
+            TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => image.clone(),
+            _ => image.convert(TextureFormat::Rgba8UnormSrgb)?,
+        };
+        let name = self
+            .loaded
+            .scene
+            .textures
+            .iter()
+            .find(|(_, texture)| texture == &handle)
+            .map_or_else(String::new, |(label, _)| label.clone());
+        let size = image.texture_descriptor.size;
+        self.ir.textures.push(IrTexture {
+            name,
+            width: size.width,
+            height: size.height,
+            rgba: image.data,
+        });
+        let index = self.ir.textures.len() - 1;
+        self.textures.insert(handle.id(), index);
+        Some(index)
+    }
+}
+
+fn primitive(mesh: &Mesh) -> Option<IrPrimitive> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    // Attributes may be quantized, see `FbxLoaderSettings::vertex_formats`.
+    let float3 = |attribute| {
+        let values = mesh
+            .attribute(attribute)
+            .and_then(quantize::dequantized::<3>);
+        values.unwrap_or_default()
+    };
+    let float4 = |attribute| {
+        let values = mesh
+            .attribute(attribute)
+            .and_then(quantize::dequantized::<4>);
+        values.unwrap_or_default()
+    };
+    let uvs = (mesh.attribute(Mesh::ATTRIBUTE_UV_0))
+        .and_then(quantize::dequantized::<2>)
+        .unwrap_or_default();
+    let positions = float3(Mesh::ATTRIBUTE_POSITION);
+    let indices = match mesh.indices() {
+        Some(Indices::U32(indices)) => indices.clone(),
+        Some(Indices::U16(indices)) => indices.iter().map(|i| *i as u32).collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    Some(IrPrimitive {
+        positions,
+        normals: float3(Mesh::ATTRIBUTE_NORMAL),
+        uvs,
+        tangents: float4(Mesh::ATTRIBUTE_TANGENT),
+        colors: match mesh.contains_attribute(Mesh::ATTRIBUTE_COLOR) {
+            true => float4(Mesh::ATTRIBUTE_COLOR),
+            false => float4(crate::FbxMesh::ATTRIBUTE_VERTEX_COLOR),
+        },
+        indices,
+        material: None,
+    })
+}
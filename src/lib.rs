@@ -1,17 +1,126 @@
-use bevy::prelude::{AddAsset, App, Plugin, Resource};
+use bevy::{
+    asset::HandleId,
+    prelude::{AddAsset, App, CoreSet, Handle, IntoSystemConfig, Plugin, Resource},
+    transform::TransformSystem,
+    utils::HashMap,
+};
 
-pub use data::{FbxMesh, FbxScene};
+pub use animation::{
+    event::{FbxAnimationEvent, FbxClipEvent},
+    player::FbxAnimationPlayer,
+    take::FbxTakeMetadata,
+    FbxAnimationClip, FbxCurve, FbxEntityPath, FbxKeyframes,
+};
+pub use camera::{FbxCameraExtras, FbxStereoCamera, FbxStereoEye};
+pub use cancel::FbxLoadCancellation;
+pub use character::FbxCharacter;
+pub use conventions::{FbxCollisionMesh, FbxCollisionShape, FbxLod, FbxMeshKind};
+pub use data::{FbxMesh, FbxMeshSkin, FbxMeshes, FbxNode, FbxPrimitive, FbxScene};
+pub use diff::FbxSceneDiff;
+pub use extract::FbxExtractedMedia;
+pub use extras::{FbxExtras, FbxPropertyCurve, FbxPropertyValue};
+pub use filter::FbxNodeFilter;
+pub use incremental::{FbxIncrementalSpawn, FbxSceneSpawned};
+pub use instance::FbxSceneInstance;
+pub use light::{FbxAmbientLight, FbxAreaLight, FbxAreaLightShape};
 pub use loader::FbxLoader;
+pub use locator::{FbxLocator, FbxLocatorLook};
+pub use look_at::FbxLookAt;
+pub use material_extras::FbxMaterialExtras;
+pub use material_override::{
+    FbxMaterialMatch, FbxMaterialName, FbxMaterialOverride, FbxMaterialOverrides,
+};
+pub use media::{FbxMediaKind, FbxMediaRef};
+pub use memory::FbxLoadedScene;
+pub use memory_usage::FbxMemoryUsage;
+pub use preset::FbxImportPreset;
+pub use progress::{FbxAssetKind, FbxAssetLoaded, FbxLoadProgress, FbxLoadStage};
+pub use property_components::{FbxPropertyComponents, FbxPropertyMapping};
+pub use reference::FbxExternalReference;
+pub use scene_root::FbxSceneRoot;
+pub use settings::{
+    FbxAdditiveClip, FbxAdditiveReference, FbxAutoplay, FbxBudget, FbxDirectionFormat, FbxFlipUv,
+    FbxLoaderSettings, FbxLogStage, FbxMirrorAxis, FbxMirroredClip, FbxNamingConventions,
+    FbxOpacityMode, FbxReflectionMap, FbxRootName, FbxSimplification, FbxTakeSlice,
+    FbxTaskPriority, FbxTaskSettings, FbxTextureStreaming, FbxUvFormat, FbxVertexColors,
+    FbxVertexFormats,
+};
+pub use skeleton::FbxSkeleton;
+pub use socket::FbxSocket;
+pub use static_scene::FbxStaticScene;
+pub use streaming::{FbxStreamedTexture, FbxTextureStreamer};
+pub use subdivision::FbxSubdivisionData;
+pub use validate::FbxValidationReport;
+pub use vertex_cache::{
+    FbxVertexCache, FbxVertexCacheFormat, FbxVertexCachePlayer, FbxVertexCacheReference,
+};
+pub use warning::{FbxLoadWarning, FbxWarningCategory};
 
+pub(crate) mod animation;
+pub(crate) mod cache;
+pub(crate) mod camera;
+pub(crate) mod cancel;
+pub(crate) mod character;
+pub(crate) mod conventions;
 pub(crate) mod data;
+pub(crate) mod diff;
+pub(crate) mod extract;
+pub(crate) mod extras;
 pub(crate) mod fbx_transform;
+pub(crate) mod filter;
+pub(crate) mod freeze;
+pub(crate) mod incremental;
+pub(crate) mod instance;
+pub mod ir;
+pub(crate) mod light;
 pub(crate) mod loader;
+pub(crate) mod locator;
+pub(crate) mod look_at;
+pub(crate) mod material_extras;
 pub mod material_loader;
+pub(crate) mod material_override;
+pub(crate) mod media;
+pub(crate) mod memory;
+pub(crate) mod memory_usage;
+pub(crate) mod names;
+pub(crate) mod object_ids;
+pub(crate) mod opacity;
+pub(crate) mod optimize;
+pub(crate) mod physical;
+pub(crate) mod preset;
+pub(crate) mod progress;
+pub(crate) mod property_components;
+pub(crate) mod quantize;
+pub(crate) mod recenter;
+pub(crate) mod reference;
+pub(crate) mod scene_root;
+pub(crate) mod settings;
+pub(crate) mod simplify;
+pub(crate) mod skeleton;
+pub(crate) mod skin;
+pub(crate) mod socket;
+pub(crate) mod static_scene;
+pub(crate) mod streaming;
+pub(crate) mod subdivision;
+pub(crate) mod tasks;
+pub(crate) mod thumbnail;
+pub(crate) mod unlit;
 pub(crate) mod utils;
+pub(crate) mod validate;
+pub(crate) mod vertex_cache;
+pub(crate) mod warning;
 
 use material_loader::MaterialLoader;
 
 /// Adds support for FBX file loading to the app.
+///
+/// All the components of spawned FBX scenes are registered for reflection,
+/// so a spawned scene can be saved with Bevy's scene serializer, as a
+/// [`DynamicScene`]. Asset handles are saved as the ids of the FBX
+/// sub-assets (meshes, materials, clips and vertex caches), they resolve
+/// once those assets are loaded again.
+///
+/// [`DynamicScene`]: bevy::scene::DynamicScene
 #[derive(Default)]
 pub struct FbxPlugin;
 
@@ -36,8 +145,75 @@ impl Default for FbxMaterialLoaders {
 
 impl Plugin for FbxPlugin {
     fn build(&self, app: &mut App) {
-        app.init_asset_loader::<FbxLoader>()
+        app.init_resource::<progress::ProgressChannel>()
+            .init_resource::<FbxLoadCancellation>()
+            .init_resource::<FbxMaterialOverrides>()
+            .init_resource::<FbxPropertyComponents>()
+            .init_resource::<FbxTextureStreamer>()
+            .add_event::<FbxLoadProgress>()
+            .add_event::<FbxAssetLoaded>()
+            .add_event::<FbxLoadWarning>()
+            .add_event::<FbxAnimationEvent>()
+            .add_event::<FbxSceneSpawned>()
+            .init_asset_loader::<FbxLoader>()
             .add_asset::<FbxMesh>()
-            .add_asset::<FbxScene>();
+            .add_asset::<FbxMeshes>()
+            .add_asset::<FbxNode>()
+            .add_asset::<FbxScene>()
+            .add_asset::<FbxSkeleton>()
+            .add_asset::<FbxAnimationClip>()
+            .add_asset::<FbxVertexCache>()
+            .add_asset::<FbxStreamedTexture>()
+            .register_type::<FbxLookAt>()
+            .register_type::<FbxLocator>()
+            .register_type::<FbxLocatorLook>()
+            .register_type::<FbxSocket>()
+            .register_type::<FbxCollisionMesh>()
+            .register_type::<FbxCollisionShape>()
+            .register_type::<FbxLod>()
+            .register_type::<FbxMeshKind>()
+            .register_type::<FbxCameraExtras>()
+            .register_type::<FbxStereoCamera>()
+            .register_type::<FbxStereoEye>()
+            .register_type::<FbxSceneRoot>()
+            .register_type::<FbxStaticScene>()
+            .register_type::<FbxMaterialName>()
+            .register_type::<FbxMaterialExtras>()
+            .register_type::<Option<Handle<bevy::prelude::Image>>>()
+            .register_type::<Option<f32>>()
+            .register_type::<Vec<String>>()
+            .register_type::<FbxAreaLight>()
+            .register_type::<FbxAreaLightShape>()
+            .register_type::<FbxAmbientLight>()
+            .register_type::<FbxAnimationPlayer>()
+            .register_type::<FbxExtras>()
+            .register_type::<FbxPropertyValue>()
+            .register_type::<FbxPropertyCurve>()
+            .register_type::<Vec<f32>>()
+            .register_type::<Vec<FbxPropertyValue>>()
+            .register_type::<HashMap<String, FbxPropertyValue>>()
+            .register_type::<HashMap<String, FbxPropertyCurve>>()
+            .register_type::<HashMap<HandleId, HashMap<String, FbxPropertyCurve>>>()
+            .register_type::<Handle<FbxAnimationClip>>()
+            .register_type::<Handle<FbxVertexCache>>()
+            .register_type::<FbxVertexCachePlayer>()
+            .add_system(progress::send_progress_events)
+            .add_system(incremental::spawn_incrementally)
+            .add_system(instance::spawn_scene_instances)
+            .add_system(light::apply_ambient_light)
+            .add_system(material_override::apply_material_overrides)
+            .add_system(property_components::apply_property_components)
+            .add_system(streaming::stream_textures)
+            .add_system(vertex_cache::play_vertex_caches)
+            .add_system(
+                animation::player::animation_player
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(
+                look_at::resolve_look_at
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            );
     }
 }
@@ -0,0 +1,326 @@
+//! Translation of FBX lights into bevy lights.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::{
+    pbr::StandardMaterial,
+    prelude::{
+        shape, Added, AmbientLight, Color, Component, DirectionalLight, FromReflect, Mesh,
+        PointLight, Quat, Query, Reflect, ReflectComponent, ResMut, SpotLight, Transform, Vec2,
+    },
+};
+use fbxcel_dom::v7400::object::{
+    model::ModelHandle, nodeattribute::TypedNodeAttributeHandle, property::ObjectProperties,
+};
+use rgb::RGB;
+
+use crate::utils::fbx_extend::{DecayType, LightType, ModelHandleExt, ObjectPropertiesExt};
+
+/// FBX light `Intensity` is a percentage, 100 being the DCC's "default" light.
+const FBX_DEFAULT_INTENSITY: f32 = 100.0;
+
+/// Bevy divides the illuminance of directional lights by this, the exposure
+/// of its hard-coded camera (f/4, 1/250 s, ISO 100), but not the intensity
+/// of other lights.
+const BEVY_DIRECTIONAL_EXPOSURE: f32 = 4800.0;
+
+/// Fraction of a light's intensity under which its contribution is negligible.
+///
+/// Used to approximate the FBX decay types into a bevy light `range`.
+const LIGHT_CUTOFF: f32 = 1.0 / 256.0;
+
+/// The `range` of lights that do not decay with distance, in bevy units.
+const NO_DECAY_RANGE: f32 = 1000.0;
+
+/// Bevy spot light angles must be lower than a quarter turn.
+const MAX_SPOT_ANGLE: f32 = FRAC_PI_2 * 0.99;
+
+/// Bevy lights always follow the inverse square law, but we can choose
+/// their `range` so that they fade out about where the FBX light would.
+///
+/// A light without decay is bright until the FBX far attenuation end,
+/// (or [`NO_DECAY_RANGE`]) while decaying lights stop when
+/// their intensity relative to `DecayStart` falls under [`LIGHT_CUTOFF`].
+fn light_range(p: ObjectProperties, unit_scale: f32) -> f32 {
+    let far_attenuation = p.get::<bool>("EnableFarAttenuation").unwrap_or(false);
+    let far_end = p.get::<f64>("FarAttenuationEnd").filter(|end| *end > 0.0);
+    if let (true, Some(end)) = (far_attenuation, far_end) {
+        return end as f32 * unit_scale;
+    }
+    let start = decay_start(p, unit_scale);
+    match p.get("DecayType").unwrap_or_default() {
+        DecayType::None => NO_DECAY_RANGE,
+        DecayType::Linear => start / LIGHT_CUTOFF,
+        DecayType::Quadratic => start / LIGHT_CUTOFF.sqrt(),
+        DecayType::Cubic => start / LIGHT_CUTOFF.cbrt(),
+    }
+}
+
+/// The distance at which a light starts decaying, in bevy units.
+///
+/// Bevy units are meters, a light starts decaying at least one meter away.
+fn decay_start(p: ObjectProperties, unit_scale: f32) -> f32 {
+    let start = p.get::<f64>("DecayStart").unwrap_or(0.0) as f32 * unit_scale;
+    start.max(1.0)
+}
+
+/// The bevy intensities of a FBX light, see [`FbxLoaderSettings::light_exposure`].
+///
+/// At full intensity, the FBX light makes a white Lambert surface facing it
+/// as bright as its own color, as in the DCC. Bevy's diffuse lighting is
+/// divided by π, so that's an irradiance of π in bevy's shader units.
+/// Lights other than directional ones have this irradiance at the distance
+/// where they start decaying, as bevy lights always follow the inverse
+/// square law.
+///
+/// [`FbxLoaderSettings::light_exposure`]: crate::FbxLoaderSettings::light_exposure
+struct Intensity {
+    /// Illuminance of directional lights, in lux.
+    illuminance: f32,
+    /// Luminous power of other lights, in lumens.
+    power: f32,
+}
+impl Intensity {
+    fn new(p: ObjectProperties, unit_scale: f32, exposure: f32) -> Self {
+        let intensity = p
+            .get::<f64>("Intensity")
+            .map_or(FBX_DEFAULT_INTENSITY, |i| i as f32)
+            / FBX_DEFAULT_INTENSITY;
+        let irradiance = PI * intensity * exposure;
+        // A point light of `power` lumens has an intensity of `power / 4π`
+        // candelas, its irradiance at `distance` being `intensity / distance²`.
+        let distance = decay_start(p, unit_scale);
+        Intensity {
+            illuminance: irradiance * BEVY_DIRECTIONAL_EXPOSURE,
+            power: irradiance * 4.0 * PI * distance * distance,
+        }
+    }
+}
+
+/// Whether a light casts shadows.
+///
+/// Bevy shadows are always black, a light whose `ShadowColor`
+/// is white casts no visible shadow.
+fn shadows_enabled(p: ObjectProperties) -> bool {
+    let cast_shadows = p.get::<bool>("CastShadows").unwrap_or(false);
+    let shadow_color = p.get::<RGB<f64>>("ShadowColor");
+    let invisible = shadow_color.is_some_and(|c| c.r.min(c.g).min(c.b) >= 1.0);
+    cast_shadows && !invisible
+}
+
+/// The bevy `(inner_angle, outer_angle)` of a spot light.
+///
+/// FBX `InnerAngle` and `OuterAngle` are the full cone angles in degrees
+/// (older exporters call them `HotSpot` and `Cone angle`),
+/// the penumbra being the region between the two.
+/// Bevy's angles are measured from the light's axis in radians.
+fn spot_angles(p: ObjectProperties) -> (f32, f32) {
+    let inner = p.get::<f64>("InnerAngle").or_else(|| p.get("HotSpot"));
+    let outer = p.get::<f64>("OuterAngle").or_else(|| p.get("Cone angle"));
+    let to_bevy = |angle: f64| (angle.to_radians() as f32 / 2.0).clamp(0.0, MAX_SPOT_ANGLE);
+    let outer = to_bevy(outer.unwrap_or(45.0));
+    let inner = to_bevy(inner.unwrap_or(0.0)).min(outer);
+    (inner, outer)
+}
+
+/// The shape of a [`FbxAreaLight`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, FromReflect)]
+pub enum FbxAreaLightShape {
+    /// A 2×2 square in the light's local space, facing the light's forward direction.
+    #[default]
+    Rectangle,
+    /// A sphere of radius 1 in the light's local space.
+    Sphere,
+}
+
+/// An FBX area light.
+///
+/// Bevy has no area lights, so this component is added to the entity of
+/// each FBX area light, so that you can substitute your own solution.
+///
+/// If [`FbxLoaderSettings::approximate_area_lights`] is enabled,
+/// the entity also gets an emissive mesh of the light's shape
+/// and a light emitting the same luminous power as children.
+/// Rectangles use a hemispherical spot light while spheres use a point light.
+///
+/// [`FbxLoaderSettings::approximate_area_lights`]: crate::FbxLoaderSettings::approximate_area_lights
+#[derive(Component, Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct FbxAreaLight {
+    pub shape: FbxAreaLightShape,
+    pub color: Color,
+    /// Luminous power in lumens, same as [`PointLight::intensity`].
+    pub intensity: f32,
+}
+
+/// The bevy lights approximating an [`FbxAreaLight`].
+#[derive(Debug, Clone)]
+pub(crate) enum AreaLightApproximation {
+    Spot(SpotLight),
+    Point(PointLight),
+}
+impl FbxAreaLight {
+    /// A light emitting as much as this area light.
+    ///
+    /// `range` and `radius` are in bevy units.
+    fn approximation(
+        &self,
+        range: f32,
+        radius: f32,
+        shadows_enabled: bool,
+    ) -> AreaLightApproximation {
+        match self.shape {
+            // A spot light emits `intensity` as if it was a point light,
+            // and therefore only sends half of it in the hemisphere a
+            // single-sided rectangle lights.
+            FbxAreaLightShape::Rectangle => AreaLightApproximation::Spot(SpotLight {
+                color: self.color,
+                intensity: self.intensity * 2.0,
+                range,
+                radius,
+                inner_angle: 0.0,
+                outer_angle: MAX_SPOT_ANGLE,
+                shadows_enabled,
+                ..Default::default()
+            }),
+            FbxAreaLightShape::Sphere => AreaLightApproximation::Point(PointLight {
+                color: self.color,
+                intensity: self.intensity,
+                range,
+                radius,
+                shadows_enabled,
+                ..Default::default()
+            }),
+        }
+    }
+    /// The mesh of the emissive surface approximating this light,
+    /// and its transform relative to the light.
+    pub(crate) fn mesh(&self) -> (Mesh, Transform) {
+        match self.shape {
+            // The quad faces +Z, while bevy lights face -Z.
+            FbxAreaLightShape::Rectangle => (
+                shape::Quad::new(Vec2::splat(2.0)).into(),
+                Transform::from_rotation(Quat::from_rotation_y(PI)),
+            ),
+            FbxAreaLightShape::Sphere => (shape::UVSphere::default().into(), Transform::IDENTITY),
+        }
+    }
+    /// The material of the emissive surface approximating this light.
+    pub(crate) fn material(&self) -> StandardMaterial {
+        StandardMaterial {
+            base_color: Color::BLACK,
+            emissive: self.color,
+            ..Default::default()
+        }
+    }
+}
+
+/// A light attached to an FBX node.
+#[derive(Debug, Clone)]
+pub(crate) enum FbxLight {
+    Point(PointLight),
+    Directional(DirectionalLight),
+    Spot(SpotLight),
+    Area {
+        light: FbxAreaLight,
+        approximation: AreaLightApproximation,
+    },
+}
+impl FbxLight {
+    /// Read the light properties of the `NodeAttribute` of `node`.
+    ///
+    /// Returns `None` if `node` has no light attribute or if it is a light type
+    /// bevy has no equivalent for.
+    ///
+    /// `unit_scale` converts FBX file units into bevy units,
+    /// it is used for the light's range. `exposure` scales the light intensity,
+    /// see [`FbxLoaderSettings::light_exposure`].
+    ///
+    /// [`FbxLoaderSettings::light_exposure`]: crate::FbxLoaderSettings::light_exposure
+    pub(crate) fn from_node(node: ModelHandle, unit_scale: f32, exposure: f32) -> Option<Self> {
+        let attribute = match node.node_attribute()? {
+            TypedNodeAttributeHandle::Light(light) => light,
+            _ => return None,
+        };
+        let p = attribute.properties_by_native_typename("FbxLight");
+        let color = p.get::<RGB<f64>>("Color").map_or(Color::WHITE, |c| {
+            Color::rgb(c.r as f32, c.g as f32, c.b as f32)
+        });
+        let intensity = Intensity::new(p, unit_scale, exposure);
+        let shadows_enabled = shadows_enabled(p);
+        let light = match p.get("LightType").unwrap_or_default() {
+            LightType::Point => FbxLight::Point(PointLight {
+                color,
+                intensity: intensity.power,
+                range: light_range(p, unit_scale),
+                shadows_enabled,
+                ..Default::default()
+            }),
+            LightType::Directional => FbxLight::Directional(DirectionalLight {
+                color,
+                illuminance: intensity.illuminance,
+                shadows_enabled,
+                ..Default::default()
+            }),
+            LightType::Spot => {
+                let (inner_angle, outer_angle) = spot_angles(p);
+                FbxLight::Spot(SpotLight {
+                    color,
+                    intensity: intensity.power,
+                    range: light_range(p, unit_scale),
+                    inner_angle,
+                    outer_angle,
+                    shadows_enabled,
+                    ..Default::default()
+                })
+            }
+            LightType::Area => {
+                let shape = match p.get::<i32>("AreaLightShape") {
+                    Some(1) => FbxAreaLightShape::Sphere,
+                    _ => FbxAreaLightShape::Rectangle,
+                };
+                let light = FbxAreaLight {
+                    shape,
+                    color,
+                    intensity: intensity.power,
+                };
+                let approximation =
+                    light.approximation(light_range(p, unit_scale), unit_scale, shadows_enabled);
+                FbxLight::Area {
+                    light,
+                    approximation,
+                }
+            }
+            LightType::Volume => return None,
+        };
+        Some(light)
+    }
+}
+
+/// FBX lights point along their node's -Y axis, bevy lights along -Z.
+pub(crate) fn rest_rotation() -> Quat {
+    Quat::from_rotation_x(-FRAC_PI_2)
+}
+
+/// The ambient color of an FBX scene.
+///
+/// Added to the scene root when [`FbxLoaderSettings::apply_ambient_light`]
+/// is enabled, the [`AmbientLight`] resource color is then set
+/// to `color` when the scene is spawned.
+///
+/// [`FbxLoaderSettings::apply_ambient_light`]: crate::FbxLoaderSettings::apply_ambient_light
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Component)]
+pub struct FbxAmbientLight {
+    pub color: Color,
+}
+
+/// Set the [`AmbientLight`] color to the one of newly spawned FBX scenes.
+pub fn apply_ambient_light(
+    spawned: Query<&FbxAmbientLight, Added<FbxAmbientLight>>,
+    ambient: Option<ResMut<AmbientLight>>,
+) {
+    if let (Some(mut ambient), Some(spawned)) = (ambient, spawned.iter().last()) {
+        ambient.color = spawned.color;
+    }
+}
@@ -1,62 +1,155 @@
-use std::path::Path;
+use std::{
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, bail, Context};
 use bevy::{
-    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
-    math::{DVec2, DVec3, Vec2},
+    asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext},
+    math::{DVec2, DVec3},
+    pbr::{NotShadowCaster, NotShadowReceiver},
     prelude::{
-        debug, error, info, trace, BuildWorldChildren, FromWorld, Handle, Image, Mesh, Name,
-        PbrBundle, Scene, StandardMaterial, Transform, TransformBundle, VisibilityBundle, World,
-        WorldChildBuilder,
+        debug, error, info, trace, BuildWorldChildren, Camera, Camera3dBundle, Color,
+        DirectionalLightBundle, Entity, FromWorld, Handle, Image, Mat4, Mesh, Name, PbrBundle,
+        PointLightBundle, Scene, SpotLightBundle, StandardMaterial, Transform, TransformBundle,
+        Visibility, VisibilityBundle, World, WorldChildBuilder,
     },
     render::{
-        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
-        render_resource::{AddressMode, SamplerDescriptor},
+        mesh::{
+            skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+            Indices, PrimitiveTopology, VertexAttributeValues,
+        },
+        primitives::Aabb,
+        render_resource::{AddressMode, SamplerDescriptor, TextureFormat},
         renderer::RenderDevice,
         texture::{CompressedImageFormats, ImageSampler, ImageType},
     },
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
-use fbxcel_dom::{
-    any::AnyDocument,
-    v7400::{
-        data::{mesh::layer::TypedLayerElementHandle, texture::WrapMode},
-        object::{
-            self,
-            model::{ModelHandle, TypedModelHandle},
-            texture::TextureHandle,
-            ObjectId, TypedObjectHandle,
+use crossbeam_channel::Sender;
+use fbxcel_dom::v7400::{
+    data::{
+        mesh::{
+            layer::TypedLayerElementHandle, ControlPointIndex, PolygonVertexIndex, PolygonVertices,
         },
-        Document,
+        texture::WrapMode,
     },
+    object::{
+        self,
+        model::{ModelHandle, TypedModelHandle},
+        nodeattribute::TypedNodeAttributeHandle,
+        texture::TextureHandle,
+        ObjectId, TypedObjectHandle,
+    },
+    Document,
 };
 
 #[cfg(feature = "profile")]
-use bevy::log::info_span;
-use glam::Vec3;
+use bevy::{log::info_span, utils::tracing::Instrument};
+use glam::{Quat, Vec3};
+use rgb::RGB;
 
 use crate::{
-    data::{FbxMesh, FbxObject, FbxScene},
-    fbx_transform::FbxTransform,
-    utils::fbx_extend::{GlobalSettingsExt, ModelTreeRootExt},
-    utils::triangulate,
-    MaterialLoader,
+    animation::{
+        additive::additive_clip, bake::Baker, constraint::Constraint, curve::Stack,
+        ktime_to_seconds, mirror::mirrored_clip, stored_pose::StoredPose, take::FbxTakeMetadata,
+    },
+    cache::{BakedClip, ConvertedMesh, FbxCache},
+    camera::{self, FbxCamera, FbxStereoEye},
+    cancel::{FbxLoadCancellation, LoadCancelled},
+    character::FbxCharacter,
+    conventions::{self, ConventionTags},
+    data::{FbxMesh, FbxMeshSkin, FbxMeshes, FbxNode, FbxObject, FbxPrimitive, FbxScene},
+    extract::{self, FbxExtractedMedia},
+    extras::{FbxExtras, FbxPropertyValue},
+    fbx_transform::{unknown_inherit_type, FbxNodeTransformInfo, FbxTransform},
+    filter, freeze,
+    light::{self, AreaLightApproximation, FbxAmbientLight, FbxLight},
+    locator::FbxLocator,
+    look_at::{aimed_rotation, FbxLookAt},
+    material_extras::FbxMaterialExtras,
+    material_override::FbxMaterialName,
+    media::FbxMediaRef,
+    memory::{FbxLoadedScene, LoadTarget, MemoryAssets},
+    memory_usage::{self, FbxMemoryUsage},
+    names::{self, UniqueNames},
+    object_ids, opacity, optimize,
+    preset::FbxImportPreset,
+    progress::{FbxAssetKind, FbxLoadStage, Progress, ProgressChannel, ProgressMessage},
+    quantize, recenter,
+    reference::FbxExternalReference,
+    simplify,
+    skeleton::FbxSkeleton,
+    skin::{GeometrySkin, SkinnedBounds, MAX_JOINTS},
+    socket::FbxSocket,
+    static_scene,
+    streaming::{self, FbxStreamedTexture},
+    subdivision::{Cage, FbxSubdivisionData},
+    tasks::LoadSlots,
+    thumbnail, unlit,
+    utils::arena::MeshArena,
+    utils::binary,
+    utils::fbx_extend::{GlobalSettingsExt, ModelHandleExt, ModelTreeRootExt, ObjectPropertiesExt},
+    utils::layer_element::{self, MeshTopology},
+    utils::mesh as mesh_utils,
+    utils::triangulate::{self, PolygonRole},
+    validate::{FbxValidationReport, WarningCollector},
+    vertex_cache::{
+        FbxVertexCache, FbxVertexCacheFormat, FbxVertexCachePlayer, FbxVertexCacheReference,
+    },
+    FbxAdditiveReference, FbxAnimationClip, FbxAnimationPlayer, FbxAutoplay, FbxLoaderSettings,
+    FbxLogStage, FbxReflectionMap, FbxRootName, FbxSceneRoot, FbxSimplification, FbxTakeSlice,
+    FbxTaskPriority, FbxVertexColors, FbxVertexFormats, FbxWarningCategory, MaterialLoader,
 };
 
+/// Log a message of `stage` at `debug` level, or at `info` level if `stage`
+/// is in [`FbxLoaderSettings::verbose_stages`].
+macro_rules! stage_log {
+    ($settings:expr, $stage:expr, $($arg:tt)+) => {
+        if $settings.verbose_stages.contains(&$stage) {
+            info!($($arg)+)
+        } else {
+            debug!($($arg)+)
+        }
+    };
+}
+
 /// Bevy is kinda "meters" based while FBX (or rather: stuff exported by maya) is in "centimeters"
 /// Although it doesn't mean much in practice.
 const FBX_TO_BEVY_SCALE_FACTOR: f32 = 0.01;
 
 pub struct Loader<'b, 'w> {
     scene: FbxScene,
-    load_context: &'b mut LoadContext<'w>,
+    load_context: LoadTarget<'b, 'w>,
     suported_compressed_formats: CompressedImageFormats,
     material_loaders: Vec<MaterialLoader>,
+    settings: FbxLoaderSettings,
+    cache: Option<FbxCache>,
+    progress: Progress,
+    /// The label of each material of the file.
+    material_labels: HashMap<ObjectId, String>,
+    /// The loaded materials.
+    material_handles: HashMap<ObjectId, Handle<StandardMaterial>>,
+    /// The FBX name of the loaded materials, or their label if they have none.
+    material_names: HashMap<Handle<StandardMaterial>, String>,
+    /// The replacements of [`FbxLoaderSettings::material_overrides`] used
+    /// by the file, loaded with the scene.
+    material_dependencies: Vec<AssetPath<'static>>,
+    /// The loaded geometries, shared by the mesh nodes instancing them.
+    geometries: HashMap<ObjectId, Vec<(GeometryVariant, GeometryMeshes)>>,
+    /// The triangle count of the loaded meshes, see [`FbxLoaderSettings::budget`].
+    triangle_counts: HashMap<Handle<Mesh>, usize>,
+    /// The intermediate buffers of mesh conversion.
+    arena: MeshArena,
 }
 
 pub struct FbxLoader {
     supported: CompressedImageFormats,
     material_loaders: Vec<MaterialLoader>,
+    settings: FbxLoaderSettings,
+    progress: Option<Sender<ProgressMessage>>,
+    cancellation: Option<FbxLoadCancellation>,
+    load_slots: LoadSlots,
 }
 impl FromWorld for FbxLoader {
     fn from_world(world: &mut World) -> Self {
@@ -66,9 +159,104 @@ impl FromWorld for FbxLoader {
             None => CompressedImageFormats::all(),
         };
         let loaders: crate::FbxMaterialLoaders = world.get_resource().cloned().unwrap_or_default();
+        let settings: FbxLoaderSettings = world.get_resource().cloned().unwrap_or_default();
+        let load_slots = LoadSlots::new(settings.tasks.max_parallel_loads);
+        let progress = world
+            .get_resource::<ProgressChannel>()
+            .map(|channel| channel.sender.clone());
+        let cancellation = world.get_resource().cloned();
         Self {
             supported,
             material_loaders: loaders.0,
+            settings,
+            progress,
+            cancellation,
+            load_slots,
+        }
+    }
+}
+impl FbxLoader {
+    /// Load the FBX file `bytes` without an `AssetServer`, for tools
+    /// and tests working on files that aren't in the asset folder.
+    ///
+    /// Only textures embedded in the file are loaded, since there is no
+    /// folder to read the referenced files from, this is also true of vertex
+    /// caches. The default material loaders are used.
+    pub fn load_from_bytes(
+        bytes: &[u8],
+        settings: FbxLoaderSettings,
+    ) -> anyhow::Result<FbxLoadedScene> {
+        let progress = Progress::new(None, None, PathBuf::new());
+        let cache = settings
+            .cache_folder
+            .as_ref()
+            .map(|folder| FbxCache::open(folder, bytes, &settings, &progress));
+        let doc = parse_document(bytes, &progress)?;
+        let loaders = crate::FbxMaterialLoaders::default().0;
+        let mut assets = MemoryAssets::default();
+        let loader = Loader::new(
+            CompressedImageFormats::all(),
+            loaders,
+            settings,
+            cache,
+            progress,
+            LoadTarget::Memory(&mut assets),
+        );
+        let handle = futures_lite::future::block_on(loader.load(doc))?;
+        let mut loaded = FbxLoadedScene {
+            scene: FbxScene::default(),
+            assets,
+        };
+        loaded.scene = loaded
+            .remove(&handle)
+            .context("The FBX scene wasn't loaded")?;
+        Ok(loaded)
+    }
+}
+impl FbxLoader {
+    /// The preview thumbnail embedded in the FBX file `bytes`, if it has one,
+    /// without converting its scene.
+    pub fn thumbnail_from_bytes(bytes: &[u8]) -> anyhow::Result<Option<Image>> {
+        let (fbx_bytes, _) = binary::check(bytes)?;
+        let (doc, _) = object_ids::parse_document(&fbx_bytes)?;
+        Ok(thumbnail::thumbnail(&doc))
+    }
+
+    /// Move the media embedded in the FBX file `bytes`, such as textures,
+    /// to a `.fbm` folder next to the file at `fbx_path`, as the FBX SDK
+    /// names it: `hero.fbm` for `hero.fbx`.
+    ///
+    /// The returned FBX file references the extracted files instead, so
+    /// that they can be edited and hot-reloaded on their own. Nothing is
+    /// written to disk until [`FbxExtractedMedia::write`].
+    ///
+    /// [`FbxExtractedMedia::write`]: crate::FbxExtractedMedia::write
+    pub fn extract_embedded_media(
+        bytes: &[u8],
+        fbx_path: &Path,
+    ) -> anyhow::Result<FbxExtractedMedia> {
+        let (fbx_bytes, _) = binary::check(bytes)?;
+        let stem = fbx_path
+            .file_stem()
+            .context("The FBX path has no file name")?;
+        let mut media_folder = stem.to_owned();
+        media_folder.push(".fbm");
+        extract::extract(&fbx_bytes, Path::new(&media_folder))
+    }
+
+    /// Load the FBX file `bytes` as [`FbxLoader::load_from_bytes`] does,
+    /// only to report whether it loads and its warnings, such as in content CI.
+    ///
+    /// The converted assets are dropped, nothing reaches the GPU. The warnings
+    /// of the load are collected in the report instead of being logged.
+    pub fn validate_bytes(bytes: &[u8], settings: FbxLoaderSettings) -> FbxValidationReport {
+        let collector = WarningCollector::default();
+        let result = bevy::utils::tracing::subscriber::with_default(collector.clone(), || {
+            FbxLoader::load_from_bytes(bytes, settings)
+        });
+        FbxValidationReport {
+            error: result.err().map(|err| format!("{err:#}")),
+            warnings: collector.warnings(),
         }
     }
 }
@@ -79,24 +267,47 @@ impl AssetLoader for FbxLoader {
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, anyhow::Result<()>> {
         Box::pin(async move {
-            let cursor = std::io::Cursor::new(bytes);
-            let reader = std::io::BufReader::new(cursor);
-            let maybe_doc =
-                AnyDocument::from_seekable_reader(reader).expect("Failed to load document");
-            if let AnyDocument::V7400(_ver, doc) = maybe_doc {
-                let loader =
-                    Loader::new(self.supported, self.material_loaders.clone(), load_context);
-                let potential_error = loader
-                    .load(*doc)
-                    .await
-                    .with_context(|| format!("failed to load {:?}", load_context.path()));
-                if let Err(err) = potential_error {
-                    error!("{err:?}");
+            let _slot = self.load_slots.take().await;
+            let progress = Progress::new(
+                self.progress.clone(),
+                self.cancellation.clone(),
+                load_context.path().to_path_buf(),
+            );
+            progress.report(FbxLoadStage::Parsing, 0, 1, None);
+            let mut settings = self.settings.clone();
+            if settings.import_presets {
+                let target = LoadTarget::Context(load_context);
+                if let Some(preset) = FbxImportPreset::read(&target).await? {
+                    debug!("Loading {:?} with {preset:?}", load_context.path());
+                    preset.apply(&mut settings);
                 }
-                Ok(())
-            } else {
-                Err(anyhow!("TODO: better error handling in fbx loader"))
             }
+            let cache = settings
+                .cache_folder
+                .as_ref()
+                .map(|folder| FbxCache::open(folder, bytes, &settings, &progress));
+            let doc = parse_document(bytes, &progress)
+                .with_context(|| format!("failed to load {:?}", load_context.path()))?;
+            let loader = Loader::new(
+                self.supported,
+                self.material_loaders.clone(),
+                settings,
+                cache,
+                progress,
+                LoadTarget::Context(load_context),
+            );
+            let potential_error = loader
+                .load(doc)
+                .await
+                .with_context(|| format!("failed to load {:?}", load_context.path()));
+            match potential_error {
+                Err(err) if err.is::<LoadCancelled>() => {
+                    info!("Cancelled loading {:?}", load_context.path());
+                }
+                Err(err) => error!("{err:?}"),
+                Ok(_) => {}
+            }
+            Ok(())
         })
     }
     fn extensions(&self) -> &[&str] {
@@ -104,38 +315,250 @@ impl AssetLoader for FbxLoader {
     }
 }
 
+/// The converted FBX objects that end up in the bevy [`Scene`].
+#[derive(Default)]
+struct SceneContents {
+    hierarchy: HashMap<ObjectId, FbxObject>,
+    models: HashMap<ObjectId, FbxMesh>,
+    lights: HashMap<ObjectId, FbxLight>,
+    cameras: HashMap<ObjectId, FbxCamera>,
+    locators: HashMap<ObjectId, FbxLocator>,
+    sockets: HashMap<ObjectId, FbxSocket>,
+    /// See [`FbxLoaderSettings::naming_conventions`].
+    conventions: HashMap<ObjectId, ConventionTags>,
+    /// Target lights and cameras, associated with the node they aim at.
+    look_ats: HashMap<ObjectId, ObjectId>,
+    /// FBX global transform of each node in `hierarchy`.
+    globals: HashMap<ObjectId, Mat4>,
+    /// FBX transform properties of each node in `hierarchy`.
+    infos: HashMap<ObjectId, FbxNodeTransformInfo>,
+    /// Component added to the scene root, if any.
+    ambient_light: Option<FbxAmbientLight>,
+    /// Clip played in a loop by the scene root, if any.
+    autoplay: Option<Handle<FbxAnimationClip>>,
+    /// The emissive surface of approximated area lights.
+    area_light_surfaces: HashMap<ObjectId, (Handle<Mesh>, Handle<StandardMaterial>, Transform)>,
+    /// The FBX name of each material.
+    material_names: HashMap<Handle<StandardMaterial>, String>,
+    material_extras: HashMap<Handle<StandardMaterial>, FbxMaterialExtras>,
+    /// The scenes of the resolved external references, spawned next to the root.
+    referenced_scenes: Vec<(Name, Handle<Scene>)>,
+    /// See [`FbxScene::skinned_aabbs`].
+    skinned_aabbs: HashMap<ObjectId, Aabb>,
+    /// See [`FbxScene::skin_joints`].
+    skin_joints: HashMap<ObjectId, Vec<ObjectId>>,
+}
+impl SceneContents {
+    /// The transform of the light or camera attached to `node`.
+    ///
+    /// If the node is aiming at a target, the rotation points toward it,
+    /// otherwise it is `rest`, the rotation needed to point the bevy object
+    /// in the FBX object's direction.
+    fn attachment_transform(&self, node: ObjectId, rest: impl FnOnce() -> Quat) -> Transform {
+        let aimed = self.look_ats.get(&node).and_then(|target| {
+            let node_global = *self.globals.get(&node)?;
+            let target_global = self.globals.get(target)?;
+            aimed_rotation(node_global, target_global.w_axis.truncate())
+        });
+        Transform::from_rotation(aimed.unwrap_or_else(rest))
+    }
+
+    /// The bevy meshes of the scene, see [`FbxMeshes`].
+    fn flat_meshes(&self) -> FbxMeshes {
+        let mut models: Vec<_> = self.models.iter().collect();
+        models.sort_by_key(|(id, _)| id.raw());
+        let mut seen = HashSet::new();
+        let meshes = models
+            .into_iter()
+            .flat_map(|(_, mesh)| &mesh.bevy_mesh_handles)
+            .filter(|handle| seen.insert(handle.id()))
+            .cloned()
+            .collect();
+        FbxMeshes(meshes)
+    }
+
+    /// The bounding box of all the meshes, transformed by `root`.
+    fn bounds(&self, root: Mat4) -> Option<Aabb> {
+        let mut corners = self
+            .models
+            .iter()
+            .flat_map(|(node, mesh)| {
+                let aabb = mesh.aabb?;
+                let global = root * *self.globals.get(node)? * Mat4::from_translation(mesh.offset);
+                let (min, max) = (aabb.min(), aabb.max());
+                let corners = (0..8).map(move |i| {
+                    let pick =
+                        |bit: usize, min: f32, max: f32| if i & bit == 0 { min } else { max };
+                    let corner = Vec3::new(
+                        pick(1, min.x, max.x),
+                        pick(2, min.y, max.y),
+                        pick(4, min.z, max.z),
+                    );
+                    global.transform_point3(corner)
+                });
+                Some(corners)
+            })
+            .flatten();
+        let first = corners.next()?;
+        let (min, max) = corners.fold((first, first), |(min, max), c| (min.min(c), max.max(c)));
+        Some(Aabb::from_min_max(min, max))
+    }
+}
+
+/// The bevy meshes of a FBX geometry.
+#[derive(Clone)]
+struct GeometryMeshes {
+    /// One mesh per material, or several if it was split,
+    /// see [`FbxLoaderSettings::max_mesh_vertices`].
+    handles: Vec<Handle<Mesh>>,
+    /// The material index of each mesh.
+    materials: Vec<usize>,
+    /// The bounding box of each mesh.
+    aabbs: Vec<Option<Aabb>>,
+    aabb: Option<Aabb>,
+    /// The control point of each vertex, empty if the geometry was subdivided.
+    vertex_control_points: Vec<u32>,
+    subdivision: Option<FbxSubdivisionData>,
+    skin: Option<FbxMeshSkin>,
+    /// The polygon edges, see [`FbxLoaderSettings::wireframe_meshes`].
+    wireframe: Option<Handle<Mesh>>,
+    /// The position the vertices were moved from, see [`FbxMesh::offset`].
+    offset: Vec3,
+}
+
+/// How a geometry is converted for the mesh nodes instancing it.
+///
+/// Nodes converting a geometry the same way share its bevy meshes,
+/// the others get their own copy.
+#[derive(PartialEq)]
+struct GeometryVariant {
+    material_count: usize,
+    simplification: Option<FbxSimplification>,
+    vertex_colors: Vec<FbxVertexColors>,
+    vertex_formats: FbxVertexFormats,
+    /// See [`FbxLoaderSettings::optimize_meshes`].
+    optimize: bool,
+}
+
+/// Bookkeeping while spawning the scene.
+#[derive(Default)]
+struct SpawnState {
+    node_entities: HashMap<ObjectId, Entity>,
+    /// Entities with a look-at target, and the target's node.
+    aimed: Vec<(Entity, ObjectId)>,
+    /// Skinned mesh entities, and their mesh node.
+    skinned: Vec<(Entity, ObjectId)>,
+    /// The camera entity of each camera node.
+    camera_entities: HashMap<ObjectId, Entity>,
+    active_camera_found: bool,
+}
+
+/// The transform of the root of the spawned scene.
+fn root_transform(fbx_file_scale: f32) -> Transform {
+    Transform::from_scale(Vec3::ONE * FBX_TO_BEVY_SCALE_FACTOR * fbx_file_scale)
+}
+
 fn spawn_scene(
     fbx_file_scale: f32,
     roots: &[ObjectId],
-    hierarchy: &HashMap<ObjectId, FbxObject>,
-    models: &HashMap<ObjectId, FbxMesh>,
+    contents: &SceneContents,
+    root_marker: (Name, FbxSceneRoot),
 ) -> Scene {
     #[cfg(feature = "profile")]
     let _generate_scene_span = info_span!("generate_scene").entered();
 
     let mut scene_world = World::default();
-    scene_world
-        .spawn((
+    let mut state = SpawnState::default();
+    let mut root = scene_world.spawn((
+        VisibilityBundle::default(),
+        TransformBundle::from_transform(root_transform(fbx_file_scale)),
+        root_marker,
+    ));
+    if let Some(ambient_light) = contents.ambient_light {
+        root.insert(ambient_light);
+    }
+    if let Some(clip) = &contents.autoplay {
+        let mut player = FbxAnimationPlayer::default();
+        player.play(clip.clone()).repeat();
+        root.insert(player);
+    }
+    root.with_children(|commands| {
+        for root in roots {
+            spawn_scene_rec(*root, commands, contents, &mut state);
+        }
+    });
+    for (name, scene) in &contents.referenced_scenes {
+        scene_world.spawn((
             VisibilityBundle::default(),
-            TransformBundle::from_transform(Transform::from_scale(
-                Vec3::ONE * FBX_TO_BEVY_SCALE_FACTOR * fbx_file_scale,
-            )),
-            Name::new("Fbx scene root"),
-        ))
-        .with_children(|commands| {
-            for root in roots {
-                spawn_scene_rec(*root, commands, hierarchy, models);
-            }
+            TransformBundle::default(),
+            name.clone(),
+            scene.clone(),
+        ));
+    }
+    for (entity, target) in state.aimed {
+        if let Some(&target) = state.node_entities.get(&target) {
+            scene_world.entity_mut(entity).insert(FbxLookAt { target });
+        }
+    }
+    for (entity, node) in state.skinned {
+        let skin = contents
+            .models
+            .get(&node)
+            .and_then(|mesh| mesh.skin.as_ref());
+        let (Some(skin), Some(&node_entity)) = (skin, state.node_entities.get(&node)) else {
+            continue;
+        };
+        let joints: Option<Vec<Entity>> = (contents.skin_joints.get(&node)).and_then(|joints| {
+            joints
+                .iter()
+                .map(|j| state.node_entities.get(j).copied())
+                .collect()
         });
+        let skinned_mesh = match joints {
+            Some(mut joints) => {
+                joints.push(node_entity);
+                SkinnedMesh {
+                    inverse_bindposes: skin.inverse_bindposes.clone(),
+                    joints,
+                }
+            }
+            None => SkinnedMesh {
+                inverse_bindposes: skin.rest_bindposes.clone(),
+                joints: vec![node_entity; skin.joints.len() + 1],
+            },
+        };
+        scene_world.entity_mut(entity).insert(skinned_mesh);
+    }
+    for (node, camera) in &contents.cameras {
+        let Some(stereo) = &camera.stereo else {
+            continue;
+        };
+        let Some(&rig) = state.camera_entities.get(node) else {
+            continue;
+        };
+        let eyes = [
+            (Some(*node), FbxStereoEye::Center),
+            (stereo.left, FbxStereoEye::Left),
+            (stereo.right, FbxStereoEye::Right),
+        ];
+        for (eye_node, eye) in eyes {
+            let eye_entity = eye_node.and_then(|eye_node| state.camera_entities.get(&eye_node));
+            if let Some(&entity) = eye_entity {
+                scene_world
+                    .entity_mut(entity)
+                    .insert(stereo.component(eye, rig));
+            }
+        }
+    }
     Scene::new(scene_world)
 }
 fn spawn_scene_rec(
     current: ObjectId,
     commands: &mut WorldChildBuilder,
-    hierarchy: &HashMap<ObjectId, FbxObject>,
-    models: &HashMap<ObjectId, FbxMesh>,
+    contents: &SceneContents,
+    state: &mut SpawnState,
 ) {
-    let current_node = match hierarchy.get(&current) {
+    let current_node = match contents.hierarchy.get(&current) {
         Some(node) => node,
         None => return,
     };
@@ -146,21 +569,152 @@ fn spawn_scene_rec(
     if let Some(name) = &current_node.name {
         entity.insert(Name::new(name.clone()));
     }
+    if !current_node.extras.is_empty() {
+        entity.insert(current_node.extras.clone());
+    }
+    if let Some(locator) = contents.locators.get(&current) {
+        entity.insert(locator.clone());
+    }
+    if let Some(socket) = contents.sockets.get(&current) {
+        entity.insert(socket.clone());
+    }
+    if let Some(tags) = contents.conventions.get(&current) {
+        if let Some(collision) = &tags.collision {
+            entity.insert(collision.clone());
+        }
+        if let Some(lod) = &tags.lod {
+            entity.insert(lod.clone());
+        }
+        if let Some(kind) = tags.kind {
+            entity.insert(kind);
+        }
+        if tags.hidden {
+            entity.insert(Visibility::Hidden);
+        }
+    }
+    state.node_entities.insert(current, entity.id());
     entity.with_children(|commands| {
-        if let Some(mesh) = models.get(&current) {
-            for (mat, bevy_mesh) in mesh.materials.iter().zip(&mesh.bevy_mesh_handles) {
+        if let Some(mesh) = contents.models.get(&current) {
+            let meshes = mesh.bevy_mesh_handles.iter().zip(&mesh.bevy_mesh_aabbs);
+            for (mat, (bevy_mesh, aabb)) in mesh.materials.iter().zip(meshes) {
                 let mut entity = commands.spawn(PbrBundle {
                     mesh: bevy_mesh.clone(),
                     material: mat.clone(),
+                    transform: Transform::from_translation(mesh.offset),
                     ..Default::default()
                 });
                 if let Some(name) = mesh.name.as_ref() {
                     entity.insert(Name::new(name.clone()));
                 }
+                if let Some(cache) = &mesh.vertex_cache {
+                    entity.insert(FbxVertexCachePlayer::new(cache.clone()));
+                }
+                if mesh.skin.is_some() {
+                    state.skinned.push((entity.id(), current));
+                }
+                // Skinned meshes get their bounds over the clips, the bounds
+                // at rest would cull limbs moving out of them.
+                let aabb = contents.skinned_aabbs.get(&current).or(aabb.as_ref());
+                if let Some(aabb) = aabb {
+                    entity.insert(*aabb);
+                }
+                if !mesh.casts_shadows {
+                    entity.insert(NotShadowCaster);
+                }
+                if !mesh.receives_shadows {
+                    entity.insert(NotShadowReceiver);
+                }
+                if let Some(name) = contents.material_names.get(mat) {
+                    entity.insert(FbxMaterialName(name.clone()));
+                }
+                if let Some(extras) = contents.material_extras.get(mat) {
+                    entity.insert(extras.clone());
+                }
+            }
+        }
+        if let Some(light) = contents.lights.get(&current) {
+            let transform = contents.attachment_transform(current, light::rest_rotation);
+            let entity = match light.clone() {
+                FbxLight::Point(point_light) => commands.spawn(PointLightBundle {
+                    point_light,
+                    transform,
+                    ..Default::default()
+                }),
+                FbxLight::Directional(directional_light) => {
+                    commands.spawn(DirectionalLightBundle {
+                        directional_light,
+                        transform,
+                        ..Default::default()
+                    })
+                }
+                FbxLight::Spot(spot_light) => commands.spawn(SpotLightBundle {
+                    spot_light,
+                    transform,
+                    ..Default::default()
+                }),
+                FbxLight::Area {
+                    light,
+                    approximation,
+                } => {
+                    let mut entity = commands.spawn((
+                        light,
+                        VisibilityBundle::default(),
+                        TransformBundle::from_transform(transform),
+                    ));
+                    if let Some(surface) = contents.area_light_surfaces.get(&current) {
+                        let (mesh, material, transform) = surface.clone();
+                        entity.with_children(|commands| {
+                            commands.spawn(PbrBundle {
+                                mesh,
+                                material,
+                                transform,
+                                ..Default::default()
+                            });
+                            match approximation {
+                                AreaLightApproximation::Spot(spot_light) => {
+                                    commands.spawn(SpotLightBundle {
+                                        spot_light,
+                                        ..Default::default()
+                                    })
+                                }
+                                AreaLightApproximation::Point(point_light) => {
+                                    commands.spawn(PointLightBundle {
+                                        point_light,
+                                        ..Default::default()
+                                    })
+                                }
+                            };
+                        });
+                    }
+                    entity
+                }
+            };
+            if let Some(target) = contents.look_ats.get(&current) {
+                state.aimed.push((entity.id(), *target));
+            }
+        }
+        if let Some(camera) = contents.cameras.get(&current) {
+            // Like the bevy glTF loader, only the first camera is active.
+            let entity = commands.spawn((
+                Camera3dBundle {
+                    camera: Camera {
+                        is_active: !state.active_camera_found,
+                        ..Default::default()
+                    },
+                    projection: camera.projection.clone(),
+                    transform: contents.attachment_transform(current, camera::rest_rotation),
+                    ..Default::default()
+                },
+                camera.extras.clone(),
+            ));
+            state.active_camera_found = true;
+            state.camera_entities.insert(current, entity.id());
+            if let Some(target) = contents.look_ats.get(&current) {
+                state.aimed.push((entity.id(), *target));
             }
         }
         for node_id in &current_node.children {
-            spawn_scene_rec(*node_id, commands, hierarchy, models);
+            spawn_scene_rec(*node_id, commands, contents, state);
         }
     });
 }
@@ -169,64 +723,345 @@ impl<'b, 'w> Loader<'b, 'w> {
     fn new(
         formats: CompressedImageFormats,
         loaders: Vec<MaterialLoader>,
-        load_context: &'b mut LoadContext<'w>,
+        settings: FbxLoaderSettings,
+        cache: Option<FbxCache>,
+        progress: Progress,
+        load_context: LoadTarget<'b, 'w>,
     ) -> Self {
         Self {
             scene: FbxScene::default(),
             load_context,
             material_loaders: loaders,
             suported_compressed_formats: formats,
+            settings,
+            cache,
+            progress,
+            material_labels: HashMap::default(),
+            material_handles: HashMap::default(),
+            material_names: HashMap::default(),
+            material_dependencies: Vec::new(),
+            geometries: HashMap::default(),
+            triangle_counts: HashMap::default(),
+            arena: MeshArena::default(),
         }
     }
 
-    async fn load(mut self, doc: Document) -> anyhow::Result<()> {
+    async fn load(mut self, doc: Document) -> anyhow::Result<Handle<FbxScene>> {
         info!(
             "Started loading scene {}#FbxScene",
             self.load_context.path().to_string_lossy(),
         );
-        let mut meshes = HashMap::new();
-        let mut hierarchy = HashMap::new();
+        let mut contents = SceneContents::default();
+        let memory_usage = &mut self.scene.memory_usage;
+        memory_usage.raw_arrays = memory_usage::raw_array_bytes(&doc);
+        memory_usage.end_stage(FbxLoadStage::Parsing);
 
         let fbx_scale = doc
             .global_settings()
             .and_then(|g| g.fbx_scale())
-            .unwrap_or(1.0);
-        let roots = doc.model_roots();
-        for root in &roots {
-            traverse_hierarchy(*root, &mut hierarchy);
+            .unwrap_or(1.0)
+            * self.settings.scale as f64;
+        let ambient_color = doc
+            .global_settings()
+            .and_then(|g| g.ambient_color())
+            .map(|c| Color::rgb(c.r as f32, c.g as f32, c.b as f32));
+        if self.settings.apply_ambient_light {
+            contents.ambient_light = ambient_color.map(|color| FbxAmbientLight { color });
+        }
+        let unit_scale = FBX_TO_BEVY_SCALE_FACTOR * fbx_scale as f32;
+        let scene_roots = doc.scene_model_roots();
+        let mut roots: Vec<_> = scene_roots.iter().flatten().copied().collect();
+        let origin = match self.settings.recenter {
+            true => recenter::scene_center(&roots).unwrap_or_default(),
+            false => DVec3::ZERO,
+        };
+        let mut poses = StoredPose::all(&doc);
+        for pose in &mut poses {
+            pose.recenter(origin);
+        }
+        contents.sockets = self.sockets(&doc);
+        let kept: HashSet<ObjectId> = poses
+            .iter()
+            .flat_map(|pose| pose.globals.keys().copied())
+            .chain(contents.sockets.keys().copied())
+            .collect();
+        let mut broken_connections = dangling_connections(&doc, &doc.scene_root_ids());
+        let mut visited = HashSet::new();
+        roots.retain(|root| {
+            traverse_hierarchy(
+                *root,
+                &kept,
+                &mut visited,
+                &mut broken_connections,
+                &mut contents,
+                origin,
+                &self.progress,
+            )
+        });
+        for broken in &broken_connections {
+            if self.settings.strict_connections {
+                bail!("{broken}");
+            }
+            self.progress.warn(
+                FbxWarningCategory::Connection,
+                None,
+                format!(
+                    "{:?}: {broken}, skipped the connection",
+                    self.load_context.path()
+                ),
+                Some("Fix the hierarchy in the authoring tool and re-export the file"),
+            );
+        }
+        let mut roots: Vec<_> = roots.into_iter().map(|obj| obj.object_id()).collect();
+        let dropped = filter::filter_hierarchy(
+            &self.settings,
+            &mut contents.hierarchy,
+            &mut contents.globals,
+            &mut roots,
+        );
+        let frozen = freeze::freeze_hierarchy(
+            &self.settings,
+            &mut contents.hierarchy,
+            &contents.globals,
+            &mut roots,
+        );
+        stage_log!(
+            self.settings,
+            FbxLogStage::Hierarchy,
+            "Hierarchy has {} nodes under {} roots, {} filtered out",
+            contents.hierarchy.len(),
+            roots.len(),
+            dropped.len()
+        );
+        let mut nodes: Vec<_> = contents.hierarchy.iter().collect();
+        nodes.sort_by_key(|(id, _)| id.raw());
+        for (id, node) in nodes {
+            stage_log!(
+                self.settings,
+                FbxLogStage::Hierarchy,
+                "Node {:?} ({}): {} children, at {}",
+                node.name.as_deref().unwrap_or_default(),
+                id.raw(),
+                node.children.len(),
+                node.transform.translation
+            );
         }
 
-        for obj in doc.objects() {
-            if let TypedObjectHandle::Model(TypedModelHandle::Mesh(mesh)) = obj.get_typed() {
-                meshes.insert(obj.object_id(), self.load_mesh(mesh).await?);
+        self.material_labels = material_labels(&doc, &self.settings);
+        let is_model =
+            |obj: &object::ObjectHandle| matches!(obj.get_typed(), TypedObjectHandle::Model(_));
+        // Sorted, so that geometries instanced by several nodes are labeled
+        // the same way at each load.
+        let mut models: Vec<_> = doc.objects().filter(is_model).collect();
+        models.sort_by_key(|obj| obj.object_id().raw());
+        let model_count = models.len();
+        for (i, obj) in models.into_iter().enumerate() {
+            let model = match obj.get_typed() {
+                TypedObjectHandle::Model(model) => model,
+                _ => continue,
+            };
+            if dropped.contains(&obj.object_id()) {
+                continue;
+            }
+            self.progress
+                .report(FbxLoadStage::Objects, i, model_count, obj.name());
+            self.progress.check_cancelled()?;
+            self.give_way().await;
+            if let Some(target) = model.look_at_target() {
+                contents.look_ats.insert(obj.object_id(), target);
+            }
+            match model {
+                // Sockets meshes are placeholders.
+                TypedModelHandle::Mesh(_) if contents.sockets.contains_key(&obj.object_id()) => {}
+                TypedModelHandle::Mesh(mesh) if mesh.geometry().is_err() => {
+                    self.progress.warn(
+                        FbxWarningCategory::Connection,
+                        obj.name(),
+                        format!(
+                            "{:?}: Mesh {:?} has no geometry, loaded it as an empty node",
+                            self.load_context.path(),
+                            obj.name()
+                        ),
+                        Some("Fix the hierarchy in the authoring tool and re-export the file"),
+                    );
+                }
+                TypedModelHandle::Mesh(mesh) => {
+                    let mesh = self.load_mesh(mesh).await?;
+                    contents.models.insert(obj.object_id(), mesh);
+                }
+                TypedModelHandle::Light(light) => {
+                    if let Some(light) =
+                        FbxLight::from_node(*light, unit_scale, self.settings.light_exposure)
+                    {
+                        if let FbxLight::Area { light, .. } = &light {
+                            if self.settings.approximate_area_lights {
+                                let surface = self.load_area_light_surface(obj.object_id(), light);
+                                contents
+                                    .area_light_surfaces
+                                    .insert(obj.object_id(), surface);
+                            }
+                        }
+                        contents.lights.insert(obj.object_id(), light);
+                    }
+                }
+                TypedModelHandle::Camera(camera) => {
+                    if let Some(camera) = FbxCamera::from_node(*camera, unit_scale) {
+                        contents.cameras.insert(obj.object_id(), camera);
+                    }
+                }
+                TypedModelHandle::Null(null) => {
+                    if let Some(locator) = FbxLocator::from_node(*null) {
+                        contents.locators.insert(obj.object_id(), locator);
+                    }
+                }
+                _ => {}
             }
         }
-        let roots: Vec<_> = roots.into_iter().map(|obj| obj.object_id()).collect();
-        let scene = spawn_scene(fbx_scale as f32, &roots, &hierarchy, &meshes);
+        self.scene.memory_usage.conversion_buffers = self.arena.capacity_bytes();
+        self.scene.memory_usage.end_stage(FbxLoadStage::Objects);
+        names::sanitize_hierarchy(&mut contents.hierarchy, &roots, &self.settings);
+        if let Some(conventions) = &self.settings.naming_conventions {
+            contents.conventions = conventions::tag_hierarchy(&contents.hierarchy, conventions);
+        }
+        let frame_rate = doc.global_settings().map_or(30.0, |g| g.frame_rate());
+        let detached = frozen.iter().map(|f| (f.node, f.parent)).collect();
+        let animations =
+            self.load_animations(&doc, &mut contents, &roots, &detached, &poses, frame_rate);
+        // Entered spans aren't `Send`, they can't be held across the awaits.
+        #[cfg(feature = "profile")]
+        let animations = animations.instrument(info_span!("load_animations"));
+        animations.await?;
+        self.scene.memory_usage.end_stage(FbxLoadStage::Animations);
+        contents.autoplay = self.autoplay_clip();
+        contents.material_names = std::mem::take(&mut self.material_names);
+        contents.material_extras = (self.scene.material_extras.iter())
+            .filter_map(|(label, extras)| {
+                let material = self.scene.materials.get(label)?;
+                Some((material.clone_weak(), extras.clone()))
+            })
+            .collect();
+        self.progress.report(FbxLoadStage::Scene, 0, 1, None);
+        self.progress.check_cancelled()?;
+        let references = self.external_references(&doc);
+        let resolved: Vec<_> = (references.iter())
+            .filter_map(|reference| {
+                let path = reference.resolved.clone()?;
+                let name = reference
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| reference.path.clone());
+                Some((name, AssetPath::new(path, None)))
+            })
+            .collect();
+        contents.referenced_scenes = (resolved.iter())
+            .map(|(name, path)| {
+                let scene = AssetPath::new_ref(path.path(), Some("Scene"));
+                (Name::new(name.clone()), self.load_context.get_handle(scene))
+            })
+            .collect();
+        // The roots of each document, in file order, without the dropped ones,
+        // followed by the frozen nodes of the document.
+        let document_roots: Vec<Vec<ObjectId>> = (scene_roots.iter())
+            .map(|document| {
+                let document: Vec<_> = (document.iter())
+                    .map(|root| root.object_id())
+                    .filter(|root| roots.contains(root))
+                    .collect();
+                let frozen = (frozen.iter())
+                    .filter(|f| document.contains(&f.root))
+                    .map(|f| f.node);
+                document.iter().copied().chain(frozen).collect()
+            })
+            .collect();
+        let spawn_document = |document: &[ObjectId]| {
+            let mut scene = spawn_scene(fbx_scale as f32, document, &contents, self.root_marker());
+            if self.settings.static_scene {
+                static_scene::make_static(&mut scene.world);
+            }
+            scene
+        };
+        let scene = spawn_document(&document_roots[0]);
+        self.check_budget(&scene)?;
+        let document_scenes: Vec<_> = document_roots.iter().map(|d| spawn_document(d)).collect();
 
         let load_context = &mut self.load_context;
-        load_context.set_labeled_asset("Scene", LoadedAsset::new(scene));
+        let dependencies = (resolved.into_iter().map(|(_, path)| path))
+            .chain(self.material_dependencies)
+            .collect();
+        load_context.set_labeled_asset_with_dependencies("Scene", scene, dependencies);
+        let document_scenes: Vec<_> = (document_scenes.into_iter().enumerate())
+            .map(|(i, scene)| load_context.set_labeled_asset(&format!("Scene{i}"), scene))
+            .collect();
+        load_context.set_labeled_asset("Meshes", contents.flat_meshes());
+
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.save() {
+                self.progress.warn(
+                    FbxWarningCategory::Cache,
+                    None,
+                    format!("Failed to write the FBX cache: {err:?}"),
+                    Some("Check that FbxLoaderSettings::cache_folder is writable"),
+                );
+            }
+        }
 
         let mut scene = self.scene;
-        scene.hierarchy = hierarchy.clone();
+        scene.memory_usage.end_stage(FbxLoadStage::Scene);
+        log_memory_usage(load_context.path(), &scene.memory_usage);
+        scene.bounds = contents.bounds(root_transform(fbx_scale as f32).compute_matrix());
+        scene.hierarchy = contents.hierarchy;
         scene.roots = roots;
-        load_context.set_labeled_asset("FbxScene", LoadedAsset::new(scene));
+        scene.skinned_aabbs = contents.skinned_aabbs;
+        scene.skin_joints = contents.skin_joints;
+        scene.scenes = document_scenes;
+        load_nodes(load_context, &mut scene);
+        scene.ambient_color = ambient_color;
+        scene.characters = FbxCharacter::all(&doc);
+        scene.media = FbxMediaRef::all(&doc);
+        scene.external_references = references;
+        let bind_pose = StoredPose::merge_bind_poses(&poses);
+        let skeleton = FbxSkeleton::new(
+            &doc,
+            &scene.hierarchy,
+            &scene.roots,
+            &contents.globals,
+            bind_pose.as_ref(),
+        );
+        scene.skeleton = skeleton.map(|s| load_context.set_labeled_asset("FbxSkeleton", s));
+        scene.thumbnail = thumbnail::thumbnail(&doc)
+            .map(|image| load_context.set_labeled_asset("Thumbnail", image));
+        let mut materials: Vec<_> = self.material_handles.into_iter().collect();
+        materials.sort_by_key(|(id, _)| id.raw());
+        scene.ordered_materials = materials.into_iter().map(|(_, handle)| handle).collect();
+        let handle = load_context.set_labeled_asset("FbxScene", scene);
+        self.progress.report(FbxLoadStage::Done, 1, 1, None);
         info!(
             "Successfully loaded scene {}#FbxScene",
             load_context.path().to_string_lossy(),
         );
-        Ok(())
+        Ok(handle)
     }
 
     fn load_bevy_mesh(
         &mut self,
         mesh_obj: object::geometry::MeshHandle,
-        num_materials: usize,
-    ) -> anyhow::Result<Vec<Handle<Mesh>>> {
-        let label = match mesh_obj.name() {
-            Some(name) if !name.is_empty() => format!("FbxMesh@{name}/Primitive"),
-            _ => format!("FbxMesh{}/Primitive", mesh_obj.object_id().raw()),
+        variant: &GeometryVariant,
+        variant_index: usize,
+        max_vertices: Option<usize>,
+    ) -> anyhow::Result<GeometryMeshes> {
+        let GeometryVariant {
+            material_count: num_materials,
+            simplification,
+            ..
+        } = *variant;
+        let mut geometry_label = match mesh_obj.name() {
+            Some(name) if !name.is_empty() => format!("FbxMesh@{name}"),
+            _ => format!("FbxMesh{}", mesh_obj.object_id().raw()),
         };
+        if variant_index > 0 {
+            geometry_label = format!("{geometry_label}/Variant{variant_index}");
+        }
+        let label = format!("{geometry_label}/Primitive");
         trace!(
             "loading geometry mesh for node_id: {:?}",
             mesh_obj.object_node_id()
@@ -235,60 +1070,271 @@ impl<'b, 'w> Loader<'b, 'w> {
         #[cfg(feature = "profile")]
         let _load_geometry_mesh = info_span!("load_geometry_mesh", label = &label).entered();
 
+        let polygon_vertices = mesh_obj
+            .polygon_vertices()
+            .context("Failed to get polygon vertices")?;
+        let layer = mesh_obj
+            .layers()
+            .next()
+            .ok_or_else(|| anyhow!("Failed to get layer"))?;
+        let edges = mesh_obj
+            .node()
+            .first_child_by_name("Edges")
+            .and_then(|node| node.attributes().first()?.get_arr_i32())
+            .unwrap_or_default();
+        let topology = MeshTopology::new(polygon_vertices.raw_polygon_vertices(), edges);
+        let subdivision = FbxSubdivisionData::from_geometry(&mesh_obj.node(), &layer, &topology)
+            .context("Failed to get subdivision data")?;
+        let vertices = recenter::control_points(mesh_obj).context("Failed to get vertices")?;
+        let offset = match self.settings.recenter {
+            true => recenter::mesh_offset(vertices),
+            false => None,
+        };
+        let vertices = recenter::offset_points(vertices, offset);
+        let wireframe = match self.settings.wireframe_meshes {
+            true => {
+                let wireframe = load_wireframe(&vertices, &topology)
+                    .context("Failed to load wireframe mesh")?;
+                let label = format!("{geometry_label}/Wireframe");
+                let bytes = memory_usage::mesh_bytes(&wireframe);
+                self.scene.memory_usage.meshes.insert(label.clone(), bytes);
+                Some(self.load_context.set_labeled_asset(&label, wireframe))
+            }
+            false => None,
+        };
+        let cache_key = format!("{label}{simplification:?}");
+        let cached = self.cache.as_ref().and_then(|cache| cache.mesh(&cache_key));
+        let converted = match cached {
+            Some(converted) => converted,
+            None => {
+                let converted = if self.settings.subdivision_level > 0 {
+                    let (mesh, all_indices) = self
+                        .load_subdivided_mesh(
+                            mesh_obj,
+                            &vertices,
+                            &topology,
+                            subdivision.as_ref(),
+                            num_materials,
+                            simplification,
+                        )
+                        .context("Failed to subdivide mesh")?;
+                    ConvertedMesh {
+                        mesh,
+                        all_indices,
+                        vertex_control_points: Vec::new(),
+                    }
+                } else {
+                    let mut arena = std::mem::take(&mut self.arena);
+                    arena.reset();
+                    layer_element::to_f32(&vertices, &mut arena.control_points);
+                    let converted = self.triangulate_mesh(
+                        &label,
+                        mesh_obj,
+                        &topology,
+                        num_materials,
+                        simplification,
+                        &mut arena,
+                    );
+                    self.arena = arena;
+                    converted?
+                };
+                if let Some(cache) = &mut self.cache {
+                    cache.insert_mesh(&cache_key, &converted);
+                }
+                converted
+            }
+        };
+        let offset = offset.unwrap_or_default().as_vec3();
+        let mut mesh = converted.mesh;
+        let skin = match GeometrySkin::new(mesh_obj) {
+            Some(_) if converted.vertex_control_points.is_empty() => {
+                stage_log!(
+                    self.settings,
+                    FbxLogStage::Meshes,
+                    "Skin of {label} is not applied to the subdivided mesh"
+                );
+                None
+            }
+            Some(skin) if skin.joints().len() >= MAX_JOINTS => {
+                self.progress.warn(
+                    FbxWarningCategory::Mesh,
+                    Some(&label),
+                    format!(
+                        "{label} is moved by {} joints, more than bevy supports, \
+                        loaded it unskinned",
+                        skin.joints().len()
+                    ),
+                    Some("Split the mesh in parts moved by fewer joints each"),
+                );
+                None
+            }
+            Some(skin) => {
+                skin.add_joint_attributes(&mut mesh, &converted.vertex_control_points);
+                let mut add_bindposes = |label: String, bindposes: Vec<Mat4>| {
+                    let bindposes = SkinnedMeshInverseBindposes::from(bindposes);
+                    self.load_context.set_labeled_asset(&label, bindposes)
+                };
+                Some(FbxMeshSkin {
+                    joints: skin.joints(),
+                    inverse_bindposes: add_bindposes(
+                        format!("{geometry_label}/InverseBindposes"),
+                        skin.inverse_bindposes(offset),
+                    ),
+                    rest_bindposes: add_bindposes(
+                        format!("{geometry_label}/RestBindposes"),
+                        skin.rest_bindposes(offset),
+                    ),
+                })
+            }
+            None => None,
+        };
+        let meshes =
+            self.add_bevy_meshes(&label, mesh, converted.all_indices, max_vertices, variant);
+        Ok(GeometryMeshes {
+            vertex_control_points: converted.vertex_control_points,
+            subdivision,
+            skin,
+            wireframe,
+            offset,
+            ..meshes
+        })
+    }
+
+    /// Triangulate the polygons of `mesh_obj` and read their vertex attributes,
+    /// with the intermediate buffers of `arena`, its control points being set
+    /// to the vertices of the mesh.
+    fn triangulate_mesh(
+        &self,
+        label: &str,
+        mesh_obj: object::geometry::MeshHandle,
+        topology: &MeshTopology,
+        num_materials: usize,
+        simplification: Option<FbxSimplification>,
+        arena: &mut MeshArena,
+    ) -> anyhow::Result<ConvertedMesh> {
         #[cfg(feature = "profile")]
         let triangulate_mesh = info_span!("traingulate_mesh", label = &label).entered();
 
         let polygon_vertices = mesh_obj
             .polygon_vertices()
             .context("Failed to get polygon vertices")?;
+        let layer = mesh_obj
+            .layers()
+            .next()
+            .ok_or_else(|| anyhow!("Failed to get layer"))?;
+        let polygon_roles = {
+            let holes = layer_element::find_element(&mesh_obj.node(), &layer, "LayerElementHole");
+            let holes = holes
+                .map(|holes| layer_element::hole_flags(&holes, topology))
+                .transpose()
+                .context("Failed to get polygon holes")?;
+            holes.map(|holes| triangulate::polygon_roles(&holes))
+        };
+
+        // `PolygonVertexIndex` is opaque, so we record the raw index of each
+        // polygon vertex while triangulating, to look up layer elements.
+        let raw_pvis = RefCell::new(std::mem::take(&mut arena.raw_pvis));
+        let next_raw_pvi = Cell::new(0);
+        let next_polygon = Cell::new(0);
+        // The outer polygon and holes collected so far, triangulated
+        // together when reaching the last hole.
+        let pending_holes = RefCell::new(Vec::new());
+        let triangulator = |pvs: &PolygonVertices,
+                            poly_pvis: &[PolygonVertexIndex],
+                            results: &mut _| {
+            let mut raw_pvis = raw_pvis.borrow_mut();
+            for pvi in poly_pvis {
+                raw_pvis.insert(*pvi, next_raw_pvi.get());
+                next_raw_pvi.set(next_raw_pvi.get() + 1);
+            }
+            let polygon = next_polygon.replace(next_polygon.get() + 1);
+            let role = polygon_roles.as_ref().and_then(|roles| roles.get(polygon));
+            match role.copied().unwrap_or(PolygonRole::Solid) {
+                PolygonRole::Solid => triangulate::triangulate(pvs, poly_pvis, results),
+                PolygonRole::Outer | PolygonRole::Hole { last: false } => {
+                    pending_holes.borrow_mut().push(poly_pvis.to_vec());
+                    Ok(())
+                }
+                PolygonRole::Hole { last: true } => {
+                    let mut pending = pending_holes.take();
+                    pending.push(poly_pvis.to_vec());
+                    triangulate::triangulate_with_holes(pvs, &pending[0], &pending[1..], results)
+                }
+                PolygonRole::Orphan => Ok(()),
+            }
+        };
         let triangle_pvi_indices = polygon_vertices
-            .triangulate_each(triangulate::triangulate)
+            .triangulate_each(triangulator)
             .context("Triangulation failed")?;
+        arena.raw_pvis = raw_pvis.into_inner();
+        for tri_vi in triangle_pvi_indices.triangle_vertex_indices() {
+            let pvi = triangle_pvi_indices.polygon_vertex_index(tri_vi);
+            let raw_pvi = pvi.and_then(|pvi| arena.raw_pvis.get(&pvi));
+            (arena.triangle_raw_pvis)
+                .push(*raw_pvi.context("Failed to get triangle polygon vertices")?);
+        }
+        let triangle_raw_pvis = &arena.triangle_raw_pvis;
+        // Vertices are indexed with `u32`, which the triangles of the huge
+        // meshes of FBX 7.5 files can outnumber.
+        if u32::try_from(triangle_raw_pvis.len()).is_err() {
+            bail!(
+                "Mesh {:?} has {} triangle vertices, more than 32-bit indices can address",
+                mesh_obj,
+                triangle_raw_pvis.len()
+            );
+        }
 
         #[cfg(feature = "profile")]
         drop(triangulate_mesh);
 
         // TODO this seems to duplicate vertices from neighboring triangles. We shouldn't
         // do that and instead set the indice attribute of the Mesh properly.
-        let get_position = |pos: Option<_>| -> Result<_, anyhow::Error> {
-            let cpi = pos.ok_or_else(|| anyhow!("Failed to get control point index"))?;
-            let point = polygon_vertices
-                .control_point(cpi)
-                .ok_or_else(|| anyhow!("Failed to get control point: cpi={:?}", cpi))?;
-            Ok(DVec3::from(point).as_vec3().into())
-        };
-        let positions = triangle_pvi_indices
-            .iter_control_point_indices()
-            .map(get_position)
-            .collect::<Result<Vec<_>, _>>()
-            .context("Failed to reconstruct position vertices")?;
-
-        debug!("Expand position lenght to {}", positions.len());
+        let mut positions = Vec::with_capacity(triangle_raw_pvis.len());
+        let mut vertex_control_points = Vec::with_capacity(triangle_raw_pvis.len());
+        for cpi in triangle_pvi_indices.iter_control_point_indices() {
+            let cpi = cpi
+                .map(ControlPointIndex::to_u32)
+                .context("Failed to reconstruct position vertices: missing control point index")?;
+            let start = cpi as usize * 3;
+            let position = arena
+                .control_points
+                .get(start..start + 3)
+                .with_context(|| {
+                    format!("Failed to reconstruct position vertices: no control point {cpi}")
+                })?;
+            positions.push([position[0], position[1], position[2]]);
+            vertex_control_points.push(cpi);
+        }
+        stage_log!(
+            self.settings,
+            FbxLogStage::Meshes,
+            "Expand position lenght to {}",
+            positions.len()
+        );
 
-        let layer = mesh_obj
-            .layers()
-            .next()
-            .ok_or_else(|| anyhow!("Failed to get layer"))?;
+        let element = |is_type: fn(&TypedLayerElementHandle) -> bool| {
+            layer
+                .layer_element_entries()
+                .filter_map(|entry| entry.typed_layer_element().ok())
+                .find(is_type)
+        };
 
         let indices_per_material = || -> Result<_, anyhow::Error> {
             if num_materials == 0 {
                 return Ok(None);
             };
             let mut indices_per_material = vec![Vec::new(); num_materials];
-            let materials = layer
-                .layer_element_entries()
-                .find_map(|entry| match entry.typed_layer_element() {
-                    Ok(TypedLayerElementHandle::Material(handle)) => Some(handle),
-                    _ => None,
-                })
-                .ok_or_else(|| anyhow!("Materials not found for mesh {:?}", mesh_obj))?
-                .materials()
+            let materials = element(|e| matches!(e, TypedLayerElementHandle::Material(_)))
+                .ok_or_else(|| anyhow!("Materials not found for mesh {:?}", mesh_obj))?;
+            let materials = layer_element::material_element(materials.node())
                 .context("Failed to get materials")?;
-            for tri_vi in triangle_pvi_indices.triangle_vertex_indices() {
+            // Triangles of polygons with holes mix vertices of several polygons,
+            // use the material of the earliest one, usually the outer polygon.
+            for (tri_i, tri_pvis) in triangle_raw_pvis.chunks_exact(3).enumerate() {
+                let pvi = *tri_pvis.iter().min().unwrap();
                 let local_material_index = materials
-                    .material_index(&triangle_pvi_indices, tri_vi)
-                    .context("Failed to get mesh-local material index")?
-                    .to_u32();
+                    .get(topology, pvi)
+                    .context("Failed to get mesh-local material index")?[0];
                 indices_per_material
                      .get_mut(local_material_index as usize)
                      .ok_or_else(|| {
@@ -298,50 +1344,76 @@ impl<'b, 'w> Loader<'b, 'w> {
                              local_material_index
                          )
                      })?
-                     .push(tri_vi.to_usize() as u32);
+                     .extend((0..3).map(|i| (tri_i * 3 + i) as u32));
             }
             Ok(Some(indices_per_material))
         };
-        let normals = {
-            let normals = layer
-                .layer_element_entries()
-                .find_map(|entry| match entry.typed_layer_element() {
-                    Ok(TypedLayerElementHandle::Normal(handle)) => Some(handle),
-                    _ => None,
-                })
-                .ok_or_else(|| anyhow!("Failed to get normals"))?
-                .normals()
+        let normals: Vec<[f32; 3]> = {
+            let normals = element(|e| matches!(e, TypedLayerElementHandle::Normal(_)))
+                .ok_or_else(|| anyhow!("Failed to get normals"))?;
+            let normals = layer_element::f64_element(normals.node(), "Normals", "NormalsIndex", 3)
                 .context("Failed to get normals")?;
-            let get_indices = |tri_vi| -> Result<_, anyhow::Error> {
-                let v = normals.normal(&triangle_pvi_indices, tri_vi)?;
-                Ok(DVec3::from(v).as_vec3().into())
-            };
-            triangle_pvi_indices
-                .triangle_vertex_indices()
-                .map(get_indices)
-                .collect::<Result<Vec<_>, _>>()
+            normals
+                .get_all_f32(topology, triangle_raw_pvis, &mut arena.values)
                 .context("Failed to reconstruct normals vertices")?
         };
-        let uv = {
-            let uv = layer
-                .layer_element_entries()
-                .find_map(|entry| match entry.typed_layer_element() {
-                    Ok(TypedLayerElementHandle::Uv(handle)) => Some(handle),
-                    _ => None,
-                })
-                .ok_or_else(|| anyhow!("Failed to get UV"))?
-                .uv()?;
-            let get_indices = |tri_vi| -> Result<_, anyhow::Error> {
-                let v = uv.uv(&triangle_pvi_indices, tri_vi)?;
-                let fbx_uv_space = DVec2::from(v).as_vec2();
-                let bevy_uv_space = fbx_uv_space * Vec2::new(1.0, -1.0) + Vec2::new(0.0, 1.0);
-                Ok(bevy_uv_space.into())
-            };
-            triangle_pvi_indices
-                .triangle_vertex_indices()
-                .map(get_indices)
-                .collect::<Result<Vec<_>, _>>()
-                .context("Failed to reconstruct UV vertices")?
+        let uv: Vec<[f32; 2]> = {
+            let uv = element(|e| matches!(e, TypedLayerElementHandle::Uv(_)))
+                .ok_or_else(|| anyhow!("Failed to get UV"))?;
+            let uv = layer_element::f64_element(uv.node(), "UV", "UVIndex", 2)?;
+            let mut uv: Vec<[f32; 2]> = uv
+                .get_all_f32(topology, triangle_raw_pvis, &mut arena.values)
+                .context("Failed to reconstruct UV vertices")?;
+            if self.settings.flip_uv_v.flips() {
+                uv.iter_mut().for_each(|[_, v]| *v = 1.0 - *v);
+            }
+            uv
+        };
+        // Keep the tangent space of the file when it has one, so that
+        // normal maps baked against it render as authored.
+        let tangents = {
+            let geometry = mesh_obj.node();
+            let tangents = layer_element::find_element(&geometry, &layer, "LayerElementTangent");
+            let binormals = layer_element::find_element(&geometry, &layer, "LayerElementBinormal");
+            match (tangents, binormals) {
+                (Some(tangents), Some(binormals)) => {
+                    layer_element::f64_element(&tangents, "Tangents", "TangentsIndex", 3)?
+                        .gather_f32(
+                            topology,
+                            triangle_raw_pvis,
+                            &mut arena.values,
+                            &mut arena.tangents,
+                        )
+                        .context("Failed to reconstruct tangent vertices")?;
+                    layer_element::f64_element(&binormals, "Binormals", "BinormalsIndex", 3)?
+                        .gather_f32(
+                            topology,
+                            triangle_raw_pvis,
+                            &mut arena.values,
+                            &mut arena.binormals,
+                        )
+                        .context("Failed to reconstruct binormal vertices")?;
+                    let tangents = normals
+                        .iter()
+                        .zip(arena.tangents.iter().zip(&arena.binormals))
+                        .map(|(n, (t, b))| {
+                            layer_element::tangent(Vec3::from(*n), Vec3::from(*t), Vec3::from(*b))
+                        })
+                        .collect::<Vec<_>>();
+                    Some(tangents)
+                }
+                _ => None,
+            }
+        };
+        let colors = match element(|e| matches!(e, TypedLayerElementHandle::Color(_))) {
+            Some(colors) => {
+                let colors = layer_element::f64_element(colors.node(), "Colors", "ColorIndex", 4)?;
+                let colors: Vec<[f32; 4]> = colors
+                    .get_all_f32(topology, triangle_raw_pvis, &mut arena.values)
+                    .context("Failed to reconstruct color vertices")?;
+                Some(colors)
+            }
+            None => None,
         };
 
         if uv.len() != positions.len() || uv.len() != normals.len() {
@@ -369,8 +1441,6 @@ impl<'b, 'w> Loader<'b, 'w> {
             vec![full_mesh_indices.clone()]
         };
 
-        debug!("Material count for {label}: {}", all_indices.len());
-
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
         mesh.insert_attribute(
             Mesh::ATTRIBUTE_POSITION,
@@ -381,89 +1451,943 @@ impl<'b, 'w> Loader<'b, 'w> {
             Mesh::ATTRIBUTE_NORMAL,
             VertexAttributeValues::Float32x3(normals),
         );
+        if let Some(colors) = colors {
+            mesh.insert_attribute(
+                Mesh::ATTRIBUTE_COLOR,
+                VertexAttributeValues::Float32x4(colors),
+            );
+        }
         mesh.set_indices(Some(Indices::U32(full_mesh_indices)));
-        mesh.generate_tangents()
-            .context("Failed to generate tangents")?;
-
-        let all_handles = all_indices
-            .into_iter()
-            .enumerate()
-            .map(|(i, material_indices)| {
-                debug!("Material {i} has {} vertices", material_indices.len());
+        match tangents {
+            Some(tangents) => mesh.insert_attribute(
+                Mesh::ATTRIBUTE_TANGENT,
+                VertexAttributeValues::Float32x4(tangents),
+            ),
+            None => mesh
+                .generate_tangents()
+                .context("Failed to generate tangents")?,
+        }
 
-                let mut material_mesh = mesh.clone();
-                material_mesh.set_indices(Some(Indices::U32(material_indices)));
+        let (mesh, all_indices, vertex_control_points) = match simplification {
+            Some(simplification) => {
+                match simplify::simplify(&mesh, &all_indices, &simplification) {
+                    Some(simplified) => {
+                        let vertex_control_points = simplified
+                            .original_vertices
+                            .iter()
+                            .map(|vertex| vertex_control_points[*vertex as usize])
+                            .collect();
+                        (
+                            simplified.mesh,
+                            simplified.all_indices,
+                            vertex_control_points,
+                        )
+                    }
+                    None => {
+                        self.progress.warn(
+                            FbxWarningCategory::Mesh,
+                            Some(label),
+                            format!("Could not simplify {label}, it has unsupported attributes"),
+                            Some("Remove the custom vertex attributes of the mesh"),
+                        );
+                        (mesh, all_indices, vertex_control_points)
+                    }
+                }
+            }
+            None => (mesh, all_indices, vertex_control_points),
+        };
+        Ok(ConvertedMesh {
+            mesh,
+            all_indices,
+            vertex_control_points,
+        })
+    }
 
-                let label = format!("{label}{i}");
+    /// Add a copy of `mesh` for each material, with the indices of the material.
+    ///
+    /// Materials using more than `max_vertices` vertices are split into
+    /// several meshes, each with only the vertices it uses.
+    /// The meshes are then optimized and their vertex attributes converted
+    /// as described by `variant`.
+    fn add_bevy_meshes(
+        &mut self,
+        label: &str,
+        mesh: Mesh,
+        all_indices: Vec<Vec<u32>>,
+        max_vertices: Option<usize>,
+        variant: &GeometryVariant,
+    ) -> GeometryMeshes {
+        stage_log!(
+            self.settings,
+            FbxLogStage::Meshes,
+            "Material count for {label}: {}",
+            all_indices.len()
+        );
+        let aabb = mesh.compute_aabb();
+        let mut meshes = GeometryMeshes {
+            handles: Vec::new(),
+            materials: Vec::new(),
+            aabbs: Vec::new(),
+            aabb,
+            vertex_control_points: Vec::new(),
+            subdivision: None,
+            skin: None,
+            wireframe: None,
+            offset: Vec3::ZERO,
+        };
+        for (i, material_indices) in all_indices.into_iter().enumerate() {
+            stage_log!(
+                self.settings,
+                FbxLogStage::Meshes,
+                "Material {i} has {} vertices",
+                material_indices.len()
+            );
 
-                let handle = self
-                    .load_context
-                    .set_labeled_asset(&label, LoadedAsset::new(material_mesh));
+            let parts = max_vertices
+                .and_then(|max_vertices| mesh_utils::split(&mesh, &material_indices, max_vertices));
+            let parts = match parts {
+                Some(parts) => {
+                    stage_log!(
+                        self.settings,
+                        FbxLogStage::Meshes,
+                        "Material {i} of {label} is split in {} meshes",
+                        parts.len()
+                    );
+                    parts
+                        .into_iter()
+                        .enumerate()
+                        .map(|(part, mesh)| {
+                            let aabb = mesh.compute_aabb();
+                            (format!("{label}{i}/Part{part}"), mesh, aabb)
+                        })
+                        .collect()
+                }
+                None => {
+                    let mut material_mesh = mesh.clone();
+                    material_mesh.set_indices(Some(Indices::U32(material_indices)));
+                    vec![(format!("{label}{i}"), material_mesh, aabb)]
+                }
+            };
+            let vertex_colors = variant.vertex_colors.get(i).copied();
+            for (label, mut part, aabb) in parts {
+                if variant.optimize {
+                    match optimize::optimize(&part) {
+                        Some(optimized) => part = optimized,
+                        None => stage_log!(
+                            self.settings,
+                            FbxLogStage::Meshes,
+                            "Can't optimize mesh {label}"
+                        ),
+                    }
+                }
+                if vertex_colors == Some(FbxVertexColors::Data) {
+                    if let Some(colors) = part.remove_attribute(Mesh::ATTRIBUTE_COLOR) {
+                        part.insert_attribute(FbxMesh::ATTRIBUTE_VERTEX_COLOR, colors);
+                    }
+                }
+                quantize::quantize(&mut part, &variant.vertex_formats, &label, &self.progress);
+                let triangles = part.indices().map_or(0, |indices| indices.len() / 3);
+                let bytes = memory_usage::mesh_bytes(&part);
+                self.scene.memory_usage.meshes.insert(label.clone(), bytes);
+                let handle = self.load_context.set_labeled_asset(&label, part);
+                self.triangle_counts.insert(handle.clone(), triangles);
                 self.scene.bevy_meshes.insert(handle.clone(), label);
-                handle
-            })
-            .collect();
-        Ok(all_handles)
+                meshes.handles.push(handle);
+                meshes.materials.push(i);
+                meshes.aabbs.push(aabb);
+            }
+        }
+        meshes
     }
 
-    // Note: FBX meshes can have multiple different materials, it's not just a mesh.
-    // the FBX equivalent of a bevy Mesh is a geometry mesh
-    async fn load_mesh(
+    /// Subdivide the polygons of `mesh_obj` with Catmull-Clark,
+    /// see [`FbxLoaderSettings::subdivision_level`].
+    ///
+    /// Returns the subdivided mesh, and the indices of each material.
+    fn load_subdivided_mesh(
         &mut self,
-        mesh_obj: object::model::MeshHandle<'_>,
-    ) -> anyhow::Result<FbxMesh> {
-        let label = if let Some(name) = mesh_obj.name() {
-            format!("FbxMesh@{name}")
-        } else {
-            format!("FbxMesh{}", mesh_obj.object_id().raw())
+        mesh_obj: object::geometry::MeshHandle,
+        vertices: &[f64],
+        topology: &MeshTopology,
+        subdivision: Option<&FbxSubdivisionData>,
+        num_materials: usize,
+        simplification: Option<FbxSimplification>,
+    ) -> anyhow::Result<(Mesh, Vec<Vec<u32>>)> {
+        let geometry = mesh_obj.node();
+        let layer = mesh_obj
+            .layers()
+            .next()
+            .ok_or_else(|| anyhow!("Failed to get layer"))?;
+        let uv = layer_element::find_element(&geometry, &layer, "LayerElementUV")
+            .ok_or_else(|| anyhow!("Failed to get UV"))?;
+        let uv = layer_element::f64_element(&uv, "UV", "UVIndex", 2)?;
+        let materials = match num_materials {
+            0 => None,
+            _ => {
+                let materials =
+                    layer_element::find_element(&geometry, &layer, "LayerElementMaterial")
+                        .ok_or_else(|| anyhow!("Materials not found for mesh {:?}", mesh_obj))?;
+                Some(layer_element::material_element(&materials)?)
+            }
         };
-        debug!("Loading FBX mesh: {label}");
 
-        let bevy_obj = mesh_obj.geometry().context("Failed to get geometry")?;
-
-        // async and iterators into for are necessary because of `async` `read_asset_bytes`
-        // call in `load_video_clip`  that virally infect everything.
-        // This can't even be ran in parallel, because we store already-encountered materials.
-        let mut materials = Vec::new();
-        for mat in mesh_obj.materials() {
-            let mat = self.load_material(mat).await;
-            let mat = mat.context("Failed to load materials for mesh")?;
-            materials.push(mat);
+        let mut cage = Cage {
+            positions: (layer_element::to_f32_arrays::<3>(vertices).into_iter())
+                .map(Vec3::from)
+                .collect(),
+            edge_creases: subdivision
+                .map(|s| s.edge_creases.clone())
+                .unwrap_or_default(),
+            vertex_creases: subdivision
+                .map(|s| s.vertex_creases.clone())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let mut polygon_materials = Vec::new();
+        for (i, polygon) in topology.polygons().enumerate() {
+            let uvs = polygon
+                .clone()
+                .map(|pvi| Ok(DVec2::from_slice(uv.get(topology, pvi)?).as_vec2()))
+                .collect::<anyhow::Result<_>>()
+                .context("Failed to reconstruct UV vertices")?;
+            if let Some(materials) = &materials {
+                polygon_materials.push(materials.get(topology, polygon.start)?[0] as usize);
+            }
+            cage.polygons
+                .push(polygon.map(|pvi| topology.control_point(pvi)).collect());
+            cage.uvs.push(uvs);
+            cage.origins.push(i);
+        }
+        for _ in 0..self.settings.subdivision_level {
+            cage = cage.subdivide();
+        }
+
+        let normals = cage.normals();
+        let flip_v = self.settings.flip_uv_v.flips();
+        let (mut positions, mut vertex_normals, mut uvs) = (Vec::new(), Vec::new(), Vec::new());
+        let mut all_indices = vec![Vec::new(); num_materials.max(1)];
+        for (i, (polygon, corner_uvs)) in cage.polygons.iter().zip(&cage.uvs).enumerate() {
+            let start = u32::try_from(positions.len())
+                .context("Subdivided mesh has more vertices than 32-bit indices can address")?;
+            for (point, uv) in polygon.iter().zip(corner_uvs) {
+                positions.push(cage.positions[*point as usize].to_array());
+                vertex_normals.push(normals[*point as usize].to_array());
+                uvs.push(match flip_v {
+                    true => [uv.x, 1.0 - uv.y],
+                    false => uv.to_array(),
+                });
+            }
+            let material = polygon_materials.get(cage.origins[i]).copied().unwrap_or(0);
+            let indices = all_indices.get_mut(material).ok_or_else(|| {
+                anyhow!("FbxMesh-local material index out of range: num_materials={num_materials:?}, got={material:?}")
+            })?;
+            // Subdivided polygons are quads.
+            indices.extend([0, 1, 2, 0, 2, 3].map(|i| start + i));
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(positions),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::Float32x2(uvs));
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            VertexAttributeValues::Float32x3(vertex_normals),
+        );
+        mesh.set_indices(Some(Indices::U32(all_indices.concat())));
+        mesh.generate_tangents()
+            .context("Failed to generate tangents")?;
+        let simplified = simplification
+            .and_then(|simplification| simplify::simplify(&mesh, &all_indices, &simplification));
+        Ok(match simplified {
+            Some(simplified) => (simplified.mesh, simplified.all_indices),
+            None => (mesh, all_indices),
+        })
+    }
+
+    /// How to simplify the mesh of a node with `extras`, overriding
+    /// [`FbxLoaderSettings::simplification`] with the node's
+    /// `SimplifyRatio` and `SimplifyError` properties.
+    fn simplification(&self, extras: &FbxExtras) -> Option<FbxSimplification> {
+        let property = |name| match extras.properties.get(name)? {
+            FbxPropertyValue::Float(value) => Some(*value),
+            FbxPropertyValue::Int(value) => Some(*value as f32),
+            _ => None,
+        };
+        let (ratio, max_error) = (property("SimplifyRatio"), property("SimplifyError"));
+        let simplification = match (ratio, max_error, self.settings.simplification) {
+            (None, None, simplification) => simplification,
+            (ratio, max_error, simplification) => {
+                let default = simplification.unwrap_or_default();
+                Some(FbxSimplification {
+                    ratio: ratio.unwrap_or(default.ratio),
+                    max_error: max_error.unwrap_or(default.max_error),
+                })
+            }
+        };
+        simplification.filter(|simplification| simplification.ratio < 1.0)
+    }
+
+    // Note: FBX meshes can have multiple different materials, it's not just a mesh.
+    // the FBX equivalent of a bevy Mesh is a geometry mesh
+    async fn load_mesh(
+        &mut self,
+        mesh_obj: object::model::MeshHandle<'_>,
+    ) -> anyhow::Result<FbxMesh> {
+        let label = if let Some(name) = mesh_obj.name() {
+            format!("FbxMesh@{name}")
+        } else {
+            format!("FbxMesh{}", mesh_obj.object_id().raw())
+        };
+        stage_log!(
+            self.settings,
+            FbxLogStage::Meshes,
+            "Loading FBX mesh: {label}"
+        );
+
+        let bevy_obj = mesh_obj.geometry().context("Failed to get geometry")?;
+        let simplification = self.simplification(&FbxExtras::from_object(&mesh_obj));
+        let shadow_flag = |names: &[&str]| {
+            let node = mesh_obj.properties_by_native_typename("FbxNode");
+            let geometry = bevy_obj.properties_by_native_typename("FbxMesh");
+            let mut flags = names
+                .iter()
+                .flat_map(|name| [node.get::<bool>(name), geometry.get::<bool>(name)]);
+            flags.find_map(|flag| flag).unwrap_or(true)
+        };
+        let casts_shadows = shadow_flag(&["Casts Shadows", "CastsShadows"]);
+        let receives_shadows =
+            shadow_flag(&["Receive Shadows", "ReceivesShadows", "ReceiveShadows"]);
+
+        // async and iterators into for are necessary because of `async` `read_asset_bytes`
+        // call in `load_video_clip`  that virally infect everything.
+        // This can't even be ran in parallel, because we store already-encountered materials.
+        let mut materials = Vec::new();
+        let mut material_names = Vec::new();
+        // The materials of the node override those of its geometry, so that
+        // nodes instancing a geometry can use their own materials.
+        let mut material_objs: Vec<_> = mesh_obj.materials().collect();
+        if material_objs.is_empty() {
+            material_objs = geometry_materials(&bevy_obj).collect();
+            if !material_objs.is_empty() {
+                stage_log!(
+                    self.settings,
+                    FbxLogStage::Materials,
+                    "Mesh {label} uses the materials of its geometry"
+                );
+            }
+        }
+        for mat in material_objs {
+            material_names.push(mat.name().filter(|name| !name.is_empty()).map(String::from));
+            let mat = self.load_material(mat).await;
+            let mat = mat.context("Failed to load materials for mesh")?;
+            materials.push(mat);
         }
         let material_count = materials.len();
         if material_count == 0 {
             materials.push(Handle::default());
         }
 
-        let bevy_mesh_handles = self
-            .load_bevy_mesh(bevy_obj, material_count)
-            .context("Failed to load geometry mesh")?;
+        let vertex_colors: Vec<_> = materials
+            .iter()
+            .map(|material| {
+                let name = self.material_names.get(material);
+                let vertex_colors = &self.settings.material_vertex_colors;
+                let mode = name.and_then(|name| vertex_colors.get(name));
+                mode.copied().unwrap_or(self.settings.vertex_colors)
+            })
+            .collect();
+        let vertex_cache_reference = FbxVertexCacheReference::from_geometry(bevy_obj);
+        // The vertex cache is played on the whole mesh, its vertices can't be
+        // split or reordered.
+        let (max_vertices, optimize) = match vertex_cache_reference {
+            Some(_) => (None, false),
+            None => (
+                self.settings.max_mesh_vertices.map(|max| max as usize),
+                self.settings.optimize_meshes,
+            ),
+        };
+        let vertex_formats = mesh_obj.name().and_then(|name| {
+            let formats = &self.settings.mesh_vertex_formats;
+            formats.get(name).copied()
+        });
+        let variant = GeometryVariant {
+            material_count,
+            simplification,
+            vertex_colors,
+            vertex_formats: vertex_formats.unwrap_or(self.settings.vertex_formats),
+            optimize,
+        };
+        let variants = self.geometries.entry(bevy_obj.object_id()).or_default();
+        let geometry = match variants.iter().find(|(v, _)| *v == variant) {
+            Some((_, geometry)) => geometry.clone(),
+            None => {
+                let index = variants.len();
+                let geometry = self
+                    .load_bevy_mesh(bevy_obj, &variant, index, max_vertices)
+                    .context("Failed to load geometry mesh")?;
+                let variants = self.geometries.entry(bevy_obj.object_id()).or_default();
+                variants.push((variant, geometry.clone()));
+                geometry
+            }
+        };
+        let GeometryMeshes {
+            handles: bevy_mesh_handles,
+            materials: mesh_materials,
+            aabbs: bevy_mesh_aabbs,
+            aabb,
+            vertex_control_points,
+            subdivision,
+            skin,
+            wireframe,
+            offset,
+        } = geometry;
+        let primitives = (bevy_mesh_handles.iter().zip(&bevy_mesh_aabbs))
+            .zip(&mesh_materials)
+            .map(|((mesh, aabb), i)| FbxPrimitive {
+                mesh: mesh.clone(),
+                material: materials[*i].clone(),
+                material_index: (*i < material_count).then_some(*i),
+                material_name: material_names.get(*i).cloned().flatten(),
+                aabb: *aabb,
+            })
+            .collect();
+        let materials = mesh_materials
+            .iter()
+            .map(|i| materials[*i].clone())
+            .collect();
+        let vertex_cache = match &vertex_cache_reference {
+            Some(_) if vertex_control_points.is_empty() => {
+                stage_log!(
+                    self.settings,
+                    FbxLogStage::Meshes,
+                    "Vertex cache of {label} is not played on the subdivided mesh"
+                );
+                None
+            }
+            Some(reference) if reference.format == FbxVertexCacheFormat::PointCache2 => {
+                let frame_rate = bevy_obj
+                    .document()
+                    .global_settings()
+                    .map_or(30.0, |g| g.frame_rate());
+                let cache = self
+                    .load_vertex_cache(reference, frame_rate, vertex_control_points)
+                    .await;
+                match cache {
+                    Ok(mut cache) => {
+                        // The cached positions are in the space of the node, like the vertices.
+                        let positions = cache.samples.iter_mut().flatten();
+                        positions.for_each(|position| *position -= offset);
+                        let label = format!("{label}/VertexCache");
+                        Some(self.load_context.set_labeled_asset(&label, cache))
+                    }
+                    Err(err) => {
+                        self.progress.warn(
+                            FbxWarningCategory::VertexCache,
+                            Some(&label),
+                            format!("Failed to load vertex cache of {label}: {err:?}"),
+                            Some("Keep the cache file at its path relative to the FBX file"),
+                        );
+                        None
+                    }
+                }
+            }
+            Some(reference) => {
+                stage_log!(
+                    self.settings,
+                    FbxLogStage::Meshes,
+                    "Unsupported vertex cache format for {label}: {:?}",
+                    reference.format
+                );
+                None
+            }
+            None => None,
+        };
 
         let mesh = FbxMesh {
-            name: mesh_obj.name().map(Into::into),
+            name: (mesh_obj.name()).map(|name| names::sanitize(name, &self.settings)),
             bevy_mesh_handles,
+            bevy_mesh_aabbs,
+            aabb,
+            offset,
             materials,
+            primitives,
+            vertex_cache_reference,
+            vertex_cache,
+            subdivision,
+            skin,
+            wireframe,
+            casts_shadows,
+            receives_shadows,
         };
 
-        let mesh_handle = self
-            .load_context
-            .set_labeled_asset(&label, LoadedAsset::new(mesh.clone()));
+        let mesh_handle = self.load_context.set_labeled_asset(&label, mesh.clone());
+        self.progress
+            .asset_loaded(FbxAssetKind::Mesh, &label, mesh_handle.clone_untyped());
 
         self.scene.meshes.insert(mesh_obj.object_id(), mesh_handle);
 
         Ok(mesh)
     }
 
+    async fn load_animations(
+        &mut self,
+        doc: &Document,
+        contents: &mut SceneContents,
+        roots: &[ObjectId],
+        detached: &HashMap<ObjectId, ObjectId>,
+        poses: &[StoredPose],
+        frame_rate: f64,
+    ) -> anyhow::Result<()> {
+        enum Job<'s> {
+            Stack(&'s Stack),
+            Slice(&'s Stack, FbxTakeSlice),
+            BindPose(StoredPose),
+            Pose(&'s StoredPose),
+        }
+        let stacks = Stack::all(doc);
+        let mut jobs = Vec::new();
+        for stack in &stacks {
+            let label = match &stack.name {
+                Some(name) => format!("FbxAnimation@{name}"),
+                None => format!("FbxAnimation{}", stack.id.raw()),
+            };
+            jobs.push((label, Job::Stack(stack)));
+        }
+        for slice in &self.settings.takes {
+            let stack = stacks
+                .iter()
+                .find(|stack| stack.name.as_deref() == Some(&slice.name));
+            match stack {
+                Some(stack) => {
+                    let label = format!("FbxAnimation@{}", slice.clip_name());
+                    jobs.push((label, Job::Slice(stack, slice.clone())));
+                }
+                None => self.progress.warn(
+                    FbxWarningCategory::Animation,
+                    Some(&slice.name),
+                    format!("Can't slice take {}: no such take in file", slice.name),
+                    Some("Check the take names of FbxLoaderSettings::takes"),
+                ),
+            }
+        }
+        if let Some(bind_pose) = StoredPose::merge_bind_poses(poses) {
+            jobs.push(("FbxBindPose".to_string(), Job::BindPose(bind_pose)));
+        }
+        for stored in poses.iter().filter(|pose| !pose.bind) {
+            let label = match &stored.name {
+                Some(name) => format!("FbxPose@{name}"),
+                None => format!("FbxPose{}", stored.id.raw()),
+            };
+            jobs.push((label, Job::Pose(stored)));
+        }
+
+        // Only bake what isn't cached, the baker itself is costly to build.
+        let mut baker = None;
+        let mut baked = Vec::with_capacity(jobs.len());
+        let job_count = jobs.len();
+        for (i, (label, job)) in jobs.into_iter().enumerate() {
+            self.progress
+                .report(FbxLoadStage::Animations, i, job_count, Some(&label));
+            self.progress.check_cancelled()?;
+            self.give_way().await;
+            if let Some(cached) = self.cache.as_ref().and_then(|cache| cache.clip(&label)) {
+                baked.push((label, job, cached));
+                continue;
+            }
+            let baker = baker.get_or_insert_with(|| {
+                let constraints = if self.settings.bake_constraints {
+                    Constraint::all(doc, &self.progress)
+                } else {
+                    Vec::new()
+                };
+                Baker::new(
+                    &contents.hierarchy,
+                    &contents.infos,
+                    roots,
+                    detached,
+                    frame_rate,
+                    constraints,
+                    &self.settings,
+                )
+            });
+            let (clip, properties) = match &job {
+                Job::Stack(stack) => baker.bake(stack, &self.progress),
+                Job::Slice(stack, slice) => baker.bake_slice(stack, slice, &self.progress),
+                Job::BindPose(stored) | &Job::Pose(stored) => {
+                    (baker.bake_pose(stored), HashMap::default())
+                }
+            };
+            let clip = BakedClip {
+                clip,
+                properties: properties
+                    .into_iter()
+                    .map(|(node, curves)| (node.raw(), curves))
+                    .collect(),
+            };
+            if let Some(cache) = &mut self.cache {
+                cache.insert_clip(&label, &clip);
+            }
+            baked.push((label, job, clip));
+        }
+        drop(baker);
+
+        // Keep a copy of the clips and poses used by the mirrored and additive clips.
+        let mirrored_clips = self.settings.mirrored_clips.clone();
+        let additive_clips = self.settings.additive_clips.clone();
+        let is_source = |name: Option<&String>| {
+            let mirrored = mirrored_clips.iter().any(|m| Some(&m.clip) == name);
+            mirrored || additive_clips.iter().any(|a| Some(&a.clip) == name)
+        };
+        let is_reference = |reference: &FbxAdditiveReference| {
+            additive_clips.iter().any(|a| &a.reference == reference)
+        };
+        let mut sources = HashMap::new();
+        let mut reference_poses = HashMap::new();
+        let mut bind_pose = None;
+
+        let mut skinned = SkinnedBounds::new(doc, &contents.hierarchy, roots, &contents.models);
+        for node in &skinned.unbound {
+            let name = contents.hierarchy[node].name.as_deref();
+            self.progress.warn(
+                FbxWarningCategory::Mesh,
+                name,
+                format!(
+                    "Mesh {name:?} instances a skinned geometry, but has no copy of its joints \
+                    under its ancestors, loaded it unskinned"
+                ),
+                Some("Give each instance a copy of the rig, with the same joint names"),
+            );
+        }
+        let object_ids: HashMap<i64, ObjectId> = contents
+            .hierarchy
+            .keys()
+            .map(|id| (id.raw(), *id))
+            .collect();
+        for (label, job, BakedClip { clip, properties }) in baked {
+            let seconds = |(start, stop)| {
+                (
+                    ktime_to_seconds(start) as f32,
+                    ktime_to_seconds(stop) as f32,
+                )
+            };
+            let metadata = match &job {
+                Job::Stack(stack) => Some(FbxTakeMetadata {
+                    original_span: stack.time_span().map(seconds),
+                    ..stack.metadata.clone()
+                }),
+                Job::Slice(stack, slice) => {
+                    let span = (
+                        (slice.start as f64 / frame_rate) as f32,
+                        (slice.end as f64 / frame_rate) as f32,
+                    );
+                    Some(FbxTakeMetadata {
+                        local_span: Some(span),
+                        original_span: Some(span),
+                        ..stack.metadata.clone()
+                    })
+                }
+                Job::BindPose(_) | Job::Pose(_) => None,
+            };
+            match job {
+                Job::Stack(_) | Job::Slice(..) => {
+                    if is_source(clip.name.as_ref()) {
+                        let name = clip.name.clone().unwrap_or_default();
+                        sources.insert(name, (clip.clone(), metadata.clone()));
+                    }
+                    skinned.add_clip(&contents.hierarchy, &clip);
+                    let clip = self.add_animation(label, clip);
+                    if let Some(metadata) = metadata {
+                        self.scene.take_metadata.insert(clip.clone(), metadata);
+                    }
+                    for (node, curves) in properties {
+                        let object = object_ids
+                            .get(&node)
+                            .and_then(|id| contents.hierarchy.get_mut(id));
+                        if let Some(object) = object {
+                            object.extras.animations.insert(clip.id(), curves);
+                        }
+                    }
+                }
+                Job::BindPose(_) => {
+                    if is_reference(&FbxAdditiveReference::BindPose) {
+                        bind_pose = Some(clip.clone());
+                    }
+                    let handle = self.load_context.set_labeled_asset(&label, clip);
+                    self.scene.bind_pose = Some(handle);
+                }
+                Job::Pose(stored) => {
+                    let name = stored.name.clone().unwrap_or(label.clone());
+                    if is_reference(&FbxAdditiveReference::Pose(name.clone())) {
+                        reference_poses.insert(name.clone(), clip.clone());
+                    }
+                    let handle = self.load_context.set_labeled_asset(&label, clip);
+                    self.scene.poses.insert(name, handle);
+                }
+            }
+        }
+        contents.skin_joints = skinned.joints();
+        contents.skinned_aabbs = skinned.aabbs(&contents.models);
+
+        for mirror in &mirrored_clips {
+            let Some((source, metadata)) = sources.get(&mirror.clip) else {
+                self.progress.warn(
+                    FbxWarningCategory::Animation,
+                    Some(&mirror.clip),
+                    format!("Can't mirror clip {}: no such clip in file", mirror.clip),
+                    Some("Check the clip names of FbxLoaderSettings::mirrored_clips"),
+                );
+                continue;
+            };
+            let clip = mirrored_clip(source, mirror);
+            let metadata = metadata.clone();
+            let name = mirror.clip_name();
+            let handle = self.add_animation(format!("FbxAnimation@{name}"), clip.clone());
+            if let Some(metadata) = &metadata {
+                self.scene.take_metadata.insert(handle, metadata.clone());
+            }
+            sources.insert(name, (clip, metadata));
+        }
+        for additive in &additive_clips {
+            let Some((source, metadata)) = sources.get(&additive.clip) else {
+                self.progress.warn(
+                    FbxWarningCategory::Animation,
+                    Some(&additive.clip),
+                    format!(
+                        "Can't make clip {} additive: no such clip in file",
+                        additive.clip
+                    ),
+                    Some("Check the clip names of FbxLoaderSettings::additive_clips"),
+                );
+                continue;
+            };
+            let (reference, time) = match &additive.reference {
+                FbxAdditiveReference::FirstFrame => (None, 0.0),
+                FbxAdditiveReference::Frame(frame) => (None, (*frame as f64 / frame_rate) as f32),
+                FbxAdditiveReference::Pose(name) => match reference_poses.get(name) {
+                    Some(pose) => (Some(pose), 0.0),
+                    None => {
+                        self.progress.warn(
+                            FbxWarningCategory::Animation,
+                            Some(&additive.clip),
+                            format!("Can't make clip {} additive: no pose {name}", additive.clip),
+                            Some("Check the pose names of FbxLoaderSettings::additive_clips"),
+                        );
+                        continue;
+                    }
+                },
+                FbxAdditiveReference::BindPose => match &bind_pose {
+                    Some(pose) => (Some(pose), 0.0),
+                    None => {
+                        self.progress.warn(
+                            FbxWarningCategory::Animation,
+                            Some(&additive.clip),
+                            format!("Can't make clip {} additive: no bind pose", additive.clip),
+                            Some("Export the file with its bind pose, or use another reference"),
+                        );
+                        continue;
+                    }
+                },
+            };
+            let name = additive.clip_name();
+            let clip = additive_clip(source, reference, time, name.clone());
+            let clip = self.add_animation(format!("FbxAnimation@{name}"), clip);
+            if let Some(metadata) = metadata {
+                self.scene.take_metadata.insert(clip, metadata.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Warn about `scene` going over [`FbxLoaderSettings::budget`],
+    /// or fail with [`FbxBudget::strict`].
+    ///
+    /// [`FbxBudget::strict`]: crate::FbxBudget::strict
+    fn check_budget(&self, scene: &Scene) -> anyhow::Result<()> {
+        let budget = &self.settings.budget;
+        let mut exceeded = Vec::new();
+        let entities = scene.world.entities().len() as usize;
+        if let Some(max) = budget.max_entities.filter(|max| entities > *max) {
+            exceeded.push(format!("{entities} entities, over the budget of {max}"));
+        }
+        let triangles: usize = (scene.world.iter_entities())
+            .filter_map(|entity| self.triangle_counts.get(entity.get::<Handle<Mesh>>()?))
+            .sum();
+        if let Some(max) = budget.max_triangles.filter(|max| triangles > *max) {
+            exceeded.push(format!("{triangles} triangles, over the budget of {max}"));
+        }
+        let megabytes = megabytes(self.scene.memory_usage.texture_bytes());
+        if let Some(max) = budget.max_texture_megabytes.filter(|max| megabytes > *max) {
+            exceeded.push(format!(
+                "{megabytes:.1} MB of textures, over the budget of {max} MB"
+            ));
+        }
+        let path = self.load_context.path();
+        for exceeded in exceeded {
+            if budget.strict {
+                bail!("{path:?} has {exceeded}");
+            }
+            self.progress.warn(
+                FbxWarningCategory::Budget,
+                None,
+                format!("{path:?} has {exceeded}"),
+                Some("Reduce the scene, or raise FbxLoaderSettings::budget"),
+            );
+        }
+        Ok(())
+    }
+
+    /// The name and marker of the root entity of the spawned scene,
+    /// according to [`FbxLoaderSettings::root_name`].
+    fn root_marker(&self) -> (Name, FbxSceneRoot) {
+        let path = self.load_context.path();
+        let name = match (&self.settings.root_name, path.file_stem()) {
+            (FbxRootName::Named(name), _) => name.clone(),
+            (FbxRootName::FileName, Some(stem)) => stem.to_string_lossy().into_owned(),
+            _ => "Fbx scene root".to_owned(),
+        };
+        let path = path.to_string_lossy().into_owned();
+        (Name::new(name), FbxSceneRoot { path })
+    }
+
+    /// The external documents referenced by `doc`, resolved in the asset
+    /// folder if [`FbxLoaderSettings::resolve_references`] is set.
+    fn external_references(&self, doc: &Document) -> Vec<FbxExternalReference> {
+        let mut references = FbxExternalReference::all(doc);
+        if !self.settings.resolve_references {
+            return references;
+        }
+        let file = self.load_context.path();
+        for reference in &mut references {
+            let candidates = reference.candidates(file, &self.settings.reference_search_paths);
+            reference.resolved = (candidates.into_iter())
+                .find(|candidate| self.load_context.asset_exists(candidate));
+            if reference.resolved.is_none() {
+                self.progress.warn(
+                    FbxWarningCategory::Reference,
+                    Some(&reference.path),
+                    format!(
+                        "{file:?}: referenced document {:?} not found",
+                        reference.path
+                    ),
+                    Some("Add its folder to FbxLoaderSettings::reference_search_paths"),
+                );
+            }
+        }
+        references
+    }
+
+    /// The nodes matching [`FbxLoaderSettings::socket_pattern`].
+    fn sockets(&self, doc: &Document) -> HashMap<ObjectId, FbxSocket> {
+        let Some(pattern) = &self.settings.socket_pattern else {
+            return HashMap::default();
+        };
+        doc.objects()
+            .filter(|obj| obj.class() == "Model")
+            .filter_map(|obj| {
+                let name = names::sanitize(obj.name()?, &self.settings);
+                let is_socket = names::glob_match(pattern, &name);
+                is_socket.then(|| (obj.object_id(), FbxSocket::new(pattern, &name)))
+            })
+            .collect()
+    }
+
+    /// The clip to play on the spawned scene, according to [`FbxLoaderSettings::autoplay`].
+    fn autoplay_clip(&self) -> Option<Handle<FbxAnimationClip>> {
+        let clip = match &self.settings.autoplay {
+            FbxAutoplay::Off => return None,
+            FbxAutoplay::First => self.scene.animations.first(),
+            FbxAutoplay::Named(name) => {
+                let clip = self.scene.named_animations.get(name);
+                if clip.is_none() {
+                    self.progress.warn(
+                        FbxWarningCategory::Animation,
+                        Some(name),
+                        format!("Can't autoplay animation {name}: no such clip in file"),
+                        Some("Check the clip name of FbxLoaderSettings::autoplay"),
+                    );
+                }
+                clip
+            }
+        };
+        clip.cloned()
+    }
+
+    fn add_animation(&mut self, label: String, clip: FbxAnimationClip) -> Handle<FbxAnimationClip> {
+        stage_log!(
+            self.settings,
+            FbxLogStage::Animations,
+            "Loaded animation {label}: {} frames at {} FPS",
+            clip.frame_count(),
+            clip.frame_rate
+        );
+        let name = clip.name.clone();
+        let bytes = memory_usage::clip_bytes(&clip);
+        let memory_usage = &mut self.scene.memory_usage;
+        memory_usage.animations.insert(label.clone(), bytes);
+        let handle = self.load_context.set_labeled_asset(&label, clip);
+        self.progress
+            .asset_loaded(FbxAssetKind::Clip, &label, handle.clone_untyped());
+        if let Some(name) = name {
+            self.scene.named_animations.insert(name, handle.clone());
+        }
+        self.scene.animations.push(handle.clone());
+        handle
+    }
+
+    fn load_area_light_surface(
+        &mut self,
+        id: ObjectId,
+        light: &light::FbxAreaLight,
+    ) -> (Handle<Mesh>, Handle<StandardMaterial>, Transform) {
+        let label = format!("FbxAreaLight{}", id.raw());
+        let (mesh, transform) = light.mesh();
+        let mesh_label = format!("{label}/Mesh");
+        let bytes = memory_usage::mesh_bytes(&mesh);
+        let memory_usage = &mut self.scene.memory_usage;
+        memory_usage.meshes.insert(mesh_label.clone(), bytes);
+        let mesh = self.load_context.set_labeled_asset(&mesh_label, mesh);
+        let material = self
+            .load_context
+            .set_labeled_asset(&format!("{label}/Material"), light.material());
+        (mesh, material, transform)
+    }
+
+    async fn load_vertex_cache(
+        &mut self,
+        reference: &FbxVertexCacheReference,
+        frame_rate: f64,
+        vertex_control_points: Vec<u32>,
+    ) -> anyhow::Result<FbxVertexCache> {
+        let parent = self.load_context.path().parent().unwrap_or(Path::new(""));
+        let cache_path = parent.join(reference.relative_path.replace('\\', "/"));
+        let bytes = self.load_context.read_asset_bytes(&cache_path).await?;
+        FbxVertexCache::from_pc2(&bytes, frame_rate, vertex_control_points)
+    }
+
+    /// Decode the image of `video_clip_obj`, returned with its file
+    /// and the extension of its file.
     async fn load_video_clip(
         &mut self,
         video_clip_obj: object::video::ClipHandle<'_>,
-    ) -> anyhow::Result<Image> {
-        debug!("Loading texture image: {:?}", video_clip_obj.name());
+    ) -> anyhow::Result<(Image, Vec<u8>, String)> {
+        stage_log!(
+            self.settings,
+            FbxLogStage::Textures,
+            "Loading texture image: {:?}",
+            video_clip_obj.name()
+        );
 
         let relative_filename = video_clip_obj
             .relative_filename()
             .context("Failed to get relative filename of texture image")?;
-        debug!("Relative filename: {:?}", relative_filename);
+        stage_log!(
+            self.settings,
+            FbxLogStage::Textures,
+            "Relative filename: {:?}",
+            relative_filename
+        );
 
         let file_ext = Path::new(&relative_filename)
             .extension()
@@ -471,31 +2395,39 @@ impl<'b, 'w> Loader<'b, 'w> {
             .to_str()
             .unwrap()
             .to_ascii_lowercase();
-        let image: Vec<u8> = if let Some(content) = video_clip_obj.content() {
+        let bytes: Vec<u8> = if let Some(content) = video_clip_obj.content() {
             // TODO: the clone here is absolutely unnecessary, but there
             // is no way to reconciliate its lifetime with the other branch of
             // this if/else
             content.to_vec()
         } else {
-            let parent = self.load_context.path().parent().unwrap();
+            let parent = self.load_context.path().parent().unwrap_or(Path::new(""));
             let clean_relative_filename = relative_filename.replace('\\', "/");
             let image_path = parent.join(clean_relative_filename);
-            self.load_context.read_asset_bytes(image_path).await?
+            self.load_context.read_asset_bytes(&image_path).await?
         };
+        if let Some(image) = self.cache.as_ref().and_then(|cache| cache.image(&bytes)) {
+            return Ok((image, bytes, file_ext));
+        }
         let is_srgb = false; // TODO
         let image = Image::from_buffer(
-            &image,
+            &bytes,
             ImageType::Extension(&file_ext),
             self.suported_compressed_formats,
             is_srgb,
         );
         let image = image.context("Failed to read image buffer data")?;
-        debug!(
+        if let Some(cache) = &mut self.cache {
+            cache.insert_image(&bytes, &image);
+        }
+        stage_log!(
+            self.settings,
+            FbxLogStage::Textures,
             "Successfully loaded texture image: {:?}",
             video_clip_obj.name()
         );
 
-        Ok(image)
+        Ok((image, bytes, file_ext))
     }
 
     async fn run_loader(
@@ -536,33 +2468,15 @@ impl<'b, 'w> Loader<'b, 'w> {
             }));
         // 3. For each of those, create an image handle (with potential caching based on the texture name)
         for (label, texture) in texture_handles_iter {
-            let handle_label = match texture {
-                TextureSource::Handle(texture_handle) => match texture_handle.name() {
-                    Some(name) if !name.is_empty() => format!("FbxTexture@{name}"),
-                    _ => format!("FbxTexture{}", texture_handle.object_id().raw()),
-                },
-                TextureSource::Processed(_) => match material_obj.name() {
-                    Some(name) if !name.is_empty() => format!("FbxTextureMat@{name}/{label}"),
-                    _ => format!("FbxTextureMat{}/{label}", material_obj.object_id().raw()),
-                },
-            };
-
-            // Either copy the already-created handle or create a new asset
-            // for each image or texture to load.
-            let handle = if let Some(handle) = self.scene.textures.get(&handle_label) {
-                debug!("Already encountered texture: {label}, skipping");
-
-                handle.clone()
-            } else {
-                let texture = match texture {
-                    TextureSource::Processed(texture) => texture,
-                    TextureSource::Handle(texture) => self.get_texture(texture).await?,
-                };
-                let handle = self
-                    .load_context
-                    .set_labeled_asset(&handle_label, LoadedAsset::new(texture));
-                self.scene.textures.insert(handle_label, handle.clone());
-                handle
+            let handle = match texture {
+                TextureSource::Handle(texture) => self.load_labeled_texture(texture).await?,
+                TextureSource::Processed(texture) => {
+                    let handle_label = self.material_texture_label(material_obj, label);
+                    match self.scene.textures.get(&handle_label) {
+                        Some(handle) => handle.clone(),
+                        None => self.add_texture(handle_label, texture),
+                    }
+                }
             };
             texture_handles.insert(label, handle);
         }
@@ -570,10 +2484,216 @@ impl<'b, 'w> Loader<'b, 'w> {
         Ok(with_textures(material_obj, texture_handles))
     }
 
+    /// Composite the opacity texture of `material_obj` into the alpha
+    /// channel of its base color, see [`FbxLoaderSettings::opacity_textures`].
+    async fn load_opacity_map(
+        &mut self,
+        material_obj: object::material::MaterialHandle<'_>,
+        material: &mut StandardMaterial,
+    ) -> anyhow::Result<()> {
+        use crate::utils::fbx_extend::*;
+        let opacity = material_obj
+            .load_texture("TransparentColor")
+            .or_else(|| material_obj.load_texture("TransparencyFactor"));
+        let Some(opacity) = opacity else {
+            return Ok(());
+        };
+        let diffuse = material_obj.load_texture("DiffuseColor");
+        let file = |texture: TextureHandle| {
+            let clip = texture.video_clip()?;
+            clip.relative_filename().ok().map(str::to_owned)
+        };
+        let same_file = diffuse.is_some_and(|diffuse| {
+            diffuse.object_id() == opacity.object_id()
+                || file(diffuse).is_some_and(|name| file(opacity) == Some(name))
+        });
+        let opacity_mode = self.settings.opacity_textures;
+        // The base color texture already has the opacity in its alpha channel.
+        if same_file {
+            let image = self.get_texture(opacity).await?;
+            material.alpha_mode = opacity::alpha_mode(opacity_mode, &image);
+            return Ok(());
+        }
+        let diffuse = match diffuse {
+            Some(diffuse) => Some(self.get_texture(diffuse).await?),
+            None => None,
+        };
+        let opacity = self.get_texture(opacity).await?;
+        let image =
+            opacity::composite(diffuse.as_ref(), &opacity).context("Unsupported texture format")?;
+        material.alpha_mode = opacity::alpha_mode(opacity_mode, &image);
+        let label = self.material_texture_label(material_obj, "DiffuseOpacity");
+        material.base_color_texture = Some(self.add_texture(label, image));
+        Ok(())
+    }
+
+    /// Handle the reflection map of `material_obj`,
+    /// see [`FbxLoaderSettings::reflection_maps`].
+    async fn load_reflection_map(
+        &mut self,
+        material_obj: object::material::MaterialHandle<'_>,
+        material: &mut StandardMaterial,
+        extras: &mut FbxMaterialExtras,
+    ) {
+        use crate::utils::fbx_extend::*;
+        let texture = material_obj.load_texture("ReflectionColor");
+        let result = match (self.settings.reflection_maps, texture) {
+            (FbxReflectionMap::Ignore, _) => return,
+            (FbxReflectionMap::Environment, None) => return,
+            (FbxReflectionMap::Environment, Some(texture)) => {
+                let handle = self.load_labeled_texture(texture).await;
+                handle.map(|handle| extras.reflection_map = Some(handle))
+            }
+            (FbxReflectionMap::Specular, Some(texture)) => {
+                let image = self.get_texture(texture).await;
+                image.map(|image| {
+                    let strength = average_intensity(&image) * extras.reflection_factor;
+                    material.reflectance += (1.0 - material.reflectance) * strength;
+                })
+            }
+            (FbxReflectionMap::Specular, None) => {
+                let color = material_obj.properties().get::<RGB<f64>>("ReflectionColor");
+                if let Some(RGB { r, g, b }) = color {
+                    let intensity = ((r + g + b) / 3.0).clamp(0.0, 1.0) as f32;
+                    let strength = intensity * extras.reflection_factor;
+                    material.reflectance += (1.0 - material.reflectance) * strength;
+                }
+                Ok(())
+            }
+        };
+        if let Err(err) = result {
+            let name = material_obj.name();
+            self.progress.warn(
+                FbxWarningCategory::Material,
+                name,
+                format!("Failed to load reflection map of material {name:?}: {err:?}"),
+                Some("Check the texture file exists and its format is enabled in bevy"),
+            );
+        }
+    }
+
+    /// Load the displacement and bump maps of `material_obj` in `extras`,
+    /// bevy's `StandardMaterial` has no slot for them.
+    async fn load_displacement_maps(
+        &mut self,
+        material_obj: object::material::MaterialHandle<'_>,
+        extras: &mut FbxMaterialExtras,
+    ) {
+        use crate::utils::fbx_extend::*;
+        for property in ["DisplacementColor", "VectorDisplacementColor", "Bump"] {
+            let Some(texture) = material_obj.load_texture(property) else {
+                continue;
+            };
+            let handle = match self.load_labeled_texture(texture).await {
+                Ok(handle) => handle,
+                Err(err) => {
+                    let name = material_obj.name();
+                    self.progress.warn(
+                        FbxWarningCategory::Material,
+                        name,
+                        format!("Failed to load {property} map of material {name:?}: {err:?}"),
+                        Some("Check the texture file exists and its format is enabled in bevy"),
+                    );
+                    continue;
+                }
+            };
+            match property {
+                "Bump" => extras.bump_map = Some(handle),
+                "VectorDisplacementColor" => extras.vector_displacement_map = Some(handle),
+                _ => extras.displacement_map = Some(handle),
+            }
+        }
+    }
+
+    /// Load `texture_obj` as a labeled sub-asset, reusing it if it was already loaded.
+    async fn load_labeled_texture(
+        &mut self,
+        texture_obj: object::texture::TextureHandle<'_>,
+    ) -> anyhow::Result<Handle<Image>> {
+        let label = match texture_obj.name() {
+            Some(name) if !name.is_empty() => format!("FbxTexture@{name}"),
+            _ => format!("FbxTexture{}", texture_obj.object_id().raw()),
+        };
+        if let Some(handle) = self.scene.textures.get(&label) {
+            stage_log!(
+                self.settings,
+                FbxLogStage::Textures,
+                "Already encountered texture: {label}, skipping"
+            );
+            return Ok(handle.clone());
+        }
+        let (texture, file, extension) = self.get_texture_file(texture_obj).await?;
+        let Some(streaming) = self.settings.texture_streaming else {
+            return Ok(self.add_texture(label, texture));
+        };
+        let budget = (streaming.budget_megabytes.max(0.0) * 1024.0 * 1024.0) as usize;
+        let used = self.scene.memory_usage.texture_bytes() + texture.data.len();
+        let proxy = (used > budget)
+            .then(|| streaming::proxy(&texture, streaming.proxy_size))
+            .flatten();
+        let Some(proxy) = proxy else {
+            return Ok(self.add_texture(label, texture));
+        };
+        let handle = self.add_texture(label.clone(), proxy);
+        let full = FbxStreamedTexture {
+            proxy: handle.clone(),
+            size: texture.texture_descriptor.size,
+            stream_after_load: streaming.stream_after_load,
+            file: file.into(),
+            extension,
+            sampler: texture.sampler_descriptor,
+            supported: self.suported_compressed_formats,
+        };
+        let full = (self.load_context).set_labeled_asset(&format!("{label}/Full"), full);
+        self.scene.streamed_textures.insert(label, full);
+        Ok(handle)
+    }
+
+    /// Let the other tasks of the thread pool run, for background loads,
+    /// see [`FbxTaskSettings::priority`].
+    async fn give_way(&self) {
+        if self.settings.tasks.priority == FbxTaskPriority::Background {
+            futures_lite::future::yield_now().await;
+        }
+    }
+
+    /// Add the texture `image` as the labeled sub-asset `label`.
+    fn add_texture(&mut self, label: String, image: Image) -> Handle<Image> {
+        let bytes = image.data.len();
+        let size = image.texture_descriptor.size;
+        stage_log!(
+            self.settings,
+            FbxLogStage::Textures,
+            "Texture {label}: {}x{} {:?}, {:.1} MB",
+            size.width,
+            size.height,
+            image.texture_descriptor.format,
+            megabytes(bytes)
+        );
+        self.scene
+            .memory_usage
+            .textures
+            .insert(label.clone(), bytes);
+        let handle = self.load_context.set_labeled_asset(&label, image);
+        self.progress
+            .asset_loaded(FbxAssetKind::Texture, &label, handle.clone_untyped());
+        self.scene.textures.insert(label, handle.clone());
+        handle
+    }
+
     async fn get_texture(
         &mut self,
         texture_obj: object::texture::TextureHandle<'_>,
     ) -> anyhow::Result<Image> {
+        Ok(self.get_texture_file(texture_obj).await?.0)
+    }
+
+    /// Load `texture_obj`, returned with the file of its image and the
+    /// extension of its file.
+    async fn get_texture_file(
+        &mut self,
+        texture_obj: object::texture::TextureHandle<'_>,
+    ) -> anyhow::Result<(Image, Vec<u8>, String)> {
         let properties = texture_obj.properties();
         let address_mode_u = {
             let val = properties
@@ -597,87 +2717,457 @@ impl<'b, 'w> Loader<'b, 'w> {
             .video_clip()
             .context("No image data for texture object")?;
 
-        let image: Result<Image, anyhow::Error> = self.load_video_clip(video_clip_obj).await;
-        let mut image = image.context("Failed to load texture image")?;
+        let image = self.load_video_clip(video_clip_obj).await;
+        let (mut image, file, extension) = image.context("Failed to load texture image")?;
 
         image.sampler_descriptor = ImageSampler::Descriptor(SamplerDescriptor {
             address_mode_u,
             address_mode_v,
             ..Default::default()
         });
-        Ok(image)
+        Ok((image, file, extension))
+    }
+
+    /// The label of a texture generated for `material_obj`, such as
+    /// `FbxTextureMat@M_Rock/DiffuseOpacity`, unique like the material label.
+    fn material_texture_label(
+        &self,
+        material_obj: object::material::MaterialHandle,
+        texture: &str,
+    ) -> String {
+        let id = material_obj.object_id();
+        match self.material_labels.get(&id) {
+            Some(label) => {
+                let suffix = label.trim_start_matches("FbxMaterial");
+                format!("FbxTextureMat{suffix}/{texture}")
+            }
+            None => format!("FbxTextureMat{}/{texture}", id.raw()),
+        }
     }
 
     async fn load_material(
         &mut self,
         material_obj: object::material::MaterialHandle<'_>,
     ) -> anyhow::Result<Handle<StandardMaterial>> {
-        let label = match material_obj.name() {
-            Some(name) if !name.is_empty() => format!("FbxMaterial@{name}"),
-            _ => format!("FbxMaterial{}", material_obj.object_id().raw()),
-        };
-        if let Some(handle) = self.scene.materials.get(&label) {
-            debug!("Already encountered material: {label}, skipping");
+        let id = material_obj.object_id();
+        if let Some(handle) = self.material_handles.get(&id) {
+            stage_log!(
+                self.settings,
+                FbxLogStage::Materials,
+                "Already encountered material: {:?}, skipping",
+                material_obj.name()
+            );
 
             return Ok(handle.clone_weak());
         }
-        debug!("Loading FBX material: {label}");
+        let name = material_obj.name().filter(|name| !name.is_empty());
+        if let Some(path) = name.and_then(|name| self.settings.material_overrides.get(name)) {
+            stage_log!(
+                self.settings,
+                FbxLogStage::Materials,
+                "Replacing FBX material {name:?} by {path}"
+            );
+            let path = AssetPath::from(path.as_str()).to_owned();
+            let handle = self.load_context.get_handle(path.clone());
+            self.material_dependencies.push(path);
+            self.material_handles.insert(id, handle.clone());
+            let name = names::sanitize(name.unwrap_or_default(), &self.settings);
+            self.material_names.insert(handle.clone_weak(), name);
+            return Ok(handle);
+        }
+        let label = match self.material_labels.get(&id) {
+            Some(label) => label.clone(),
+            None => format!("FbxMaterial{}", id.raw()),
+        };
+        stage_log!(
+            self.settings,
+            FbxLogStage::Materials,
+            "Loading FBX material: {label}"
+        );
 
         let mut material = None;
         let loaders = self.material_loaders.clone();
-        for &loader in &loaders {
+        for (i, &loader) in loaders.iter().enumerate() {
             if let Some(loader_material) = self.run_loader(material_obj, loader).await? {
+                stage_log!(
+                    self.settings,
+                    FbxLogStage::Materials,
+                    "Material {label} loaded by material loader {i}"
+                );
                 material = Some(loader_material);
                 break;
             }
         }
-        let material = material.context("None of the material loaders could load this material")?;
-        let handle = self
-            .load_context
-            .set_labeled_asset(&label, LoadedAsset::new(material));
-        debug!("Successfully loaded material: {label}");
+        let mut material =
+            material.context("None of the material loaders could load this material")?;
+        if let Err(err) = self.load_opacity_map(material_obj, &mut material).await {
+            self.progress.warn(
+                FbxWarningCategory::Material,
+                Some(&label),
+                format!("Failed to apply the opacity texture of material {label}: {err:?}"),
+                Some("Use 8-bit opacity and base color textures"),
+            );
+        }
+        let detect_unlit = self.settings.detect_unlit_materials;
+        unlit::apply_unlit(material_obj, &mut material, detect_unlit);
+        let mut extras = FbxMaterialExtras::from_material(material_obj);
+        self.load_reflection_map(material_obj, &mut material, &mut extras)
+            .await;
+        self.load_displacement_maps(material_obj, &mut extras).await;
+        let handle = self.load_context.set_labeled_asset(&label, material);
+        stage_log!(
+            self.settings,
+            FbxLogStage::Materials,
+            "Successfully loaded material: {label}"
+        );
+        self.progress
+            .asset_loaded(FbxAssetKind::Material, &label, handle.clone_untyped());
 
-        self.scene.materials.insert(label, handle.clone());
+        self.scene.materials.insert(label.clone(), handle.clone());
+        if !extras.unmapped.is_empty() {
+            stage_log!(
+                self.settings,
+                FbxLogStage::Materials,
+                "Unmapped parameters of material {label}: {:?}",
+                extras.unmapped
+            );
+        }
+        self.scene.material_extras.insert(label.clone(), extras);
+        self.material_handles.insert(id, handle.clone());
+        let name = match material_obj.name() {
+            Some(name) if !name.is_empty() => names::sanitize(name, &self.settings),
+            _ => label,
+        };
+        self.material_names.insert(handle.clone_weak(), name);
         Ok(handle)
     }
 }
 
-fn traverse_hierarchy(node: ModelHandle, hierarchy: &mut HashMap<ObjectId, FbxObject>) {
+/// The average intensity of the color channels of `image`, from `0.0` to `1.0`.
+fn average_intensity(image: &Image) -> f32 {
+    let Some(rgba) = image.convert(TextureFormat::Rgba8UnormSrgb) else {
+        return 0.0;
+    };
+    let (sum, count) = rgba
+        .data
+        .chunks_exact(4)
+        .fold((0_u64, 0_u64), |(sum, count), texel| {
+            let rgb = texel[0] as u64 + texel[1] as u64 + texel[2] as u64;
+            (sum + rgb, count + 3)
+        });
+    if count == 0 {
+        return 0.0;
+    }
+    sum as f32 / count as f32 / 255.0
+}
+
+/// Add the nodes of the hierarchy of `scene` as [`FbxNode`] assets.
+fn load_nodes(load_context: &mut LoadTarget, scene: &mut FbxScene) {
+    fn load(
+        load_context: &mut LoadTarget,
+        scene: &FbxScene,
+        indices: &HashMap<ObjectId, usize>,
+        node: ObjectId,
+        handles: &mut [Option<Handle<FbxNode>>],
+    ) -> Option<Handle<FbxNode>> {
+        let object = scene.hierarchy.get(&node)?;
+        let children = (object.children.iter())
+            .filter_map(|child| load(load_context, scene, indices, *child, handles))
+            .collect();
+        let fbx_node = FbxNode {
+            name: object.name.clone(),
+            transform: object.transform,
+            mesh: scene.meshes.get(&node).cloned(),
+            children,
+            extras: object.extras.clone(),
+        };
+        let index = indices[&node];
+        let handle = load_context.set_labeled_asset(&format!("FbxNode{index}"), fbx_node);
+        handles[index] = Some(handle.clone());
+        Some(handle)
+    }
+    let mut ids: Vec<_> = scene.hierarchy.keys().copied().collect();
+    ids.sort_by_key(|id| id.raw());
+    let indices = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut handles = vec![None; ids.len()];
+    for root in &scene.roots {
+        load(load_context, scene, &indices, *root, &mut handles);
+    }
+    for (id, handle) in ids.iter().zip(&handles) {
+        let name = scene.hierarchy[id].name.clone();
+        if let (Some(name), Some(handle)) = (name, handle) {
+            scene
+                .named_nodes
+                .entry(name)
+                .or_insert_with(|| handle.clone());
+        }
+    }
+    scene.nodes = handles.into_iter().flatten().collect();
+}
+
+/// The asset label of each material of `doc`.
+///
+/// Materials are labeled by sanitized name, see [`names::sanitize`]. If several
+/// materials have the same name, the one with the lowest object id keeps it,
+/// the others get a `.1`, `.2`... suffix. Unnamed materials are labeled by id.
+fn material_labels(doc: &Document, settings: &FbxLoaderSettings) -> HashMap<ObjectId, String> {
+    let mut materials: Vec<_> = doc
+        .objects()
+        .filter(|obj| matches!(obj.get_typed(), TypedObjectHandle::Material(_)))
+        .map(|obj| (obj.object_id(), obj.name()))
+        .collect();
+    materials.sort_by_key(|(id, _)| id.raw());
+    let mut names = UniqueNames::default();
+    materials
+        .into_iter()
+        .map(|(id, name)| {
+            let name = name.map(|name| names::sanitize(name, settings));
+            let label = match name {
+                Some(name) if !name.is_empty() => format!("FbxMaterial@{}", names.insert(name)),
+                _ => format!("FbxMaterial{}", id.raw()),
+            };
+            (id, label)
+        })
+        .collect()
+}
+
+/// A line list of the polygon edges between the control points `vertices`,
+/// see [`FbxLoaderSettings::wireframe_meshes`].
+fn load_wireframe(vertices: &[f64], topology: &MeshTopology) -> anyhow::Result<Mesh> {
+    let positions = layer_element::to_f32_arrays::<3>(vertices);
+    let mut edges = HashSet::new();
+    let mut indices = Vec::new();
+    for pvi in 0..topology.len() {
+        let (a, b) = topology.edge(pvi);
+        if a as usize >= positions.len() || b as usize >= positions.len() {
+            bail!("Control point out of range in polygon vertex {pvi}");
+        }
+        if a != b && edges.insert((a, b)) {
+            indices.extend([a, b]);
+        }
+    }
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    Ok(mesh)
+}
+
+/// Collect the relevant nodes under `node`, `kept` are nodes always kept,
+/// such as the nodes of the stored poses and sockets.
+///
+/// `visited` are the nodes already traversed, connections to them, from
+/// cycles or second parents, are skipped and described in `broken`.
+/// The root `node` is placed relative to `origin`, see [`FbxLoaderSettings::recenter`].
+/// Returns `false` if `node` was already traversed.
+fn traverse_hierarchy(
+    node: ModelHandle,
+    kept: &HashSet<ObjectId>,
+    visited: &mut HashSet<ObjectId>,
+    broken: &mut Vec<String>,
+    contents: &mut SceneContents,
+    origin: DVec3,
+    progress: &Progress,
+) -> bool {
     #[cfg(feature = "profile")]
     let _hierarchy_span = info_span!("traverse_fbx_hierarchy").entered();
 
-    traverse_hierarchy_rec(node, None, hierarchy);
-    debug!("Tree has {} nodes", hierarchy.len());
+    if !visited.insert(node.object_id()) {
+        broken.push(format!(
+            "Root node {:?} also has a parent node",
+            node.name().unwrap_or_default()
+        ));
+        return false;
+    }
+    let mut traversal = Traversal {
+        kept,
+        visited,
+        broken,
+        hierarchy: &mut contents.hierarchy,
+        globals: &mut contents.globals,
+        infos: &mut contents.infos,
+        origin,
+        progress,
+    };
+    traversal.visit(node, None);
     trace!("root: {:?}", node.object_node_id());
+    true
 }
-fn traverse_hierarchy_rec(
-    node: ModelHandle,
-    parent: Option<FbxTransform>,
-    hierarchy: &mut HashMap<ObjectId, FbxObject>,
-) -> bool {
-    let name = node.name().map(|s| s.to_owned());
-    let data = FbxTransform::from_node(node, parent);
+/// Check and parse the FBX file `bytes`, reporting the problems of its
+/// framing and object ids worked around.
+fn parse_document(bytes: &[u8], progress: &Progress) -> anyhow::Result<Document> {
+    let (fbx_bytes, warnings) = binary::check(bytes)?;
+    for warning in warnings {
+        progress.warn(
+            FbxWarningCategory::File,
+            None,
+            format!("{:?}: {warning}", progress.path()),
+            Some("Re-export the file with an up to date exporter"),
+        );
+    }
+    let (doc, warnings) = object_ids::parse_document(&fbx_bytes)?;
+    for warning in warnings {
+        progress.warn(
+            FbxWarningCategory::ObjectId,
+            None,
+            format!("{:?}: {warning}", progress.path()),
+            Some("Re-export the file, or fix the exporter giving several objects the same id"),
+        );
+    }
+    Ok(doc)
+}
+/// Whether `node` is an empty used as a spawn point or socket, that is a
+/// null without children, see [`FbxLocator`].
+fn is_locator(node: ModelHandle) -> bool {
+    let is_null = matches!(
+        node.node_attribute(),
+        Some(TypedNodeAttributeHandle::Null(_))
+    );
+    is_null && node.child_models().next().is_none()
+}
+/// The state of [`traverse_hierarchy`].
+struct Traversal<'a> {
+    kept: &'a HashSet<ObjectId>,
+    visited: &'a mut HashSet<ObjectId>,
+    broken: &'a mut Vec<String>,
+    hierarchy: &'a mut HashMap<ObjectId, FbxObject>,
+    globals: &'a mut HashMap<ObjectId, Mat4>,
+    infos: &'a mut HashMap<ObjectId, FbxNodeTransformInfo>,
+    /// The origin the root is placed relative to.
+    origin: DVec3,
+    progress: &'a Progress,
+}
+impl Traversal<'_> {
+    fn visit(&mut self, node: ModelHandle, parent: Option<FbxTransform>) -> bool {
+        let name = node.name().map(|s| s.to_owned());
+        if let Some(value) = unknown_inherit_type(*node) {
+            self.progress.warn(
+                FbxWarningCategory::Transform,
+                name.as_deref(),
+                format!(
+                    "Node {:?} has an unknown InheritType {value}, inheriting with the default RrSs",
+                    name.as_deref().unwrap_or_default(),
+                ),
+                Some("Re-export the file with an up to date exporter"),
+            );
+        }
+        let origin = match parent {
+            Some(_) => DVec3::ZERO,
+            None => self.origin,
+        };
+        let info = FbxNodeTransformInfo::recentered(*node, origin).unwrap();
+        let data = FbxTransform::from_fbxtrans(info.clone(), parent);
 
-    let mut mesh_leaf = false;
-    node.child_models().for_each(|child| {
-        mesh_leaf |= traverse_hierarchy_rec(*child, Some(data), hierarchy);
-    });
-    if node.subclass() == "Mesh" {
-        mesh_leaf = true;
-    }
-    // Only keep nodes that have Mesh children
-    // (ie defines something visible in the scene)
-    // I've found some very unwindy FBX files with several thousand
-    // nodes that served no practical purposes,
-    // This also trims deformers and limb nodes, which we currently
-    // do not support
-    if mesh_leaf {
-        let fbx_object = FbxObject {
-            name,
-            transform: data.as_local_transform(parent.as_ref().map(|p| p.global)),
-            children: node.child_models().map(|c| c.object_id()).collect(),
-        };
-        hierarchy.insert(node.object_id(), fbx_object);
-    }
-    mesh_leaf
+        let mut mesh_leaf = false;
+        let mut children = Vec::new();
+        for child in node.child_models() {
+            if !self.visited.insert(child.object_id()) {
+                self.broken.push(format!(
+                    "Node {:?} is connected again to {:?}, by a cycle or as a second parent",
+                    child.name().unwrap_or_default(),
+                    node.name().unwrap_or_default(),
+                ));
+                continue;
+            }
+            children.push(child.object_id());
+            mesh_leaf |= self.visit(*child, Some(data));
+        }
+        if matches!(
+            node.subclass(),
+            "Mesh" | "Light" | "Camera" | "LimbNode" | "Limb" | "Root"
+        ) || node.is_look_at_target()
+            || node.is_animated()
+            || node.is_constraint_member()
+            || node.is_character_member()
+            || self.kept.contains(&node.object_id())
+            || is_locator(node)
+        {
+            mesh_leaf = true;
+        }
+        // Only keep nodes that have Mesh, Light, Camera or skeleton joint children
+        // (ie defines something visible in the scene, or a rig), that are aimed at
+        // by a light or camera, that are animated, part of a constraint or
+        // character, posed, sockets or locators.
+        // I've found some very unwindy FBX files with several thousand
+        // nodes that served no practical purposes,
+        // This also trims deformers, which we currently do not support
+        if mesh_leaf {
+            let fbx_object = FbxObject {
+                name,
+                transform: data.as_local_transform(parent.as_ref().map(|p| p.global)),
+                children,
+                extras: FbxExtras::from_object(&node),
+            };
+            self.hierarchy.insert(node.object_id(), fbx_object);
+            self.globals.insert(node.object_id(), data.global);
+            self.infos.insert(node.object_id(), info);
+        }
+        mesh_leaf
+    }
+}
+
+/// `bytes` in megabytes.
+fn megabytes(bytes: usize) -> f32 {
+    bytes as f32 / (1024.0 * 1024.0)
+}
+
+/// Log the memory used by the load of the file at `path`, with the largest assets.
+fn log_memory_usage(path: &Path, usage: &FbxMemoryUsage) {
+    let stages: Vec<_> = (usage.stages.iter())
+        .map(|(stage, bytes)| format!("{stage:?} {:.1} MB", megabytes(*bytes)))
+        .collect();
+    debug!(
+        "{path:?} used {:.1} MB at most ({}): {:.1} MB of FBX arrays, {:.1} MB of conversion \
+        buffers, {:.1} MB of textures, {:.1} MB of meshes, {:.1} MB of animations",
+        megabytes(usage.peak()),
+        stages.join(", "),
+        megabytes(usage.raw_arrays),
+        megabytes(usage.conversion_buffers),
+        megabytes(usage.texture_bytes()),
+        megabytes(usage.mesh_bytes()),
+        megabytes(usage.animation_bytes()),
+    );
+    let assets = (usage.textures.iter())
+        .chain(&usage.meshes)
+        .chain(&usage.animations);
+    let mut assets: Vec<_> = assets.collect();
+    assets.sort_by(|(a_label, a), (b_label, b)| b.cmp(a).then(a_label.cmp(b_label)));
+    for (label, bytes) in assets.into_iter().take(5) {
+        debug!("{path:?}: {label} uses {:.1} MB", megabytes(*bytes));
+    }
+}
+
+/// The materials connected to the geometry `geometry`, used by the nodes
+/// instancing it without materials of their own.
+fn geometry_materials<'a>(
+    geometry: &object::geometry::MeshHandle<'a>,
+) -> impl Iterator<Item = object::material::MaterialHandle<'a>> {
+    (geometry.source_objects())
+        .filter(|obj| obj.label().is_none())
+        .filter_map(|obj| obj.object_handle())
+        .filter_map(|obj| match obj.get_typed() {
+            TypedObjectHandle::Material(material) => Some(material),
+            _ => None,
+        })
+}
+
+/// Describe the connections of `doc` to objects missing from the file,
+/// other than the implicit `scene_roots`.
+fn dangling_connections(doc: &Document, scene_roots: &[i64]) -> Vec<String> {
+    let mut dangling = Vec::new();
+    for object in doc.objects() {
+        let missing = (object.destination_objects())
+            .chain(object.source_objects())
+            .filter(|other| {
+                !scene_roots.contains(&other.object_id().raw()) && other.object_handle().is_none()
+            });
+        for other in missing {
+            dangling.push(format!(
+                "{} {:?} is connected to the missing object {}",
+                object.class(),
+                object.name().unwrap_or_default(),
+                other.object_id().raw(),
+            ));
+        }
+    }
+    dangling
 }
@@ -0,0 +1,68 @@
+//! Display settings of FBX null nodes (locators).
+
+use bevy::prelude::{Color, Component, Reflect, ReflectComponent};
+use fbxcel_dom::v7400::object::{model::ModelHandle, nodeattribute::TypedNodeAttributeHandle};
+use rgb::RGB;
+
+use crate::utils::fbx_extend::{ModelHandleExt, ObjectPropertiesExt};
+
+/// How a locator is drawn, the `Look` property of FBX nulls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum FbxLocatorLook {
+    /// Not drawn.
+    None,
+    /// Three axis-aligned lines crossing at the node's origin.
+    #[default]
+    Cross,
+    /// A cube centered on the node's origin.
+    Box,
+}
+
+/// The display settings of a FBX null node, as drawn in the authoring tool.
+///
+/// Nulls are often used as spawn points or attachment sockets, this lets
+/// editors draw matching gizmos for them. Added to the node entity of
+/// nulls with a `Null` node attribute.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct FbxLocator {
+    /// The display size, in percent of the authoring tool's default
+    /// locator size.
+    pub size: f32,
+    pub look: FbxLocatorLook,
+    /// The wireframe color of the locator, if set in the file.
+    pub color: Option<Color>,
+}
+impl Default for FbxLocator {
+    fn default() -> Self {
+        FbxLocator {
+            size: 100.0,
+            look: FbxLocatorLook::default(),
+            color: None,
+        }
+    }
+}
+impl FbxLocator {
+    /// Read the display properties of the `Null` node attribute of `node`.
+    pub(crate) fn from_node(node: ModelHandle) -> Option<Self> {
+        let attribute = match node.node_attribute()? {
+            TypedNodeAttributeHandle::Null(null) => null,
+            _ => return None,
+        };
+        let p = attribute.properties_by_native_typename("FbxNull");
+        let look = match p.get::<i32>("Look") {
+            Some(0) => FbxLocatorLook::None,
+            Some(2) => FbxLocatorLook::Box,
+            _ => FbxLocatorLook::Cross,
+        };
+        let color = p.get::<RGB<f64>>("Color").or_else(|| {
+            let p = node.properties_by_native_typename("FbxNode");
+            p.get::<RGB<f64>>("Color")
+        });
+        Some(FbxLocator {
+            size: p.get::<f64>("Size").unwrap_or(100.0) as f32,
+            look,
+            color: color.map(|c| Color::rgb(c.r as f32, c.g as f32, c.b as f32)),
+        })
+    }
+}
@@ -0,0 +1,75 @@
+//! Target ("look-at") cameras and lights.
+//!
+//! FBX target lights and cameras ignore their own rotation and instead
+//! always aim at a separate target node.
+//! The aim is resolved once at import time, and kept up to date by
+//! [`resolve_look_at`] when the target moves.
+
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::{
+        Component, Entity, FromWorld, GlobalTransform, Mat4, Parent, Quat, Query, Reflect,
+        ReflectComponent, Transform, Vec3, World,
+    },
+};
+
+/// The light or camera holding this component always aims at `target`.
+///
+/// Its [`Transform`] rotation is overwritten each frame so that its forward
+/// direction points toward the [`GlobalTransform`] of `target`.
+/// Remove this component to control the rotation yourself.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct FbxLookAt {
+    /// The entity of the FBX target node.
+    pub target: Entity,
+}
+impl FromWorld for FbxLookAt {
+    fn from_world(_world: &mut World) -> Self {
+        FbxLookAt {
+            target: Entity::PLACEHOLDER,
+        }
+    }
+}
+impl MapEntities for FbxLookAt {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.target = entity_map.get(self.target)?;
+        Ok(())
+    }
+}
+
+/// The local rotation of an aimed object, given the global transform of
+/// its parent and the global position of its target.
+///
+/// Returns `None` if the target is at the object's position.
+pub(crate) fn aimed_rotation(parent_global: Mat4, target: Vec3) -> Option<Quat> {
+    let parent_inverse = parent_global.inverse();
+    let target = parent_inverse.transform_point3(target);
+    let up = parent_inverse.transform_vector3(Vec3::Y);
+    if target.length_squared() < f32::EPSILON {
+        return None;
+    }
+    Some(Transform::IDENTITY.looking_at(target, up).rotation)
+}
+
+/// Aim entities with a [`FbxLookAt`] at their target.
+///
+/// Uses the previous frame's [`GlobalTransform`], so the aim lags
+/// one frame behind a moving target.
+pub fn resolve_look_at(
+    mut aimed: Query<(&mut Transform, &Parent, &FbxLookAt)>,
+    globals: Query<&GlobalTransform>,
+) {
+    for (mut transform, parent, look_at) in &mut aimed {
+        let transforms = (globals.get(parent.get()), globals.get(look_at.target));
+        if let (Ok(parent), Ok(target)) = transforms {
+            let aimed = aimed_rotation(parent.compute_matrix(), target.translation());
+            if let Some(rotation) = aimed {
+                transform.rotation = rotation;
+            }
+        }
+    }
+}
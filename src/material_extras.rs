@@ -0,0 +1,185 @@
+//! Material parameters bevy's `StandardMaterial` can't represent.
+//!
+//! They are kept in [`FbxScene::material_extras`], and on the spawned mesh
+//! entities as a [`FbxMaterialExtras`] component, so that games with
+//! their own shaders can still use them, see [`FbxMaterialOverrides`].
+//!
+//! [`FbxScene::material_extras`]: crate::FbxScene::material_extras
+//! [`FbxMaterialOverrides`]: crate::FbxMaterialOverrides
+
+use bevy::{
+    prelude::{Color, Component, Handle, Image, ReflectComponent},
+    reflect::Reflect,
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::object::material::MaterialHandle;
+use rgb::RGB;
+
+use crate::{
+    physical::{Param, Physical},
+    utils::fbx_extend::ObjectPropertiesExt,
+    FbxExtras, FbxPropertyValue,
+};
+
+/// The parameters of a FBX material not mapped on its `StandardMaterial`,
+/// and its user-defined properties.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct FbxMaterialExtras {
+    /// How much light passes through the surface, from `0.0` to `1.0`.
+    ///
+    /// The `StandardMaterial` approximates it with alpha blending.
+    pub transmission: f32,
+    /// The tint of the light passing through the surface.
+    pub transmission_color: Color,
+    /// Index of refraction of the material, if the FBX material has one.
+    pub ior: Option<f32>,
+    /// Whether the surface is an infinitely thin shell, such as a soap
+    /// bubble, rather than the boundary of a volume.
+    pub thin_walled: bool,
+    /// Thickness of the thin film coating (soap bubble, oil slick), in
+    /// nanometers. `0.0` if there is none.
+    pub thin_film_thickness: f32,
+    /// Index of refraction of the thin film coating.
+    pub thin_film_ior: Option<f32>,
+    /// Weight of the clear coat layer, `0.0` if there is none.
+    pub coat: f32,
+    pub coat_color: Color,
+    pub coat_roughness: f32,
+    pub coat_ior: Option<f32>,
+    /// Weight of the sheen layer (cloth, velvet), `0.0` if there is none.
+    pub sheen: f32,
+    pub sheen_color: Color,
+    pub sheen_roughness: f32,
+    /// The reflection map of Phong materials, when
+    /// [`FbxLoaderSettings::reflection_maps`] is [`FbxReflectionMap::Environment`].
+    ///
+    /// [`FbxLoaderSettings::reflection_maps`]: crate::FbxLoaderSettings::reflection_maps
+    /// [`FbxReflectionMap::Environment`]: crate::FbxReflectionMap::Environment
+    pub reflection_map: Option<Handle<Image>>,
+    /// How much of the reflection map is reflected, from `0.0` to `1.0`.
+    pub reflection_factor: f32,
+    /// Height map of the material, white being the highest, for terrain
+    /// tessellation or parallax mapping.
+    pub displacement_map: Option<Handle<Image>>,
+    /// Distance between black and white texels of the
+    /// [`displacement_map`](Self::displacement_map), in FBX units.
+    pub displacement_factor: f32,
+    /// Vector displacement map of the material, each texel
+    /// being an offset in tangent space.
+    pub vector_displacement_map: Option<Handle<Image>>,
+    pub vector_displacement_factor: f32,
+    /// Bump map of the material. Depending on the exporter, this is
+    /// either a grayscale height map or a normal map.
+    pub bump_map: Option<Handle<Image>>,
+    pub bump_factor: f32,
+    /// The user-defined properties of the material, such as
+    /// `footstep=wood` for gameplay code.
+    pub properties: HashMap<String, FbxPropertyValue>,
+    /// The FBX properties of the material used by the DCC tool, but not
+    /// mapped on its `StandardMaterial`, as their FBX property name.
+    ///
+    /// The bevy version this crate targets has no clear coat, sheen or thin
+    /// film, materials using them render without those layers.
+    pub unmapped: Vec<String>,
+}
+impl Default for FbxMaterialExtras {
+    fn default() -> Self {
+        FbxMaterialExtras {
+            transmission: 0.0,
+            transmission_color: Color::WHITE,
+            ior: None,
+            thin_walled: false,
+            thin_film_thickness: 0.0,
+            thin_film_ior: None,
+            coat: 0.0,
+            coat_color: Color::WHITE,
+            coat_roughness: 0.0,
+            coat_ior: None,
+            sheen: 0.0,
+            sheen_color: Color::WHITE,
+            sheen_roughness: 0.0,
+            reflection_map: None,
+            reflection_factor: 0.0,
+            displacement_map: None,
+            displacement_factor: 1.0,
+            vector_displacement_map: None,
+            vector_displacement_factor: 1.0,
+            bump_map: None,
+            bump_factor: 1.0,
+            properties: HashMap::default(),
+            unmapped: Vec::new(),
+        }
+    }
+}
+impl FbxMaterialExtras {
+    pub(crate) fn from_material(material: MaterialHandle) -> Self {
+        let mut extras = match Physical::new(material) {
+            Some(physical) => Self::from_physical(&physical),
+            None => Self::from_legacy(material),
+        };
+        let properties = material.properties();
+        let factor = |name: &str| properties.get::<f64>(name).map_or(1.0, |f| f as f32);
+        extras.displacement_factor = factor("DisplacementFactor");
+        extras.vector_displacement_factor = factor("VectorDisplacementFactor");
+        extras.bump_factor = factor("BumpFactor");
+        extras.properties = FbxExtras::from_object(&material).properties;
+        extras
+    }
+
+    fn from_physical(physical: &Physical) -> Self {
+        let mut extras = FbxMaterialExtras {
+            transmission: physical.f32(Param::Transmission).unwrap_or(0.0),
+            transmission_color: physical
+                .color(Param::TransmissionColor)
+                .unwrap_or(Color::WHITE),
+            ior: physical.f32(Param::Ior),
+            thin_walled: physical.bool(Param::ThinWalled).unwrap_or(false),
+            thin_film_thickness: physical.f32(Param::ThinFilmThickness).unwrap_or(0.0),
+            thin_film_ior: physical.f32(Param::ThinFilmIor),
+            coat: physical.f32(Param::Coat).unwrap_or(0.0),
+            coat_color: physical.color(Param::CoatColor).unwrap_or(Color::WHITE),
+            coat_roughness: physical.f32(Param::CoatRoughness).unwrap_or(0.0),
+            coat_ior: physical.f32(Param::CoatIor),
+            sheen: physical.f32(Param::Sheen).unwrap_or(0.0),
+            sheen_color: physical.color(Param::SheenColor).unwrap_or(Color::WHITE),
+            sheen_roughness: physical.f32(Param::SheenRoughness).unwrap_or(0.0),
+            ..Default::default()
+        };
+        let mut unmapped = |used: bool, params: &[Param]| {
+            if used {
+                let present = params.iter().filter(|p| physical.has(**p));
+                let names = present.filter_map(|p| p.name(physical.vendor));
+                extras.unmapped.extend(names.map(str::to_owned));
+            }
+        };
+        use Param::*;
+        unmapped(
+            extras.coat > 0.0,
+            &[Coat, CoatColor, CoatRoughness, CoatIor],
+        );
+        unmapped(extras.sheen > 0.0, &[Sheen, SheenColor, SheenRoughness]);
+        unmapped(
+            extras.thin_film_thickness > 0.0,
+            &[ThinFilmThickness, ThinFilmIor],
+        );
+        extras
+    }
+
+    /// Lambert and Phong materials only have a transparency and a reflection.
+    fn from_legacy(material: MaterialHandle) -> Self {
+        let properties = material.properties();
+        let color = properties.get::<RGB<f64>>("TransparentColor");
+        let factor = properties.get::<f64>("TransparencyFactor");
+        let transmission = match (color, factor) {
+            (Some(RGB { r, g, b }), factor) => (r + g + b) / 3.0 * factor.unwrap_or(1.0),
+            (None, _) => 0.0,
+        };
+        let reflection_factor = properties.get::<f64>("ReflectionFactor").unwrap_or(1.0);
+        FbxMaterialExtras {
+            transmission: transmission.clamp(0.0, 1.0) as f32,
+            reflection_factor: reflection_factor.clamp(0.0, 1.0) as f32,
+            ..Default::default()
+        }
+    }
+}
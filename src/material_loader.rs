@@ -9,6 +9,11 @@ use bevy::{
 use fbxcel_dom::v7400::{data::material::ShadingModel, object::material::MaterialHandle};
 use rgb::RGB;
 
+use crate::{
+    material_extras::FbxMaterialExtras,
+    physical::{self, Param, Physical, Vendor},
+};
+
 /// Load materials from an FBX file.
 ///
 /// Define your own to extend `bevy_mod_fbx`'s material loading capabilities.
@@ -80,12 +85,14 @@ pub const LOAD_LAMBERT_PHONG: MaterialLoader = MaterialLoader {
             return None;
         };
         let transparent = textures.get("TransparentColor").cloned();
-        let is_transparent = transparent.is_some();
-        let diffuse = transparent.or_else(|| textures.get("DiffuseColor").cloned());
-        let base_color = properties
+        let transparency = FbxMaterialExtras::from_material(material_obj).transmission;
+        let is_transparent = transparent.is_some() || transparency > 0.0;
+        let diffuse = textures.get("DiffuseColor").cloned().or(transparent);
+        let base_color: Color = properties
             .diffuse_color_or_default()
             .map_or(Default::default(), ColorAdapter)
             .into();
+        let base_color = base_color.with_a(1.0 - transparency);
         let specular = properties.specular_or_default().unwrap_or_default();
         let metallic = (specular.r + specular.g + specular.b) / 3.0;
         let metallic = metallic as f32 * SPECULAR_TO_METALLIC_RATIO;
@@ -110,6 +117,61 @@ pub const LOAD_LAMBERT_PHONG: MaterialLoader = MaterialLoader {
     },
 };
 
+/// Physically based materials of Arnold (`aiStandardSurface`) and
+/// 3ds Max (`Physical Material`).
+///
+/// Transmission is approximated with alpha blending, and the index of
+/// refraction sets the reflectance. The parameters bevy can't represent
+/// are kept in [`FbxMaterialExtras`].
+pub const LOAD_PHYSICAL: MaterialLoader = MaterialLoader {
+    static_load: &[
+        "Maya|baseColor",
+        "Maya|normalCamera",
+        "Maya|emissionColor",
+        "3dsMax|Parameters|base_color_map",
+        "3dsMax|Parameters|bump_map",
+        "3dsMax|Parameters|emit_color_map",
+    ],
+    dynamic_load: &[],
+    preprocess_textures: |_, _| {},
+    with_textures: |material_obj, textures| {
+        let physical = Physical::new(material_obj)?;
+        let texture = |arnold: &str, max: &str| {
+            let name = match physical.vendor {
+                Vendor::Arnold => arnold,
+                Vendor::MaxPhysical => max,
+            };
+            textures.get(name).cloned()
+        };
+        let weight = physical.f32(Param::BaseWeight).unwrap_or(1.0);
+        let base_color = physical.color(Param::BaseColor).unwrap_or(Color::WHITE) * weight;
+        let transmission = physical.f32(Param::Transmission).unwrap_or(0.0);
+        let emission = physical.f32(Param::Emission).unwrap_or(0.0);
+        let emissive = physical.color(Param::EmissionColor).unwrap_or(Color::BLACK) * emission;
+        let reflectance = physical
+            .f32(Param::Ior)
+            .map_or(0.5, physical::ior_to_reflectance);
+        Some(StandardMaterial {
+            base_color: base_color.with_a(1.0 - transmission.clamp(0.0, 1.0)),
+            base_color_texture: texture("Maya|baseColor", "3dsMax|Parameters|base_color_map"),
+            emissive,
+            emissive_texture: texture("Maya|emissionColor", "3dsMax|Parameters|emit_color_map"),
+            metallic: physical.f32(Param::Metalness).unwrap_or(0.0),
+            perceptual_roughness: physical.f32(Param::Roughness).unwrap_or(0.2),
+            reflectance,
+            normal_map_texture: texture("Maya|normalCamera", "3dsMax|Parameters|bump_map"),
+            flip_normal_map_y: true,
+            double_sided: physical.bool(Param::ThinWalled).unwrap_or(false),
+            alpha_mode: if transmission > 0.0 {
+                AlphaMode::Blend
+            } else {
+                AlphaMode::Opaque
+            },
+            ..Default::default()
+        })
+    },
+};
+
 /// The default material if all else fails.
 ///
 /// Picks up the non-texture material values if possible,
@@ -272,6 +334,7 @@ pub const fn default_loader_order() -> &'static [MaterialLoader] {
     &[
         #[cfg(feature = "maya_3dsmax_pbr")]
         LOAD_MAYA_PBR,
+        LOAD_PHYSICAL,
         LOAD_LAMBERT_PHONG,
         LOAD_FALLBACK,
     ]
@@ -0,0 +1,123 @@
+//! Replacing the materials of spawned FBX scenes.
+//!
+//! Games often use their own shaders for some materials, such as glass or
+//! water. Rather than post-processing every spawned scene, register the
+//! replacements once in the [`FbxMaterialOverrides`] resource.
+
+use std::sync::Arc;
+
+use bevy::{
+    ecs::system::EntityCommands,
+    pbr::Material,
+    prelude::{
+        Added, Commands, Component, Entity, Handle, Parent, Query, Reflect, ReflectComponent, Res,
+        Resource, StandardMaterial,
+    },
+};
+
+use crate::{FbxExtras, FbxPropertyValue};
+
+/// The FBX name of the material of a spawned mesh entity.
+///
+/// Materials without a name are named after their asset label.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct FbxMaterialName(pub String);
+
+/// Which mesh entities an override applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FbxMaterialMatch {
+    /// Meshes using the material with this FBX name.
+    Name(String),
+    /// Meshes of nodes with a user-defined property of this value,
+    /// see [`FbxExtras`].
+    Property {
+        name: String,
+        value: FbxPropertyValue,
+    },
+}
+
+/// The replacement of the material of matching mesh entities.
+#[derive(Clone)]
+pub enum FbxMaterialOverride {
+    Standard(Handle<StandardMaterial>),
+    /// Called on the mesh entity, after its `Handle<StandardMaterial>` was removed.
+    Custom(Arc<dyn Fn(&mut EntityCommands) + Send + Sync>),
+}
+impl FbxMaterialOverride {
+    /// Replace the material with a custom [`Material`].
+    pub fn custom<M: Material>(material: Handle<M>) -> Self {
+        FbxMaterialOverride::Custom(Arc::new(move |entity| {
+            entity.insert(material.clone());
+        }))
+    }
+}
+
+/// Resource of the material replacements applied to spawned FBX scenes.
+///
+/// Overrides are checked in the order they were added, the first matching
+/// one is applied when the mesh entity is spawned. Scenes already spawned
+/// are not affected by later changes.
+#[derive(Resource, Clone, Default)]
+pub struct FbxMaterialOverrides {
+    overrides: Vec<(FbxMaterialMatch, FbxMaterialOverride)>,
+}
+impl FbxMaterialOverrides {
+    /// Replace the materials with the FBX name `name`.
+    pub fn by_name(&mut self, name: impl Into<String>, material: FbxMaterialOverride) -> &mut Self {
+        self.add(FbxMaterialMatch::Name(name.into()), material)
+    }
+
+    /// Replace the materials of the nodes whose user-defined property `name` is `value`.
+    pub fn by_property(
+        &mut self,
+        name: impl Into<String>,
+        value: FbxPropertyValue,
+        material: FbxMaterialOverride,
+    ) -> &mut Self {
+        let name = name.into();
+        self.add(FbxMaterialMatch::Property { name, value }, material)
+    }
+
+    pub fn add(&mut self, matches: FbxMaterialMatch, material: FbxMaterialOverride) -> &mut Self {
+        self.overrides.push((matches, material));
+        self
+    }
+
+    fn find(&self, name: &str, extras: Option<&FbxExtras>) -> Option<&FbxMaterialOverride> {
+        let matches = |matches: &FbxMaterialMatch| match matches {
+            FbxMaterialMatch::Name(material) => material == name,
+            FbxMaterialMatch::Property { name, value } => {
+                extras.and_then(|extras| extras.properties.get(name)) == Some(value)
+            }
+        };
+        let (_, material) = self.overrides.iter().find(|(m, _)| matches(m))?;
+        Some(material)
+    }
+}
+
+/// Apply the [`FbxMaterialOverrides`] to newly spawned mesh entities.
+pub(crate) fn apply_material_overrides(
+    mut commands: Commands,
+    overrides: Res<FbxMaterialOverrides>,
+    meshes: Query<(Entity, &FbxMaterialName, Option<&Parent>), Added<FbxMaterialName>>,
+    extras: Query<&FbxExtras>,
+) {
+    if overrides.overrides.is_empty() {
+        return;
+    }
+    for (entity, name, parent) in &meshes {
+        let node_extras = parent.and_then(|parent| extras.get(parent.get()).ok());
+        match overrides.find(&name.0, node_extras) {
+            Some(FbxMaterialOverride::Standard(material)) => {
+                commands.entity(entity).insert(material.clone());
+            }
+            Some(FbxMaterialOverride::Custom(insert)) => {
+                let mut entity = commands.entity(entity);
+                entity.remove::<Handle<StandardMaterial>>();
+                insert(&mut entity);
+            }
+            None => {}
+        }
+    }
+}
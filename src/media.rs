@@ -0,0 +1,118 @@
+//! Audio clips and video files referenced by FBX files, for cinematics
+//! and video textures, see [`FbxScene::media`].
+//!
+//! [`FbxScene::media`]: crate::FbxScene::media
+
+use fbxcel_dom::v7400::{object::ObjectHandle, Document};
+
+use crate::{animation::ktime_to_seconds, utils::fbx_extend::ObjectPropertiesExt};
+
+/// Extensions of the still images of `Video` objects, which are loaded as
+/// textures rather than listed as media.
+const IMAGE_EXTENSIONS: [&str; 14] = [
+    "png", "jpg", "jpeg", "tga", "dds", "bmp", "ktx2", "hdr", "exr", "tif", "tiff", "gif", "webp",
+    "psd",
+];
+
+/// The kind of a [`FbxMediaRef`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FbxMediaKind {
+    /// An `Audio` object, such as the soundtrack of a cinematic.
+    Audio,
+    /// A `Video` object referencing a movie file, which may be used
+    /// as a video texture.
+    Video,
+}
+
+/// An audio clip or a video file referenced by the file, for cutscene
+/// systems to play alongside the animations.
+///
+/// Nothing is loaded from the referenced file, the FBX only gives its path,
+/// its placement on the timeline and, sometimes, its embedded content.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FbxMediaRef {
+    pub name: Option<String>,
+    pub kind: FbxMediaKind,
+    /// The `RelativeFilename` of the media, relative to the FBX file.
+    pub relative_path: Option<String>,
+    /// The `Filename` of the media, as saved on the author's machine.
+    pub absolute_path: Option<String>,
+    /// When the media starts playing on the scene timeline, in seconds.
+    pub offset: f32,
+    /// The part of the media that is played, in seconds from its start,
+    /// if it is trimmed.
+    pub clip: Option<(f32, f32)>,
+    /// The playback speed, `1.0` being the media's own speed.
+    pub play_speed: f32,
+    pub looping: bool,
+    /// The content of the media file, when it is embedded in the FBX file.
+    pub embedded: Option<Vec<u8>>,
+}
+impl FbxMediaRef {
+    /// The media of `object`, `None` for the still images of `Video` objects.
+    fn from_object(object: ObjectHandle, kind: FbxMediaKind) -> Option<Self> {
+        let node = object.node();
+        let child_string = |name| {
+            let attribute = node.first_child_by_name(name)?.attributes().first()?;
+            Some(attribute.get_string()?.to_owned()).filter(|s| !s.is_empty())
+        };
+        let p = object.properties_by_native_typename(match kind {
+            FbxMediaKind::Audio => "FbxAudio",
+            FbxMediaKind::Video => "FbxVideo",
+        });
+        let property_string = |name| {
+            let property = p.get_property(name)?;
+            let value = property.value_part().first()?.get_string()?;
+            Some(value.to_owned()).filter(|s| !s.is_empty())
+        };
+        let relative_path =
+            child_string("RelativeFilename").or_else(|| property_string("RelativeFilename"));
+        let absolute_path = child_string("Filename").or_else(|| property_string("Path"));
+        let path = relative_path.as_ref().or(absolute_path.as_ref());
+        if kind == FbxMediaKind::Video && path.is_some_and(|path| is_image(path)) {
+            return None;
+        }
+        let seconds = |name| p.get::<i64>(name).map(|t| ktime_to_seconds(t) as f32);
+        let clip = match kind {
+            FbxMediaKind::Audio => seconds("ClipIn").zip(seconds("ClipOut")),
+            FbxMediaKind::Video => {
+                let frame_rate = p.get::<f64>("FrameRate").filter(|rate| *rate > 0.0);
+                let frame = |name| Some((p.get::<i32>(name)? as f64 / frame_rate?) as f32);
+                frame("StartFrame").zip(frame("StopFrame"))
+            }
+        };
+        let embedded = node
+            .first_child_by_name("Content")
+            .and_then(|content| content.attributes().first()?.get_binary())
+            .filter(|content| !content.is_empty())
+            .map(<[u8]>::to_vec);
+        Some(FbxMediaRef {
+            name: object.name().filter(|n| !n.is_empty()).map(Into::into),
+            kind,
+            relative_path,
+            absolute_path,
+            offset: seconds("Offset").unwrap_or(0.0),
+            clip: clip.filter(|(start, end)| start < end),
+            play_speed: p.get::<f64>("PlaySpeed").unwrap_or(1.0) as f32,
+            looping: p.get::<bool>("Loop").unwrap_or(false),
+            embedded,
+        })
+    }
+
+    /// The audio clips and the videos that aren't still images of `doc`.
+    pub(crate) fn all(doc: &Document) -> Vec<Self> {
+        doc.objects()
+            .filter_map(|object| match object.node().name() {
+                "Audio" => FbxMediaRef::from_object(object, FbxMediaKind::Audio),
+                "Video" => FbxMediaRef::from_object(object, FbxMediaKind::Video),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Whether `path` is a still image, loaded as a texture.
+fn is_image(path: &str) -> bool {
+    let extension = path.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+    extension.is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+}
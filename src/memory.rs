@@ -0,0 +1,160 @@
+use std::{any::Any, path::Path};
+
+use anyhow::bail;
+use bevy::{
+    asset::{Asset, AssetPath, HandleId, LoadContext, LoadedAsset},
+    prelude::{Assets, Handle, World},
+    utils::HashMap,
+};
+
+use crate::{diff, FbxScene, FbxSceneDiff};
+
+type InsertFn = fn(HandleId, Box<dyn Any + Send + Sync>, &mut World);
+
+/// A FBX file loaded with [`FbxLoader::load_from_bytes`], without an `AssetServer`.
+///
+/// The handles of the scene are weak handles to the converted assets,
+/// which are stored here rather than in the `Assets` resources.
+/// Use [`FbxLoadedScene::get`] to read them, or [`FbxLoadedScene::insert_into`]
+/// to add them to a world.
+///
+/// [`FbxLoader::load_from_bytes`]: crate::FbxLoader::load_from_bytes
+pub struct FbxLoadedScene {
+    pub scene: FbxScene,
+    pub(crate) assets: MemoryAssets,
+}
+impl FbxLoadedScene {
+    /// The converted asset of `handle`, if it was loaded from this file.
+    pub fn get<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        let (asset, _) = self.assets.assets.get(&handle.id())?;
+        asset.downcast_ref()
+    }
+
+    /// The converted asset with the given `label`, as in `"file.fbx#label"` asset paths.
+    pub fn get_labeled<T: Asset>(&self, label: &str) -> Option<&T> {
+        let id = self.assets.labels.get(label)?;
+        let (asset, _) = self.assets.assets.get(id)?;
+        asset.downcast_ref()
+    }
+
+    /// The nodes, meshes and materials added, removed or changed from this
+    /// scene to `other`, see [`FbxScene::diff`].
+    pub fn diff(&self, other: &FbxLoadedScene) -> FbxSceneDiff {
+        diff::diff(&self.scene, self, &other.scene, other)
+    }
+
+    /// Take the converted asset of `handle` out of this scene.
+    pub fn remove<T: Asset>(&mut self, handle: &Handle<T>) -> Option<T> {
+        let (asset, _) = self.assets.assets.remove(&handle.id())?;
+        asset.downcast().ok().map(|asset| *asset)
+    }
+
+    /// Add every converted asset to the `Assets` resources of `world`,
+    /// returning the handle of the [`FbxScene`].
+    ///
+    /// The assets are set untracked, they are not freed when their handles are dropped.
+    ///
+    /// # Panics
+    ///
+    /// If the `Assets` resource of one of the converted asset types is missing.
+    pub fn insert_into(self, world: &mut World) -> Handle<FbxScene> {
+        for (id, (asset, insert)) in self.assets.assets {
+            insert(id, asset, world);
+        }
+        let id = HandleId::random::<FbxScene>();
+        world
+            .resource_mut::<Assets<FbxScene>>()
+            .set_untracked(id, self.scene);
+        Handle::weak(id)
+    }
+}
+
+fn insert<T: Asset>(id: HandleId, asset: Box<dyn Any + Send + Sync>, world: &mut World) {
+    if let Ok(asset) = asset.downcast::<T>() {
+        world.resource_mut::<Assets<T>>().set_untracked(id, *asset);
+    }
+}
+
+/// Converted assets, by the id of their weak handle.
+#[derive(Default)]
+pub(crate) struct MemoryAssets {
+    assets: HashMap<HandleId, (Box<dyn Any + Send + Sync>, InsertFn)>,
+    labels: HashMap<String, HandleId>,
+}
+
+/// Where the `Loader` stores the assets it converts.
+pub(crate) enum LoadTarget<'b, 'w> {
+    Context(&'b mut LoadContext<'w>),
+    Memory(&'b mut MemoryAssets),
+}
+impl<'b, 'w> LoadTarget<'b, 'w> {
+    pub(crate) fn path(&self) -> &Path {
+        match self {
+            LoadTarget::Context(context) => context.path(),
+            LoadTarget::Memory(_) => Path::new(""),
+        }
+    }
+
+    pub(crate) fn set_labeled_asset<T: Asset>(&mut self, label: &str, asset: T) -> Handle<T> {
+        match self {
+            LoadTarget::Context(context) => {
+                context.set_labeled_asset(label, LoadedAsset::new(asset))
+            }
+            LoadTarget::Memory(assets) => {
+                let id = HandleId::random::<T>();
+                assets.assets.insert(id, (Box::new(asset), insert::<T>));
+                assets.labels.insert(label.to_owned(), id);
+                Handle::weak(id)
+            }
+        }
+    }
+
+    /// Set the labeled asset `label`, loading `dependencies` along with it.
+    ///
+    /// Files loaded from memory have no folder, dependencies are ignored.
+    pub(crate) fn set_labeled_asset_with_dependencies<T: Asset>(
+        &mut self,
+        label: &str,
+        asset: T,
+        dependencies: Vec<AssetPath<'static>>,
+    ) -> Handle<T> {
+        match self {
+            LoadTarget::Context(context) => {
+                let asset = dependencies
+                    .into_iter()
+                    .fold(LoadedAsset::new(asset), LoadedAsset::with_dependency);
+                context.set_labeled_asset(label, asset)
+            }
+            LoadTarget::Memory(_) => self.set_labeled_asset(label, asset),
+        }
+    }
+
+    /// A handle to the asset at `path`, which must be loaded as a dependency
+    /// of one of the assets of the file to be available.
+    pub(crate) fn get_handle<T: Asset>(&self, path: AssetPath) -> Handle<T> {
+        match self {
+            LoadTarget::Context(context) => context.get_handle(path),
+            LoadTarget::Memory(_) => Handle::weak(path.into()),
+        }
+    }
+
+    /// Whether there is a file at `path`, relative to the asset folder.
+    pub(crate) fn asset_exists(&self, path: &Path) -> bool {
+        match self {
+            LoadTarget::Context(context) => context.asset_io().get_metadata(path).is_ok(),
+            LoadTarget::Memory(_) => false,
+        }
+    }
+
+    /// Read the file at `path`, relative to the asset folder.
+    ///
+    /// Files loaded from memory have no folder, so this always fails for them.
+    pub(crate) async fn read_asset_bytes(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        match self {
+            LoadTarget::Context(context) => Ok(context.read_asset_bytes(path).await?),
+            LoadTarget::Memory(_) => {
+                bail!("Can't read {path:?}: files loaded from memory have no asset folder")
+            }
+        }
+    }
+}
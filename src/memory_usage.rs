@@ -0,0 +1,121 @@
+//! Memory used by the load of a FBX file, see [`FbxScene::memory_usage`].
+//!
+//! [`FbxScene::memory_usage`]: crate::FbxScene::memory_usage
+
+use bevy::{prelude::Mesh, render::mesh::Indices, utils::HashMap};
+use fbxcel_dom::{
+    fbxcel::{low::v7400::AttributeValue, tree::v7400::NodeHandle},
+    v7400::Document,
+};
+
+use crate::{FbxAnimationClip, FbxCurve, FbxKeyframes, FbxLoadStage};
+
+/// The memory used by the load of a FBX file, in bytes, to tell which part
+/// of a file makes loading it expensive.
+///
+/// This counts the data decoded and produced by the loader, not the
+/// overhead of the allocator and of the collections holding the data, so
+/// the process uses somewhat more memory than reported.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FbxMemoryUsage {
+    /// The array attributes of the FBX document, such as vertices, layer
+    /// elements, keyframes and embedded files, decoded when parsing the
+    /// file and kept until the end of the load.
+    pub raw_arrays: usize,
+    /// The largest size of the intermediate buffers of mesh conversion,
+    /// kept until the end of the load.
+    pub conversion_buffers: usize,
+    /// The decoded pixels of each texture, by label.
+    pub textures: HashMap<String, usize>,
+    /// The vertex attributes and indices of each bevy mesh, by label.
+    pub meshes: HashMap<String, usize>,
+    /// The keyframes of each clip of [`FbxScene::animations`], by label.
+    ///
+    /// [`FbxScene::animations`]: crate::FbxScene::animations
+    pub animations: HashMap<String, usize>,
+    /// The memory used at the end of each stage of the load, in order.
+    pub stages: Vec<(FbxLoadStage, usize)>,
+}
+impl FbxMemoryUsage {
+    /// The memory used at the stage of the load using the most memory.
+    pub fn peak(&self) -> usize {
+        self.stages
+            .iter()
+            .map(|(_, bytes)| *bytes)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The memory used by the decoded textures.
+    pub fn texture_bytes(&self) -> usize {
+        self.textures.values().sum()
+    }
+
+    /// The memory used by the bevy meshes.
+    pub fn mesh_bytes(&self) -> usize {
+        self.meshes.values().sum()
+    }
+
+    /// The memory used by the animation clips.
+    pub fn animation_bytes(&self) -> usize {
+        self.animations.values().sum()
+    }
+
+    /// The memory used so far.
+    pub fn total(&self) -> usize {
+        self.raw_arrays
+            + self.conversion_buffers
+            + self.texture_bytes()
+            + self.mesh_bytes()
+            + self.animation_bytes()
+    }
+
+    /// Record the memory used at the end of `stage`.
+    pub(crate) fn end_stage(&mut self, stage: FbxLoadStage) {
+        self.stages.push((stage, self.total()));
+    }
+}
+
+/// The size of the array and binary attributes of the nodes of `doc`.
+pub(crate) fn raw_array_bytes(doc: &Document) -> usize {
+    fn node_bytes(node: NodeHandle) -> usize {
+        let attributes: usize = (node.attributes().iter())
+            .map(|attribute| match attribute {
+                AttributeValue::ArrBool(values) => values.len(),
+                AttributeValue::ArrI32(values) => values.len() * 4,
+                AttributeValue::ArrI64(values) => values.len() * 8,
+                AttributeValue::ArrF32(values) => values.len() * 4,
+                AttributeValue::ArrF64(values) => values.len() * 8,
+                AttributeValue::Binary(values) => values.len(),
+                _ => 0,
+            })
+            .sum();
+        attributes + node.children().map(node_bytes).sum::<usize>()
+    }
+    node_bytes(doc.tree().root())
+}
+
+/// The size of the vertex attributes and indices of `mesh`.
+pub(crate) fn mesh_bytes(mesh: &Mesh) -> usize {
+    let attributes: usize = (mesh.attributes())
+        .map(|(_, values)| values.get_bytes().len())
+        .sum();
+    let indices = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.len() * 2,
+        Some(Indices::U32(indices)) => indices.len() * 4,
+        None => 0,
+    };
+    attributes + indices
+}
+
+/// The size of the keyframes of `clip`.
+pub(crate) fn clip_bytes(clip: &FbxAnimationClip) -> usize {
+    let curve_bytes = |curve: &FbxCurve| {
+        let values = match &curve.keyframes {
+            FbxKeyframes::Translation(values) | FbxKeyframes::Scale(values) => values.len() * 12,
+            FbxKeyframes::Rotation(values) => values.len() * 16,
+        };
+        curve.keyframe_timestamps.len() * 4 + values
+    };
+    clip.curves.values().flatten().map(curve_bytes).sum()
+}
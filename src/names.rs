@@ -0,0 +1,140 @@
+//! Sanitization of FBX node and material names, see
+//! [`FbxLoaderSettings::strip_namespaces`] and
+//! [`FbxLoaderSettings::namespace_remap`].
+//!
+//! Bevy looks entities up by [`Name`] path, for animations and
+//! [`FbxEntityPath`]s, so siblings must have distinct names.
+//!
+//! [`FbxLoaderSettings::strip_namespaces`]: crate::FbxLoaderSettings::strip_namespaces
+//! [`FbxLoaderSettings::namespace_remap`]: crate::FbxLoaderSettings::namespace_remap
+//! [`Name`]: bevy::core::Name
+//! [`FbxEntityPath`]: crate::FbxEntityPath
+
+use bevy::utils::{HashMap, HashSet};
+use fbxcel_dom::v7400::object::ObjectId;
+
+use crate::{data::FbxObject, FbxLoaderSettings};
+
+/// `name` without control characters, with its namespace renamed according
+/// to `settings`, or removed (`char:body_geo` becomes `body_geo`)
+/// if [`FbxLoaderSettings::strip_namespaces`] is set.
+pub(crate) fn sanitize(name: &str, settings: &FbxLoaderSettings) -> String {
+    let name = remap_namespace(name, &settings.namespace_remap);
+    let name = match name.rsplit_once(':') {
+        Some((_, stripped)) if settings.strip_namespaces && !stripped.is_empty() => stripped,
+        _ => &name,
+    };
+    name.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// `name` with the longest of its namespaces found in `remap` replaced.
+fn remap_namespace(name: &str, remap: &HashMap<String, String>) -> String {
+    let mut end = name.rfind(':');
+    while let Some(i) = end {
+        if let Some(namespace) = remap.get(&name[..i]) {
+            let rest = &name[i + 1..];
+            return match namespace.trim_end_matches(':') {
+                "" => rest.to_owned(),
+                namespace => format!("{namespace}:{rest}"),
+            };
+        }
+        end = name[..i].rfind(':');
+    }
+    name.to_owned()
+}
+
+/// Names already given to a set of objects, to make new ones unique.
+#[derive(Default)]
+pub(crate) struct UniqueNames(HashSet<String>);
+impl UniqueNames {
+    /// `name`, or `name.1`, `name.2`... if it was already given.
+    pub(crate) fn insert(&mut self, name: String) -> String {
+        if self.0.insert(name.clone()) {
+            return name;
+        }
+        let mut suffix = 1;
+        loop {
+            let candidate = format!("{name}.{suffix}");
+            if self.0.insert(candidate.clone()) {
+                return candidate;
+            }
+            suffix += 1;
+        }
+    }
+}
+
+/// Sanitize the names of the nodes of `hierarchy`, and make them unique
+/// among their siblings.
+///
+/// Siblings are renamed in the order of their FBX object ids, the first
+/// one keeping its name. Renamed nodes keep their name in the file in
+/// [`FbxExtras::original_name`](crate::FbxExtras::original_name).
+pub(crate) fn sanitize_hierarchy(
+    hierarchy: &mut HashMap<ObjectId, FbxObject>,
+    roots: &[ObjectId],
+    settings: &FbxLoaderSettings,
+) {
+    let mut groups = vec![roots.to_vec()];
+    groups.extend(hierarchy.values().map(|node| node.children.clone()));
+    for mut siblings in groups {
+        siblings.retain(|id| hierarchy.contains_key(id));
+        siblings.sort_by_key(|id| id.raw());
+        siblings.dedup();
+        let mut names = UniqueNames::default();
+        for id in siblings {
+            let node = hierarchy.get_mut(&id).unwrap();
+            let Some(original) = &node.name else {
+                continue;
+            };
+            let name = names.insert(sanitize(original, settings));
+            if &name != original {
+                node.extras.original_name = node.name.replace(name);
+            }
+        }
+    }
+}
+
+/// Whether `name` matches the glob `pattern`, where `*` matches any
+/// characters, including none, and `?` matches one character.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` in `pattern`, and the position in `name`
+    // it matches up to, to backtrack when the rest doesn't match.
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// The part of `name` matched by the wildcards of `pattern`, without the
+/// text around them: `SOCKET_hand_r` gives `hand_r` with `SOCKET_*`.
+pub(crate) fn wildcard_match<'a>(pattern: &str, name: &'a str) -> &'a str {
+    let is_wildcard = |c: char| c == '*' || c == '?';
+    match (pattern.find(is_wildcard), pattern.rfind(is_wildcard)) {
+        (Some(first), Some(last)) => {
+            let suffix = pattern.len() - last - 1;
+            name.get(first..name.len() - suffix).unwrap_or(name)
+        }
+        _ => name,
+    }
+}
@@ -0,0 +1,264 @@
+//! Files with duplicate object ids, which the FBX DOM refuses to load.
+//!
+//! Some exporters give several objects the same id, or give an object the
+//! id `0` of the scene root. Such objects get new ids before the document
+//! is built, and their connections are kept only when their classes tell
+//! which object they connect, so that no mesh is attached to the wrong node.
+
+use std::io;
+
+use anyhow::bail;
+use bevy::utils::{HashMap, HashSet};
+use fbxcel::{
+    low::v7400::AttributeValue,
+    tree::{
+        any::AnyTree,
+        v7400::{NodeId, Tree},
+    },
+};
+use fbxcel_dom::v7400::{Document, Loader};
+
+/// The id of the scene root in connections.
+const ROOT_ID: i64 = 0;
+/// The class of the scene root, see [`can_connect`].
+const ROOT_CLASS: &str = "Root";
+
+/// Parse the FBX file `bytes` into a document, fixing its object ids
+/// first. Returns the problems found along with the document.
+pub(crate) fn parse_document(bytes: &[u8]) -> anyhow::Result<(Document, Vec<String>)> {
+    let mut tree = match AnyTree::from_seekable_reader(io::Cursor::new(bytes))? {
+        AnyTree::V7400(_, tree, _) => tree,
+        _ => bail!("Unsupported FBX version, only 7.4 and 7.5 are supported"),
+    };
+    let warnings = fix_object_ids(&mut tree);
+    Ok((Loader::new().load_from_tree(tree)?, warnings))
+}
+
+/// An object sharing its id with other objects or the root.
+struct Duplicate {
+    /// The id given to the object.
+    id: i64,
+    /// The node name of the object, such as `Model` or `Geometry`.
+    class: String,
+}
+
+/// Give unique ids to the objects of `tree` sharing their id, and resolve
+/// or drop the connections to them. Also drops repeated connections.
+fn fix_object_ids(tree: &mut Tree) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let Some(objects) = tree.root().first_child_by_name("Objects") else {
+        return warnings;
+    };
+    let objects: Vec<(NodeId, i64, String)> = (objects.children())
+        .filter_map(|node| {
+            let id = node.attributes().first()?.get_i64()?;
+            Some((node.node_id(), id, node.name().to_owned()))
+        })
+        .collect();
+    let mut counts = HashMap::<i64, usize>::default();
+    for (_, id, _) in &objects {
+        *counts.entry(*id).or_default() += 1;
+    }
+    let is_duplicate = |id: i64| id == ROOT_ID || counts[&id] > 1;
+
+    // The first object of each id keeps it, except for the id of the root.
+    let mut next_id = objects.iter().map(|(_, id, _)| *id).max().unwrap_or(0) + 1;
+    let mut duplicates = HashMap::<i64, Vec<Duplicate>>::default();
+    let mut classes = HashMap::<i64, &str>::default();
+    for (node, id, class) in &objects {
+        if !is_duplicate(*id) {
+            classes.insert(*id, class);
+            continue;
+        }
+        let owners = duplicates.entry(*id).or_default();
+        let new_id = if owners.is_empty() && *id != ROOT_ID {
+            *id
+        } else {
+            next_id += 1;
+            if let Some(attribute) = tree.get_attribute_mut(*node, 0) {
+                *attribute = AttributeValue::I64(next_id - 1);
+            }
+            next_id - 1
+        };
+        owners.push(Duplicate {
+            id: new_id,
+            class: class.clone(),
+        });
+    }
+    for (id, owners) in &duplicates {
+        let classes: Vec<&str> = owners.iter().map(|owner| owner.class.as_str()).collect();
+        let problem = match *id {
+            ROOT_ID => "the id of the scene root",
+            _ => "a duplicate id",
+        };
+        warnings.push(format!(
+            "Objects {classes:?} have {problem} {id}, gave them unique ids"
+        ));
+    }
+
+    // The candidates of an end of a connection: the objects with its id.
+    let candidates = |id: i64| -> Vec<(i64, &str)> {
+        let mut candidates = Vec::new();
+        if id == ROOT_ID {
+            candidates.push((ROOT_ID, ROOT_CLASS));
+        }
+        match duplicates.get(&id) {
+            Some(owners) => (owners.iter()).for_each(|o| candidates.push((o.id, &o.class))),
+            None => candidates.extend(classes.get(&id).map(|class| (id, *class))),
+        }
+        candidates
+    };
+    let connections: Vec<(NodeId, (i64, i64), Option<String>)> = (tree.root())
+        .first_child_by_name("Connections")
+        .iter()
+        .flat_map(|node| node.children_by_name("C"))
+        .filter_map(|connection| {
+            let attributes = connection.attributes();
+            let child = attributes.get(1)?.get_i64()?;
+            let parent = attributes.get(2)?.get_i64()?;
+            let label = attributes.get(3).and_then(|a| a.get_string());
+            Some((
+                connection.node_id(),
+                (child, parent),
+                label.map(str::to_owned),
+            ))
+        })
+        .collect();
+    let mut copies = HashMap::<_, usize>::default();
+    for (_, ends, label) in &connections {
+        *copies.entry((*ends, label.clone())).or_default() += 1;
+    }
+    let mut rewrites = Vec::new();
+    let mut removed = Vec::new();
+    let mut seen = HashMap::<_, usize>::default();
+    let mut unique = HashSet::default();
+    for (connection, (child, parent), label) in connections {
+        let key = ((child, parent), label);
+        let copy = *seen.entry(key.clone()).and_modify(|i| *i += 1).or_default();
+        let mut ends = (child, parent);
+        if duplicates.contains_key(&child) || duplicates.contains_key(&parent) {
+            let mut pairs: Vec<_> = (candidates(child).into_iter())
+                .flat_map(|child| candidates(parent).into_iter().map(move |p| (child, p)))
+                .filter(|((child_id, _), (parent_id, _))| child_id != parent_id)
+                .filter(|((_, child), (_, parent))| can_connect(child, parent))
+                .collect();
+            // Exporters always write the root as `0`, objects only sometimes.
+            if pairs.iter().any(|(_, (_, parent))| *parent == ROOT_CLASS) {
+                pairs.retain(|(_, (_, parent))| *parent == ROOT_CLASS);
+            }
+            // Copies of a connection can't be told apart, so as many copies
+            // as objects they can connect connect one object each.
+            let pair = match pairs.len() {
+                1 => Some(pairs[0]),
+                count if count == copies[&key] => Some(pairs[copy]),
+                _ => None,
+            };
+            let Some(((new_child, _), (new_parent, _))) = pair else {
+                warnings.push(format!(
+                    "Connection of {child} to {parent} is ambiguous, \
+                    their ids are shared by several objects, skipped it"
+                ));
+                removed.push(connection);
+                continue;
+            };
+            ends = (new_child, new_parent);
+            rewrites.push((connection, ends));
+        }
+        if !unique.insert((ends, key.1)) {
+            warnings.push(format!(
+                "Connection of {} to {} is repeated, skipped the copy",
+                ends.0, ends.1
+            ));
+            removed.push(connection);
+        }
+    }
+    for (connection, (child, parent)) in rewrites {
+        for (i, id) in [(1, child), (2, parent)] {
+            if let Some(attribute) = tree.get_attribute_mut(connection, i) {
+                *attribute = AttributeValue::I64(id);
+            }
+        }
+    }
+    for connection in removed {
+        tree.detach(connection);
+    }
+    fix_pose_nodes(tree, &duplicates, &mut warnings);
+    warnings
+}
+
+/// Point the nodes of the poses at the only model with their id, or
+/// drop them if several models share it.
+fn fix_pose_nodes(
+    tree: &mut Tree,
+    duplicates: &HashMap<i64, Vec<Duplicate>>,
+    warnings: &mut Vec<String>,
+) {
+    let Some(objects) = tree.root().first_child_by_name("Objects") else {
+        return;
+    };
+    let mut rewrites = Vec::new();
+    let mut removed = Vec::new();
+    for pose_node in (objects.children_by_name("Pose")).flat_map(|p| p.children_by_name("PoseNode"))
+    {
+        let Some(node) = pose_node.first_child_by_name("Node") else {
+            continue;
+        };
+        let Some(owners) = (node.attributes().first())
+            .and_then(|a| a.get_i64())
+            .and_then(|id| duplicates.get(&id))
+        else {
+            continue;
+        };
+        let mut models = owners.iter().filter(|owner| owner.class == "Model");
+        match (models.next(), models.next()) {
+            (Some(model), None) => rewrites.push((node.node_id(), model.id)),
+            _ => {
+                warnings.push(format!(
+                    "Pose node of {} is ambiguous, its id is shared by several models, \
+                    skipped it",
+                    owners[0].id
+                ));
+                removed.push(pose_node.node_id());
+            }
+        }
+    }
+    for (node, id) in rewrites {
+        if let Some(attribute) = tree.get_attribute_mut(node, 0) {
+            *attribute = AttributeValue::I64(id);
+        }
+    }
+    for pose_node in removed {
+        tree.detach(pose_node);
+    }
+}
+
+/// Whether an object of class `child` can be connected to an object of
+/// class `parent`, by their node names.
+///
+/// Unknown classes can be connected to anything but the root, only models
+/// are connected to the root.
+fn can_connect(child: &str, parent: &str) -> bool {
+    match (child, parent) {
+        ("Model", ROOT_CLASS) => true,
+        (ROOT_CLASS, _) | (_, ROOT_CLASS) => false,
+        ("Model", parent) => matches!(
+            parent,
+            "Model" | "Deformer" | "CollectionExclusive" | "Constraint"
+        ),
+        ("Geometry", parent) => matches!(parent, "Model" | "Deformer"),
+        ("NodeAttribute", parent) => parent == "Model",
+        ("Material", parent) => matches!(parent, "Model" | "Geometry"),
+        ("Texture" | "LayeredTexture", parent) => {
+            matches!(parent, "Material" | "LayeredTexture" | "Model")
+        }
+        ("Video", parent) => parent == "Texture",
+        ("Deformer", parent) => matches!(parent, "Geometry" | "Deformer"),
+        ("AnimationCurve", parent) => parent == "AnimationCurveNode",
+        ("AnimationLayer", parent) => parent == "AnimationStack",
+        ("AnimationCurveNode", parent) => !matches!(
+            parent,
+            "Video" | "AnimationCurve" | "AnimationCurveNode" | "AnimationStack"
+        ),
+        _ => true,
+    }
+}
@@ -0,0 +1,93 @@
+//! Compositing of opacity textures into the alpha channel of base color
+//! textures, see [`FbxLoaderSettings::opacity_textures`].
+//!
+//! [`FbxLoaderSettings::opacity_textures`]: crate::FbxLoaderSettings::opacity_textures
+
+use bevy::{
+    pbr::AlphaMode,
+    prelude::Image,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::FbxOpacityMode;
+
+/// Alpha values below this or above its complement are considered binary.
+const BINARY_TOLERANCE: u8 = 16;
+
+/// The opacity of each texel of `opacity`, from its alpha channel if it has
+/// one, otherwise from its luminance, white being opaque.
+fn opacity_values(opacity: &Image) -> Option<Vec<u8>> {
+    let rgba = opacity.convert(TextureFormat::Rgba8UnormSrgb)?;
+    let texels = rgba.data.chunks_exact(4);
+    if texels.clone().any(|texel| texel[3] < u8::MAX) {
+        return Some(texels.map(|texel| texel[3]).collect());
+    }
+    let luminance = |texel: &[u8]| {
+        let sum = texel[0] as u16 + texel[1] as u16 + texel[2] as u16;
+        (sum / 3) as u8
+    };
+    Some(texels.map(luminance).collect())
+}
+
+/// `diffuse` with the opacity of `opacity` as its alpha channel, or a white
+/// image if there is no diffuse texture.
+///
+/// The opacity is sampled with nearest neighbor filtering if the textures
+/// have different sizes.
+pub(crate) fn composite(diffuse: Option<&Image>, opacity: &Image) -> Option<Image> {
+    let alpha = opacity_values(opacity)?;
+    let opacity_size = opacity.texture_descriptor.size;
+    let (mut data, size, sampler) = match diffuse {
+        Some(diffuse) => {
+            let rgba = diffuse.convert(TextureFormat::Rgba8UnormSrgb)?;
+            let sampler = diffuse.sampler_descriptor.clone();
+            (rgba.data, diffuse.texture_descriptor.size, sampler)
+        }
+        None => {
+            let texels = (opacity_size.width * opacity_size.height) as usize;
+            let sampler = opacity.sampler_descriptor.clone();
+            (vec![u8::MAX; texels * 4], opacity_size, sampler)
+        }
+    };
+    for (i, texel) in data.chunks_exact_mut(4).enumerate() {
+        let x = i as u32 % size.width;
+        let y = i as u32 / size.width;
+        let ox = x * opacity_size.width / size.width;
+        let oy = y * opacity_size.height / size.height;
+        texel[3] = alpha[(oy * opacity_size.width + ox) as usize];
+    }
+    let size = Extent3d {
+        depth_or_array_layers: 1,
+        ..size
+    };
+    let mut image = Image::new(
+        size,
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler_descriptor = sampler;
+    Some(image)
+}
+
+/// The alpha mode of a material whose base color alpha comes from `image`.
+pub(crate) fn alpha_mode(mode: FbxOpacityMode, image: &Image) -> AlphaMode {
+    match mode {
+        FbxOpacityMode::Blend => AlphaMode::Blend,
+        FbxOpacityMode::Mask(cutoff) => AlphaMode::Mask(cutoff),
+        FbxOpacityMode::Auto => {
+            let Some(rgba) = image.convert(TextureFormat::Rgba8UnormSrgb) else {
+                return AlphaMode::Blend;
+            };
+            let binary = rgba
+                .data
+                .chunks_exact(4)
+                .all(|texel| texel[3] < BINARY_TOLERANCE || texel[3] > u8::MAX - BINARY_TOLERANCE);
+            if binary {
+                AlphaMode::Mask(0.5)
+            } else {
+                AlphaMode::Blend
+            }
+        }
+    }
+}
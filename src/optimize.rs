@@ -0,0 +1,353 @@
+//! Mesh optimization for the GPU, see [`FbxLoaderSettings::optimize_meshes`].
+//!
+//! Triangles are ordered for the post-transform vertex cache with Tom
+//! Forsyth's algorithm, then clusters of them are sorted to reduce overdraw,
+//! and vertices are ordered by first use, like meshoptimizer does.
+//!
+//! [`FbxLoaderSettings::optimize_meshes`]: crate::FbxLoaderSettings::optimize_meshes
+
+use bevy::{
+    math::Vec3,
+    prelude::Mesh,
+    render::mesh::{Indices, VertexAttributeValues},
+    utils::HashMap,
+};
+
+use crate::utils::mesh::{select, ATTRIBUTES};
+
+/// The number of vertices of the simulated post-transform cache,
+/// smaller than the cache of most GPUs.
+const CACHE_SIZE: usize = 16;
+
+/// `mesh` with its identical vertices welded, and its triangles and vertices
+/// reordered to make the best use of the vertex cache and reduce overdraw.
+///
+/// Returns `None` for meshes with attributes we can't reorder,
+/// or without triangle list indices.
+pub(crate) fn optimize(mesh: &Mesh) -> Option<Mesh> {
+    let Some(Indices::U32(indices)) = mesh.indices() else {
+        return None;
+    };
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    let known = ATTRIBUTES.iter().filter(|a| mesh.contains_attribute(a.id));
+    if mesh.attributes().count() != known.count() || indices.len() % 3 != 0 {
+        return None;
+    }
+    let welded = weld(mesh, indices);
+    let cached = vertex_cache_order(&welded, mesh.count_vertices());
+    let sorted = overdraw_order(&cached, positions);
+
+    // Order the vertices by first use, for the vertex fetch cache.
+    let mut order: HashMap<u32, u32> = HashMap::default();
+    let mut original_vertices = Vec::new();
+    let indices = sorted
+        .iter()
+        .map(|vertex| {
+            *order.entry(*vertex).or_insert_with(|| {
+                original_vertices.push(*vertex);
+                original_vertices.len() as u32 - 1
+            })
+        })
+        .collect();
+    let mut optimized = Mesh::new(mesh.primitive_topology());
+    for attribute in ATTRIBUTES {
+        if let Some(values) = mesh.attribute(attribute.id) {
+            optimized.insert_attribute(attribute, select(values, &original_vertices)?);
+        }
+    }
+    optimized.set_indices(Some(Indices::U32(indices)));
+    Some(optimized)
+}
+
+/// `indices` pointing to the first of the vertices with the same attributes.
+///
+/// Meshes are converted with one vertex per triangle corner, welding
+/// makes neighboring triangles share vertices.
+fn weld(mesh: &Mesh, indices: &[u32]) -> Vec<u32> {
+    let attributes: Vec<&[u8]> = (mesh.attributes())
+        .map(|(_, values)| values.get_bytes())
+        .collect();
+    let count = mesh.count_vertices().max(1);
+    let mut welded: HashMap<Vec<u8>, u32> = HashMap::default();
+    let mut first = vec![None; mesh.count_vertices()];
+    indices
+        .iter()
+        .map(|vertex| {
+            *first[*vertex as usize].get_or_insert_with(|| {
+                let key = (attributes.iter())
+                    .flat_map(|bytes| {
+                        let size = bytes.len() / count;
+                        &bytes[*vertex as usize * size..][..size]
+                    })
+                    .copied()
+                    .collect();
+                *welded.entry(key).or_insert(*vertex)
+            })
+        })
+        .collect()
+}
+
+/// The score of a vertex in Forsyth's algorithm, higher for the vertices
+/// in the cache and with few triangles left to draw.
+fn vertex_score(cache_position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return 0.0;
+    }
+    let cache_score = match cache_position {
+        // The vertices of the last triangle, favoring a new triangle
+        // sharing an edge with it would produce long strips.
+        Some(position) if position < 3 => 0.75,
+        Some(position) => {
+            let scale = 1.0 / (CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(1.5)
+        }
+        None => 0.0,
+    };
+    cache_score + 2.0 / (remaining as f32).sqrt()
+}
+
+/// The triangles `indices` ordered for the vertex cache,
+/// with Tom Forsyth's linear-speed vertex cache optimization.
+fn vertex_cache_order(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    // The triangles of each vertex, `offsets[v]..offsets[v + 1]` in `adjacency`.
+    let mut offsets = vec![0; vertex_count + 1];
+    for vertex in indices {
+        offsets[*vertex as usize + 1] += 1;
+    }
+    for i in 0..vertex_count {
+        offsets[i + 1] += offsets[i];
+    }
+    let mut adjacency = vec![0; indices.len()];
+    let mut filled = offsets.clone();
+    for (i, vertex) in indices.iter().enumerate() {
+        adjacency[filled[*vertex as usize]] = i / 3;
+        filled[*vertex as usize] += 1;
+    }
+    let mut remaining: Vec<usize> = (0..vertex_count)
+        .map(|v| offsets[v + 1] - offsets[v])
+        .collect();
+    let mut scores: Vec<f32> = (remaining.iter())
+        .map(|remaining| vertex_score(None, *remaining))
+        .collect();
+    let triangle = |t: usize| &indices[t * 3..t * 3 + 3];
+    let mut triangle_scores: Vec<f32> = (0..triangle_count)
+        .map(|t| triangle(t).iter().map(|v| scores[*v as usize]).sum())
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut ordered = Vec::with_capacity(indices.len());
+    let mut next_unemitted = 0;
+    let mut best =
+        (0..triangle_count).max_by(|a, b| triangle_scores[*a].total_cmp(&triangle_scores[*b]));
+    while let Some(current) = best {
+        emitted[current] = true;
+        ordered.extend_from_slice(triangle(current));
+        for vertex in triangle(current) {
+            remaining[*vertex as usize] -= 1;
+        }
+        // Move the vertices of the triangle to the front of the cache,
+        // the vertices pushed out of it lose their cache score.
+        let mut new_cache: Vec<u32> = triangle(current).to_vec();
+        new_cache.extend(cache.iter().filter(|v| !triangle(current).contains(v)));
+        let evicted = new_cache.split_off(new_cache.len().min(CACHE_SIZE));
+        cache = new_cache;
+        let updated = cache.iter().enumerate().map(|(i, v)| (*v, Some(i)));
+        for (vertex, position) in updated.chain(evicted.iter().map(|v| (*v, None))) {
+            let vertex = vertex as usize;
+            let score = vertex_score(position, remaining[vertex]);
+            let delta = score - scores[vertex];
+            scores[vertex] = score;
+            for t in &adjacency[offsets[vertex]..offsets[vertex + 1]] {
+                triangle_scores[*t] += delta;
+            }
+        }
+        // The next triangle is the best one using the cached vertices,
+        // or the next one in the original order at dead ends.
+        best = (cache.iter())
+            .flat_map(|v| &adjacency[offsets[*v as usize]..offsets[*v as usize + 1]])
+            .filter(|t| !emitted[**t])
+            .max_by(|a, b| triangle_scores[**a].total_cmp(&triangle_scores[**b]))
+            .copied();
+        if best.is_none() {
+            while next_unemitted < triangle_count && emitted[next_unemitted] {
+                next_unemitted += 1;
+            }
+            best = (next_unemitted < triangle_count).then_some(next_unemitted);
+        }
+    }
+    ordered
+}
+
+/// The triangles `indices`, ordered for the vertex cache, split in clusters
+/// starting with a triangle missing the cache and sorted to draw the
+/// clusters facing outward first, which tend to occlude the others.
+fn overdraw_order(indices: &[u32], positions: &[[f32; 3]]) -> Vec<u32> {
+    let position = |vertex: u32| Vec3::from(positions[vertex as usize]);
+    let mut clusters: Vec<&[u32]> = Vec::new();
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE);
+    let mut start = 0;
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        let mut misses = 0;
+        for vertex in triangle {
+            if !cache.contains(vertex) {
+                misses += 1;
+                if cache.len() == CACHE_SIZE {
+                    cache.remove(0);
+                }
+                cache.push(*vertex);
+            }
+        }
+        if misses == 3 && t * 3 > start {
+            clusters.push(&indices[start..t * 3]);
+            start = t * 3;
+        }
+    }
+    clusters.push(&indices[start..]);
+
+    let mesh_center = match indices.len() {
+        0 => Vec3::ZERO,
+        len => indices.iter().map(|v| position(*v)).sum::<Vec3>() / len as f32,
+    };
+    let mut keyed: Vec<(f32, &[u32])> = clusters
+        .into_iter()
+        .map(|cluster| {
+            let (mut center, mut normal, mut area) = (Vec3::ZERO, Vec3::ZERO, 0.0);
+            for triangle in cluster.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| position(triangle[i]));
+                let cross = (b - a).cross(c - a);
+                let triangle_area = cross.length();
+                center += (a + b + c) / 3.0 * triangle_area;
+                normal += cross;
+                area += triangle_area;
+            }
+            let center = if area > 0.0 {
+                center / area
+            } else {
+                mesh_center
+            };
+            let key = (center - mesh_center).dot(normal.normalize_or_zero());
+            (key, cluster)
+        })
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    keyed
+        .into_iter()
+        .flat_map(|(_, cluster)| cluster)
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::PrimitiveTopology;
+
+    use super::*;
+
+    const CELLS: u32 = 16;
+
+    /// The triangles of a grid of `CELLS`×`CELLS` squares, shuffled.
+    fn grid() -> Vec<u32> {
+        let vertex = |x: u32, y: u32| y * (CELLS + 1) + x;
+        let triangles: Vec<[u32; 3]> = (0..CELLS * CELLS)
+            .flat_map(|cell| {
+                let (x, y) = (cell % CELLS, cell / CELLS);
+                let [a, b, c, d] = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+                let [a, b, c, d] = [a, b, c, d].map(|(x, y)| vertex(x, y));
+                [[a, b, c], [b, d, c]]
+            })
+            .collect();
+        // 101 is prime with the triangle count, this visits every triangle.
+        let count = triangles.len();
+        (0..count)
+            .flat_map(|i| triangles[i * 101 % count])
+            .collect()
+    }
+
+    /// `triangles` as a sorted list, each rotated to start
+    /// with its smallest vertex, which keeps its winding.
+    fn triangle_set(triangles: impl Iterator<Item = [u32; 3]>) -> Vec<[u32; 3]> {
+        let mut set: Vec<_> = triangles
+            .map(|mut triangle| {
+                let smallest = (0..3).min_by_key(|i| triangle[*i]).unwrap();
+                triangle.rotate_left(smallest);
+                triangle
+            })
+            .collect();
+        set.sort_unstable();
+        set
+    }
+    fn triangles(indices: &[u32]) -> impl Iterator<Item = [u32; 3]> + '_ {
+        indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]])
+    }
+
+    /// The average number of vertices missing a FIFO cache per triangle.
+    fn cache_misses(indices: &[u32]) -> f32 {
+        let mut cache = Vec::new();
+        let mut misses = 0;
+        for vertex in indices {
+            if !cache.contains(vertex) {
+                misses += 1;
+                if cache.len() == CACHE_SIZE {
+                    cache.remove(0);
+                }
+                cache.push(*vertex);
+            }
+        }
+        misses as f32 / (indices.len() / 3) as f32
+    }
+
+    #[test]
+    fn vertex_cache_order_keeps_triangles() {
+        let indices = grid();
+        let ordered = vertex_cache_order(&indices, ((CELLS + 1) * (CELLS + 1)) as usize);
+        assert_eq!(
+            triangle_set(triangles(&ordered)),
+            triangle_set(triangles(&indices))
+        );
+        let (before, after) = (cache_misses(&indices), cache_misses(&ordered));
+        assert!(
+            after < before * 0.5,
+            "{before} misses per triangle, {after} after"
+        );
+    }
+
+    #[test]
+    fn optimize_keeps_triangles() {
+        // One vertex per corner, as meshes are converted.
+        let indices = grid();
+        let position = |v: u32| [(v % (CELLS + 1)) as f32, (v / (CELLS + 1)) as f32, 0.0];
+        let positions: Vec<[f32; 3]> = indices.iter().map(|v| position(*v)).collect();
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_indices(Some(Indices::U32((0..indices.len() as u32).collect())));
+
+        let optimized = optimize(&mesh).unwrap();
+        assert_eq!(
+            optimized.count_vertices(),
+            ((CELLS + 1) * (CELLS + 1)) as usize
+        );
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            optimized.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("the positions should be kept");
+        };
+        // Identify the vertices by their grid index, to compare with the input.
+        let grid_index = |v: u32| {
+            let [x, y, _] = positions[v as usize];
+            y as u32 * (CELLS + 1) + x as u32
+        };
+        let Some(Indices::U32(optimized_indices)) = optimized.indices() else {
+            panic!("the indices should be u32");
+        };
+        let optimized_triangles = triangles(optimized_indices).map(|t| t.map(grid_index));
+        assert_eq!(
+            triangle_set(optimized_triangles),
+            triangle_set(triangles(&indices))
+        );
+    }
+}
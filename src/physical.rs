@@ -0,0 +1,161 @@
+//! Parameters of physically based DCC materials: Arnold `aiStandardSurface`
+//! (exported from Maya) and 3ds Max `Physical Material`.
+//!
+//! Both are exported as FBX materials with an unknown shading model,
+//! their parameters stored as vendor-prefixed properties.
+
+use bevy::prelude::Color;
+use fbxcel_dom::v7400::object::material::MaterialHandle;
+use rgb::RGB;
+
+use crate::utils::fbx_extend::{MaterialHandleQuickPropsExt, ObjectPropertiesExt};
+
+/// Maya type id of Arnold's `aiStandardSurface`.
+const AI_STANDARD_SURFACE_TYPE_ID: i32 = 1138001;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Vendor {
+    Arnold,
+    MaxPhysical,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Param {
+    BaseWeight,
+    BaseColor,
+    Metalness,
+    Roughness,
+    Ior,
+    Transmission,
+    TransmissionColor,
+    ThinWalled,
+    ThinFilmThickness,
+    ThinFilmIor,
+    Emission,
+    EmissionColor,
+    Coat,
+    CoatColor,
+    CoatRoughness,
+    CoatIor,
+    Sheen,
+    SheenColor,
+    SheenRoughness,
+}
+impl Param {
+    pub(crate) fn name(self, vendor: Vendor) -> Option<&'static str> {
+        use Param::*;
+        let name = match vendor {
+            Vendor::Arnold => match self {
+                BaseWeight => "Maya|base",
+                BaseColor => "Maya|baseColor",
+                Metalness => "Maya|metalness",
+                Roughness => "Maya|specularRoughness",
+                Ior => "Maya|specularIOR",
+                Transmission => "Maya|transmission",
+                TransmissionColor => "Maya|transmissionColor",
+                ThinWalled => "Maya|thinWalled",
+                ThinFilmThickness => "Maya|thinFilmThickness",
+                ThinFilmIor => "Maya|thinFilmIOR",
+                Emission => "Maya|emission",
+                EmissionColor => "Maya|emissionColor",
+                Coat => "Maya|coat",
+                CoatColor => "Maya|coatColor",
+                CoatRoughness => "Maya|coatRoughness",
+                CoatIor => "Maya|coatIOR",
+                Sheen => "Maya|sheen",
+                SheenColor => "Maya|sheenColor",
+                SheenRoughness => "Maya|sheenRoughness",
+            },
+            Vendor::MaxPhysical => match self {
+                BaseWeight => "3dsMax|Parameters|base_weight",
+                BaseColor => "3dsMax|Parameters|base_color",
+                Metalness => "3dsMax|Parameters|metalness",
+                Roughness => "3dsMax|Parameters|roughness",
+                Ior => "3dsMax|Parameters|trans_ior",
+                Transmission => "3dsMax|Parameters|transparency",
+                TransmissionColor => "3dsMax|Parameters|trans_color",
+                ThinWalled => "3dsMax|Parameters|thin_walled",
+                ThinFilmThickness | ThinFilmIor => return None,
+                Emission => "3dsMax|Parameters|emission",
+                EmissionColor => "3dsMax|Parameters|emit_color",
+                Coat => "3dsMax|Parameters|coating",
+                CoatColor => "3dsMax|Parameters|coat_color",
+                CoatRoughness => "3dsMax|Parameters|coat_roughness",
+                CoatIor => "3dsMax|Parameters|coat_ior",
+                Sheen => "3dsMax|Parameters|sheen",
+                SheenColor => "3dsMax|Parameters|sheen_color",
+                SheenRoughness => "3dsMax|Parameters|sheen_roughness",
+            },
+        };
+        Some(name)
+    }
+}
+
+/// The parameters of a physically based material.
+#[derive(Clone, Copy)]
+pub(crate) struct Physical<'a> {
+    material: MaterialHandle<'a>,
+    pub(crate) vendor: Vendor,
+}
+impl<'a> Physical<'a> {
+    /// The physical parameters of `material`, `None` if it isn't
+    /// an Arnold or 3ds Max physical material.
+    pub(crate) fn new(material: MaterialHandle<'a>) -> Option<Self> {
+        let vendor = if material.get_i32("Maya|TypeId") == Some(AI_STANDARD_SURFACE_TYPE_ID) {
+            Vendor::Arnold
+        } else if material
+            .properties()
+            .get_property("3dsMax|Parameters|base_color")
+            .is_some()
+        {
+            Vendor::MaxPhysical
+        } else {
+            return None;
+        };
+        Some(Physical { material, vendor })
+    }
+
+    /// Whether the material has the property of `param`.
+    pub(crate) fn has(&self, param: Param) -> bool {
+        let Some(name) = param.name(self.vendor) else {
+            return false;
+        };
+        self.material.properties().get_property(name).is_some()
+    }
+
+    pub(crate) fn f32(&self, param: Param) -> Option<f32> {
+        let name = param.name(self.vendor)?;
+        let properties = self.material.properties();
+        properties
+            .get::<f32>(name)
+            .or_else(|| properties.get::<f64>(name).map(|value| value as f32))
+    }
+
+    pub(crate) fn bool(&self, param: Param) -> Option<bool> {
+        let name = param.name(self.vendor)?;
+        let properties = self.material.properties();
+        properties
+            .get::<bool>(name)
+            .or_else(|| properties.get::<i32>(name).map(|value| value != 0))
+    }
+
+    pub(crate) fn color(&self, param: Param) -> Option<Color> {
+        let name = param.name(self.vendor)?;
+        let properties = self.material.properties();
+        let RGB { r, g, b } = properties.get::<RGB<f64>>(name).or_else(|| {
+            properties
+                .get::<RGB<f32>>(name)
+                .map(|RGB { r, g, b }| RGB::new(r.into(), g.into(), b.into()))
+        })?;
+        Some(Color::rgb(r as f32, g as f32, b as f32))
+    }
+}
+
+/// The reflectance of bevy's `StandardMaterial` for an index of refraction.
+///
+/// Bevy maps `reflectance` to a specular intensity at normal incidence
+/// of `0.16 * reflectance²`, FBX materials store the IOR instead.
+pub(crate) fn ior_to_reflectance(ior: f32) -> f32 {
+    let f0 = ((ior - 1.0) / (ior + 1.0)).powi(2);
+    (f0 / 0.16).sqrt().clamp(0.0, 1.0)
+}
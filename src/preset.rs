@@ -0,0 +1,94 @@
+//! Loader settings versioned with the FBX files,
+//! see [`FbxLoaderSettings::import_presets`].
+//!
+//! [`FbxLoaderSettings::import_presets`]: crate::FbxLoaderSettings::import_presets
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{memory::LoadTarget, FbxLoaderSettings, FbxNodeFilter, FbxTakeSlice};
+
+/// The settings of a single FBX file, read from a RON file next to it,
+/// see [`FbxLoaderSettings::import_presets`].
+///
+/// ```ron
+/// (
+///     scale: Some(0.5),
+///     takes: Some([(name: "Take 001", start: 0, end: 30, rename: Some("Idle"))]),
+///     exclude_nodes: Some([Name("Camera*"), Property(name: "editor_only", value: Bool(true))]),
+///     material_overrides: {"Glass": "materials/shared.fbx#FbxMaterial@Glass"},
+/// )
+/// ```
+///
+/// Fields left out keep the value of the settings of the app.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FbxImportPreset {
+    /// Replaces [`FbxLoaderSettings::scale`].
+    pub scale: Option<f32>,
+    /// Replaces [`FbxLoaderSettings::takes`].
+    pub takes: Option<Vec<FbxTakeSlice>>,
+    /// Replaces [`FbxLoaderSettings::include_nodes`].
+    pub include_nodes: Option<Vec<FbxNodeFilter>>,
+    /// Replaces [`FbxLoaderSettings::exclude_nodes`].
+    pub exclude_nodes: Option<Vec<FbxNodeFilter>>,
+    /// Replaces [`FbxLoaderSettings::frozen_nodes`].
+    pub frozen_nodes: Option<Vec<FbxNodeFilter>>,
+    /// Added to [`FbxLoaderSettings::material_overrides`], replacing
+    /// the overrides of the same materials.
+    pub material_overrides: HashMap<String, String>,
+}
+impl FbxImportPreset {
+    /// Parse the RON text of a preset file.
+    pub fn from_ron(text: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(text)?)
+    }
+
+    /// Override `settings` with the fields set in this preset.
+    pub fn apply(&self, settings: &mut FbxLoaderSettings) {
+        if let Some(scale) = self.scale {
+            settings.scale = scale;
+        }
+        if let Some(takes) = &self.takes {
+            settings.takes = takes.clone();
+        }
+        if let Some(include_nodes) = &self.include_nodes {
+            settings.include_nodes = include_nodes.clone();
+        }
+        if let Some(exclude_nodes) = &self.exclude_nodes {
+            settings.exclude_nodes = exclude_nodes.clone();
+        }
+        if let Some(frozen_nodes) = &self.frozen_nodes {
+            settings.frozen_nodes = frozen_nodes.clone();
+        }
+        let overrides = self.material_overrides.iter();
+        let overrides = overrides.map(|(name, path)| (name.clone(), path.clone()));
+        settings.material_overrides.extend(overrides);
+    }
+
+    /// The paths the preset of the FBX file `path` is read from, in order
+    /// of preference: `hero.fbx.meta`, then `hero.fbximport`.
+    pub(crate) fn paths(path: &Path) -> [PathBuf; 2] {
+        let mut meta = path.as_os_str().to_owned();
+        meta.push(".meta");
+        [PathBuf::from(meta), path.with_extension("fbximport")]
+    }
+
+    /// The preset next to the file being loaded, if there is one.
+    pub(crate) async fn read(target: &LoadTarget<'_, '_>) -> anyhow::Result<Option<Self>> {
+        let paths = FbxImportPreset::paths(target.path());
+        let Some(path) = paths.iter().find(|path| target.asset_exists(path)) else {
+            return Ok(None);
+        };
+        let bytes = target.read_asset_bytes(path).await?;
+        let text = std::str::from_utf8(&bytes).with_context(|| format!("invalid {path:?}"))?;
+        let preset =
+            FbxImportPreset::from_ron(text).with_context(|| format!("invalid {path:?}"))?;
+        Ok(Some(preset))
+    }
+}
@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::{warn, EventWriter, HandleUntyped, Res, Resource};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    cancel::{FbxLoadCancellation, LoadCancelled},
+    FbxLoadWarning, FbxWarningCategory,
+};
+
+/// The step of the load of a FBX file, see [`FbxLoadProgress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FbxLoadStage {
+    /// Reading the FBX document.
+    Parsing,
+    /// Converting meshes, materials, textures, lights and cameras.
+    Objects,
+    /// Baking animation stacks, slices and poses.
+    Animations,
+    /// Building the bevy `Scene`.
+    Scene,
+    /// The `FbxScene` is ready.
+    Done,
+}
+
+/// Event sent while the `FbxLoader` loads a file, to show a loading bar.
+///
+/// Events are sent at the start of each stage, and before each object
+/// or animation is converted, so large files report progress regularly.
+#[derive(Debug, Clone)]
+pub struct FbxLoadProgress {
+    /// Path of the FBX file, relative to the asset folder.
+    pub path: PathBuf,
+    /// Progress of the whole load, from `0.0` to `1.0`.
+    pub progress: f32,
+    pub stage: FbxLoadStage,
+    /// Name of the object or animation being converted, if any.
+    pub object: Option<String>,
+}
+
+/// The kind of a sub-asset of a FBX file, see [`FbxAssetLoaded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FbxAssetKind {
+    /// A `FbxMesh`, with its bevy meshes.
+    Mesh,
+    /// An `Image`.
+    Texture,
+    /// A `StandardMaterial`.
+    Material,
+    /// A `FbxAnimationClip`.
+    Clip,
+}
+
+/// Event sent while the `FbxLoader` loads a file, as each mesh, texture,
+/// material or clip is converted.
+///
+/// Bevy adds the sub-assets to their `Assets` with the rest of the file,
+/// once it is loaded: use the handle to queue the work on the assets
+/// of the file as they are converted, such as prioritizing what
+/// to display first, the assets are available with the `FbxScene`.
+#[derive(Debug, Clone)]
+pub struct FbxAssetLoaded {
+    /// Path of the FBX file, relative to the asset folder.
+    pub path: PathBuf,
+    pub kind: FbxAssetKind,
+    /// Label of the sub-asset, as in `"file.fbx#label"` asset paths.
+    pub label: String,
+    pub handle: HandleUntyped,
+}
+
+/// The events sent from the asset loading tasks.
+pub(crate) enum ProgressMessage {
+    Progress(FbxLoadProgress),
+    AssetLoaded(FbxAssetLoaded),
+    Warning(FbxLoadWarning),
+}
+
+/// Channel of the progress events, sent from the asset loading tasks.
+#[derive(Resource)]
+pub(crate) struct ProgressChannel {
+    pub(crate) sender: Sender<ProgressMessage>,
+    receiver: Receiver<ProgressMessage>,
+}
+impl Default for ProgressChannel {
+    fn default() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        ProgressChannel { sender, receiver }
+    }
+}
+
+/// Reports the progress of the load of a single file, and whether it was cancelled.
+pub(crate) struct Progress {
+    sender: Option<Sender<ProgressMessage>>,
+    cancellation: Option<FbxLoadCancellation>,
+    path: PathBuf,
+}
+impl Progress {
+    pub(crate) fn new(
+        sender: Option<Sender<ProgressMessage>>,
+        cancellation: Option<FbxLoadCancellation>,
+        path: PathBuf,
+    ) -> Self {
+        if let Some(cancellation) = &cancellation {
+            cancellation.start(&path);
+        }
+        Progress {
+            sender,
+            cancellation,
+            path,
+        }
+    }
+
+    /// Fails if the load was cancelled with [`FbxLoadCancellation::cancel`].
+    pub(crate) fn check_cancelled(&self) -> Result<(), LoadCancelled> {
+        match &self.cancellation {
+            Some(cancellation) => cancellation.check(&self.path),
+            None => Ok(()),
+        }
+    }
+
+    /// Path of the FBX file, relative to the asset folder.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Report `done` out of `total` steps of `stage`.
+    pub(crate) fn report(
+        &self,
+        stage: FbxLoadStage,
+        done: usize,
+        total: usize,
+        object: Option<&str>,
+    ) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let (start, end) = stage_span(stage);
+        let fraction = if total == 0 {
+            0.0
+        } else {
+            done as f32 / total as f32
+        };
+        // The receiver lives as long as the app, ignore errors on exit.
+        let _ = sender.send(ProgressMessage::Progress(FbxLoadProgress {
+            path: self.path.clone(),
+            progress: start + (end - start) * fraction,
+            stage,
+            object: object.map(str::to_owned),
+        }));
+    }
+
+    /// Report that the sub-asset `label` was converted.
+    pub(crate) fn asset_loaded(&self, kind: FbxAssetKind, label: &str, handle: HandleUntyped) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let _ = sender.send(ProgressMessage::AssetLoaded(FbxAssetLoaded {
+            path: self.path.clone(),
+            kind,
+            label: label.to_owned(),
+            handle,
+        }));
+    }
+
+    /// Log the warning `message`, and report it as a [`FbxLoadWarning`].
+    pub(crate) fn warn(
+        &self,
+        category: FbxWarningCategory,
+        object: Option<&str>,
+        message: String,
+        suggestion: Option<&str>,
+    ) {
+        warn!("{message}");
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let _ = sender.send(ProgressMessage::Warning(FbxLoadWarning {
+            path: self.path.clone(),
+            category,
+            object: object.map(str::to_owned),
+            message,
+            suggestion: suggestion.map(str::to_owned),
+        }));
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Some(cancellation) = &self.cancellation {
+            cancellation.finish(&self.path);
+        }
+    }
+}
+
+/// The share of the whole load taken by `stage`, roughly.
+fn stage_span(stage: FbxLoadStage) -> (f32, f32) {
+    match stage {
+        FbxLoadStage::Parsing => (0.0, 0.1),
+        FbxLoadStage::Objects => (0.1, 0.75),
+        FbxLoadStage::Animations => (0.75, 0.95),
+        FbxLoadStage::Scene => (0.95, 1.0),
+        FbxLoadStage::Done => (1.0, 1.0),
+    }
+}
+
+pub(crate) fn send_progress_events(
+    channel: Res<ProgressChannel>,
+    mut progress_events: EventWriter<FbxLoadProgress>,
+    mut asset_events: EventWriter<FbxAssetLoaded>,
+    mut warning_events: EventWriter<FbxLoadWarning>,
+) {
+    for message in channel.receiver.try_iter() {
+        match message {
+            ProgressMessage::Progress(event) => progress_events.send(event),
+            ProgressMessage::AssetLoaded(event) => asset_events.send(event),
+            ProgressMessage::Warning(event) => warning_events.send(event),
+        }
+    }
+}
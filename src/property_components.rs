@@ -0,0 +1,88 @@
+//! Components inserted on spawned FBX nodes from their user-defined properties.
+//!
+//! Register once, in the [`FbxPropertyComponents`] resource, the component a
+//! property stands for. Artists then place lights, triggers or spawn points
+//! by adding properties to nodes in their DCC tool, like in a level editor.
+
+use std::sync::Arc;
+
+use bevy::{
+    ecs::system::EntityCommands,
+    prelude::{warn, Added, Commands, Component, Entity, Query, Res, Resource},
+};
+
+use crate::{FbxExtras, FbxPropertyValue};
+
+/// Called on the entity of a node with the value of the mapped property.
+pub type FbxPropertyMapping = Arc<dyn Fn(&FbxPropertyValue, &mut EntityCommands) + Send + Sync>;
+
+/// Resource of the components inserted on the entities of the nodes with
+/// some user-defined properties, see [`FbxExtras`].
+///
+/// ```ignore
+/// app.world
+///     .resource_mut::<FbxPropertyComponents>()
+///     .component("pointlight_radius", |value| match value {
+///         FbxPropertyValue::Float(radius) => Some(PointLightRadius(*radius)),
+///         _ => None,
+///     });
+/// ```
+///
+/// All the mappings of the properties of a node are applied, in the order
+/// they were added, when the node is spawned. Scenes already spawned are
+/// not affected by later changes.
+#[derive(Resource, Clone, Default)]
+pub struct FbxPropertyComponents {
+    mappings: Vec<(String, FbxPropertyMapping)>,
+}
+impl FbxPropertyComponents {
+    /// Call `insert` on the entities of the nodes with the user-defined
+    /// property `name`, with the value of the property.
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        insert: impl Fn(&FbxPropertyValue, &mut EntityCommands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.mappings.push((name.into(), Arc::new(insert)));
+        self
+    }
+
+    /// Insert the component `component` makes from the value of the
+    /// user-defined property `name`.
+    ///
+    /// Values it returns `None` for, such as values of another type,
+    /// are skipped with a warning.
+    pub fn component<C: Component>(
+        &mut self,
+        name: impl Into<String>,
+        component: impl Fn(&FbxPropertyValue) -> Option<C> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let name = name.into();
+        let property = name.clone();
+        self.add(name, move |value, entity| match component(value) {
+            Some(component) => {
+                entity.insert(component);
+            }
+            None => warn!("Can't map the value {value:?} of property {property} to a component"),
+        })
+    }
+}
+
+/// Apply the [`FbxPropertyComponents`] to newly spawned nodes.
+pub(crate) fn apply_property_components(
+    mut commands: Commands,
+    components: Res<FbxPropertyComponents>,
+    nodes: Query<(Entity, &FbxExtras), Added<FbxExtras>>,
+) {
+    if components.mappings.is_empty() {
+        return;
+    }
+    for (entity, extras) in &nodes {
+        let mut entity_commands = commands.entity(entity);
+        for (name, insert) in &components.mappings {
+            if let Some(value) = extras.properties.get(name) {
+                insert(value, &mut entity_commands);
+            }
+        }
+    }
+}
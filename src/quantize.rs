@@ -0,0 +1,121 @@
+//! Compact vertex attribute formats, see [`FbxLoaderSettings::vertex_formats`].
+//!
+//! [`FbxLoaderSettings::vertex_formats`]: crate::FbxLoaderSettings::vertex_formats
+
+use bevy::{
+    prelude::Mesh,
+    render::{
+        mesh::{MeshVertexAttribute, VertexAttributeValues},
+        render_resource::VertexFormat,
+    },
+};
+
+use crate::{
+    progress::Progress, FbxDirectionFormat, FbxUvFormat, FbxVertexFormats, FbxWarningCategory,
+};
+
+/// Store the normals, tangents and UVs of `mesh` in `formats`.
+///
+/// Attributes the formats can't hold keep their format, with a warning
+/// naming the mesh `label`.
+pub(crate) fn quantize(
+    mesh: &mut Mesh,
+    formats: &FbxVertexFormats,
+    label: &str,
+    progress: &Progress,
+) {
+    let directions = [
+        (Mesh::ATTRIBUTE_NORMAL, formats.normals),
+        (Mesh::ATTRIBUTE_TANGENT, formats.tangents),
+    ];
+    for (attribute, format) in directions {
+        // Normals are padded to four components, there are no three-component
+        // normalized formats.
+        let values: Vec<[f32; 4]> = match mesh.attribute(attribute.id) {
+            Some(VertexAttributeValues::Float32x3(values)) => {
+                values.iter().map(|[x, y, z]| [*x, *y, *z, 0.0]).collect()
+            }
+            Some(VertexAttributeValues::Float32x4(values)) => values.clone(),
+            _ => continue,
+        };
+        let quantized = match format {
+            FbxDirectionFormat::Float32 => continue,
+            FbxDirectionFormat::Snorm16 => {
+                let to_snorm = |v| snorm(v, i16::MAX as f32) as i16;
+                VertexAttributeValues::Snorm16x4(values.iter().map(|v| v.map(to_snorm)).collect())
+            }
+            FbxDirectionFormat::Snorm8 => {
+                let to_snorm = |v| snorm(v, i8::MAX as f32) as i8;
+                VertexAttributeValues::Snorm8x4(values.iter().map(|v| v.map(to_snorm)).collect())
+            }
+        };
+        insert(mesh, attribute, quantized);
+    }
+    if formats.uvs == FbxUvFormat::Unorm16 {
+        if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            // Tiling UVs wrap around textures, normalized integers can't.
+            if uvs.iter().flatten().all(|v| (0.0..=1.0).contains(v)) {
+                let to_unorm = |v: f32| (v * u16::MAX as f32).round() as u16;
+                let uvs = uvs.iter().map(|uv| uv.map(to_unorm)).collect();
+                insert(
+                    mesh,
+                    Mesh::ATTRIBUTE_UV_0,
+                    VertexAttributeValues::Unorm16x2(uvs),
+                );
+            } else {
+                progress.warn(
+                    FbxWarningCategory::Mesh,
+                    Some(label),
+                    format!("UVs of {label} are outside of 0..1, keeping them as f32"),
+                    Some("Use FbxUvFormat::Float32 for meshes with tiling UVs"),
+                );
+            }
+        }
+    }
+}
+
+/// The values of an attribute as `f32`, whatever their format,
+/// with the first `N` components of each value.
+///
+/// `None` for integer attributes, and for values with less than `N` components.
+pub(crate) fn dequantized<const N: usize>(values: &VertexAttributeValues) -> Option<Vec<[f32; N]>> {
+    fn first<T: Copy, const M: usize, const N: usize>(
+        values: &[[T; M]],
+        to_f32: impl Fn(T) -> f32,
+    ) -> Option<Vec<[f32; N]>> {
+        let first = |value: &[T; M]| std::array::from_fn(|i| to_f32(value[i]));
+        (N <= M).then(|| values.iter().map(first).collect())
+    }
+    let snorm16 = |v: i16| (v as f32 / i16::MAX as f32).max(-1.0);
+    let snorm8 = |v: i8| (v as f32 / i8::MAX as f32).max(-1.0);
+    let unorm16 = |v: u16| v as f32 / u16::MAX as f32;
+    match values {
+        VertexAttributeValues::Float32x2(values) => first(values, |v| v),
+        VertexAttributeValues::Float32x3(values) => first(values, |v| v),
+        VertexAttributeValues::Float32x4(values) => first(values, |v| v),
+        VertexAttributeValues::Snorm16x2(values) => first(values, snorm16),
+        VertexAttributeValues::Snorm16x4(values) => first(values, snorm16),
+        VertexAttributeValues::Snorm8x2(values) => first(values, snorm8),
+        VertexAttributeValues::Snorm8x4(values) => first(values, snorm8),
+        VertexAttributeValues::Unorm16x2(values) => first(values, unorm16),
+        VertexAttributeValues::Unorm16x4(values) => first(values, unorm16),
+        _ => None,
+    }
+}
+
+/// `value` as a signed-normalized integer up to `max`, from -1 to 1.
+fn snorm(value: f32, max: f32) -> f32 {
+    (value.clamp(-1.0, 1.0) * max).round()
+}
+
+/// Replace `attribute` of `mesh` by `values`, in their format.
+///
+/// The attribute keeps its id, which shaders bind it by: the vertex buffer
+/// layout of bevy meshes follows the format of their attributes.
+fn insert(mesh: &mut Mesh, attribute: MeshVertexAttribute, values: VertexAttributeValues) {
+    let attribute = MeshVertexAttribute {
+        format: VertexFormat::from(&values),
+        ..attribute
+    };
+    mesh.insert_attribute(attribute, values);
+}
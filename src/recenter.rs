@@ -0,0 +1,92 @@
+//! Scenes moved to the origin at import, see [`FbxLoaderSettings::recenter`].
+//!
+//! [`FbxLoaderSettings::recenter`]: crate::FbxLoaderSettings::recenter
+
+use std::borrow::Cow;
+
+use bevy::{math::DVec3, utils::HashSet};
+use fbxcel_dom::v7400::object::{
+    geometry::MeshHandle,
+    model::{ModelHandle, TypedModelHandle},
+    TypedObjectHandle,
+};
+
+use crate::fbx_transform::FbxTransform;
+
+/// Distance from their node, in file units, past which the vertices of a mesh
+/// are moved closer to it, `f32` values being precise to less than a hundredth
+/// of a unit below it.
+const FAR: f64 = 65536.0;
+
+/// The `Vertices` array of `geometry`.
+pub(crate) fn control_points(geometry: MeshHandle<'_>) -> Option<&[f64]> {
+    let vertices = geometry.node().first_child_by_name("Vertices")?;
+    vertices.attributes().first()?.get_arr_f64()
+}
+
+/// The center of the bounding box of the meshes under `roots`, in the
+/// space of the file, rounded with [`snap`]. `None` without meshes.
+pub(crate) fn scene_center(roots: &[ModelHandle]) -> Option<DVec3> {
+    let mut bounds: Option<(DVec3, DVec3)> = None;
+    let mut visited = HashSet::new();
+    let mut stack: Vec<(ModelHandle, Option<FbxTransform>)> =
+        roots.iter().map(|root| (*root, None)).collect();
+    while let Some((node, parent)) = stack.pop() {
+        if !visited.insert(node.object_id()) {
+            continue;
+        }
+        let transform = FbxTransform::from_node(node, parent);
+        if let TypedObjectHandle::Model(TypedModelHandle::Mesh(mesh)) = node.get_typed() {
+            let global = transform.global.as_dmat4();
+            let points = mesh.geometry().ok().and_then(control_points);
+            for point in points.unwrap_or_default().chunks_exact(3) {
+                let point = global.transform_point3(DVec3::from_slice(point));
+                bounds = Some(match bounds {
+                    Some((min, max)) => (min.min(point), max.max(point)),
+                    None => (point, point),
+                });
+            }
+        }
+        let children = node.child_models().map(|child| (*child, Some(transform)));
+        stack.extend(children);
+    }
+    bounds.map(|(min, max)| snap((min + max) / 2.0))
+}
+
+/// The position the vertices `points` of a mesh far from its node are moved
+/// from, the center of their bounding box rounded with [`snap`].
+///
+/// `None` for meshes near their node.
+pub(crate) fn mesh_offset(points: &[f64]) -> Option<DVec3> {
+    let mut points = points.chunks_exact(3).map(DVec3::from_slice);
+    let first = points.next()?;
+    let (min, max) = points.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+    let center = snap((min + max) / 2.0);
+    (center.abs().max_element() > FAR).then_some(center)
+}
+
+/// `points` moved by `-offset`, the difference being taken in `f64`.
+pub(crate) fn offset_points(points: &[f64], offset: Option<DVec3>) -> Cow<'_, [f64]> {
+    match offset {
+        Some(offset) => {
+            let offset = offset.to_array();
+            let moved = points.iter().enumerate().map(|(i, v)| v - offset[i % 3]);
+            Cow::Owned(moved.collect())
+        }
+        None => Cow::Borrowed(points),
+    }
+}
+
+/// `point` rounded to a multiple of a power of two, large enough for the
+/// result to be exact in `f32`.
+///
+/// The sums and differences of the rounded values, such as the translation
+/// of a node and the offset of its mesh, are then exact in `f32` too.
+pub(crate) fn snap(point: DVec3) -> DVec3 {
+    let snap = |value: f64| {
+        // The 24 bits of the `f32` mantissa, minus one for the sums.
+        let step = (value.abs().log2().ceil() - 23.0).max(0.0).exp2();
+        (value / step).round() * step
+    };
+    DVec3::new(snap(point.x), snap(point.y), snap(point.z))
+}
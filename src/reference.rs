@@ -0,0 +1,90 @@
+//! References to external FBX documents, such as 3ds Max containers or
+//! files exported with references to other files.
+//!
+//! See [`FbxScene::external_references`] and
+//! [`FbxLoaderSettings::resolve_references`].
+//!
+//! [`FbxScene::external_references`]: crate::FbxScene::external_references
+//! [`FbxLoaderSettings::resolve_references`]: crate::FbxLoaderSettings::resolve_references
+
+use std::path::{Path, PathBuf};
+
+use bevy::utils::HashSet;
+use fbxcel_dom::{fbxcel::tree::v7400::NodeHandle, v7400::Document};
+
+/// An external FBX document referenced by a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FbxExternalReference {
+    /// The name of the reference or of the container holding it.
+    pub name: Option<String>,
+    /// The path of the referenced document, as written in the file.
+    pub path: String,
+    /// The referenced document in the asset folder, if it was found
+    /// with [`FbxLoaderSettings::resolve_references`]. Its scene is then
+    /// spawned alongside the scene of the referencing file.
+    ///
+    /// [`FbxLoaderSettings::resolve_references`]: crate::FbxLoaderSettings::resolve_references
+    pub resolved: Option<PathBuf>,
+}
+impl FbxExternalReference {
+    /// The external documents referenced by `doc`, in the `References`
+    /// section of the file or by `Container` objects.
+    pub(crate) fn all(doc: &Document) -> Vec<Self> {
+        let mut references = Vec::new();
+        let root = doc.tree().root();
+        if let Some(section) = root.first_child_by_name("References") {
+            for reference in section.children() {
+                let name = reference.attributes().first().and_then(|a| a.get_string());
+                if let Some(path) = fbx_path(reference) {
+                    references.push(FbxExternalReference {
+                        name: name.filter(|n| !n.is_empty()).map(Into::into),
+                        path,
+                        resolved: None,
+                    });
+                }
+            }
+        }
+        for container in doc.objects().filter(|obj| obj.node().name() == "Container") {
+            if let Some(path) = fbx_path(container.node()) {
+                references.push(FbxExternalReference {
+                    name: container.name().filter(|n| !n.is_empty()).map(Into::into),
+                    path,
+                    resolved: None,
+                });
+            }
+        }
+        let mut seen = HashSet::new();
+        references.retain(|reference| seen.insert(reference.path.clone()));
+        references
+    }
+
+    /// The places in the asset folder where the referenced document is
+    /// looked for, by order of preference: at its relative path from
+    /// `file`, then by file name next to `file` and in each of `folders`.
+    pub(crate) fn candidates(&self, file: &Path, folders: &[PathBuf]) -> Vec<PathBuf> {
+        let path = PathBuf::from(self.path.replace('\\', "/"));
+        let parent = file.parent().unwrap_or(Path::new(""));
+        let mut candidates = Vec::new();
+        if path.is_relative() {
+            candidates.push(parent.join(&path));
+        }
+        if let Some(file_name) = path.file_name() {
+            candidates.push(parent.join(file_name));
+            candidates.extend(folders.iter().map(|folder| folder.join(file_name)));
+        }
+        candidates.retain(|candidate| candidate != file);
+        candidates
+    }
+}
+
+/// The first path to a FBX file among the string attributes
+/// of `node` and its descendants.
+fn fbx_path(node: NodeHandle) -> Option<String> {
+    let is_fbx = |s: &&str| s.to_ascii_lowercase().ends_with(".fbx");
+    node.attributes()
+        .iter()
+        .filter_map(|attribute| attribute.get_string())
+        .find(is_fbx)
+        .map(Into::into)
+        .or_else(|| node.children().find_map(fbx_path))
+}
@@ -0,0 +1,16 @@
+//! Marker of the root entity of spawned FBX scenes.
+
+use bevy::prelude::{Component, Reflect, ReflectComponent};
+
+/// The root entity of a spawned FBX scene.
+///
+/// Query this to find the FBX scenes spawned in the world, its parent is
+/// the entity holding the `Handle<Scene>`. Its name is set by
+/// [`FbxLoaderSettings::root_name`](crate::FbxLoaderSettings::root_name).
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct FbxSceneRoot {
+    /// The asset path of the FBX file, empty for files loaded
+    /// with [`FbxLoader::load_from_bytes`](crate::FbxLoader::load_from_bytes).
+    pub path: String,
+}
@@ -0,0 +1,991 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::Resource, utils::HashMap};
+use serde::Deserialize;
+
+use crate::{FbxCollisionShape, FbxNodeFilter};
+
+/// Resource to control how the `FbxLoader` converts FBX files.
+///
+/// Like [`FbxMaterialLoaders`], you should insert this as a resource
+/// **before** adding the `FbxPlugin` to the app,
+/// as the loader reads it only once when it is created.
+///
+/// [`FbxMaterialLoaders`]: crate::FbxMaterialLoaders
+#[derive(Clone, Debug, Resource)]
+pub struct FbxLoaderSettings {
+    /// Spawn an emissive mesh and a light for each FBX area light.
+    ///
+    /// Area lights are always spawned with a [`FbxAreaLight`] component,
+    /// this only controls whether we attempt to approximate them.
+    /// Defaults to `false`.
+    ///
+    /// [`FbxAreaLight`]: crate::FbxAreaLight
+    pub approximate_area_lights: bool,
+
+    /// Set the [`AmbientLight`] color to the FBX scene's ambient color
+    /// when spawning the scene.
+    ///
+    /// The ambient color is always available in [`FbxScene::ambient_color`],
+    /// this only controls whether the [`AmbientLight`] resource is modified.
+    /// Defaults to `false`.
+    ///
+    /// [`AmbientLight`]: bevy::pbr::AmbientLight
+    /// [`FbxScene::ambient_color`]: crate::FbxScene::ambient_color
+    pub apply_ambient_light: bool,
+
+    /// Shift the clips so they start at time zero.
+    ///
+    /// Takes may start at a negative time or long after zero, keeping the
+    /// timeline times gives clips with dead time at their start. The original
+    /// span of each clip is in [`FbxTakeMetadata::original_span`].
+    /// Defaults to `true`.
+    ///
+    /// [`FbxTakeMetadata::original_span`]: crate::FbxTakeMetadata::original_span
+    pub normalize_clip_start: bool,
+
+    /// Additional clips to cut out of the FBX takes.
+    ///
+    /// Long takes, such as motion capture sessions, often contain several
+    /// animations, each [`FbxTakeSlice`] is imported as a separate clip
+    /// next to the full take.
+    /// Defaults to no slices.
+    pub takes: Vec<FbxTakeSlice>,
+
+    /// Additive versions of imported clips to generate, such as
+    /// hit reactions or aim offsets, see [`FbxAdditiveClip`].
+    /// Defaults to no additive clips.
+    pub additive_clips: Vec<FbxAdditiveClip>,
+
+    /// Mirrored versions of imported clips to generate, such as a
+    /// `StrafeRight` clip from `StrafeLeft`, see [`FbxMirroredClip`].
+    /// Defaults to no mirrored clips.
+    pub mirrored_clips: Vec<FbxMirroredClip>,
+
+    /// Add a [`FbxAnimationPlayer`] to the scene root, playing
+    /// and looping one of the imported clips.
+    /// Defaults to [`FbxAutoplay::Off`].
+    ///
+    /// [`FbxAnimationPlayer`]: crate::FbxAnimationPlayer
+    pub autoplay: FbxAutoplay,
+
+    /// Evaluate the FBX constraints (position, rotation, scale, parent,
+    /// aim and two-bone IK) at each frame of the imported clips.
+    ///
+    /// Without this, constrained nodes only follow their own curves,
+    /// which may differ a lot from what the animator saw.
+    /// Defaults to `false`.
+    pub bake_constraints: bool,
+
+    /// The rate the animation curves are sampled at, in samples per second.
+    ///
+    /// Lower rates make smaller clips, the player interpolates linearly
+    /// between samples. Defaults to `None`, the frame rate of the FBX scene.
+    pub sample_rate: Option<f64>,
+
+    /// Also sample the curves at the time of each of their keys, so that
+    /// keys between two samples, such as the peak of a bounce, aren't lost.
+    /// Defaults to `false`.
+    pub keep_original_keys: bool,
+
+    /// Drop the samples that interpolating between the samples around them
+    /// reproduces within this tolerance: in scene units for translations,
+    /// in radians for rotations and as a ratio for scales.
+    ///
+    /// Curves are usually smooth enough that a tolerance around `0.001`
+    /// divides the size of the clips several times.
+    /// Defaults to `0.0`, all the samples are kept.
+    pub tolerance: f32,
+
+    /// A glob pattern, such as `EVENT_*`, of the names of the nodes and
+    /// user-defined properties converted into [`FbxAnimationClip::events`].
+    ///
+    /// Each key of a matching node is an event, such as a null keyed at each
+    /// footstep. Matching animated properties give an event at each non-zero
+    /// key, so a property keyed to `1` then back to `0` gives one event.
+    /// The events are named after the node or property, without the text
+    /// around the wildcards: `EVENT_footstep` is `footstep` with `EVENT_*`.
+    /// The [`FbxAnimationPlayer`] sends an [`FbxAnimationEvent`] when it
+    /// reaches one. Defaults to `None`, no events.
+    ///
+    /// [`FbxAnimationClip::events`]: crate::FbxAnimationClip::events
+    /// [`FbxAnimationPlayer`]: crate::FbxAnimationPlayer
+    /// [`FbxAnimationEvent`]: crate::FbxAnimationEvent
+    pub event_pattern: Option<String>,
+
+    /// Whether to flip the V axis of texture coordinates.
+    ///
+    /// FBX puts the UV origin at the bottom left of textures, while Bevy
+    /// puts it at the top left, but some exporters already write flipped UVs,
+    /// which shows up as vertically flipped textures.
+    /// Defaults to [`FbxFlipUv::Auto`].
+    pub flip_uv_v: FbxFlipUv,
+
+    /// Number of Catmull-Clark subdivision levels applied to meshes at import.
+    ///
+    /// Edge and vertex creases of the meshes are respected, see
+    /// [`FbxSubdivisionData`]. Each level multiplies the polygon count by 4,
+    /// and vertex caches are not played on subdivided meshes.
+    /// Defaults to `0`, no subdivision.
+    ///
+    /// [`FbxSubdivisionData`]: crate::FbxSubdivisionData
+    pub subdivision_level: u32,
+
+    /// Reduce the triangle count of meshes at import.
+    ///
+    /// This can be overridden per node with the `SimplifyRatio` and
+    /// `SimplifyError` user-defined properties of the node holding the mesh,
+    /// which also enable simplification when this is `None`.
+    /// Defaults to `None`, no simplification.
+    pub simplification: Option<FbxSimplification>,
+
+    /// Split meshes using more vertices than this into several bevy meshes.
+    ///
+    /// Huge meshes, such as photogrammetry scans, are otherwise loaded as a
+    /// single vertex buffer, which is slow to upload and can't be culled
+    /// in parts. Each part only keeps the triangles and vertices it uses,
+    /// and is spawned as its own entity. Meshes with a vertex cache
+    /// are not split.
+    /// Defaults to `Some(1 << 20)`, `None` disables splitting.
+    pub max_mesh_vertices: Option<u32>,
+
+    /// Reorder the triangles and vertices of meshes for the GPU.
+    ///
+    /// Meshes are converted with a vertex per triangle corner, in the
+    /// polygon order of the file. This welds the identical vertices, orders
+    /// the triangles to reuse the vertices in the GPU vertex cache and to
+    /// draw the outer surfaces first, reducing overdraw, then orders the
+    /// vertices by first use. It speeds up the rendering of dense meshes,
+    /// for a longer load. Meshes with a vertex cache are kept as is.
+    /// Defaults to `false`.
+    pub optimize_meshes: bool,
+
+    /// Add a wireframe mesh of the original polygons of each mesh,
+    /// in [`FbxMesh::wireframe`].
+    ///
+    /// Rendering triangulated meshes as wireframe shows the triangulation
+    /// edges, this keeps the quads and n-gons as modeled, for design review.
+    /// The wireframe follows the polygons of the file, before subdivision
+    /// or simplification.
+    /// Defaults to `false`.
+    ///
+    /// [`FbxMesh::wireframe`]: crate::FbxMesh::wireframe
+    pub wireframe_meshes: bool,
+
+    /// The formats of the normals, tangents and UVs of the bevy meshes.
+    ///
+    /// Compact formats cut the GPU memory of meshes, for mobile and web
+    /// targets, at the cost of some precision.
+    /// Defaults to `f32` attributes, see [`FbxVertexFormats`].
+    pub vertex_formats: FbxVertexFormats,
+
+    /// Overrides of [`FbxLoaderSettings::vertex_formats`] for some meshes,
+    /// by FBX mesh node name.
+    /// Defaults to no overrides.
+    pub mesh_vertex_formats: HashMap<String, FbxVertexFormats>,
+
+    /// Folder where converted meshes, decoded textures and baked animations
+    /// are stored between runs.
+    ///
+    /// Entries are keyed by a hash of the FBX file content and of the
+    /// settings changing the conversion, so editing either invalidates them.
+    /// The FBX file is still parsed to build the hierarchy, but triangulation,
+    /// texture decoding and animation baking are skipped on cache hits. A
+    /// cache file is only written the first time a given file is loaded.
+    /// Defaults to `None`, disabling the cache.
+    pub cache_folder: Option<PathBuf>,
+
+    /// Read the settings of each file from a RON preset next to it, named
+    /// after the file: `hero.fbx.meta`, or else `hero.fbximport`. The preset
+    /// overrides these settings for that file, see [`FbxImportPreset`].
+    ///
+    /// Files loaded with [`FbxLoader::load_from_bytes`] have no folder,
+    /// use [`FbxImportPreset::apply`] on their settings instead.
+    /// Defaults to `true`.
+    ///
+    /// [`FbxImportPreset`]: crate::FbxImportPreset
+    /// [`FbxImportPreset::apply`]: crate::FbxImportPreset::apply
+    /// [`FbxLoader::load_from_bytes`]: crate::FbxLoader::load_from_bytes
+    pub import_presets: bool,
+
+    /// Import materials without diffuse color but with a strong emission,
+    /// such as UI panels and skydomes, as unlit.
+    ///
+    /// Artists can also force a material to be lit or unlit with a
+    /// `bevy_unlit` user-defined property on the material, which is
+    /// respected whatever this setting is.
+    /// Defaults to `false`.
+    pub detect_unlit_materials: bool,
+
+    /// What to do with the reflection maps of Phong materials,
+    /// which bevy's `StandardMaterial` has no slot for.
+    /// Defaults to [`FbxReflectionMap::Environment`].
+    pub reflection_maps: FbxReflectionMap,
+
+    /// The alpha mode of materials with an opacity texture.
+    ///
+    /// Opacity textures (the `TransparentColor` or `TransparencyFactor`
+    /// channels of Lambert and Phong materials) are composited into the
+    /// alpha channel of the base color texture at import, so cut-out
+    /// foliage and decals work without editing their textures.
+    /// Defaults to [`FbxOpacityMode::Auto`].
+    pub opacity_textures: FbxOpacityMode,
+
+    /// Whether the vertex colors of meshes tint their base color.
+    /// Defaults to [`FbxVertexColors::Tint`].
+    pub vertex_colors: FbxVertexColors,
+
+    /// Overrides of [`FbxLoaderSettings::vertex_colors`] for the meshes
+    /// using a material, by FBX material name.
+    /// Defaults to no overrides.
+    pub material_vertex_colors: HashMap<String, FbxVertexColors>,
+
+    /// Materials replaced by another material asset, by FBX material name,
+    /// such as `"materials/shared.fbx#FbxMaterial@Glass"`.
+    ///
+    /// The replaced materials are not loaded, the replacements are loaded
+    /// with the scene, with paths relative to the asset folder. Use
+    /// [`FbxMaterialOverrides`] to replace materials with custom materials.
+    /// Defaults to no overrides.
+    ///
+    /// [`FbxMaterialOverrides`]: crate::FbxMaterialOverrides
+    pub material_overrides: HashMap<String, String>,
+
+    /// Remove the namespaces of node and material names,
+    /// `char:body_geo` becoming `body_geo`.
+    ///
+    /// Whatever this is, control characters are removed from names, and
+    /// siblings with the same name get a `.1`, `.2`... suffix, so they can be
+    /// told apart by [`FbxEntityPath`]. Renamed nodes keep their name in the
+    /// file in [`FbxExtras::original_name`]. Names must still be valid UTF-8,
+    /// files with other encodings fail to load.
+    /// Defaults to `false`.
+    ///
+    /// [`FbxEntityPath`]: crate::FbxEntityPath
+    /// [`FbxExtras::original_name`]: crate::FbxExtras::original_name
+    pub strip_namespaces: bool,
+
+    /// Namespaces to rename, such as those left by Maya references,
+    /// by namespace without its trailing `:`.
+    ///
+    /// With `ns1:ns2` mapped to `hero`, `ns1:ns2:body_geo` becomes
+    /// `hero:body_geo`, an empty replacement removes the namespace.
+    /// The longest namespace of a name found in the map is replaced.
+    /// This applies to the names of nodes, joints, meshes and materials,
+    /// and so to the [`FbxEntityPath`]s animations target. It is applied
+    /// before [`FbxLoaderSettings::strip_namespaces`].
+    /// Defaults to no remapping.
+    ///
+    /// [`FbxEntityPath`]: crate::FbxEntityPath
+    pub namespace_remap: HashMap<String, String>,
+
+    /// The [`Name`] of the root entity of spawned scenes.
+    ///
+    /// The root entity also always has a [`FbxSceneRoot`] component
+    /// with the path of its file.
+    /// Defaults to [`FbxRootName::Default`].
+    ///
+    /// [`Name`]: bevy::core::Name
+    /// [`FbxSceneRoot`]: crate::FbxSceneRoot
+    pub root_name: FbxRootName,
+
+    /// Scale of the root entity of spawned scenes, on top of the conversion
+    /// of the unit of the file to meters.
+    /// Defaults to `1.0`.
+    pub scale: f32,
+
+    /// Scale of the intensity of imported lights.
+    ///
+    /// With `1.0`, a FBX light at its default intensity (100) lights a white
+    /// surface facing it as brightly as the light's color, which is how
+    /// DCC tools display it. For lights other than directional ones, this is
+    /// at the distance where the light starts decaying (`DecayStart`, or one
+    /// meter), they then follow the inverse square law like all bevy lights.
+    /// Raise this if your camera setup expects physical light units.
+    /// Defaults to `1.0`.
+    pub light_exposure: f32,
+
+    /// A glob pattern, such as `SOCKET_*`, of the names of the nodes used as
+    /// attachment sockets, `*` matching any characters and `?` one character.
+    /// Matching is case-sensitive.
+    ///
+    /// Matching nodes are always kept and get a [`FbxSocket`] component,
+    /// the placeholder mesh of the node, if any, is not loaded. Names are
+    /// matched after [`FbxLoaderSettings::strip_namespaces`] is applied.
+    /// Defaults to `None`, no sockets.
+    ///
+    /// [`FbxSocket`]: crate::FbxSocket
+    pub socket_pattern: Option<String>,
+
+    /// The naming conventions of game pipelines to follow, tagging collision
+    /// meshes, levels of detail and static or skeletal meshes by the name
+    /// of their node, see [`FbxNamingConventions`].
+    ///
+    /// Names are matched after [`FbxLoaderSettings::strip_namespaces`] is applied.
+    /// Defaults to `None`, node names have no meaning.
+    pub naming_conventions: Option<FbxNamingConventions>,
+
+    /// If not empty, only the nodes matching one of these filters are
+    /// imported, with their descendants and the ancestors they need.
+    /// Defaults to no filters, all the nodes are imported.
+    pub include_nodes: Vec<FbxNodeFilter>,
+
+    /// The nodes matching one of these filters are dropped at import,
+    /// with their descendants, such as reference rigs, blockout meshes or
+    /// review cameras. This takes precedence over
+    /// [`FbxLoaderSettings::include_nodes`].
+    /// Defaults to no filters.
+    pub exclude_nodes: Vec<FbxNodeFilter>,
+
+    /// The nodes matching one of these filters are detached from their
+    /// ancestors at import: they are spawned as children of the scene root,
+    /// with their world transform, and keep their descendants. Props can then
+    /// be moved to another parent at runtime without carrying the transforms
+    /// of the groups they were under in the DCC.
+    ///
+    /// Tag the nodes with a user-defined property, with
+    /// [`FbxNodeFilter::Property`], to freeze them from the DCC. Animations of
+    /// frozen nodes and their former ancestors are baked in world space.
+    /// Defaults to no filters.
+    pub frozen_nodes: Vec<FbxNodeFilter>,
+
+    /// Move the scene so that the center of its meshes is at the origin, for
+    /// files with coordinates too large for `f32`, such as survey and GIS data.
+    ///
+    /// The offset is removed in `f64` from the translation of the root nodes,
+    /// and the vertices of the meshes far from their node are moved closer to
+    /// it, keeping the precision `f32` loses far from the origin. The offset is
+    /// [`FbxScene::origin`], the offset of the vertices [`FbxMesh::offset`].
+    /// Defaults to `false`.
+    ///
+    /// [`FbxScene::origin`]: crate::FbxScene::origin
+    /// [`FbxMesh::offset`]: crate::FbxMesh::offset
+    pub recenter: bool,
+
+    /// Spawn the scenes without `Transform`s, with the `GlobalTransform`
+    /// of each entity computed at import, for environments that never move.
+    ///
+    /// Bevy then skips the scenes when propagating transforms each frame.
+    /// The scenes must be spawned under an entity at the origin, and their
+    /// entities can't be moved or animated.
+    /// Their root is marked with [`FbxStaticScene`].
+    /// Defaults to `false`.
+    ///
+    /// [`FbxStaticScene`]: crate::FbxStaticScene
+    pub static_scene: bool,
+
+    /// Fail loading files with broken connections between objects, instead
+    /// of skipping the broken connections with a warning.
+    ///
+    /// Some exporters write connections to objects missing from the file,
+    /// cycles between nodes or nodes with several parents.
+    /// Defaults to `false`.
+    pub strict_connections: bool,
+
+    /// Soft limits on the size of the loaded scenes, to keep shipped scenes
+    /// within performance targets.
+    ///
+    /// The loader warns about the files going over budget, or fails to load
+    /// them with [`FbxBudget::strict`].
+    /// Defaults to no limits.
+    pub budget: FbxBudget,
+
+    /// Load the textures of the file at a small proxy resolution once the
+    /// textures loaded at full resolution exceed a memory budget, and
+    /// stream their full resolution afterwards, for large scenes whose
+    /// textures don't fit in memory at first.
+    ///
+    /// The full resolution of each proxy is kept in
+    /// [`FbxScene::streamed_textures`] and streamed by the
+    /// [`FbxTextureStreamer`].
+    /// Defaults to `None`, loading all textures at full resolution.
+    ///
+    /// [`FbxScene::streamed_textures`]: crate::FbxScene::streamed_textures
+    /// [`FbxTextureStreamer`]: crate::FbxTextureStreamer
+    pub texture_streaming: Option<FbxTextureStreaming>,
+
+    /// How many FBX files are converted at the same time, and whether
+    /// their conversion gives way to the other tasks of the thread pool,
+    /// for loads in the background of gameplay.
+    /// Defaults to no limit, at normal priority.
+    pub tasks: FbxTaskSettings,
+
+    /// Stages of the conversion whose per-object messages are logged at
+    /// `info` level rather than `debug`, to follow a single stage, such as
+    /// the materials, of a large file without enabling all debug logs.
+    /// Defaults to no stages.
+    pub verbose_stages: Vec<FbxLogStage>,
+
+    /// Look for the external FBX documents referenced by the file, and spawn
+    /// their scene alongside the scene of the file.
+    ///
+    /// References are always listed in [`FbxScene::external_references`],
+    /// this only controls whether they are resolved. They are looked for
+    /// at their path relative to the file, then by file name next to the
+    /// file and in [`FbxLoaderSettings::reference_search_paths`].
+    /// Defaults to `false`.
+    ///
+    /// [`FbxScene::external_references`]: crate::FbxScene::external_references
+    pub resolve_references: bool,
+
+    /// Folders of the asset folder where referenced FBX documents are
+    /// looked for, see [`FbxLoaderSettings::resolve_references`].
+    /// Defaults to no folders.
+    pub reference_search_paths: Vec<PathBuf>,
+}
+impl Default for FbxLoaderSettings {
+    fn default() -> Self {
+        FbxLoaderSettings {
+            approximate_area_lights: false,
+            apply_ambient_light: false,
+            normalize_clip_start: true,
+            takes: Vec::new(),
+            additive_clips: Vec::new(),
+            mirrored_clips: Vec::new(),
+            autoplay: FbxAutoplay::default(),
+            bake_constraints: false,
+            sample_rate: None,
+            keep_original_keys: false,
+            tolerance: 0.0,
+            event_pattern: None,
+            flip_uv_v: FbxFlipUv::default(),
+            subdivision_level: 0,
+            simplification: None,
+            max_mesh_vertices: Some(1 << 20),
+            optimize_meshes: false,
+            wireframe_meshes: false,
+            vertex_formats: FbxVertexFormats::default(),
+            mesh_vertex_formats: HashMap::default(),
+            cache_folder: None,
+            import_presets: true,
+            detect_unlit_materials: false,
+            reflection_maps: FbxReflectionMap::default(),
+            opacity_textures: FbxOpacityMode::default(),
+            vertex_colors: FbxVertexColors::default(),
+            material_vertex_colors: HashMap::default(),
+            material_overrides: HashMap::default(),
+            strip_namespaces: false,
+            namespace_remap: HashMap::default(),
+            root_name: FbxRootName::default(),
+            scale: 1.0,
+            light_exposure: 1.0,
+            socket_pattern: None,
+            naming_conventions: None,
+            include_nodes: Vec::new(),
+            exclude_nodes: Vec::new(),
+            frozen_nodes: Vec::new(),
+            recenter: false,
+            static_scene: false,
+            strict_connections: false,
+            budget: FbxBudget::default(),
+            texture_streaming: None,
+            tasks: FbxTaskSettings::default(),
+            verbose_stages: Vec::new(),
+            resolve_references: false,
+            reference_search_paths: Vec::new(),
+        }
+    }
+}
+
+/// How much the `FbxLoader` simplifies meshes,
+/// see [`FbxLoaderSettings::simplification`].
+///
+/// Edges are collapsed until the mesh reaches `ratio` of its triangles,
+/// or until a collapse would move the surface further than `max_error`.
+/// The borders of the mesh and of its materials are kept as is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FbxSimplification {
+    /// Target ratio of triangles to keep, from 0 to 1.
+    pub ratio: f32,
+    /// Maximum distance of the simplified surface to the original,
+    /// relative to the size of the mesh (the diagonal of its bounding box).
+    ///
+    /// For example, `0.01` is 1 cm on a 1 m model, keeping the error
+    /// below a pixel when the mesh spans less than 100 pixels on screen.
+    pub max_error: f32,
+}
+impl Default for FbxSimplification {
+    fn default() -> Self {
+        FbxSimplification {
+            ratio: 0.5,
+            max_error: 0.01,
+        }
+    }
+}
+
+/// The naming conventions the `FbxLoader` follows,
+/// see [`FbxLoaderSettings::naming_conventions`].
+///
+/// The defaults are the conventions of Unreal Engine. Prefixes and suffixes
+/// are case-sensitive, set one to `None` or empty the prefixes to ignore
+/// a convention.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FbxNamingConventions {
+    /// The prefixes of the names of collision meshes, with their shape.
+    /// Matching nodes get a [`FbxCollisionMesh`] component.
+    ///
+    /// Defaults to `UCX_`, `UBX_`, `USP_` and `UCP_`.
+    ///
+    /// [`FbxCollisionMesh`]: crate::FbxCollisionMesh
+    pub collision_prefixes: Vec<(String, FbxCollisionShape)>,
+    /// Spawn the collision meshes hidden. Defaults to `true`.
+    pub hide_collision_meshes: bool,
+    /// The suffix of the levels of detail of a mesh, followed by the level:
+    /// `Rock_LOD0`, `Rock_LOD1`... Matching nodes get a [`FbxLod`] component.
+    ///
+    /// Defaults to `_LOD`.
+    ///
+    /// [`FbxLod`]: crate::FbxLod
+    pub lod_suffix: Option<String>,
+    /// Spawn the levels of detail other than the first hidden,
+    /// for the game to show them by distance. Defaults to `true`.
+    pub hide_lods: bool,
+    /// The prefix of the names of static meshes, which get
+    /// [`FbxMeshKind::Static`]. Defaults to `SM_`.
+    ///
+    /// [`FbxMeshKind::Static`]: crate::FbxMeshKind::Static
+    pub static_mesh_prefix: Option<String>,
+    /// The prefix of the names of skeletal meshes, which get
+    /// [`FbxMeshKind::Skeletal`]. Defaults to `SK_`.
+    ///
+    /// [`FbxMeshKind::Skeletal`]: crate::FbxMeshKind::Skeletal
+    pub skeletal_mesh_prefix: Option<String>,
+}
+impl Default for FbxNamingConventions {
+    fn default() -> Self {
+        let collision = |prefix: &str, shape| (prefix.to_owned(), shape);
+        FbxNamingConventions {
+            collision_prefixes: vec![
+                collision("UCX_", FbxCollisionShape::Convex),
+                collision("UBX_", FbxCollisionShape::Box),
+                collision("USP_", FbxCollisionShape::Sphere),
+                collision("UCP_", FbxCollisionShape::Capsule),
+            ],
+            hide_collision_meshes: true,
+            lod_suffix: Some("_LOD".to_owned()),
+            hide_lods: true,
+            static_mesh_prefix: Some("SM_".to_owned()),
+            skeletal_mesh_prefix: Some("SK_".to_owned()),
+        }
+    }
+}
+
+/// Soft limits on the size of a loaded scene, see [`FbxLoaderSettings::budget`].
+///
+/// Limits are checked against the scene spawned from the file, once loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FbxBudget {
+    /// Maximum number of entities of the scene, including its root.
+    pub max_entities: Option<usize>,
+    /// Maximum number of triangles of the scene, counted for each entity
+    /// with a mesh, so that instanced meshes count each time they are used.
+    pub max_triangles: Option<usize>,
+    /// Maximum size of the decoded textures of the file, in megabytes
+    /// of 1024 × 1024 bytes.
+    pub max_texture_megabytes: Option<f32>,
+    /// Fail loading the files going over budget, instead of warning about them.
+    pub strict: bool,
+}
+
+/// How the `FbxLoader` splits textures into a proxy and a streamed full
+/// resolution, see [`FbxLoaderSettings::texture_streaming`].
+///
+/// Only textures decoded to 8 bits per channel without mipmaps have a
+/// proxy, the others and the textures processed at import, such as packed
+/// or combined maps, are always loaded at full resolution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FbxTextureStreaming {
+    /// Size of the textures of a file loaded at full resolution, in
+    /// megabytes of 1024 × 1024 bytes, before the next ones are proxies.
+    pub budget_megabytes: f32,
+    /// Size of the longest side of the proxies, in pixels.
+    pub proxy_size: u32,
+    /// Stream the full resolution of the proxies as soon as the file is
+    /// loaded, instead of waiting for [`FbxTextureStreamer::request`].
+    ///
+    /// [`FbxTextureStreamer::request`]: crate::FbxTextureStreamer::request
+    pub stream_after_load: bool,
+}
+impl Default for FbxTextureStreaming {
+    fn default() -> Self {
+        FbxTextureStreaming {
+            budget_megabytes: 64.0,
+            proxy_size: 128,
+            stream_after_load: true,
+        }
+    }
+}
+
+/// Limits on the threads used by FBX loads, see [`FbxLoaderSettings::tasks`].
+///
+/// Bevy converts each FBX file on a single thread of its `IoTaskPool`,
+/// these settings are only read from the [`FbxLoaderSettings`] resource.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FbxTaskSettings {
+    /// Maximum number of FBX files converted at the same time, the other
+    /// loads waiting for a conversion to finish before starting theirs.
+    pub max_parallel_loads: Option<usize>,
+    pub priority: FbxTaskPriority,
+}
+
+/// A stage of the conversion of FBX files with its own log messages,
+/// see [`FbxLoaderSettings::verbose_stages`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FbxLogStage {
+    /// The node hierarchy and its connections.
+    Hierarchy,
+    /// Triangulating, splitting, optimizing and simplifying meshes.
+    Meshes,
+    /// Reading and decoding texture images.
+    Textures,
+    /// Resolving materials: overrides, material loaders and unmapped parameters.
+    Materials,
+    /// Baking animation clips.
+    Animations,
+}
+
+/// How a FBX load shares its thread, see [`FbxTaskSettings::priority`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FbxTaskPriority {
+    /// Convert the whole file without interruption.
+    #[default]
+    Normal,
+    /// Give way to the other tasks of the pool after each object
+    /// and clip, so that long loads don't hold a thread for long.
+    Background,
+}
+
+/// How the `FbxLoader` handles the reflection maps of materials,
+/// see [`FbxLoaderSettings::reflection_maps`].
+///
+/// Phong reflection maps are usually baked environments, spherical or
+/// cubic, which bevy can only use as an [`EnvironmentMapLight`] for the
+/// whole view.
+///
+/// [`EnvironmentMapLight`]: bevy::pbr::EnvironmentMapLight
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FbxReflectionMap {
+    /// Ignore the reflection maps.
+    Ignore,
+    /// Load the reflection maps as textures, in
+    /// [`FbxMaterialExtras::reflection_map`], as candidates for an
+    /// [`EnvironmentMapLight`]. The materials are not modified.
+    ///
+    /// [`FbxMaterialExtras::reflection_map`]: crate::FbxMaterialExtras::reflection_map
+    /// [`EnvironmentMapLight`]: bevy::pbr::EnvironmentMapLight
+    #[default]
+    Environment,
+    /// Raise the `reflectance` of the materials by the average intensity of
+    /// their reflection map, scaled by their `ReflectionFactor`.
+    ///
+    /// This is a rough approximation: a strong reflection map makes the
+    /// material more specular everywhere, whatever it reflects. Materials
+    /// without a map use their `ReflectionColor` instead.
+    Specular,
+}
+
+/// The alpha mode of materials with an opacity texture,
+/// see [`FbxLoaderSettings::opacity_textures`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FbxOpacityMode {
+    /// Alpha test ([`AlphaMode::Mask`]) if the opacity is either fully
+    /// opaque or fully transparent everywhere, such as cut-out foliage,
+    /// alpha blending otherwise.
+    ///
+    /// [`AlphaMode::Mask`]: bevy::pbr::AlphaMode::Mask
+    #[default]
+    Auto,
+    /// Always use alpha blending.
+    Blend,
+    /// Always use alpha testing, discarding fragments with an opacity lower
+    /// than the cutoff.
+    Mask(f32),
+}
+
+/// How the `FbxLoader` imports the vertex colors of meshes,
+/// see [`FbxLoaderSettings::vertex_colors`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FbxVertexColors {
+    /// Store them as [`Mesh::ATTRIBUTE_COLOR`], which bevy's
+    /// `StandardMaterial` multiplies with the base color.
+    ///
+    /// [`Mesh::ATTRIBUTE_COLOR`]: bevy::prelude::Mesh::ATTRIBUTE_COLOR
+    #[default]
+    Tint,
+    /// Store them as [`FbxMesh::ATTRIBUTE_VERTEX_COLOR`], as raw data for
+    /// custom shaders (wind weights, blend masks...), ignored by the
+    /// `StandardMaterial`.
+    ///
+    /// [`FbxMesh::ATTRIBUTE_VERTEX_COLOR`]: crate::FbxMesh::ATTRIBUTE_VERTEX_COLOR
+    Data,
+}
+
+/// How the `FbxLoader` converts the V axis of texture coordinates,
+/// see [`FbxLoaderSettings::flip_uv_v`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FbxFlipUv {
+    /// Follow the FBX convention, flipping V to match Bevy.
+    ///
+    /// This is currently the same as [`FbxFlipUv::On`], as files do not
+    /// record where their exporter put the UV origin.
+    #[default]
+    Auto,
+    /// Always flip V, `v` becomes `1 - v`.
+    On,
+    /// Keep the V coordinates of the file as is.
+    Off,
+}
+impl FbxFlipUv {
+    /// Whether V coordinates should be flipped.
+    pub fn flips(self) -> bool {
+        match self {
+            FbxFlipUv::Auto | FbxFlipUv::On => true,
+            FbxFlipUv::Off => false,
+        }
+    }
+}
+
+/// The formats of the vertex attributes of the bevy meshes,
+/// see [`FbxLoaderSettings::vertex_formats`].
+///
+/// Each bevy mesh is checked against the formats when loaded: an attribute
+/// whose values the format can't hold keeps its `f32` format, with a warning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FbxVertexFormats {
+    /// The format of [`Mesh::ATTRIBUTE_NORMAL`].
+    ///
+    /// [`Mesh::ATTRIBUTE_NORMAL`]: bevy::prelude::Mesh::ATTRIBUTE_NORMAL
+    pub normals: FbxDirectionFormat,
+    /// The format of [`Mesh::ATTRIBUTE_TANGENT`].
+    ///
+    /// [`Mesh::ATTRIBUTE_TANGENT`]: bevy::prelude::Mesh::ATTRIBUTE_TANGENT
+    pub tangents: FbxDirectionFormat,
+    /// The format of [`Mesh::ATTRIBUTE_UV_0`].
+    ///
+    /// [`Mesh::ATTRIBUTE_UV_0`]: bevy::prelude::Mesh::ATTRIBUTE_UV_0
+    pub uvs: FbxUvFormat,
+}
+
+/// The format of the unit vectors of meshes, normals and tangents,
+/// see [`FbxVertexFormats`].
+///
+/// The normalized formats are read as `f32` by shaders, they need
+/// no change to materials.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FbxDirectionFormat {
+    /// Three or four `f32`, 12 or 16 bytes per vertex.
+    #[default]
+    Float32,
+    /// Four signed-normalized 16-bit integers, 8 bytes per vertex,
+    /// precise to a few thousandths of a degree.
+    Snorm16,
+    /// Four signed-normalized 8-bit integers, 4 bytes per vertex,
+    /// precise to half a degree, which shows on smooth shiny surfaces.
+    Snorm8,
+}
+
+/// The format of the texture coordinates of meshes, see [`FbxVertexFormats`].
+///
+/// Bevy meshes have no 16-bit float attributes, UVs are compacted as
+/// normalized integers instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FbxUvFormat {
+    /// Two `f32`, 8 bytes per vertex.
+    #[default]
+    Float32,
+    /// Two unsigned-normalized 16-bit integers, 4 bytes per vertex.
+    ///
+    /// This only holds UVs from 0 to 1, meshes with tiling UVs keep `f32` UVs.
+    Unorm16,
+}
+
+/// Which clip the `FbxLoader` should automatically play on spawned scenes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FbxAutoplay {
+    /// Do not add an animation player.
+    #[default]
+    Off,
+    /// Play the first clip of the file, if any.
+    First,
+    /// Play the clip with the given name, if it exists.
+    ///
+    /// This can be a take name or the name of a [`FbxTakeSlice`].
+    Named(String),
+}
+
+/// The name of the root entity of spawned scenes,
+/// see [`FbxLoaderSettings::root_name`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FbxRootName {
+    /// `Fbx scene root`.
+    #[default]
+    Default,
+    /// The file name without extension, `character` for `models/character.fbx`.
+    FileName,
+    /// The given name.
+    Named(String),
+}
+
+/// A frame range of an FBX take to import as its own [`FbxAnimationClip`].
+///
+/// Frames are counted at the FBX scene's frame rate,
+/// as displayed in the timeline of the DCC the file was exported from.
+/// The resulting clip starts at time zero.
+///
+/// [`FbxAnimationClip`]: crate::FbxAnimationClip
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct FbxTakeSlice {
+    /// Name of the FBX take (animation stack) to slice.
+    pub name: String,
+    /// First frame of the slice.
+    pub start: i64,
+    /// Last frame of the slice, included.
+    pub end: i64,
+    /// Name of the resulting clip.
+    ///
+    /// Defaults to `"{name} {start}-{end}"`.
+    #[serde(default)]
+    pub rename: Option<String>,
+}
+impl FbxTakeSlice {
+    /// The name of the clip produced by this slice.
+    pub fn clip_name(&self) -> String {
+        match &self.rename {
+            Some(rename) => rename.clone(),
+            None => format!("{} {}-{}", self.name, self.start, self.end),
+        }
+    }
+}
+
+/// A version of an imported clip mirrored across a plane of the
+/// character, imported as its own [`FbxAnimationClip`].
+///
+/// The curves of each node are moved to its counterpart on the other side,
+/// found by swapping the names of [`FbxMirroredClip::name_pairs`], and
+/// mirrored across the plane perpendicular to [`FbxMirroredClip::axis`].
+/// This assumes the joints of both sides are oriented symmetrically.
+///
+/// [`FbxAnimationClip`]: crate::FbxAnimationClip
+#[derive(Clone, Debug)]
+pub struct FbxMirroredClip {
+    /// Name of the source clip, a take name or the name of a [`FbxTakeSlice`].
+    pub clip: String,
+    /// The axis across which the clip is mirrored, the one pointing from
+    /// the left to the right of the character.
+    pub axis: FbxMirrorAxis,
+    /// Pairs of left and right name parts, such as `("Left", "Right")`.
+    ///
+    /// The first pair found in a node name is swapped, nodes without any
+    /// are mirrored in place. Defaults to the `Left`/`Right`, `left`/`right`,
+    /// `_L`/`_R`, `_l`/`_r` and `.L`/`.R` pairs.
+    pub name_pairs: Vec<(String, String)>,
+    /// Name of the resulting clip.
+    ///
+    /// Defaults to the name of the source clip with the `name_pairs`
+    /// swapped, or `"{clip} mirrored"` if it has none.
+    pub rename: Option<String>,
+}
+impl Default for FbxMirroredClip {
+    fn default() -> Self {
+        let pairs = [
+            ("Left", "Right"),
+            ("left", "right"),
+            ("_L", "_R"),
+            ("_l", "_r"),
+            (".L", ".R"),
+        ];
+        FbxMirroredClip {
+            clip: String::new(),
+            axis: FbxMirrorAxis::default(),
+            name_pairs: pairs.map(|(l, r)| (l.to_owned(), r.to_owned())).into(),
+            rename: None,
+        }
+    }
+}
+impl FbxMirroredClip {
+    /// `name` with the first of [`FbxMirroredClip::name_pairs`] it contains
+    /// swapped, `None` if it contains none.
+    pub fn mirrored_name(&self, name: &str) -> Option<String> {
+        self.name_pairs.iter().find_map(|(left, right)| {
+            if name.contains(left.as_str()) {
+                Some(name.replace(left.as_str(), right))
+            } else if name.contains(right.as_str()) {
+                Some(name.replace(right.as_str(), left))
+            } else {
+                None
+            }
+        })
+    }
+    /// The name of the clip produced by this mirrored clip.
+    pub fn clip_name(&self) -> String {
+        match (&self.rename, self.mirrored_name(&self.clip)) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(mirrored)) => mirrored,
+            (None, None) => format!("{} mirrored", self.clip),
+        }
+    }
+}
+
+/// The axis of a [`FbxMirroredClip`], in the space of each node's parent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FbxMirrorAxis {
+    #[default]
+    X,
+    Y,
+    Z,
+}
+
+/// An additive version of an imported clip, imported as its own
+/// [`FbxAnimationClip`] with [`FbxAnimationClip::additive`] set.
+///
+/// Each keyframe of the additive clip is the difference between the
+/// keyframe of the source clip and the reference pose: translations are
+/// offsets, rotations and scales are to be multiplied with the transform
+/// the clip is layered on.
+///
+/// [`FbxAnimationClip`]: crate::FbxAnimationClip
+/// [`FbxAnimationClip::additive`]: crate::FbxAnimationClip::additive
+#[derive(Clone, Debug, Default)]
+pub struct FbxAdditiveClip {
+    /// Name of the source clip, a take name, the name of a [`FbxTakeSlice`]
+    /// or of a [`FbxMirroredClip`].
+    pub clip: String,
+    /// The pose subtracted from each keyframe.
+    pub reference: FbxAdditiveReference,
+    /// Name of the resulting clip.
+    ///
+    /// Defaults to `"{clip} additive"`.
+    pub rename: Option<String>,
+}
+impl FbxAdditiveClip {
+    /// The name of the clip produced by this additive clip.
+    pub fn clip_name(&self) -> String {
+        match &self.rename {
+            Some(rename) => rename.clone(),
+            None => format!("{} additive", self.clip),
+        }
+    }
+}
+
+/// The reference pose of a [`FbxAdditiveClip`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FbxAdditiveReference {
+    /// The first frame of the source clip.
+    #[default]
+    FirstFrame,
+    /// A frame of the source clip, counted from its start at the FBX
+    /// scene's frame rate.
+    Frame(u32),
+    /// A stored pose of the file, by name, see [`FbxScene::poses`].
+    ///
+    /// Nodes missing from the pose use the first frame of the source clip.
+    ///
+    /// [`FbxScene::poses`]: crate::FbxScene::poses
+    Pose(String),
+    /// The bind pose of the skeletons of the file, see [`FbxScene::bind_pose`].
+    ///
+    /// Nodes missing from the bind pose use the first frame of the source clip.
+    ///
+    /// [`FbxScene::bind_pose`]: crate::FbxScene::bind_pose
+    BindPose,
+}
@@ -0,0 +1,468 @@
+//! Mesh simplification.
+//!
+//! Meshes are decimated by collapsing edges in order of their quadric error,
+//! see [`FbxLoaderSettings::simplification`].
+//!
+//! [`FbxLoaderSettings::simplification`]: crate::FbxLoaderSettings::simplification
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{math::DVec3, prelude::Mesh, render::mesh::VertexAttributeValues, utils::HashMap};
+
+use crate::{
+    utils::mesh::{select, ATTRIBUTES},
+    FbxSimplification,
+};
+
+/// A simplified mesh.
+pub(crate) struct Simplified {
+    pub(crate) mesh: Mesh,
+    /// The indices of each material.
+    pub(crate) all_indices: Vec<Vec<u32>>,
+    /// The vertex of the original mesh each vertex comes from.
+    pub(crate) original_vertices: Vec<u32>,
+}
+
+/// A symmetric 4×4 matrix, the sum of squared distances to a set of planes.
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+impl Quadric {
+    fn from_plane(normal: DVec3, point: DVec3) -> Self {
+        let [a, b, c] = normal.to_array();
+        let d = -normal.dot(point);
+        Quadric([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+    }
+    fn add(&mut self, other: &Quadric) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+    }
+    fn error(&self, p: DVec3) -> f64 {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        let error = aa * x * x
+            + 2.0 * ab * x * y
+            + 2.0 * ac * x * z
+            + 2.0 * ad * x
+            + bb * y * y
+            + 2.0 * bc * y * z
+            + 2.0 * bd * y
+            + cc * z * z
+            + 2.0 * cd * z
+            + dd;
+        error.max(0.0)
+    }
+}
+
+/// Weld the identical vertices of `mesh`, and simplify each material
+/// of `all_indices` separately.
+///
+/// Returns `None` if the mesh has attributes we can't simplify.
+pub(crate) fn simplify(
+    mesh: &Mesh,
+    all_indices: &[Vec<u32>],
+    settings: &FbxSimplification,
+) -> Option<Simplified> {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+        VertexAttributeValues::Float32x3(positions) => positions,
+        _ => return None,
+    };
+    if mesh.attributes().count()
+        != ATTRIBUTES
+            .iter()
+            .filter(|a| mesh.contains_attribute(a.id))
+            .count()
+    {
+        return None;
+    }
+    let attributes: Vec<_> = ATTRIBUTES
+        .iter()
+        .filter_map(|a| Some((a.clone(), mesh.attribute(a.id)?)))
+        .collect();
+
+    // Weld vertices with the exact same attributes.
+    let buffers: Vec<_> = attributes.iter().map(|(_, v)| v.get_bytes()).collect();
+    let mut welded: HashMap<Vec<u8>, u32> = HashMap::default();
+    let mut original_vertices = Vec::new();
+    let weld: Vec<u32> = (0..positions.len())
+        .map(|vertex| {
+            let key: Vec<u8> = buffers
+                .iter()
+                .flat_map(|buffer| {
+                    let stride = buffer.len() / positions.len();
+                    &buffer[vertex * stride..(vertex + 1) * stride]
+                })
+                .copied()
+                .collect();
+            *welded.entry(key).or_insert_with(|| {
+                original_vertices.push(vertex as u32);
+                original_vertices.len() as u32 - 1
+            })
+        })
+        .collect();
+    let welded_positions: Vec<DVec3> = original_vertices
+        .iter()
+        .map(|v| DVec3::from(positions[*v as usize].map(f64::from)))
+        .collect();
+
+    let mut triangles = Vec::new();
+    for (material, indices) in all_indices.iter().enumerate() {
+        for triangle in indices.chunks_exact(3) {
+            let triangle = [0, 1, 2].map(|i| weld[triangle[i] as usize]);
+            triangles.push((material, triangle));
+        }
+    }
+    let (min, max) = welded_positions.iter().fold(
+        (DVec3::splat(f64::MAX), DVec3::splat(f64::MIN)),
+        |(min, max), p| (min.min(*p), max.max(*p)),
+    );
+    let extent = (max - min).length();
+    let max_error = f64::from(settings.max_error) * extent;
+    let target = (triangles.len() as f64 * f64::from(settings.ratio.clamp(0.0, 1.0))).ceil();
+    let triangles = Decimator::new(welded_positions, triangles).run(target as usize, max_error);
+
+    // Keep only used vertices.
+    let mut all_indices = vec![Vec::new(); all_indices.len()];
+    let mut compact: HashMap<u32, u32> = HashMap::default();
+    let mut kept_vertices = Vec::new();
+    for (material, triangle) in triangles {
+        for vertex in triangle {
+            let index = *compact.entry(vertex).or_insert_with(|| {
+                kept_vertices.push(original_vertices[vertex as usize]);
+                kept_vertices.len() as u32 - 1
+            });
+            all_indices[material].push(index);
+        }
+    }
+    let mut simplified = Mesh::new(mesh.primitive_topology());
+    for (attribute, values) in attributes {
+        simplified.insert_attribute(attribute, select(values, &kept_vertices)?);
+    }
+    Some(Simplified {
+        mesh: simplified,
+        all_indices,
+        original_vertices: kept_vertices,
+    })
+}
+
+/// Edge collapse decimation of a welded triangle mesh.
+///
+/// Vertices at the same position form a group, groups are collapsed into
+/// one of their neighbors (half-edge collapse), so that vertices keep their
+/// attributes. Groups on the border of a material are never moved.
+struct Decimator {
+    /// The group of each vertex.
+    groups: Vec<u32>,
+    group_positions: Vec<DVec3>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    /// Incremented when a group changes, to invalidate queued collapses.
+    versions: Vec<u32>,
+    /// The triangles around each group, possibly removed.
+    group_triangles: Vec<Vec<usize>>,
+    triangles: Vec<(usize, [u32; 3])>,
+    alive: Vec<bool>,
+    alive_count: usize,
+}
+impl Decimator {
+    fn new(positions: Vec<DVec3>, triangles: Vec<(usize, [u32; 3])>) -> Self {
+        let mut group_ids: HashMap<[u64; 3], u32> = HashMap::default();
+        let mut group_positions = Vec::new();
+        let groups: Vec<u32> = positions
+            .iter()
+            .map(|p| {
+                let key = p.to_array().map(f64::to_bits);
+                *group_ids.entry(key).or_insert_with(|| {
+                    group_positions.push(*p);
+                    group_positions.len() as u32 - 1
+                })
+            })
+            .collect();
+        let group_count = group_positions.len();
+        let mut quadrics = vec![Quadric::default(); group_count];
+        let mut group_triangles = vec![Vec::new(); group_count];
+        let mut edge_uses: HashMap<(usize, u32, u32), u32> = HashMap::default();
+        for (t, (material, triangle)) in triangles.iter().enumerate() {
+            let corners = triangle.map(|v| groups[v as usize]);
+            let [a, b, c] = corners.map(|g| group_positions[g as usize]);
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            let quadric = Quadric::from_plane(normal, a);
+            for (i, group) in corners.iter().enumerate() {
+                quadrics[*group as usize].add(&quadric);
+                group_triangles[*group as usize].push(t);
+                let next = corners[(i + 1) % 3];
+                let edge = (*material, (*group).min(next), (*group).max(next));
+                *edge_uses.entry(edge).or_default() += 1;
+            }
+        }
+        let mut locked = vec![false; group_count];
+        for ((_, a, b), uses) in edge_uses {
+            if uses != 2 {
+                locked[a as usize] = true;
+                locked[b as usize] = true;
+            }
+        }
+        Decimator {
+            groups,
+            group_positions,
+            quadrics,
+            locked,
+            versions: vec![0; group_count],
+            group_triangles,
+            alive: vec![true; triangles.len()],
+            alive_count: triangles.len(),
+            triangles,
+        }
+    }
+
+    fn group_of(&self, vertex: u32) -> u32 {
+        self.groups[vertex as usize]
+    }
+
+    fn alive_triangles(&self, group: u32) -> impl Iterator<Item = usize> + '_ {
+        self.group_triangles[group as usize]
+            .iter()
+            .copied()
+            .filter(|t| self.alive[*t])
+    }
+
+    /// The groups sharing a triangle with `group`.
+    fn neighbors(&self, group: u32) -> Vec<u32> {
+        let mut neighbors: Vec<u32> = self
+            .alive_triangles(group)
+            .flat_map(|t| self.triangles[t].1)
+            .map(|v| self.group_of(v))
+            .filter(|g| *g != group)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    fn cost(&self, from: u32, to: u32) -> f64 {
+        let mut quadric = self.quadrics[from as usize];
+        quadric.add(&self.quadrics[to as usize]);
+        quadric.error(self.group_positions[to as usize])
+    }
+
+    /// The vertex of `to` replacing each vertex of `from` in the triangles
+    /// kept by collapsing `from` into `to`, or `None` if the collapse
+    /// would tear an attribute seam or flip a triangle.
+    fn collapse_remap(&self, from: u32, to: u32) -> Option<HashMap<u32, u32>> {
+        let mut remap = HashMap::default();
+        // Vertices of `from` and `to` on the collapsed edge are on the same side of seams.
+        for t in self.alive_triangles(from) {
+            let triangle = self.triangles[t].1;
+            let source = triangle.iter().find(|v| self.group_of(**v) == from);
+            let target = triangle.iter().find(|v| self.group_of(**v) == to);
+            if let (Some(source), Some(target)) = (source, target) {
+                remap.entry(*source).or_insert(*target);
+            }
+        }
+        let target_position = self.group_positions[to as usize];
+        for t in self.alive_triangles(from) {
+            let triangle = self.triangles[t].1;
+            if triangle.iter().any(|v| self.group_of(*v) == to) {
+                continue;
+            }
+            let mut moved = triangle.map(|v| self.group_positions[self.group_of(v) as usize]);
+            let before = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+            for (i, vertex) in triangle.iter().enumerate() {
+                if self.group_of(*vertex) == from {
+                    remap.get(vertex)?;
+                    moved[i] = target_position;
+                }
+            }
+            let after = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+            if before.dot(after) <= 0.0 {
+                return None;
+            }
+        }
+        Some(remap)
+    }
+
+    fn run(mut self, target: usize, max_error: f64) -> Vec<(usize, [u32; 3])> {
+        type Candidate = (Reverse<u64>, u32, u32, u32, u32);
+        let mut queue: BinaryHeap<Candidate> = BinaryHeap::new();
+        let push = |this: &Self, queue: &mut BinaryHeap<Candidate>, from: u32, to: u32| {
+            if this.locked[from as usize] {
+                return;
+            }
+            let cost = this.cost(from, to);
+            let versions = (this.versions[from as usize], this.versions[to as usize]);
+            queue.push((Reverse(cost.to_bits()), from, to, versions.0, versions.1));
+        };
+        for group in 0..self.group_positions.len() as u32 {
+            for neighbor in self.neighbors(group) {
+                push(&self, &mut queue, group, neighbor);
+            }
+        }
+        while self.alive_count > target {
+            let (Reverse(cost), from, to, from_version, to_version) = match queue.pop() {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            if self.versions[from as usize] != from_version
+                || self.versions[to as usize] != to_version
+            {
+                continue;
+            }
+            if f64::from_bits(cost).sqrt() > max_error {
+                break;
+            }
+            let remap = match self.collapse_remap(from, to) {
+                Some(remap) => remap,
+                None => continue,
+            };
+            for t in self.group_triangles[from as usize].clone() {
+                if !self.alive[t] {
+                    continue;
+                }
+                let triangle = &mut self.triangles[t].1;
+                if triangle.iter().any(|v| self.groups[*v as usize] == to) {
+                    self.alive[t] = false;
+                    self.alive_count -= 1;
+                    continue;
+                }
+                for vertex in triangle.iter_mut() {
+                    if let Some(target) = remap.get(vertex) {
+                        *vertex = *target;
+                    }
+                }
+                self.group_triangles[to as usize].push(t);
+            }
+            let from_quadric = self.quadrics[from as usize];
+            self.quadrics[to as usize].add(&from_quadric);
+            self.versions[from as usize] += 1;
+            self.versions[to as usize] += 1;
+            self.locked[from as usize] = true;
+            for neighbor in self.neighbors(to) {
+                push(&self, &mut queue, to, neighbor);
+                push(&self, &mut queue, neighbor, to);
+            }
+        }
+        let Decimator {
+            triangles, alive, ..
+        } = self;
+        triangles
+            .into_iter()
+            .zip(alive)
+            .filter_map(|(triangle, alive)| alive.then_some(triangle))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        math::Vec3,
+        render::mesh::{MeshVertexAttribute, PrimitiveTopology},
+    };
+
+    use super::*;
+
+    const CELLS: u32 = 8;
+
+    /// A flat grid of `CELLS`×`CELLS` unit squares facing +Z. With `seam`,
+    /// the vertices of its middle column are split, the left half has the
+    /// UV `(0, 0)` and the right half `(1, 0)`.
+    fn grid(seam: bool) -> (Mesh, Vec<u32>) {
+        let mut vertices: HashMap<(u32, u32, u32), u32> = HashMap::default();
+        let (mut positions, mut uvs) = (Vec::new(), Vec::new());
+        let mut indices = Vec::new();
+        for y in 0..CELLS {
+            for x in 0..CELLS {
+                let side = u32::from(seam && x >= CELLS / 2);
+                let mut vertex = |x: u32, y: u32| {
+                    *vertices.entry((x, y, side)).or_insert_with(|| {
+                        positions.push([x as f32, y as f32, 0.0]);
+                        uvs.push([side as f32, 0.0]);
+                        positions.len() as u32 - 1
+                    })
+                };
+                let [a, b, c, d] = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+                let [a, b, c, d] = [a, b, c, d].map(|(x, y)| vertex(x, y));
+                indices.extend([a, b, c, b, d, c]);
+            }
+        }
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        (mesh, indices)
+    }
+
+    fn values<const N: usize>(mesh: &Mesh, attribute: MeshVertexAttribute) -> Vec<[f32; N]> {
+        crate::quantize::dequantized(mesh.attribute(attribute.id).unwrap()).unwrap()
+    }
+
+    /// Twice the area of each triangle of `simplified`, positive if it faces +Z.
+    fn areas(simplified: &Simplified) -> Vec<f32> {
+        let positions: Vec<[f32; 3]> = values(&simplified.mesh, Mesh::ATTRIBUTE_POSITION);
+        (simplified.all_indices[0].chunks_exact(3))
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(positions[t[i] as usize]));
+                (b - a).cross(c - a).z
+            })
+            .collect()
+    }
+
+    fn simplify_grid(seam: bool, ratio: f32) -> Simplified {
+        let (mesh, indices) = grid(seam);
+        let settings = FbxSimplification {
+            ratio,
+            max_error: 1.0,
+        };
+        simplify(&mesh, &[indices], &settings).unwrap()
+    }
+
+    #[test]
+    fn target_ratio() {
+        let triangles = (CELLS * CELLS * 2) as usize;
+        let kept = simplify_grid(false, 1.0);
+        assert_eq!(kept.all_indices[0].len(), triangles * 3);
+
+        let simplified = simplify_grid(false, 0.5);
+        let areas = areas(&simplified);
+        assert!(areas.len() <= triangles / 2, "{} triangles", areas.len());
+        // The grid is flat, its border locked: the surface doesn't change.
+        assert!(areas.iter().all(|area| *area > 0.0));
+        assert_eq!(areas.iter().sum::<f32>(), 2.0 * (CELLS * CELLS) as f32);
+        let vertex_count = simplified.mesh.count_vertices();
+        assert_eq!(simplified.original_vertices.len(), vertex_count);
+    }
+
+    #[test]
+    fn uv_seams() {
+        let simplified = simplify_grid(true, 0.25);
+        let areas = areas(&simplified);
+        assert!(areas.len() < (CELLS * CELLS * 2) as usize);
+        assert_eq!(areas.iter().sum::<f32>(), 2.0 * (CELLS * CELLS) as f32);
+        // No triangle crosses the seam, on either side.
+        let uvs: Vec<[f32; 2]> = values(&simplified.mesh, Mesh::ATTRIBUTE_UV_0);
+        let positions: Vec<[f32; 3]> = values(&simplified.mesh, Mesh::ATTRIBUTE_POSITION);
+        let middle = (CELLS / 2) as f32;
+        for triangle in simplified.all_indices[0].chunks_exact(3) {
+            let side = uvs[triangle[0] as usize][0];
+            for vertex in triangle.iter().map(|v| *v as usize) {
+                assert_eq!(uvs[vertex][0], side);
+                let x = positions[vertex][0];
+                assert!(if side == 0.0 {
+                    x <= middle
+                } else {
+                    x >= middle
+                });
+            }
+        }
+    }
+}
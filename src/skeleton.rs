@@ -0,0 +1,106 @@
+//! The skeleton of a FBX file as a standalone asset.
+
+use bevy::{
+    prelude::{Mat4, Transform},
+    reflect::TypeUuid,
+    utils::{HashMap, HashSet},
+};
+use fbxcel_dom::v7400::{object::ObjectId, Document};
+
+use crate::{animation::stored_pose::StoredPose, data::FbxObject, fbx_transform::decompose};
+
+/// The joints of the skeletons of a FBX file, labeled `FbxSkeleton`,
+/// see [`FbxScene::skeleton`].
+///
+/// Joints are the `LimbNode`, `Limb` and `Root` nodes, and the nodes of
+/// the bind poses other than meshes. They are sorted parents first, all
+/// the vectors of the skeleton have one entry per joint, in that order.
+///
+/// Transforms are in FBX units, the space of the roots of the skeleton
+/// being the space of the children of the spawned scene root.
+///
+/// [`FbxScene::skeleton`]: crate::FbxScene::skeleton
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "e7ee51e7-68c7-4f4d-980f-8e39bdfaa3bf"]
+pub struct FbxSkeleton {
+    /// The node of each joint in [`FbxScene::hierarchy`].
+    ///
+    /// [`FbxScene::hierarchy`]: crate::FbxScene::hierarchy
+    pub joints: Vec<ObjectId>,
+    pub names: Vec<Option<String>>,
+    /// The index of the parent joint of each joint, `None` for the roots.
+    ///
+    /// The parent joint is the closest ancestor node that is a joint.
+    pub parents: Vec<Option<usize>>,
+    /// The transform of each joint in the bind pose, relative to its parent joint.
+    ///
+    /// Joints not in a bind pose use their rest transform.
+    pub bind_transforms: Vec<Transform>,
+    /// The inverse of the global transform of each joint in the bind pose.
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+impl FbxSkeleton {
+    /// The index of the joint named `name`.
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n.as_deref() == Some(name))
+    }
+
+    /// The skeleton of `doc`, if it has joints.
+    ///
+    /// `globals` are the FBX global transforms of the nodes at rest.
+    pub(crate) fn new(
+        doc: &Document,
+        hierarchy: &HashMap<ObjectId, FbxObject>,
+        roots: &[ObjectId],
+        globals: &HashMap<ObjectId, Mat4>,
+        bind_pose: Option<&StoredPose>,
+    ) -> Option<Self> {
+        let bind_globals = bind_pose.map(|pose| &pose.globals);
+        let joints: HashSet<ObjectId> = doc
+            .objects()
+            .filter(|obj| obj.class() == "Model")
+            .filter(|obj| match obj.subclass() {
+                "LimbNode" | "Limb" | "Root" => true,
+                "Mesh" => false,
+                _ => bind_globals.is_some_and(|g| g.contains_key(&obj.object_id())),
+            })
+            .map(|obj| obj.object_id())
+            .filter(|id| hierarchy.contains_key(id))
+            .collect();
+        if joints.is_empty() {
+            return None;
+        }
+        let mut skeleton = FbxSkeleton::default();
+        let mut bind_matrices = Vec::new();
+        // Depth first, so that parents come before their children.
+        let mut stack: Vec<(ObjectId, Option<usize>)> =
+            roots.iter().rev().map(|root| (*root, None)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            let Some(object) = hierarchy.get(&node) else {
+                continue;
+            };
+            let mut joint_parent = parent;
+            if joints.contains(&node) {
+                let global = bind_globals
+                    .and_then(|g| g.get(&node))
+                    .or_else(|| globals.get(&node))
+                    .copied()
+                    .unwrap_or(Mat4::IDENTITY);
+                let local = match parent {
+                    Some(parent) => bind_matrices[parent] * global,
+                    None => global,
+                };
+                joint_parent = Some(skeleton.joints.len());
+                skeleton.joints.push(node);
+                skeleton.names.push(object.name.clone());
+                skeleton.parents.push(parent);
+                skeleton.bind_transforms.push(decompose(local).0);
+                bind_matrices.push(global.inverse());
+            }
+            let children = object.children.iter().rev();
+            stack.extend(children.map(|child| (*child, joint_parent)));
+        }
+        skeleton.inverse_bind_matrices = bind_matrices;
+        Some(skeleton)
+    }
+}
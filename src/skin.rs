@@ -0,0 +1,482 @@
+//! Skinned meshes: the joints and weights of their vertices, their inverse
+//! bind poses, and their bounds over the animations.
+//!
+//! Linear blend skinning moves each vertex within the convex hull of its
+//! positions moved by each of its joints. The box of the vertices of each
+//! cluster in the space of its joint, moved by the joint at each frame of
+//! the clips, then bounds the mesh whatever its weights, see
+//! [`FbxScene::skinned_aabbs`].
+//!
+//! [`FbxScene::skinned_aabbs`]: crate::FbxScene::skinned_aabbs
+
+use bevy::{
+    math::{DMat4, DVec3},
+    prelude::{Mat4, Mesh, Transform, Vec3},
+    render::{mesh::VertexAttributeValues, primitives::Aabb},
+    utils::HashMap,
+};
+use fbxcel_dom::v7400::{
+    object::{
+        geometry::{MeshHandle, TypedGeometryHandle},
+        ObjectHandle, ObjectId, TypedObjectHandle,
+    },
+    Document,
+};
+
+use crate::{
+    animation::player::apply_curve,
+    data::{entity_paths, FbxObject},
+    recenter, FbxAnimationClip, FbxEntityPath, FbxMesh,
+};
+
+/// The most joints a bevy [`SkinnedMesh`] is moved by.
+///
+/// [`SkinnedMesh`]: bevy::render::mesh::skinning::SkinnedMesh
+pub(crate) const MAX_JOINTS: usize = 256;
+
+/// The `Cluster` deformers of the `Skin` deformers of a geometry.
+pub(crate) struct GeometrySkin {
+    clusters: Vec<SkinCluster>,
+}
+
+/// A `Cluster` deformer: the control points a joint moves.
+struct SkinCluster {
+    joint: ObjectId,
+    /// From the space of the geometry to the space of the joint, at bind time.
+    to_joint: DMat4,
+    /// The weighted control points, with their weight.
+    weights: Vec<(usize, f32)>,
+}
+
+/// The vertices a joint moves.
+#[derive(Clone)]
+struct Cluster {
+    joint: ObjectId,
+    /// The bounding box of the vertices in the space of the joint at bind time.
+    min: Vec3,
+    max: Vec3,
+}
+
+/// The bounds of the skinned meshes, grown with each clip.
+pub(crate) struct SkinnedBounds {
+    /// The clusters of each skinned mesh node.
+    clusters: HashMap<ObjectId, Vec<Cluster>>,
+    /// The joints of each skinned mesh node, one per cluster of its skin.
+    joints: HashMap<ObjectId, Vec<ObjectId>>,
+    paths: HashMap<ObjectId, FbxEntityPath>,
+    roots: Vec<ObjectId>,
+    /// The bounds of each skinned mesh node so far, in the space of the node.
+    bounds: HashMap<ObjectId, (Vec3, Vec3)>,
+    /// The mesh nodes instancing a skinned geometry whose rig lacks
+    /// the joints of the skin, left unskinned.
+    pub(crate) unbound: Vec<ObjectId>,
+}
+impl SkinnedBounds {
+    /// The skinned meshes of `models`, with their bounds at rest.
+    pub(crate) fn new(
+        doc: &Document,
+        hierarchy: &HashMap<ObjectId, FbxObject>,
+        roots: &[ObjectId],
+        models: &HashMap<ObjectId, FbxMesh>,
+    ) -> Self {
+        let mut clusters: HashMap<ObjectId, Vec<Cluster>> = HashMap::default();
+        let mut joints = HashMap::default();
+        let mut unbound = Vec::new();
+        let rigs = Rigs::new(hierarchy, roots);
+        let mut geometries: Vec<_> = doc
+            .objects()
+            .filter_map(|obj| match obj.get_typed() {
+                TypedObjectHandle::Geometry(TypedGeometryHandle::Mesh(mesh)) => Some(mesh),
+                _ => None,
+            })
+            .collect();
+        geometries.sort_by_key(|geometry| geometry.object_id().raw());
+        for geometry in geometries {
+            let Some(skin) = GeometrySkin::new(geometry) else {
+                continue;
+            };
+            let Some(points) = recenter::control_points(geometry) else {
+                continue;
+            };
+            // Meshes loaded unskinned, such as subdivided ones, keep their bounds.
+            let nodes: Vec<_> = (geometry.destination_objects())
+                .map(|obj| obj.object_id())
+                .filter(|node| models.get(node).is_some_and(|mesh| mesh.skin.is_some()))
+                .collect();
+            let skin_clusters: Vec<_> = (skin.clusters.iter())
+                .map(|cluster| Cluster::new(cluster, points))
+                .collect();
+            for (node, node_joints) in rigs.instance_joints(&nodes, &skin.joints()) {
+                let Some(node_joints) = node_joints else {
+                    unbound.push(node);
+                    continue;
+                };
+                let node_clusters = clusters.entry(node).or_default();
+                let instance_clusters = skin_clusters.iter().zip(&node_joints);
+                node_clusters.extend(instance_clusters.filter_map(|(cluster, joint)| {
+                    Some(Cluster {
+                        joint: *joint,
+                        ..cluster.clone()?
+                    })
+                }));
+                joints.insert(node, node_joints);
+            }
+        }
+        // Vertices without weights keep their position.
+        let bounds = (clusters.keys())
+            .filter_map(|node| {
+                let mesh = &models[node];
+                let aabb = mesh.aabb?;
+                let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+                Some((*node, (min + mesh.offset, max + mesh.offset)))
+            })
+            .collect();
+        let mut skinned = SkinnedBounds {
+            clusters,
+            joints,
+            paths: entity_paths(hierarchy, roots),
+            roots: roots.to_vec(),
+            bounds,
+            unbound,
+        };
+        if !skinned.clusters.is_empty() {
+            skinned.add_frame(&skinned.globals(hierarchy, None, 0.0));
+        }
+        skinned
+    }
+
+    /// Grow the bounds to the poses of `clip` at each of its frames.
+    pub(crate) fn add_clip(
+        &mut self,
+        hierarchy: &HashMap<ObjectId, FbxObject>,
+        clip: &FbxAnimationClip,
+    ) {
+        if self.clusters.is_empty() {
+            return;
+        }
+        for frame in 0..clip.frame_count() {
+            let time = (frame as f64 / clip.frame_rate) as f32;
+            let globals = self.globals(hierarchy, Some(clip), time.min(clip.duration));
+            self.add_frame(&globals);
+        }
+    }
+
+    /// The joints of each skinned mesh node, see [`FbxScene::skin_joints`].
+    ///
+    /// [`FbxScene::skin_joints`]: crate::FbxScene::skin_joints
+    pub(crate) fn joints(&self) -> HashMap<ObjectId, Vec<ObjectId>> {
+        self.joints.clone()
+    }
+
+    /// The bounds of each skinned mesh node, in the space of its mesh entities.
+    pub(crate) fn aabbs(self, models: &HashMap<ObjectId, FbxMesh>) -> HashMap<ObjectId, Aabb> {
+        (self.bounds.into_iter())
+            .map(|(node, (min, max))| {
+                let offset = models[&node].offset;
+                (node, Aabb::from_min_max(min - offset, max - offset))
+            })
+            .collect()
+    }
+
+    /// The global transform of the nodes, in FBX units, with `clip` at `time`.
+    fn globals(
+        &self,
+        hierarchy: &HashMap<ObjectId, FbxObject>,
+        clip: Option<&FbxAnimationClip>,
+        time: f32,
+    ) -> HashMap<ObjectId, Mat4> {
+        let mut globals = HashMap::default();
+        let mut stack: Vec<(ObjectId, Mat4)> = self
+            .roots
+            .iter()
+            .map(|root| (*root, Mat4::IDENTITY))
+            .collect();
+        while let Some((node, parent)) = stack.pop() {
+            let Some(object) = hierarchy.get(&node) else {
+                continue;
+            };
+            let mut local: Transform = object.transform;
+            let curves = clip.zip(self.paths.get(&node));
+            if let Some(curves) = curves.and_then(|(clip, path)| clip.curves.get(path)) {
+                for curve in curves {
+                    apply_curve(curve, time, &mut local);
+                }
+            }
+            let global = parent * local.compute_matrix();
+            globals.insert(node, global);
+            stack.extend(object.children.iter().map(|child| (*child, global)));
+        }
+        globals
+    }
+
+    /// Grow the bounds to the pose of the nodes at `globals`.
+    fn add_frame(&mut self, globals: &HashMap<ObjectId, Mat4>) {
+        for (node, clusters) in &self.clusters {
+            let Some(node_global) = globals.get(node) else {
+                continue;
+            };
+            let to_node = node_global.inverse();
+            let corners = clusters.iter().flat_map(|cluster| {
+                let joint = globals.get(&cluster.joint).map(|joint| to_node * *joint);
+                let (min, max) = (cluster.min, cluster.max);
+                (0..8).filter_map(move |i| {
+                    let pick =
+                        |bit: usize, min: f32, max: f32| if i & bit == 0 { min } else { max };
+                    let corner = Vec3::new(
+                        pick(1, min.x, max.x),
+                        pick(2, min.y, max.y),
+                        pick(4, min.z, max.z),
+                    );
+                    Some(joint?.transform_point3(corner))
+                })
+            });
+            for corner in corners {
+                let bounds = self.bounds.entry(*node).or_insert((corner, corner));
+                *bounds = (bounds.0.min(corner), bounds.1.max(corner));
+            }
+        }
+    }
+}
+impl Cluster {
+    /// The box of the vertices of `points` weighted by `cluster`.
+    ///
+    /// `None` for clusters without weighted vertices.
+    fn new(cluster: &SkinCluster, points: &[f64]) -> Option<Self> {
+        let mut weighted = cluster.weights.iter().filter_map(|(index, _)| {
+            let point = points.get(index * 3..index * 3 + 3)?;
+            let point = DVec3::from_slice(point);
+            Some(cluster.to_joint.transform_point3(point).as_vec3())
+        });
+        let first = weighted.next()?;
+        let (min, max) = weighted.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        Some(Cluster {
+            joint: cluster.joint,
+            min,
+            max,
+        })
+    }
+}
+
+impl GeometrySkin {
+    /// The clusters of the skins of `geometry`, `None` if it isn't skinned.
+    pub(crate) fn new(geometry: MeshHandle) -> Option<Self> {
+        let skins = (geometry.source_objects())
+            .filter_map(|obj| obj.object_handle())
+            .filter(|obj| obj.class() == "Deformer" && obj.subclass() == "Skin");
+        let clusters: Vec<_> = skins
+            .flat_map(|skin| skin.source_objects())
+            .filter_map(|obj| obj.object_handle())
+            .filter_map(SkinCluster::new)
+            .collect();
+        (!clusters.is_empty()).then_some(GeometrySkin { clusters })
+    }
+
+    /// The joint of each cluster, the joint index of the vertices.
+    pub(crate) fn joints(&self) -> Vec<ObjectId> {
+        self.clusters.iter().map(|cluster| cluster.joint).collect()
+    }
+
+    /// Add the joint indices and weights of the vertices of `mesh`, whose
+    /// control points are `vertex_control_points`.
+    ///
+    /// Vertices keep their four largest weights, normalized. Vertices
+    /// without weights are bound to the joint after those of the clusters,
+    /// the mesh node itself, see [`GeometrySkin::inverse_bindposes`].
+    pub(crate) fn add_joint_attributes(&self, mesh: &mut Mesh, vertex_control_points: &[u32]) {
+        let rest = self.clusters.len() as u16;
+        let mut point_weights: HashMap<usize, Vec<(u16, f32)>> = HashMap::default();
+        for (joint, cluster) in self.clusters.iter().enumerate() {
+            for (point, weight) in &cluster.weights {
+                let weights = point_weights.entry(*point).or_default();
+                weights.push((joint as u16, *weight));
+            }
+        }
+        let count = vertex_control_points.len();
+        let (mut indices, mut weights) = (Vec::with_capacity(count), Vec::with_capacity(count));
+        for point in vertex_control_points {
+            let mut joints = (point_weights.get(&(*point as usize)))
+                .cloned()
+                .unwrap_or_default();
+            joints.sort_by(|a, b| b.1.total_cmp(&a.1));
+            joints.truncate(4);
+            let mut total: f32 = joints.iter().map(|(_, weight)| weight).sum();
+            if total <= 0.0 {
+                (joints, total) = (vec![(rest, 1.0)], 1.0);
+            }
+            let (mut vertex_indices, mut vertex_weights) = ([0; 4], [0.0; 4]);
+            for (i, (joint, weight)) in joints.iter().enumerate() {
+                vertex_indices[i] = *joint;
+                vertex_weights[i] = weight / total;
+            }
+            indices.push(vertex_indices);
+            weights.push(vertex_weights);
+        }
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_JOINT_INDEX,
+            VertexAttributeValues::Uint16x4(indices),
+        );
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_JOINT_WEIGHT,
+            VertexAttributeValues::Float32x4(weights),
+        );
+    }
+
+    /// The inverse bind pose of each joint, then of the mesh node, for
+    /// vertices moved by `offset` from their node.
+    pub(crate) fn inverse_bindposes(&self, offset: Vec3) -> Vec<Mat4> {
+        let offset = Mat4::from_translation(offset);
+        (self.clusters.iter())
+            .map(|cluster| cluster.to_joint.as_mat4() * offset)
+            .chain([offset])
+            .collect()
+    }
+
+    /// Inverse bind poses binding all the vertices to the mesh node, for
+    /// instances whose joints are missing.
+    pub(crate) fn rest_bindposes(&self, offset: Vec3) -> Vec<Mat4> {
+        vec![Mat4::from_translation(offset); self.clusters.len() + 1]
+    }
+}
+
+impl SkinCluster {
+    /// The `Cluster` deformer `cluster`, `None` for clusters without a joint.
+    fn new(cluster: ObjectHandle) -> Option<Self> {
+        if cluster.class() != "SubDeformer" || cluster.subclass() != "Cluster" {
+            return None;
+        }
+        let joint = (cluster.source_objects())
+            .filter_map(|obj| obj.object_handle())
+            .find(|obj| obj.class() == "Model")?
+            .object_id();
+        let array = |name| {
+            cluster
+                .node()
+                .first_child_by_name(name)?
+                .attributes()
+                .first()
+        };
+        let matrix = |name| {
+            let values = array(name)?.get_arr_f64()?;
+            (values.len() == 16).then(|| DMat4::from_cols_slice(values))
+        };
+        let indexes = array("Indexes")
+            .and_then(|a| a.get_arr_i32())
+            .unwrap_or_default();
+        let weights = array("Weights").and_then(|a| a.get_arr_f64());
+        // From the space of the geometry to the space of the joint, at bind time.
+        let transform = matrix("Transform").unwrap_or(DMat4::IDENTITY);
+        let link = matrix("TransformLink").unwrap_or(DMat4::IDENTITY);
+        let weights = (indexes.iter().enumerate())
+            .filter_map(|(i, index)| {
+                let weight = weights.map_or(Some(1.0), |w| w.get(i).copied())?;
+                let index = usize::try_from(*index).ok()?;
+                (weight > 0.0).then_some((index, weight as f32))
+            })
+            .collect();
+        Some(SkinCluster {
+            joint,
+            to_joint: link.inverse() * transform,
+            weights,
+        })
+    }
+}
+
+/// The rigs of the instances of skinned geometries.
+///
+/// A skin binds its geometry to the joints of one of the nodes instancing
+/// it, the reference: the node with the closest common ancestor with the
+/// joints. The other nodes are bound to the joints at the same path of
+/// names under their ancestor at the same depth, their own copy of the rig.
+struct Rigs<'a> {
+    hierarchy: &'a HashMap<ObjectId, FbxObject>,
+    roots: &'a [ObjectId],
+    parents: HashMap<ObjectId, ObjectId>,
+}
+impl<'a> Rigs<'a> {
+    fn new(hierarchy: &'a HashMap<ObjectId, FbxObject>, roots: &'a [ObjectId]) -> Self {
+        let parents = (hierarchy.iter())
+            .flat_map(|(id, object)| object.children.iter().map(move |child| (*child, *id)))
+            .collect();
+        Rigs {
+            hierarchy,
+            roots,
+            parents,
+        }
+    }
+
+    /// `node` followed by its ancestors, `None` being the scene root.
+    fn ancestors(&self, node: ObjectId) -> Vec<Option<ObjectId>> {
+        let mut ancestors = vec![Some(node)];
+        let mut current = node;
+        while let Some(parent) = self.parents.get(&current) {
+            ancestors.push(Some(*parent));
+            current = *parent;
+        }
+        ancestors.push(None);
+        ancestors
+    }
+
+    /// The joints matching `joints` in the rig of each of the `instances`
+    /// of a geometry, `None` for the instances whose rig lacks some joints.
+    fn instance_joints(
+        &self,
+        instances: &[ObjectId],
+        joints: &[ObjectId],
+    ) -> Vec<(ObjectId, Option<Vec<ObjectId>>)> {
+        if instances.len() < 2 {
+            return (instances.iter())
+                .map(|instance| (*instance, Some(joints.to_vec())))
+                .collect();
+        }
+        let joint_ancestors: Vec<_> = joints.iter().map(|j| self.ancestors(*j)).collect();
+        // The scene root is a common ancestor of all nodes.
+        let depth = |instance: ObjectId| {
+            let ancestors = self.ancestors(instance);
+            let shared = |a: &Option<ObjectId>| joint_ancestors.iter().all(|j| j.contains(a));
+            ancestors
+                .iter()
+                .position(shared)
+                .unwrap_or(ancestors.len() - 1)
+        };
+        let reference = *instances.iter().min_by_key(|i| depth(**i)).unwrap();
+        let depth = depth(reference);
+        let anchor = self.ancestors(reference)[depth];
+        let paths: Option<Vec<Vec<&str>>> = (joint_ancestors.iter())
+            .map(|ancestors| {
+                let below = &ancestors[..ancestors.iter().position(|a| *a == anchor)?];
+                (below.iter().rev())
+                    .map(|node| self.hierarchy.get(&(*node)?)?.name.as_deref())
+                    .collect()
+            })
+            .collect();
+        (instances.iter())
+            .map(|instance| {
+                let instance_anchor = self.ancestors(*instance).get(depth).copied();
+                let instance_joints = match instance_anchor {
+                    Some(instance_anchor) if instance_anchor == anchor => Some(joints.to_vec()),
+                    Some(instance_anchor) => paths.as_ref().and_then(|paths| {
+                        (paths.iter())
+                            .map(|path| self.find(instance_anchor, path))
+                            .collect()
+                    }),
+                    None => None,
+                };
+                (*instance, instance_joints)
+            })
+            .collect()
+    }
+
+    /// The node at the path of names `path` under `anchor`.
+    fn find(&self, anchor: Option<ObjectId>, path: &[&str]) -> Option<ObjectId> {
+        let mut node = anchor;
+        for name in path {
+            let children = match node {
+                Some(node) => &self.hierarchy.get(&node)?.children[..],
+                None => self.roots,
+            };
+            let name_of = |child: &&ObjectId| self.hierarchy.get(child)?.name.as_deref();
+            node = Some(*children.iter().find(|child| name_of(child) == Some(name))?);
+        }
+        node
+    }
+}
@@ -0,0 +1,27 @@
+//! Attachment sockets, see [`FbxLoaderSettings::socket_pattern`].
+//!
+//! [`FbxLoaderSettings::socket_pattern`]: crate::FbxLoaderSettings::socket_pattern
+
+use bevy::prelude::{Component, Reflect, ReflectComponent};
+
+use crate::names;
+
+/// A node where props or weapons are attached, added to the nodes matching
+/// [`FbxLoaderSettings::socket_pattern`].
+///
+/// [`FbxLoaderSettings::socket_pattern`]: crate::FbxLoaderSettings::socket_pattern
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct FbxSocket {
+    /// The name of the node, without the text around the wildcards of the
+    /// pattern: `SOCKET_hand_r` is `hand_r` with the pattern `SOCKET_*`.
+    pub name: String,
+}
+impl FbxSocket {
+    /// The socket of the node `name`, which matches `pattern`.
+    pub(crate) fn new(pattern: &str, name: &str) -> Self {
+        FbxSocket {
+            name: names::wildcard_match(pattern, name).to_owned(),
+        }
+    }
+}
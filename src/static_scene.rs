@@ -0,0 +1,45 @@
+//! Scenes spawned without transform propagation,
+//! see [`FbxLoaderSettings::static_scene`].
+//!
+//! [`FbxLoaderSettings::static_scene`]: crate::FbxLoaderSettings::static_scene
+
+use bevy::prelude::{
+    Children, Component, Entity, GlobalTransform, Reflect, ReflectComponent, Transform, With, World,
+};
+
+use crate::FbxSceneRoot;
+
+/// Added to the root of the scenes loaded with
+/// [`FbxLoaderSettings::static_scene`].
+///
+/// The entities of the scene have no `Transform`, only their
+/// `GlobalTransform`, computed at import, which bevy doesn't update.
+///
+/// [`FbxLoaderSettings::static_scene`]: crate::FbxLoaderSettings::static_scene
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct FbxStaticScene;
+
+/// Replace the `Transform`s of the hierarchy under the [`FbxSceneRoot`]
+/// of `world` by the `GlobalTransform` they add up to.
+pub(crate) fn make_static(world: &mut World) {
+    let mut roots = world.query_filtered::<Entity, With<FbxSceneRoot>>();
+    let roots: Vec<Entity> = roots.iter(world).collect();
+    let mut stack: Vec<_> = (roots.iter())
+        .map(|root| (*root, GlobalTransform::IDENTITY))
+        .collect();
+    while let Some((entity, parent)) = stack.pop() {
+        let mut entity = world.entity_mut(entity);
+        let Some(transform) = entity.take::<Transform>() else {
+            continue;
+        };
+        let global = parent.mul_transform(transform);
+        entity.insert(global);
+        if let Some(children) = entity.get::<Children>() {
+            stack.extend(children.iter().map(|child| (*child, global)));
+        }
+    }
+    for root in roots {
+        world.entity_mut(root).insert(FbxStaticScene);
+    }
+}
@@ -0,0 +1,177 @@
+//! Textures loaded at a proxy resolution first, their full resolution
+//! streamed afterwards, see [`FbxLoaderSettings::texture_streaming`].
+//!
+//! [`FbxLoaderSettings::texture_streaming`]: crate::FbxLoaderSettings::texture_streaming
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use bevy::{
+    prelude::{warn, AssetEvent, Assets, EventReader, Handle, Image, Res, ResMut, Resource, Vec2},
+    reflect::TypeUuid,
+    render::{
+        render_resource::{Extent3d, TextureFormat},
+        texture::{CompressedImageFormats, ImageSampler, ImageType},
+    },
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+
+/// The full resolution of a texture loaded as a proxy, see
+/// [`FbxLoaderSettings::texture_streaming`].
+///
+/// Holds the image file the texture was read from, decoded when the
+/// texture is streamed with the [`FbxTextureStreamer`].
+///
+/// [`FbxLoaderSettings::texture_streaming`]: crate::FbxLoaderSettings::texture_streaming
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "0b9a7e3c-52d4-4c1e-9f6a-7d2e8c41b5a9"]
+pub struct FbxStreamedTexture {
+    /// The proxy texture, replaced by the full resolution once streamed.
+    pub proxy: Handle<Image>,
+    /// The size of the texture at full resolution.
+    pub size: Extent3d,
+    /// Whether the texture is streamed as soon as it is loaded,
+    /// see [`FbxTextureStreaming::stream_after_load`].
+    ///
+    /// [`FbxTextureStreaming::stream_after_load`]: crate::FbxTextureStreaming::stream_after_load
+    pub stream_after_load: bool,
+    pub(crate) file: Arc<[u8]>,
+    pub(crate) extension: String,
+    pub(crate) sampler: ImageSampler,
+    pub(crate) supported: CompressedImageFormats,
+}
+impl FbxStreamedTexture {
+    /// Decode the texture at full resolution.
+    fn decode(&self) -> impl FnOnce() -> anyhow::Result<Image> {
+        let (file, extension) = (self.file.clone(), self.extension.clone());
+        let (sampler, supported) = (self.sampler.clone(), self.supported);
+        move || {
+            let image_type = ImageType::Extension(&extension);
+            let mut image = Image::from_buffer(&file, image_type, supported, false)
+                .context("Failed to read image buffer data")?;
+            image.sampler_descriptor = sampler;
+            Ok(image)
+        }
+    }
+}
+
+/// Resource streaming the full resolution of the textures loaded as
+/// proxies, see [`FbxLoaderSettings::texture_streaming`].
+///
+/// The textures loaded with [`FbxTextureStreaming::stream_after_load`]
+/// are streamed as soon as their file is loaded, the others when requested.
+/// Textures are decoded on the `AsyncComputeTaskPool`, then replace their
+/// proxy in `Assets<Image>`, so the materials using them are updated.
+///
+/// [`FbxLoaderSettings::texture_streaming`]: crate::FbxLoaderSettings::texture_streaming
+/// [`FbxTextureStreaming::stream_after_load`]: crate::FbxTextureStreaming::stream_after_load
+#[derive(Resource, Default)]
+pub struct FbxTextureStreamer {
+    requests: Vec<Handle<FbxStreamedTexture>>,
+    tasks: Vec<(Handle<Image>, Task<anyhow::Result<Image>>)>,
+}
+impl FbxTextureStreamer {
+    /// Stream the full resolution of `texture`, see [`FbxScene::streamed_textures`].
+    ///
+    /// [`FbxScene::streamed_textures`]: crate::FbxScene::streamed_textures
+    pub fn request(&mut self, texture: Handle<FbxStreamedTexture>) {
+        self.requests.push(texture);
+    }
+
+    /// The number of textures requested or being decoded.
+    pub fn pending(&self) -> usize {
+        self.requests.len() + self.tasks.len()
+    }
+}
+
+/// Decode the requested textures, and replace their proxy once decoded.
+pub(crate) fn stream_textures(
+    mut streamer: ResMut<FbxTextureStreamer>,
+    mut events: EventReader<AssetEvent<FbxStreamedTexture>>,
+    textures: Res<Assets<FbxStreamedTexture>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } = event {
+            let texture = textures.get(handle);
+            if texture.is_some_and(|texture| texture.stream_after_load) {
+                streamer.request(handle.clone());
+            }
+        }
+    }
+    let streamer = &mut *streamer;
+    let pool = AsyncComputeTaskPool::get();
+    streamer.requests.retain(|handle| {
+        let Some(texture) = textures.get(handle) else {
+            return true;
+        };
+        let decode = texture.decode();
+        let task = pool.spawn(async move { decode() });
+        streamer.tasks.push((texture.proxy.clone_weak(), task));
+        false
+    });
+    streamer.tasks.retain_mut(|(proxy, task)| {
+        let Some(image) = future::block_on(future::poll_once(task)) else {
+            return true;
+        };
+        match image {
+            Ok(image) => images.set_untracked(proxy.id(), image),
+            Err(err) => warn!("Failed to stream texture: {err:?}"),
+        }
+        false
+    });
+}
+
+/// `image` scaled down to fit in `max_size`, averaging its texels.
+///
+/// `None` if it already fits, or if its format isn't 8 bits per channel.
+pub(crate) fn proxy(image: &Image, max_size: u32) -> Option<Image> {
+    let channels = match image.texture_descriptor.format {
+        TextureFormat::R8Unorm => 1,
+        TextureFormat::Rg8Unorm => 2,
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb => 4,
+        _ => return None,
+    };
+    let size = image.texture_descriptor.size;
+    let layers = size.depth_or_array_layers as usize;
+    let max_size = max_size.max(1);
+    if size.width.max(size.height) <= max_size || image.texture_descriptor.mip_level_count > 1 {
+        return None;
+    }
+    let (width, height) = (size.width as usize, size.height as usize);
+    if image.data.len() != width * height * layers * channels {
+        return None;
+    }
+    let scale = max_size as f32 / size.width.max(size.height) as f32;
+    let proxy_size = (Vec2::new(size.width as f32, size.height as f32) * scale).ceil();
+    let (proxy_width, proxy_height) = (proxy_size.x as usize, proxy_size.y as usize);
+    let mut data = Vec::with_capacity(proxy_width * proxy_height * layers * channels);
+    for layer in image.data.chunks_exact(width * height * channels) {
+        for y in 0..proxy_height {
+            let (y0, y1) = (y * height / proxy_height, (y + 1) * height / proxy_height);
+            for x in 0..proxy_width {
+                let (x0, x1) = (x * width / proxy_width, (x + 1) * width / proxy_width);
+                let count = ((x1 - x0) * (y1 - y0)).max(1) as u32;
+                for channel in 0..channels {
+                    let sum: u32 = (y0..y1)
+                        .flat_map(|y| (x0..x1).map(move |x| (y * width + x) * channels + channel))
+                        .map(|i| layer[i] as u32)
+                        .sum();
+                    data.push((sum / count) as u8);
+                }
+            }
+        }
+    }
+    let mut proxy = image.clone();
+    proxy.data = data;
+    proxy.texture_descriptor.size = Extent3d {
+        width: proxy_width as u32,
+        height: proxy_height as u32,
+        depth_or_array_layers: size.depth_or_array_layers,
+    };
+    Some(proxy)
+}
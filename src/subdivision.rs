@@ -0,0 +1,364 @@
+//! Subdivision surfaces.
+//!
+//! Meshes modeled as subdivision cages carry creases, sharpening edges
+//! and vertices of the smoothed surface, and the subdivision levels used
+//! by the DCC. They are exposed as [`FbxSubdivisionData`], and the
+//! `FbxLoader` can subdivide meshes at import,
+//! see [`FbxLoaderSettings::subdivision_level`].
+//!
+//! [`FbxLoaderSettings::subdivision_level`]: crate::FbxLoaderSettings::subdivision_level
+
+use bevy::{
+    math::{Vec2, Vec3},
+    utils::{HashMap, HashSet},
+};
+use fbxcel_dom::{fbxcel::tree::v7400::NodeHandle, v7400::data::mesh::layer::MappingMode};
+
+use crate::utils::layer_element::{self, MeshTopology};
+
+/// The subdivision surface data of a mesh.
+///
+/// Edges are identified by the control points at their ends, smallest first.
+#[derive(Clone, Debug, Default)]
+pub struct FbxSubdivisionData {
+    /// The crease of each creased edge, from 0 (smooth) to 1 (sharp).
+    pub edge_creases: HashMap<(u32, u32), f32>,
+    /// The crease of each creased control point, from 0 (smooth) to 1 (corner).
+    pub vertex_creases: HashMap<u32, f32>,
+    /// Edges with a hard normal, from the mesh smoothing
+    /// (either by edge, or by polygon smoothing groups).
+    pub hard_edges: HashSet<(u32, u32)>,
+    /// The subdivision level displayed in the DCC viewport (`PreviewDivisionLevels`).
+    pub preview_levels: Option<u32>,
+    /// The subdivision level used when rendering (`RenderDivisionLevels`).
+    pub render_levels: Option<u32>,
+}
+impl FbxSubdivisionData {
+    /// Read the subdivision data of `geometry`, `None` if it has none.
+    pub(crate) fn from_geometry(
+        geometry: &NodeHandle,
+        layer: &NodeHandle,
+        topology: &MeshTopology,
+    ) -> anyhow::Result<Option<Self>> {
+        let mut data = FbxSubdivisionData::default();
+        let element = |ty| layer_element::find_element(geometry, layer, ty);
+        if let Some(creases) = element("LayerElementEdgeCrease") {
+            let creases = layer_element::f64_element(&creases, "EdgeCrease", "EdgeCreaseIndex", 1)?;
+            for pvi in 0..topology.len() {
+                let crease = creases.get(topology, pvi)?[0] as f32;
+                if crease > 0.0 {
+                    data.edge_creases.insert(topology.edge(pvi), crease);
+                }
+            }
+        }
+        if let Some(creases) = element("LayerElementVertexCrease") {
+            let creases =
+                layer_element::f64_element(&creases, "VertexCrease", "VertexCreaseIndex", 1)?;
+            for pvi in 0..topology.len() {
+                let crease = creases.get(topology, pvi)?[0] as f32;
+                if crease > 0.0 {
+                    data.vertex_creases
+                        .insert(topology.control_point(pvi), crease);
+                }
+            }
+        }
+        if let Some(smoothing) = element("LayerElementSmoothing") {
+            let (mapping, smoothing) = layer_element::i32_element(&smoothing, "Smoothing")?;
+            data.hard_edges = hard_edges(mapping, &smoothing, topology)?;
+        }
+        let level = |name| {
+            let node = geometry.first_child_by_name(name)?;
+            let level = node.attributes().first()?.get_i32()?;
+            u32::try_from(level).ok()
+        };
+        data.preview_levels = level("PreviewDivisionLevels");
+        data.render_levels = level("RenderDivisionLevels");
+        let is_empty = data.edge_creases.is_empty()
+            && data.vertex_creases.is_empty()
+            && data.hard_edges.is_empty()
+            && data.preview_levels.is_none()
+            && data.render_levels.is_none();
+        Ok((!is_empty).then_some(data))
+    }
+}
+
+/// The hard edges of a `LayerElementSmoothing`.
+///
+/// By edge, 0 marks hard edges. By polygon, each value is a bit set of
+/// smoothing groups, and edges between polygons sharing no group are hard.
+fn hard_edges(
+    mapping: MappingMode,
+    smoothing: &layer_element::LayerElement<i32>,
+    topology: &MeshTopology,
+) -> anyhow::Result<HashSet<(u32, u32)>> {
+    let mut hard_edges = HashSet::default();
+    if mapping == MappingMode::ByPolygon {
+        let mut groups: HashMap<(u32, u32), Vec<i32>> = HashMap::default();
+        for pvi in 0..topology.len() {
+            let group = smoothing.get(topology, pvi)?[0];
+            groups.entry(topology.edge(pvi)).or_default().push(group);
+        }
+        for (edge, groups) in groups {
+            if let [a, b] = groups[..] {
+                if a & b == 0 {
+                    hard_edges.insert(edge);
+                }
+            }
+        }
+    } else {
+        for pvi in 0..topology.len() {
+            if smoothing.get(topology, pvi)?[0] == 0 {
+                hard_edges.insert(topology.edge(pvi));
+            }
+        }
+    }
+    Ok(hard_edges)
+}
+
+/// A polygon mesh, subdivided with Catmull-Clark.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Cage {
+    pub(crate) positions: Vec<Vec3>,
+    /// The control points of each polygon.
+    pub(crate) polygons: Vec<Vec<u32>>,
+    /// The UV of each corner of each polygon.
+    pub(crate) uvs: Vec<Vec<Vec2>>,
+    /// For each polygon, the polygon of the original cage it comes from.
+    pub(crate) origins: Vec<usize>,
+    pub(crate) edge_creases: HashMap<(u32, u32), f32>,
+    pub(crate) vertex_creases: HashMap<u32, f32>,
+}
+impl Cage {
+    /// One level of Catmull-Clark subdivision, every polygon becomes
+    /// one quad per corner.
+    ///
+    /// Creases blend the smooth rules with the sharp rules,
+    /// and are kept on both halves of creased edges.
+    /// Boundary edges are always sharp.
+    pub(crate) fn subdivide(&self) -> Cage {
+        let key = |a: u32, b: u32| (a.min(b), a.max(b));
+        let corners = |polygon: &[u32]| {
+            let next = polygon.iter().cycle().skip(1);
+            polygon
+                .iter()
+                .zip(next)
+                .map(|(a, b)| (*a, *b))
+                .collect::<Vec<_>>()
+        };
+        let average = |points: &mut dyn Iterator<Item = Vec3>| {
+            let (sum, count) = points.fold((Vec3::ZERO, 0.0), |(s, c), p| (s + p, c + 1.0));
+            sum / f32::max(count, 1.0)
+        };
+
+        let face_points: Vec<Vec3> = self
+            .polygons
+            .iter()
+            .map(|polygon| average(&mut polygon.iter().map(|i| self.positions[*i as usize])))
+            .collect();
+
+        let mut edges: Vec<(u32, u32)> = Vec::new();
+        let mut edge_faces: HashMap<(u32, u32), Vec<usize>> = HashMap::default();
+        for (face, polygon) in self.polygons.iter().enumerate() {
+            for (a, b) in corners(polygon) {
+                let faces = edge_faces.entry(key(a, b)).or_default();
+                if faces.is_empty() {
+                    edges.push(key(a, b));
+                }
+                faces.push(face);
+            }
+        }
+        let crease = |edge: &(u32, u32)| match edge_faces[edge].len() {
+            2 => self
+                .edge_creases
+                .get(edge)
+                .map_or(0.0, |c| c.clamp(0.0, 1.0)),
+            _ => 1.0,
+        };
+
+        let point_count = self.positions.len() as u32;
+        let face_start = point_count;
+        let edge_start = face_start + face_points.len() as u32;
+        let edge_indices: HashMap<(u32, u32), u32> = edges
+            .iter()
+            .enumerate()
+            .map(|(i, edge)| (*edge, edge_start + i as u32))
+            .collect();
+
+        let mut positions = Vec::with_capacity(edge_start as usize + edges.len());
+        // Vertex points.
+        let mut vertex_faces = vec![Vec::new(); self.positions.len()];
+        for (face, polygon) in self.polygons.iter().enumerate() {
+            for point in polygon {
+                vertex_faces[*point as usize].push(face);
+            }
+        }
+        let mut vertex_edges = vec![Vec::new(); self.positions.len()];
+        for edge in &edges {
+            vertex_edges[edge.0 as usize].push(*edge);
+            vertex_edges[edge.1 as usize].push(*edge);
+        }
+        for (i, point) in self.positions.iter().enumerate() {
+            let (faces, incident) = (&vertex_faces[i], &vertex_edges[i]);
+            if faces.is_empty() {
+                positions.push(*point);
+                continue;
+            }
+            let other = |edge: &(u32, u32)| match edge.0 == i as u32 {
+                true => self.positions[edge.1 as usize],
+                false => self.positions[edge.0 as usize],
+            };
+            let valence = incident.len() as f32;
+            let f = average(&mut faces.iter().map(|f| face_points[*f]));
+            let r = average(&mut incident.iter().map(|e| (*point + other(e)) / 2.0));
+            let smooth = (f + 2.0 * r + (valence - 3.0) * *point) / valence;
+            let sharp: Vec<_> = incident
+                .iter()
+                .map(|e| (other(e), crease(e)))
+                .filter(|(_, crease)| *crease > 0.0)
+                .collect();
+            let sharpness = sharp.iter().map(|(_, c)| c).sum::<f32>() / sharp.len().max(1) as f32;
+            let vertex_point = match sharp[..] {
+                [] | [_] => smooth,
+                [(a, _), (b, _)] => smooth.lerp((6.0 * *point + a + b) / 8.0, sharpness),
+                _ => smooth.lerp(*point, sharpness),
+            };
+            let corner = self.vertex_creases.get(&(i as u32)).copied();
+            let corner = corner.unwrap_or(0.0).clamp(0.0, 1.0);
+            positions.push(vertex_point.lerp(*point, corner));
+        }
+        positions.extend_from_slice(&face_points);
+        // Edge points.
+        for edge in &edges {
+            let (a, b) = (
+                self.positions[edge.0 as usize],
+                self.positions[edge.1 as usize],
+            );
+            let middle = (a + b) / 2.0;
+            let smooth = match edge_faces[edge][..] {
+                [f0, f1] => (a + b + face_points[f0] + face_points[f1]) / 4.0,
+                _ => middle,
+            };
+            positions.push(smooth.lerp(middle, crease(edge)));
+        }
+
+        let mut cage = Cage {
+            positions,
+            vertex_creases: self.vertex_creases.clone(),
+            ..Default::default()
+        };
+        for (edge, crease) in &self.edge_creases {
+            if let Some(middle) = edge_indices.get(edge) {
+                cage.edge_creases.insert(key(edge.0, *middle), *crease);
+                cage.edge_creases.insert(key(*middle, edge.1), *crease);
+            }
+        }
+        for (face, polygon) in self.polygons.iter().enumerate() {
+            let n = polygon.len();
+            let uvs = &self.uvs[face];
+            let face_uv = uvs.iter().sum::<Vec2>() / n.max(1) as f32;
+            for i in 0..n {
+                let (prev, next) = ((i + n - 1) % n, (i + 1) % n);
+                cage.polygons.push(vec![
+                    polygon[i],
+                    edge_indices[&key(polygon[i], polygon[next])],
+                    face_start + face as u32,
+                    edge_indices[&key(polygon[prev], polygon[i])],
+                ]);
+                cage.uvs.push(vec![
+                    uvs[i],
+                    (uvs[i] + uvs[next]) / 2.0,
+                    face_uv,
+                    (uvs[prev] + uvs[i]) / 2.0,
+                ]);
+                cage.origins.push(self.origins[face]);
+            }
+        }
+        cage
+    }
+
+    /// The smooth normal of each point, averaged over the polygons around it.
+    pub(crate) fn normals(&self) -> Vec<Vec3> {
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+        for polygon in &self.polygons {
+            let position = |i: usize| self.positions[polygon[i % polygon.len()] as usize];
+            // Newell's method, robust to non planar polygons.
+            let normal: Vec3 = (0..polygon.len())
+                .map(|i| position(i).cross(position(i + 1)))
+                .sum();
+            for point in polygon {
+                normals[*point as usize] += normal;
+            }
+        }
+        normals.iter().map(|n| n.normalize_or_zero()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit cube, its faces wound outward.
+    fn cube() -> Cage {
+        let positions = (0..8)
+            .map(|i| Vec3::new((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2) as f32))
+            .collect();
+        let polygons: Vec<Vec<u32>> = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        Cage {
+            positions,
+            uvs: vec![vec![Vec2::ZERO; 4]; polygons.len()],
+            origins: (0..polygons.len()).collect(),
+            polygons,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cube_counts() {
+        let once = cube().subdivide();
+        // A vertex per corner, face and edge of the cage.
+        assert_eq!(once.positions.len(), 8 + 6 + 12);
+        assert_eq!(once.polygons.len(), 6 * 4);
+        assert!(once.polygons.iter().all(|polygon| polygon.len() == 4));
+        assert_eq!(once.uvs.len(), once.polygons.len());
+        assert_eq!(once.origins.iter().filter(|o| **o == 5).count(), 4);
+
+        let twice = once.subdivide();
+        assert_eq!(twice.positions.len(), 26 + 24 + 48);
+        assert_eq!(twice.polygons.len(), 24 * 4);
+    }
+
+    #[test]
+    fn smooth_cube_shrinks() {
+        let once = cube().subdivide();
+        let center = Vec3::splat(0.5);
+        // Corners move in, to 5/9 of their distance to the center.
+        for corner in &once.positions[..8] {
+            let distance = (*corner - center).abs();
+            assert!(distance.abs_diff_eq(Vec3::splat(0.5 * 5.0 / 9.0), 1e-6));
+        }
+        let normals = once.normals();
+        assert!(normals[0].abs_diff_eq(-Vec3::ONE.normalize(), 1e-6));
+    }
+
+    #[test]
+    fn creased_cube_keeps_its_corners() {
+        let mut cage = cube();
+        for polygon in &cage.polygons {
+            for (i, a) in polygon.iter().enumerate() {
+                let b = polygon[(i + 1) % polygon.len()];
+                cage.edge_creases.insert((*a.min(&b), *a.max(&b)), 1.0);
+            }
+        }
+        let once = cage.subdivide();
+        assert_eq!(&once.positions[..8], &cage.positions[..]);
+        // Creases are kept on both halves of the edges.
+        assert_eq!(once.edge_creases.len(), 2 * 12);
+    }
+}
@@ -0,0 +1,37 @@
+//! Limits on the threads used by FBX loads, see [`FbxLoaderSettings::tasks`].
+//!
+//! [`FbxLoaderSettings::tasks`]: crate::FbxLoaderSettings::tasks
+
+use async_channel::{Receiver, Sender};
+
+/// The slots of the FBX files being converted at the same time,
+/// see [`FbxTaskSettings::max_parallel_loads`].
+///
+/// [`FbxTaskSettings::max_parallel_loads`]: crate::FbxTaskSettings::max_parallel_loads
+#[derive(Clone, Default)]
+pub(crate) struct LoadSlots(Option<(Sender<()>, Receiver<()>)>);
+impl LoadSlots {
+    pub(crate) fn new(max_parallel_loads: Option<usize>) -> Self {
+        LoadSlots(max_parallel_loads.map(|max| async_channel::bounded(max.max(1))))
+    }
+
+    /// Wait for a free slot, taken until the returned guard is dropped.
+    pub(crate) async fn take(&self) -> LoadSlot {
+        let Some((sender, receiver)) = &self.0 else {
+            return LoadSlot(None);
+        };
+        // The channel is never closed, both ends being kept here.
+        let _ = sender.send(()).await;
+        LoadSlot(Some(receiver.clone()))
+    }
+}
+
+/// A slot of [`LoadSlots`], freed when dropped.
+pub(crate) struct LoadSlot(Option<Receiver<()>>);
+impl Drop for LoadSlot {
+    fn drop(&mut self) {
+        if let Some(receiver) = &self.0 {
+            let _ = receiver.try_recv();
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Preview thumbnails embedded in FBX files, see [`FbxScene::thumbnail`].
+//!
+//! [`FbxScene::thumbnail`]: crate::FbxScene::thumbnail
+
+use bevy::{
+    prelude::Image,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use fbxcel_dom::{fbxcel::low::v7400::AttributeValue, v7400::Document};
+
+/// The preview thumbnail embedded in `doc`, if it has one.
+///
+/// The FBX SDK stores it in the `SceneInfo` of the header, older exporters
+/// directly in the header or at the top level of the file. Only raw 8 bits
+/// RGB or RGBA texels are supported, the format is told by the data length.
+pub(crate) fn thumbnail(doc: &Document) -> Option<Image> {
+    let root = doc.tree().root();
+    let header = root.first_child_by_name("FBXHeaderExtension");
+    let node = header
+        .and_then(|header| header.first_child_by_name("SceneInfo"))
+        .and_then(|info| info.first_child_by_name("Thumbnail"))
+        .or_else(|| header?.first_child_by_name("Thumbnail"))
+        .or_else(|| root.first_child_by_name("Thumbnail"))?;
+    let attribute = |name| node.first_child_by_name(name)?.attributes().first();
+    if attribute("Encoding").and_then(|e| e.get_i32()).unwrap_or(0) != 0 {
+        return None;
+    }
+    let size = |value: &AttributeValue| u32::try_from(value.get_i32()?).ok().filter(|s| *s > 0);
+    let (width, height) = match node.first_child_by_name("Size")?.attributes() {
+        [width, height, ..] => (size(width)?, size(height)?),
+        [side] => (size(side)?, size(side)?),
+        [] => return None,
+    };
+    let data = attribute("ImageData")?.get_binary()?;
+    let texels = width as usize * height as usize;
+    let rgba = if data.len() == texels * 4 {
+        data.to_vec()
+    } else if data.len() == texels * 3 {
+        let rgb = data.chunks_exact(3);
+        rgb.flat_map(|t| [t[0], t[1], t[2], u8::MAX]).collect()
+    } else {
+        return None;
+    };
+    let size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    Some(Image::new(
+        size,
+        TextureDimension::D2,
+        rgba,
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+}
@@ -0,0 +1,81 @@
+//! Detection of materials that should ignore lighting, such as UI panels
+//! and skydomes, see [`FbxLoaderSettings::detect_unlit_materials`].
+//!
+//! [`FbxLoaderSettings::detect_unlit_materials`]: crate::FbxLoaderSettings::detect_unlit_materials
+
+use bevy::prelude::{Color, StandardMaterial};
+use fbxcel_dom::v7400::object::material::MaterialHandle;
+use rgb::RGB;
+
+use crate::{utils::fbx_extend::ObjectPropertiesExt, FbxExtras, FbxPropertyValue};
+
+/// The user-defined material property forcing a material to be lit or unlit.
+const UNLIT_PROPERTY: &str = "bevy_unlit";
+
+/// Emissive materials brighter than this, on their brightest channel,
+/// are considered self-illuminated.
+const MIN_EMISSIVE: f32 = 0.5;
+
+/// Make `material` unlit if the artist asked for it with the `bevy_unlit`
+/// property of `material_obj`, or, when `detect` is set, if it only emits light.
+pub(crate) fn apply_unlit(
+    material_obj: MaterialHandle,
+    material: &mut StandardMaterial,
+    detect: bool,
+) {
+    let emissive = emissive_color(material_obj, material);
+    let emission_only = !has_diffuse(material_obj, material)
+        && (material.emissive_texture.is_some() || max_channel(emissive) >= MIN_EMISSIVE);
+    let unlit = match unlit_property(material_obj) {
+        Some(unlit) => unlit,
+        None => detect && emission_only,
+    };
+    if !unlit {
+        return;
+    }
+    material.unlit = true;
+    // Unlit materials render their base color, move the emission there.
+    if emission_only {
+        material.base_color = emissive.with_a(material.base_color.a());
+        material.base_color_texture = material.emissive_texture.take();
+        material.emissive = Color::BLACK;
+    }
+}
+
+fn unlit_property(material_obj: MaterialHandle) -> Option<bool> {
+    let extras = FbxExtras::from_object(&material_obj);
+    match extras.properties.get(UNLIT_PROPERTY)? {
+        FbxPropertyValue::Bool(unlit) => Some(*unlit),
+        FbxPropertyValue::Int(unlit) => Some(*unlit != 0),
+        FbxPropertyValue::Float(unlit) => Some(*unlit != 0.0),
+        _ => None,
+    }
+}
+
+fn has_diffuse(material_obj: MaterialHandle, material: &StandardMaterial) -> bool {
+    let factor = material_obj.properties().get::<f64>("DiffuseFactor");
+    let colored = material.base_color_texture.is_some() || max_channel(material.base_color) > 1e-3;
+    factor.map_or(true, |factor| factor > 0.0) && colored
+}
+
+/// The emissive color of the material, the material loaders may ignore
+/// the FBX `EmissiveColor` of Lambert and Phong materials.
+fn emissive_color(material_obj: MaterialHandle, material: &StandardMaterial) -> Color {
+    if max_channel(material.emissive) > 0.0 {
+        return material.emissive;
+    }
+    let properties = material_obj.properties();
+    let Some(RGB { r, g, b }) = properties.get::<RGB<f64>>("EmissiveColor") else {
+        return material.emissive;
+    };
+    let factor = properties.get::<f64>("EmissiveFactor").unwrap_or(1.0);
+    Color::rgb(
+        (r * factor) as f32,
+        (g * factor) as f32,
+        (b * factor) as f32,
+    )
+}
+
+fn max_channel(color: Color) -> f32 {
+    color.r().max(color.g()).max(color.b())
+}
@@ -0,0 +1,50 @@
+//! Intermediate buffers of mesh conversion, reused for the whole load.
+
+use bevy::utils::HashMap;
+use fbxcel_dom::v7400::data::mesh::PolygonVertexIndex;
+
+/// The buffers a mesh conversion only needs while converting the mesh.
+///
+/// A load keeps a single arena for all its meshes: converting a mesh only
+/// [`reset`]s the buffers, reusing their memory rather than freeing it. The
+/// buffers grow to the size needed by the largest mesh once, instead of being
+/// allocated and freed again for each of the thousands of meshes some scenes
+/// are made of. The memory goes back to the allocator at the end of the load.
+///
+/// [`reset`]: MeshArena::reset
+#[derive(Default)]
+pub(crate) struct MeshArena {
+    /// The raw index of the polygon vertices, recorded while triangulating.
+    pub(crate) raw_pvis: HashMap<PolygonVertexIndex, usize>,
+    /// The raw polygon vertex index of each triangle vertex.
+    pub(crate) triangle_raw_pvis: Vec<usize>,
+    /// The control points, converted to `f32`.
+    pub(crate) control_points: Vec<f32>,
+    /// The values of the layer element being read, converted to `f32`.
+    pub(crate) values: Vec<f32>,
+    /// The tangent of each triangle vertex, combined with
+    /// [`binormals`](MeshArena::binormals) into the Bevy tangents.
+    pub(crate) tangents: Vec<[f32; 3]>,
+    /// The binormal of each triangle vertex.
+    pub(crate) binormals: Vec<[f32; 3]>,
+}
+impl MeshArena {
+    /// The memory held by the buffers.
+    pub(crate) fn capacity_bytes(&self) -> usize {
+        let raw_pvis = std::mem::size_of::<(PolygonVertexIndex, usize)>();
+        self.raw_pvis.capacity() * raw_pvis
+            + self.triangle_raw_pvis.capacity() * std::mem::size_of::<usize>()
+            + (self.control_points.capacity() + self.values.capacity()) * 4
+            + (self.tangents.capacity() + self.binormals.capacity()) * 12
+    }
+
+    /// Empty the buffers for the next mesh, keeping their memory.
+    pub(crate) fn reset(&mut self) {
+        self.raw_pvis.clear();
+        self.triangle_raw_pvis.clear();
+        self.control_points.clear();
+        self.values.clear();
+        self.tangents.clear();
+        self.binormals.clear();
+    }
+}
@@ -0,0 +1,175 @@
+//! Checks of the binary framing of FBX files: the header and the footer,
+//! which some exporters write incorrectly.
+//!
+//! The parser ignores the footer, but fails on a bad header and reports
+//! unsupported files with cryptic errors, so they are checked beforehand.
+
+use std::borrow::Cow;
+
+use anyhow::bail;
+
+/// The magic string at the start of binary FBX files.
+const MAGIC: &[u8] = b"Kaydara FBX Binary  \x00";
+/// The two bytes following [`MAGIC`], some exporters write other values.
+const MAGIC_END: [u8; 2] = [0x1a, 0x00];
+/// Length of the header: magic string, its two last bytes and the version.
+const HEADER_LEN: usize = MAGIC.len() + MAGIC_END.len() + 4;
+/// The last 16 bytes of files written by the FBX SDK.
+const FOOTER_END: [u8; 16] = [
+    0xf8, 0x5a, 0x8c, 0x6a, 0xde, 0xf5, 0xd9, 0x7e, 0xec, 0xe9, 0x0c, 0xe3, 0x75, 0x8f, 0x29, 0x0b,
+];
+/// Length of the end of the footer, after its padding: 4 zeroes,
+/// the version, 120 zeroes and [`FOOTER_END`].
+const FOOTER_TAIL_LEN: usize = 4 + 4 + 120 + FOOTER_END.len();
+
+/// Check the header and footer of the FBX file `bytes`.
+///
+/// Returns the bytes to parse, with known-bad headers fixed, and the
+/// anomalies found, which don't prevent loading the file. Fails with an
+/// explicit error for files that can't be parsed, such as ASCII FBX files.
+pub(crate) fn check(bytes: &[u8]) -> anyhow::Result<(Cow<'_, [u8]>, Vec<String>)> {
+    if !bytes.starts_with(MAGIC) {
+        let start = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]);
+        if start.starts_with("; FBX") || start.contains("FBXHeaderExtension:") {
+            bail!("ASCII FBX files are not supported, export the file as binary FBX");
+        }
+        bail!("Not a binary FBX file, it doesn't start with `Kaydara FBX Binary`");
+    }
+    if bytes.len() < HEADER_LEN {
+        bail!("Truncated FBX file, the header is incomplete");
+    }
+    let mut warnings = Vec::new();
+    let mut bytes = Cow::Borrowed(bytes);
+    let magic_end = &bytes[MAGIC.len()..MAGIC.len() + 2];
+    if magic_end != MAGIC_END {
+        warnings.push(format!(
+            "Unexpected bytes {magic_end:02x?} after the FBX magic string, expected {MAGIC_END:02x?}"
+        ));
+        bytes.to_mut()[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&MAGIC_END);
+    }
+    let version = read_u32(&bytes, HEADER_LEN - 4);
+    if !(7000..8000).contains(&version) {
+        let (major, minor) = (version / 1000, version % 1000 / 100);
+        bail!("FBX {major}.{minor} files are not supported, only FBX 7.x files are");
+    }
+
+    if !bytes.ends_with(&FOOTER_END) || bytes.len() < HEADER_LEN + FOOTER_TAIL_LEN {
+        warnings.push(
+            "Missing or non-standard FBX footer, the file may be truncated \
+             or written by a third party exporter"
+                .to_owned(),
+        );
+        return Ok((bytes, warnings));
+    }
+    let tail = bytes.len() - FOOTER_TAIL_LEN;
+    if tail % 16 != 0 {
+        warnings.push(format!(
+            "The padding of the FBX footer is wrong, it ends at byte {tail}, \
+             which isn't a multiple of 16"
+        ));
+    }
+    let footer_version = read_u32(&bytes, tail + 4);
+    if footer_version != version {
+        warnings.push(format!(
+            "The FBX footer is for version {footer_version}, the header for version {version}"
+        ));
+    }
+    let zeroes = bytes[tail..tail + 4]
+        .iter()
+        .chain(&bytes[tail + 8..tail + 128]);
+    if zeroes.copied().any(|byte| byte != 0) {
+        warnings.push("The FBX footer has unexpected non-zero bytes".to_owned());
+    }
+    Ok((bytes, warnings))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSION: u32 = 7400;
+
+    /// A binary FBX file of `version` with `body_len` bytes of content,
+    /// and a footer of `footer_version` padded to 16 bytes.
+    fn file(version: u32, body_len: usize, footer_version: u32) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(MAGIC_END);
+        bytes.extend(version.to_le_bytes());
+        bytes.resize(bytes.len() + body_len, 0xcc);
+        bytes.resize((bytes.len() + 15) / 16 * 16, 0);
+        bytes.extend([0; 4]);
+        bytes.extend(footer_version.to_le_bytes());
+        bytes.extend([0; 120]);
+        bytes.extend(FOOTER_END);
+        bytes
+    }
+
+    fn warnings(bytes: &[u8]) -> Vec<String> {
+        check(bytes).unwrap().1
+    }
+
+    #[test]
+    fn valid_file() {
+        let bytes = file(VERSION, 37, VERSION);
+        let (checked, warnings) = check(&bytes).unwrap();
+        assert!(matches!(checked, Cow::Borrowed(_)));
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn bad_magic_tail_is_fixed() {
+        let mut bytes = file(VERSION, 37, VERSION);
+        bytes[MAGIC.len() + 1] = 0x01;
+        let (checked, warnings) = check(&bytes).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("after the FBX magic string"));
+        assert_eq!(checked[MAGIC.len()..MAGIC.len() + 2], MAGIC_END);
+        assert_eq!(checked[MAGIC.len() + 2..], bytes[MAGIC.len() + 2..]);
+    }
+
+    #[test]
+    fn ascii_file_fails() {
+        let bytes = b"; FBX 7.4.0 project file\nFBXHeaderExtension:  {\n}\n";
+        let err = check(bytes).unwrap_err().to_string();
+        assert!(err.contains("ASCII"), "{err}");
+    }
+
+    #[test]
+    fn old_version_fails() {
+        let err = check(&file(6100, 37, 6100)).unwrap_err().to_string();
+        assert!(err.contains("FBX 6.1"), "{err}");
+    }
+
+    #[test]
+    fn wrong_footer_version() {
+        let warnings = warnings(&file(VERSION, 37, 7500));
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("footer is for version 7500"),
+            "{warnings:?}"
+        );
+    }
+
+    #[test]
+    fn misaligned_padding() {
+        let mut bytes = file(VERSION, 37, VERSION);
+        let tail = bytes.len() - FOOTER_TAIL_LEN;
+        bytes.insert(tail, 0);
+        let warnings = warnings(&bytes);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("padding"), "{warnings:?}");
+    }
+
+    #[test]
+    fn missing_footer() {
+        let mut bytes = file(VERSION, 37, VERSION);
+        bytes.truncate(bytes.len() - FOOTER_TAIL_LEN);
+        let warnings = warnings(&bytes);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Missing"), "{warnings:?}");
+    }
+}
@@ -10,23 +10,26 @@ use fbxcel_dom::{
         object::{
             material::MaterialHandle,
             model::ModelHandle,
+            nodeattribute::TypedNodeAttributeHandle,
             property::{
                 loaders::{MintLoader, PrimitiveLoader, RgbLoader},
                 LoadProperty, ObjectProperties, PropertyHandle,
             },
             texture::TextureHandle,
-            ObjectHandle, TypedObjectHandle,
+            ObjectHandle, ObjectId, TypedObjectHandle,
         },
         Document, GlobalSettings,
     },
 };
 use rgb::{RGB, RGBA};
 
+use crate::extras::is_user_property;
+
 pub trait MaterialHandleExt<'a> {
-    fn load_texture(&self, name: &'static str) -> Option<TextureHandle>;
+    fn load_texture(&self, name: &'static str) -> Option<TextureHandle<'_>>;
 }
 impl<'a> MaterialHandleExt<'a> for MaterialHandle<'a> {
-    fn load_texture(&self, name: &'static str) -> Option<TextureHandle> {
+    fn load_texture(&self, name: &'static str) -> Option<TextureHandle<'_>> {
         self.source_objects()
             .filter(|obj| obj.label() == Some(name))
             .filter_map(|obj| obj.object_handle())
@@ -37,6 +40,7 @@ impl<'a> MaterialHandleExt<'a> for MaterialHandle<'a> {
     }
 }
 
+#[allow(dead_code)]
 pub trait MaterialHandleQuickPropsExt<'a> {
     fn get_f32(&self, field: &str) -> Option<f32>;
     fn get_u32(&self, field: &str) -> Option<u32>;
@@ -68,21 +72,51 @@ impl<'a> MaterialHandleQuickPropsExt<'a> for MaterialHandle<'a> {
 
 pub trait GlobalSettingsExt<'a> {
     fn fbx_scale(&self) -> Option<f64>;
+    fn ambient_color(&self) -> Option<RGB<f64>>;
+    /// The frame rate animations were authored at, defaults to 30 FPS.
+    fn frame_rate(&self) -> f64;
 }
 impl<'a> GlobalSettingsExt<'a> for GlobalSettings<'a> {
     fn fbx_scale(&self) -> Option<f64> {
         let prop = self.raw_properties().get_property("UnitScaleFactor")?;
-        let attribute = prop.value_part().get(0)?;
+        let attribute = prop.value_part().first()?;
         match attribute {
             AttributeValue::F64(scale) => Some(*scale),
             _ => None,
         }
     }
+    fn ambient_color(&self) -> Option<RGB<f64>> {
+        let prop = self.raw_properties().get_property("AmbientColor")?;
+        prop.load_value(RgbLoader::<RGB<f64>>::default()).ok()
+    }
+    fn frame_rate(&self) -> f64 {
+        let props = self.raw_properties();
+        let load = |name| {
+            let prop = props.get_property(name)?;
+            prop.load_value(PrimitiveLoader::<f64>::new()).ok()
+        };
+        let time_mode = props
+            .get_property("TimeMode")
+            .and_then(|prop| prop.load_value(PrimitiveLoader::<i32>::new()).ok())
+            .and_then(|mode| TimeMode::try_from(mode).ok())
+            .unwrap_or_default();
+        time_mode.frame_rate(load("CustomFrameRate"))
+    }
 }
 
 pub trait Loadable: Sized {
     fn get_property(properties: ObjectProperties, attribute: &str) -> anyhow::Result<Self>;
 }
+
+pub trait ObjectPropertiesExt {
+    /// Load `attribute`, returning `None` if it is missing or has the wrong type.
+    fn get<T: Loadable>(&self, attribute: &str) -> Option<T>;
+}
+impl<'a> ObjectPropertiesExt for ObjectProperties<'a> {
+    fn get<T: Loadable>(&self, attribute: &str) -> Option<T> {
+        T::get_property(*self, attribute).ok()
+    }
+}
 struct EnumLoader<T> {
     enum_name: &'static str,
     try_into: fn(i32) -> anyhow::Result<T>,
@@ -138,6 +172,9 @@ pub enum InheritType {
     /// Parent Rotation → Parent Scale → child rotation → child scale
     RSrs,
     /// Parent Rotation → child rotation → child scale
+    ///
+    /// This is how Maya exports joints with "Segment Scale Compensate":
+    /// the joint keeps the scale of its parent's parent, not of its parent.
     Rrs,
 }
 impl TryFrom<i32> for InheritType {
@@ -152,6 +189,168 @@ impl TryFrom<i32> for InheritType {
         }
     }
 }
+// Part of the "FbxLight" native_typename
+/// The `LightType` property, equivalent to `EType` of `FbxLight` in the FBX SDK.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum LightType {
+    #[default]
+    Point,
+    Directional,
+    Spot,
+    Area,
+    Volume,
+}
+impl TryFrom<i32> for LightType {
+    type Error = anyhow::Error;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        use LightType::*;
+        match value {
+            0 => Ok(Point),
+            1 => Ok(Directional),
+            2 => Ok(Spot),
+            3 => Ok(Area),
+            4 => Ok(Volume),
+            i => Err(anyhow::anyhow!("{i} not in range of LightType enum")),
+        }
+    }
+}
+// Part of the "FbxLight" native_typename
+/// The `DecayType` property, how the light intensity falls off with distance.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum DecayType {
+    None,
+    Linear,
+    #[default]
+    Quadratic,
+    Cubic,
+}
+impl TryFrom<i32> for DecayType {
+    type Error = anyhow::Error;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        use DecayType::*;
+        match value {
+            0 => Ok(None),
+            1 => Ok(Linear),
+            2 => Ok(Quadratic),
+            3 => Ok(Cubic),
+            i => Err(anyhow::anyhow!("{i} not in range of DecayType enum")),
+        }
+    }
+}
+// Part of the "FbxCamera" native_typename
+/// The `ApertureMode` property, tells which of the camera properties
+/// defines its field of view.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum ApertureMode {
+    /// Both `FieldOfViewX` and `FieldOfViewY` are used.
+    HorizAndVert,
+    /// `FieldOfView` is the horizontal field of view (default)
+    #[default]
+    Horizontal,
+    /// `FieldOfView` is the vertical field of view
+    Vertical,
+    /// The field of view is derived from `FocalLength` and the film size.
+    FocalLength,
+}
+impl TryFrom<i32> for ApertureMode {
+    type Error = anyhow::Error;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        use ApertureMode::*;
+        match value {
+            0 => Ok(HorizAndVert),
+            1 => Ok(Horizontal),
+            2 => Ok(Vertical),
+            3 => Ok(FocalLength),
+            i => Err(anyhow::anyhow!("{i} not in range of ApertureMode enum")),
+        }
+    }
+}
+// Part of the GlobalSettings
+/// The `TimeMode` property, equivalent to `FbxTime::EMode` in the FBX SDK.
+///
+/// This is the frame rate the animations were authored at. Note that the
+/// FBX key times do not depend on it, they are always expressed in "KTime" ticks.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum TimeMode {
+    #[default]
+    Default,
+    Frames120,
+    Frames100,
+    Frames60,
+    Frames50,
+    Frames48,
+    Frames30,
+    Frames30Drop,
+    NtscDropFrame,
+    NtscFullFrame,
+    Pal,
+    Frames24,
+    Frames1000,
+    FilmFullFrame,
+    /// Uses the `CustomFrameRate` property.
+    Custom,
+    Frames96,
+    Frames72,
+    Frames59dot94,
+    Frames119dot88,
+}
+impl TimeMode {
+    /// Frames per second of this time mode, `custom` is the `CustomFrameRate`
+    /// property, used in [`TimeMode::Custom`] mode.
+    pub fn frame_rate(self, custom: Option<f64>) -> f64 {
+        use TimeMode::*;
+        const NTSC: f64 = 30.0 / 1.001;
+        match self {
+            Default | Frames30 | Frames30Drop => 30.0,
+            Frames120 => 120.0,
+            Frames100 => 100.0,
+            Frames60 => 60.0,
+            Frames50 => 50.0,
+            Frames48 => 48.0,
+            NtscDropFrame | NtscFullFrame => NTSC,
+            Pal => 25.0,
+            Frames24 => 24.0,
+            Frames1000 => 1000.0,
+            FilmFullFrame => 24.0 / 1.001,
+            Custom => custom.filter(|rate| *rate > 0.0).unwrap_or(30.0),
+            Frames96 => 96.0,
+            Frames72 => 72.0,
+            Frames59dot94 => NTSC * 2.0,
+            Frames119dot88 => NTSC * 4.0,
+        }
+    }
+}
+impl TryFrom<i32> for TimeMode {
+    type Error = anyhow::Error;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        use TimeMode::*;
+        let modes = [
+            Default,
+            Frames120,
+            Frames100,
+            Frames60,
+            Frames50,
+            Frames48,
+            Frames30,
+            Frames30Drop,
+            NtscDropFrame,
+            NtscFullFrame,
+            Pal,
+            Frames24,
+            Frames1000,
+            FilmFullFrame,
+            Custom,
+            Frames96,
+            Frames72,
+            Frames59dot94,
+            Frames119dot88,
+        ];
+        usize::try_from(value)
+            .ok()
+            .and_then(|i| modes.get(i).copied())
+            .ok_or_else(|| anyhow::anyhow!("{value} not in range of TimeMode enum"))
+    }
+}
 // Part of the "FbxNode" native_typename
 /// The order of rotation of the `Rotation` attributes.
 ///
@@ -245,6 +444,9 @@ impl_loadable!(
     MintLoader::<Vector4<f64>>::default() => DVec4,
     EnumLoader::<InheritType>::new("InheritType") => InheritType,
     EnumLoader::<RotationOrder>::new("RotationOrder") => EulerRot,
+    EnumLoader::<LightType>::new("LightType") => LightType,
+    EnumLoader::<DecayType>::new("DecayType") => DecayType,
+    EnumLoader::<ApertureMode>::new("ApertureMode") => ApertureMode,
 );
 
 // TODO: additional useful fields in the Model node:
@@ -252,23 +454,110 @@ impl_loadable!(
 // - "Casts Shadows"
 // - "Receive Shadows"
 // - "Culling"
-fn is_object_root(object: &ObjectHandle) -> bool {
+fn is_object_root(object: &ObjectHandle, root: i64) -> bool {
     object
         .destination_objects()
-        .any(|obj| obj.label().is_none() && obj.object_id().raw() == 0)
+        .any(|obj| obj.label().is_none() && obj.object_id().raw() == root)
 }
 
 pub trait ModelTreeRootExt {
-    fn model_roots(&self) -> Vec<ModelHandle<'_>>;
+    /// The id of the root object of each scene (`Document`) of the file, in
+    /// file order, or the usual root `0` if the file declares no scene.
+    ///
+    /// Root objects are implicit, the file has no object with these ids.
+    fn scene_root_ids(&self) -> Vec<i64>;
+    /// The root models of each scene of the file, see
+    /// [`ModelTreeRootExt::scene_root_ids`].
+    fn scene_model_roots(&self) -> Vec<Vec<ModelHandle<'_>>>;
 }
 impl ModelTreeRootExt for Document {
-    fn model_roots(&self) -> Vec<ModelHandle<'_>> {
-        self.objects()
-            .filter(is_object_root)
+    fn scene_root_ids(&self) -> Vec<i64> {
+        let mut ids: Vec<i64> = self
+            .scenes()
+            .filter_map(|scene| scene.root_object_id().ok())
+            .map(|id| id.raw())
+            .collect();
+        if ids.is_empty() {
+            ids.push(0);
+        }
+        ids.dedup();
+        ids
+    }
+    fn scene_model_roots(&self) -> Vec<Vec<ModelHandle<'_>>> {
+        let models: Vec<ModelHandle> = self
+            .objects()
             .filter_map(|obj| match obj.get_typed() {
                 TypedObjectHandle::Model(o) => Some(*o),
                 _ => None,
             })
+            .collect();
+        self.scene_root_ids()
+            .into_iter()
+            .map(|root| {
+                (models.iter().copied())
+                    .filter(|model| is_object_root(model, root))
+                    .collect()
+            })
             .collect()
     }
 }
+
+pub trait ModelHandleExt<'a> {
+    /// The `NodeAttribute` holding the light/camera/null specific properties of this model.
+    fn node_attribute(&self) -> Option<TypedNodeAttributeHandle<'a>>;
+    /// The node this model is aiming at, if it is a target light or camera.
+    fn look_at_target(&self) -> Option<ObjectId>;
+    /// Whether another model aims at this one.
+    fn is_look_at_target(&self) -> bool;
+    /// Whether this model's local transform or user-defined properties are animated.
+    fn is_animated(&self) -> bool;
+    /// Whether this model is constrained by, or a source of, a `Constraint`.
+    fn is_constraint_member(&self) -> bool;
+    /// Whether this model is a joint of a HumanIK `Character`.
+    fn is_character_member(&self) -> bool;
+}
+impl<'a> ModelHandleExt<'a> for ModelHandle<'a> {
+    fn node_attribute(&self) -> Option<TypedNodeAttributeHandle<'a>> {
+        self.source_objects()
+            .filter(|obj| obj.label().is_none())
+            .filter_map(|obj| obj.object_handle())
+            .find_map(|obj| match obj.get_typed() {
+                TypedObjectHandle::NodeAttribute(o) => Some(o),
+                _ => None,
+            })
+    }
+    fn look_at_target(&self) -> Option<ObjectId> {
+        self.source_objects()
+            .find(|obj| obj.label() == Some("LookAtProperty"))
+            .map(|obj| obj.object_id())
+    }
+    fn is_look_at_target(&self) -> bool {
+        self.destination_objects()
+            .any(|obj| obj.label() == Some("LookAtProperty"))
+    }
+    fn is_animated(&self) -> bool {
+        self.source_objects().any(|obj| {
+            let is_curve_node = obj
+                .object_handle()
+                .is_some_and(|obj| obj.node().name() == "AnimationCurveNode");
+            let is_animatable = match obj.label() {
+                Some("Lcl Translation" | "Lcl Rotation" | "Lcl Scaling") => true,
+                Some(label) => is_user_property(**self, label),
+                None => false,
+            };
+            is_curve_node && is_animatable
+        })
+    }
+    fn is_constraint_member(&self) -> bool {
+        self.destination_objects().any(|obj| {
+            obj.object_handle()
+                .is_some_and(|obj| obj.node().name() == "Constraint")
+        })
+    }
+    fn is_character_member(&self) -> bool {
+        self.destination_objects().any(|obj| {
+            obj.object_handle()
+                .is_some_and(|obj| obj.node().name() == "Character")
+        })
+    }
+}
@@ -0,0 +1,584 @@
+//! Lookup of layer element values (normals, UVs, colors, materials).
+//!
+//! FBX layer elements store their values with a mapping mode, telling what
+//! each value is attached to (control point, polygon vertex, polygon, edge or
+//! the whole mesh), and a reference mode, telling whether values are stored
+//! in mapping order (`Direct`) or through an index array (`IndexToDirect`).
+//! fbxcel-dom only resolves some of those combinations,
+//! so we read the raw arrays ourselves.
+
+use anyhow::{anyhow, bail, Context};
+use bevy::{math::Vec3, utils::HashMap};
+use fbxcel_dom::{
+    fbxcel::{
+        low::v7400::{AttributeType, AttributeValue},
+        tree::v7400::NodeHandle,
+    },
+    v7400::data::mesh::layer::{MappingMode, ReferenceMode},
+};
+
+/// The polygons of a mesh, as needed to find the value
+/// of a layer element for a polygon vertex.
+#[derive(Debug, Clone)]
+pub(crate) struct MeshTopology {
+    /// The control point of each polygon vertex.
+    control_points: Vec<u32>,
+    /// The polygon of each polygon vertex.
+    polygons: Vec<u32>,
+    /// The edge starting at each polygon vertex, if listed in `Edges`.
+    edges: Vec<Option<u32>>,
+    /// The first polygon vertex of each polygon.
+    first_vertices: Vec<usize>,
+    /// The polygon vertex following each polygon vertex in its polygon.
+    next_vertices: Vec<usize>,
+}
+impl MeshTopology {
+    /// Build the topology from the `PolygonVertexIndex` and `Edges` arrays
+    /// of a geometry.
+    ///
+    /// In `PolygonVertexIndex`, the last vertex of each polygon is stored
+    /// as `!control_point`. Each entry of `Edges` is the polygon vertex
+    /// starting the edge, going to the next vertex of the same polygon.
+    pub(crate) fn new(polygon_vertices: &[i32], edges: &[i32]) -> Self {
+        let mut control_points = Vec::with_capacity(polygon_vertices.len());
+        let mut polygons = Vec::with_capacity(polygon_vertices.len());
+        let mut polygon_starts = Vec::with_capacity(polygon_vertices.len());
+        let mut first_vertices = Vec::new();
+        let mut polygon = 0;
+        let mut start = 0;
+        for (pvi, raw) in polygon_vertices.iter().enumerate() {
+            control_points.push(if *raw < 0 { !*raw } else { *raw } as u32);
+            polygons.push(polygon);
+            polygon_starts.push(start);
+            if pvi == start {
+                first_vertices.push(pvi);
+            }
+            if *raw < 0 {
+                polygon += 1;
+                start = pvi + 1;
+            }
+        }
+        let next_vertices: Vec<_> = (0..polygon_vertices.len())
+            .map(|pvi| match polygon_vertices[pvi] < 0 {
+                true => polygon_starts[pvi],
+                false => pvi + 1,
+            })
+            .collect();
+        let edge_key = |pvi: usize| {
+            let (a, b) = (control_points[pvi], control_points[next_vertices[pvi]]);
+            (a.min(b), a.max(b))
+        };
+        // Edges are shared between polygons, so they are matched
+        // by the control points at their ends.
+        let edge_indices: HashMap<(u32, u32), u32> = edges
+            .iter()
+            .enumerate()
+            .filter(|(_, pvi)| (0..polygon_vertices.len() as i32).contains(pvi))
+            .map(|(edge, pvi)| (edge_key(*pvi as usize), edge as u32))
+            .collect();
+        let edges = (0..polygon_vertices.len())
+            .map(|pvi| edge_indices.get(&edge_key(pvi)).copied())
+            .collect();
+        MeshTopology {
+            control_points,
+            polygons,
+            edges,
+            first_vertices,
+            next_vertices,
+        }
+    }
+
+    /// Number of polygon vertices.
+    pub(crate) fn len(&self) -> usize {
+        self.control_points.len()
+    }
+    /// The control point of the polygon vertex `pvi`.
+    pub(crate) fn control_point(&self, pvi: usize) -> u32 {
+        self.control_points[pvi]
+    }
+    /// The control points at the ends of the edge starting at `pvi`,
+    /// smallest first.
+    pub(crate) fn edge(&self, pvi: usize) -> (u32, u32) {
+        let (a, b) = (
+            self.control_points[pvi],
+            self.control_points[self.next_vertices[pvi]],
+        );
+        (a.min(b), a.max(b))
+    }
+    /// The polygon vertices of each polygon.
+    pub(crate) fn polygons(&self) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+        let ends = self
+            .first_vertices
+            .iter()
+            .skip(1)
+            .copied()
+            .chain([self.len()]);
+        self.first_vertices
+            .iter()
+            .zip(ends)
+            .map(|(start, end)| *start..end)
+    }
+}
+
+/// The values of a layer element.
+///
+/// Each value is `components` consecutive entries of `data`.
+#[derive(Debug, Clone)]
+pub(crate) struct LayerElement<'a, T> {
+    mapping: MappingMode,
+    /// The index array, for the `IndexToDirect` reference mode.
+    indices: Option<&'a [i32]>,
+    data: &'a [T],
+    components: usize,
+}
+impl<'a, T> LayerElement<'a, T> {
+    pub(crate) fn new(
+        mapping: MappingMode,
+        indices: Option<&'a [i32]>,
+        data: &'a [T],
+        components: usize,
+    ) -> Self {
+        LayerElement {
+            mapping,
+            indices,
+            data,
+            components,
+        }
+    }
+
+    /// The value for the polygon vertex `pvi` of `topology`.
+    pub(crate) fn get(&self, topology: &MeshTopology, pvi: usize) -> anyhow::Result<&'a [T]> {
+        let index = self.index(topology, pvi)?;
+        let start = index * self.components;
+        self.data
+            .get(start..start + self.components)
+            .ok_or_else(|| anyhow!("Layer element value out of range: {index}"))
+    }
+
+    /// The index of the value for the polygon vertex `pvi` of `topology`.
+    fn index(&self, topology: &MeshTopology, pvi: usize) -> anyhow::Result<usize> {
+        let index = match self.mapping {
+            MappingMode::ByControlPoint => topology.control_points.get(pvi).copied(),
+            MappingMode::ByPolygonVertex => Some(pvi as u32),
+            MappingMode::ByPolygon => topology.polygons.get(pvi).copied(),
+            MappingMode::ByEdge => topology.edges.get(pvi).copied().flatten(),
+            MappingMode::AllSame => Some(0),
+            MappingMode::None => None,
+        };
+        let index = index
+            .ok_or_else(|| anyhow!("No {:?} value for polygon vertex {pvi}", self.mapping))?
+            as usize;
+        let index = match self.indices {
+            Some(indices) => {
+                let direct = indices
+                    .get(index)
+                    .ok_or_else(|| anyhow!("Layer element index out of range: {index}"))?;
+                usize::try_from(*direct)
+                    .map_err(|_| anyhow!("Negative layer element index: {direct}"))?
+            }
+            None => index,
+        };
+        Ok(index)
+    }
+}
+impl LayerElement<'_, f64> {
+    /// The value for each of the polygon vertices `pvis` of `topology`,
+    /// converted to `f32`, for elements of `N` components.
+    ///
+    /// `converted` is a buffer for the values converted to `f32`, see
+    /// [`gather_f32`](LayerElement::gather_f32).
+    pub(crate) fn get_all_f32<const N: usize>(
+        &self,
+        topology: &MeshTopology,
+        pvis: &[usize],
+        converted: &mut Vec<f32>,
+    ) -> anyhow::Result<Vec<[f32; N]>> {
+        let mut gathered = Vec::with_capacity(pvis.len());
+        self.gather_f32(topology, pvis, converted, &mut gathered)?;
+        Ok(gathered)
+    }
+
+    /// Append to `gathered` the value for each of the polygon vertices `pvis`
+    /// of `topology`, converted to `f32`, for elements of `N` components.
+    ///
+    /// The values are converted once to `converted`, see [`to_f32`], and then
+    /// copied to the polygon vertices using them, rather than converted again
+    /// for each polygon vertex.
+    pub(crate) fn gather_f32<const N: usize>(
+        &self,
+        topology: &MeshTopology,
+        pvis: &[usize],
+        converted: &mut Vec<f32>,
+        gathered: &mut Vec<[f32; N]>,
+    ) -> anyhow::Result<()> {
+        if self.components != N {
+            bail!("Expected {N} components per value, got {}", self.components);
+        }
+        to_f32(self.data, converted);
+        let value = |index: usize| {
+            let value = converted.get(index * N..index * N + N);
+            let value = value.ok_or_else(|| anyhow!("Layer element value out of range: {index}"));
+            value.map(|value| -> [f32; N] { std::array::from_fn(|i| value[i]) })
+        };
+        gathered.reserve(pvis.len());
+        // Direct values per polygon vertex, the most common layout of normals
+        // and UVs, don't need the mapping of `index`.
+        if let (MappingMode::ByPolygonVertex, None) = (self.mapping, self.indices) {
+            for pvi in pvis {
+                gathered.push(value(*pvi)?);
+            }
+            return Ok(());
+        }
+        for pvi in pvis {
+            gathered.push(value(self.index(topology, *pvi)?)?);
+        }
+        Ok(())
+    }
+}
+
+/// Replace the content of `converted` with `data` as `f32` values.
+///
+/// This is a single pass over contiguous memory, which the compiler
+/// vectorizes. Prefer it to converting values one at a time in the loops
+/// gathering them per vertex.
+pub(crate) fn to_f32(data: &[f64], converted: &mut Vec<f32>) {
+    converted.clear();
+    converted.extend(data.iter().map(|value| *value as f32));
+}
+
+/// `data` as `f32` values of `N` components, ignoring a trailing partial value,
+/// for values kept after the conversion, see [`to_f32`].
+pub(crate) fn to_f32_arrays<const N: usize>(data: &[f64]) -> Vec<[f32; N]> {
+    (data.chunks_exact(N))
+        .map(|value| std::array::from_fn(|i| value[i] as f32))
+        .collect()
+}
+
+/// The string attribute of the `name` child of `node`, parsed.
+fn parse<T>(node: &NodeHandle, name: &str) -> anyhow::Result<T>
+where
+    T: std::str::FromStr<Err = anyhow::Error>,
+{
+    node.first_child_by_name(name)
+        .and_then(|child| child.attributes().first()?.get_string())
+        .with_context(|| format!("`{name}` not found in `{}`", node.name()))?
+        .parse()
+}
+
+/// Read the mapping mode, reference mode and index array of `element`.
+fn mapping<'a>(
+    element: &NodeHandle<'a>,
+    index_name: &str,
+) -> anyhow::Result<(MappingMode, Option<&'a [i32]>)> {
+    let mapping = parse(element, "MappingInformationType")?;
+    let indices = match parse(element, "ReferenceInformationType")? {
+        ReferenceMode::Direct => None,
+        ReferenceMode::IndexToDirect => {
+            Some(array(element, index_name, |a| a.get_arr_i32_or_type())?)
+        }
+    };
+    Ok((mapping, indices))
+}
+
+/// The array attribute of the `name` child of `node`.
+fn array<'a, T: 'a>(
+    node: &NodeHandle<'a>,
+    name: &str,
+    get: impl FnOnce(&'a AttributeValue) -> Result<&'a [T], AttributeType>,
+) -> anyhow::Result<&'a [T]> {
+    let attribute = node
+        .first_child_by_name(name)
+        .and_then(|child| child.attributes().first())
+        .with_context(|| format!("`{name}` not found in `{}`", node.name()))?;
+    get(attribute).map_err(|ty| anyhow!("Expected an array in `{name}`, got {ty:?}"))
+}
+
+/// A `f64` layer element, such as `LayerElementNormal` (`Normals`),
+/// `LayerElementUV` (`UV`) or `LayerElementColor` (`Colors`).
+pub(crate) fn f64_element<'a>(
+    element: &NodeHandle<'a>,
+    data_name: &str,
+    index_name: &str,
+    components: usize,
+) -> anyhow::Result<LayerElement<'a, f64>> {
+    let (mapping, indices) = mapping(element, index_name)?;
+    let data = array(element, data_name, |a| a.get_arr_f64_or_type())?;
+    Ok(LayerElement::new(mapping, indices, data, components))
+}
+
+/// An `i32` layer element with a single component, such as
+/// `LayerElementSmoothing` (`Smoothing`), and its mapping mode.
+pub(crate) fn i32_element<'a>(
+    element: &NodeHandle<'a>,
+    data_name: &str,
+) -> anyhow::Result<(MappingMode, LayerElement<'a, i32>)> {
+    let (mapping, indices) = mapping(element, &format!("{data_name}Index"))?;
+    let data = array(element, data_name, |a| a.get_arr_i32_or_type())?;
+    Ok((mapping, LayerElement::new(mapping, indices, data, 1)))
+}
+
+/// The `LayerElementMaterial` element.
+///
+/// Its `Materials` array holds material indices, and is used as is
+/// whatever the reference mode, which is always `IndexToDirect`.
+pub(crate) fn material_element<'a>(
+    element: &NodeHandle<'a>,
+) -> anyhow::Result<LayerElement<'a, i32>> {
+    let mapping = parse(element, "MappingInformationType")?;
+    if !matches!(mapping, MappingMode::AllSame | MappingMode::ByPolygon) {
+        bail!("Unsupported material mapping mode: {mapping:?}");
+    }
+    let data = array(element, "Materials", |a| a.get_arr_i32_or_type())?;
+    Ok(LayerElement::new(mapping, None, data, 1))
+}
+
+/// Whether each polygon is a hole, from a `LayerElementHole` element.
+pub(crate) fn hole_flags(
+    element: &NodeHandle,
+    topology: &MeshTopology,
+) -> anyhow::Result<Vec<bool>> {
+    let (mapping, indices) = mapping(element, "HoleIndex")?;
+    let attribute = element
+        .first_child_by_name("Hole")
+        .and_then(|child| child.attributes().first())
+        .context("`Hole` not found in `LayerElementHole`")?;
+    let data: Vec<bool> = match (attribute.get_arr_bool(), attribute.get_arr_i32()) {
+        (Some(data), _) => data.to_vec(),
+        (None, Some(data)) => data.iter().map(|hole| *hole != 0).collect(),
+        (None, None) => bail!("Expected a bool or int array in `Hole`"),
+    };
+    let holes = LayerElement::new(mapping, indices, &data, 1);
+    topology
+        .first_vertices
+        .iter()
+        .map(|pvi| Ok(holes.get(topology, *pvi)?[0]))
+        .collect()
+}
+
+/// The `element_type` layer element (such as `LayerElementTangent`)
+/// of `geometry` used by `layer`, or the first one if `layer` doesn't list it.
+///
+/// This is for the element types not handled by fbxcel-dom.
+pub(crate) fn find_element<'a>(
+    geometry: &NodeHandle<'a>,
+    layer: &NodeHandle<'a>,
+    element_type: &str,
+) -> Option<NodeHandle<'a>> {
+    let typed_index = layer.children_by_name("LayerElement").find_map(|entry| {
+        let ty = entry
+            .first_child_by_name("Type")?
+            .attributes()
+            .first()?
+            .get_string()?;
+        let index = entry
+            .first_child_by_name("TypedIndex")?
+            .attributes()
+            .first()?;
+        (ty == element_type).then(|| index.get_i32())?
+    });
+    let mut elements = geometry.children_by_name(element_type);
+    match typed_index {
+        Some(index) => {
+            elements.find(|e| e.attributes().first().and_then(|a| a.get_i32()) == Some(index))
+        }
+        None => elements.next(),
+    }
+}
+
+/// The Bevy tangent (with handedness in `w`) of a vertex,
+/// from its FBX normal, tangent and binormal.
+///
+/// The tangent is made orthogonal to the normal, and `w` is the sign
+/// such that `cross(normal, tangent) * w` points like the binormal.
+pub(crate) fn tangent(normal: Vec3, tangent: Vec3, binormal: Vec3) -> [f32; 4] {
+    let normal = normal.normalize_or_zero();
+    let orthogonal = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+    let orthogonal = match orthogonal == Vec3::ZERO {
+        true => normal.any_orthonormal_vector(),
+        false => orthogonal,
+    };
+    let w = match normal.cross(orthogonal).dot(binormal) < 0.0 {
+        true => -1.0,
+        false => 1.0,
+    };
+    orthogonal.extend(w).to_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A quad (0 1 2 3) and a triangle (1 4 2), sharing the edge 1-2.
+    const POLYGON_VERTICES: [i32; 7] = [0, 1, 2, !3, 1, 4, !2];
+    /// Edges 0-1, 1-2, 2-3, 3-0, 1-4, 4-2 as their first polygon vertex.
+    const EDGES: [i32; 6] = [0, 1, 2, 3, 4, 5];
+
+    fn topology() -> MeshTopology {
+        MeshTopology::new(&POLYGON_VERTICES, &EDGES)
+    }
+
+    /// The first component of the value of each polygon vertex.
+    fn resolve(element: &LayerElement<f64>) -> Vec<f64> {
+        let topology = topology();
+        (0..POLYGON_VERTICES.len())
+            .map(|pvi| element.get(&topology, pvi).unwrap()[0])
+            .collect()
+    }
+
+    /// Values `10 * i` for `i` in `0..count`, with two components each.
+    fn data(count: usize) -> Vec<f64> {
+        (0..count).flat_map(|i| [10.0 * i as f64, -1.0]).collect()
+    }
+
+    #[test]
+    fn by_control_point_direct() {
+        let data = data(5);
+        let element = LayerElement::new(MappingMode::ByControlPoint, None, &data, 2);
+        assert_eq!(resolve(&element), [0.0, 10.0, 20.0, 30.0, 10.0, 40.0, 20.0]);
+    }
+
+    #[test]
+    fn by_control_point_index_to_direct() {
+        let data = data(2);
+        let indices = [1, 0, 1, 0, 1];
+        let element = LayerElement::new(MappingMode::ByControlPoint, Some(&indices), &data, 2);
+        assert_eq!(resolve(&element), [10.0, 0.0, 10.0, 0.0, 0.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn by_polygon_vertex_direct() {
+        let data = data(7);
+        let element = LayerElement::new(MappingMode::ByPolygonVertex, None, &data, 2);
+        assert_eq!(resolve(&element), [0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0]);
+    }
+
+    #[test]
+    fn by_polygon_vertex_index_to_direct() {
+        let data = data(3);
+        let indices = [2, 2, 1, 1, 0, 0, 1];
+        let element = LayerElement::new(MappingMode::ByPolygonVertex, Some(&indices), &data, 2);
+        assert_eq!(resolve(&element), [20.0, 20.0, 10.0, 10.0, 0.0, 0.0, 10.0]);
+    }
+
+    #[test]
+    fn by_polygon_direct() {
+        let data = data(2);
+        let element = LayerElement::new(MappingMode::ByPolygon, None, &data, 2);
+        assert_eq!(resolve(&element), [0.0, 0.0, 0.0, 0.0, 10.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn by_polygon_index_to_direct() {
+        let data = data(3);
+        let indices = [2, 1];
+        let element = LayerElement::new(MappingMode::ByPolygon, Some(&indices), &data, 2);
+        assert_eq!(
+            resolve(&element),
+            [20.0, 20.0, 20.0, 20.0, 10.0, 10.0, 10.0]
+        );
+    }
+
+    #[test]
+    fn by_edge_direct() {
+        let data = data(6);
+        let element = LayerElement::new(MappingMode::ByEdge, None, &data, 2);
+        // The triangle's edge 2-1 is the quad's edge 1-2.
+        assert_eq!(resolve(&element), [0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 10.0]);
+    }
+
+    #[test]
+    fn by_edge_index_to_direct() {
+        let data = data(2);
+        let indices = [0, 1, 0, 1, 0, 1];
+        let element = LayerElement::new(MappingMode::ByEdge, Some(&indices), &data, 2);
+        assert_eq!(resolve(&element), [0.0, 10.0, 0.0, 10.0, 0.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn by_edge_missing_edges() {
+        let data = data(6);
+        let element = LayerElement::new(MappingMode::ByEdge, None, &data, 2);
+        let topology = MeshTopology::new(&POLYGON_VERTICES, &[]);
+        assert!(element.get(&topology, 0).is_err());
+    }
+
+    #[test]
+    fn all_same_direct() {
+        let data = data(1);
+        let element = LayerElement::new(MappingMode::AllSame, None, &data, 2);
+        assert_eq!(resolve(&element), [0.0; 7]);
+    }
+
+    #[test]
+    fn all_same_index_to_direct() {
+        let data = data(2);
+        let indices = [1];
+        let element = LayerElement::new(MappingMode::AllSame, Some(&indices), &data, 2);
+        assert_eq!(resolve(&element), [10.0; 7]);
+    }
+
+    #[test]
+    fn components() {
+        let data = data(7);
+        let element = LayerElement::new(MappingMode::ByPolygonVertex, None, &data, 2);
+        assert_eq!(element.get(&topology(), 3).unwrap(), [30.0, -1.0]);
+    }
+
+    #[test]
+    fn all_f32_matches_get() {
+        let data = data(3);
+        let indices = [2, 2, 1, 1, 0, 0, 1];
+        let pvis = [6, 0, 3];
+        let converted = &mut Vec::new();
+        let element = LayerElement::new(MappingMode::ByPolygonVertex, Some(&indices), &data, 2);
+        let all = element.get_all_f32::<2>(&topology(), &pvis, converted);
+        assert_eq!(all.unwrap(), [[10.0, -1.0], [20.0, -1.0], [10.0, -1.0]]);
+        let element = LayerElement::new(MappingMode::ByControlPoint, None, &data, 2);
+        assert!(element
+            .get_all_f32::<3>(&topology(), &pvis, converted)
+            .is_err());
+    }
+
+    #[test]
+    fn gather_f32_appends() {
+        let data = data(7);
+        let element = LayerElement::new(MappingMode::ByPolygonVertex, None, &data, 2);
+        let (converted, gathered) = (&mut vec![5.0; 20], &mut vec![[1.0, 2.0]]);
+        element
+            .gather_f32(&topology(), &[3], converted, gathered)
+            .unwrap();
+        assert_eq!(*gathered, [[1.0, 2.0], [30.0, -1.0]]);
+        assert_eq!(converted.len(), data.len());
+    }
+
+    #[test]
+    fn f32_arrays_drop_partial_values() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        assert_eq!(
+            to_f32_arrays::<3>(&data),
+            [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]
+        );
+    }
+
+    #[test]
+    fn tangent_orthogonalized() {
+        let tangent = super::tangent(Vec3::Z, Vec3::new(2.0, 0.0, 1.0), Vec3::Y);
+        assert_eq!(tangent, [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn tangent_mirrored() {
+        let tangent = super::tangent(Vec3::Z, Vec3::X, Vec3::NEG_Y);
+        assert_eq!(tangent, [1.0, 0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let data = data(2);
+        let element = LayerElement::new(MappingMode::ByPolygonVertex, None, &data, 2);
+        assert!(element.get(&topology(), 5).is_err());
+        let indices = [0, -1];
+        let element = LayerElement::new(MappingMode::ByPolygon, Some(&indices), &data, 2);
+        assert!(element.get(&topology(), 5).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Vertex buffer manipulation of the meshes produced by the `FbxLoader`.
+
+use bevy::{
+    prelude::Mesh,
+    render::mesh::{Indices, MeshVertexAttribute, VertexAttributeValues},
+    utils::HashMap,
+};
+
+/// The attributes of meshes produced by the `FbxLoader`.
+pub(crate) const ATTRIBUTES: [MeshVertexAttribute; 7] = [
+    Mesh::ATTRIBUTE_POSITION,
+    Mesh::ATTRIBUTE_NORMAL,
+    Mesh::ATTRIBUTE_UV_0,
+    Mesh::ATTRIBUTE_TANGENT,
+    Mesh::ATTRIBUTE_COLOR,
+    Mesh::ATTRIBUTE_JOINT_INDEX,
+    Mesh::ATTRIBUTE_JOINT_WEIGHT,
+];
+
+/// The values of `attribute` for the vertices `selected`.
+pub(crate) fn select(
+    values: &VertexAttributeValues,
+    selected: &[u32],
+) -> Option<VertexAttributeValues> {
+    fn pick<T: Copy>(values: &[T], selected: &[u32]) -> Vec<T> {
+        selected.iter().map(|i| values[*i as usize]).collect()
+    }
+    let values = match values {
+        VertexAttributeValues::Float32x2(v) => VertexAttributeValues::Float32x2(pick(v, selected)),
+        VertexAttributeValues::Float32x3(v) => VertexAttributeValues::Float32x3(pick(v, selected)),
+        VertexAttributeValues::Float32x4(v) => VertexAttributeValues::Float32x4(pick(v, selected)),
+        VertexAttributeValues::Uint16x4(v) => VertexAttributeValues::Uint16x4(pick(v, selected)),
+        _ => return None,
+    };
+    Some(values)
+}
+
+/// Split the triangles `indices` of `mesh` into parts of at most
+/// `max_vertices` vertices each, keeping the triangles in order.
+///
+/// Returns `None` if the triangles already use few enough vertices,
+/// or if the mesh has attributes we can't split.
+pub(crate) fn split(mesh: &Mesh, indices: &[u32], max_vertices: usize) -> Option<Vec<Mesh>> {
+    let max_vertices = max_vertices.max(3);
+    let mut used = vec![false; mesh.count_vertices()];
+    let used_count = indices
+        .iter()
+        .filter(|i| !std::mem::replace(&mut used[**i as usize], true))
+        .count();
+    if used_count <= max_vertices {
+        return None;
+    }
+    if mesh.attributes().count()
+        != ATTRIBUTES
+            .iter()
+            .filter(|a| mesh.contains_attribute(a.id))
+            .count()
+    {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    let mut part_indices = Vec::new();
+    let mut compact: HashMap<u32, u32> = HashMap::default();
+    let mut original_vertices = Vec::new();
+    let mut finish_part = |part_indices: &mut Vec<u32>, original_vertices: &mut Vec<u32>| {
+        let mut part = Mesh::new(mesh.primitive_topology());
+        for attribute in ATTRIBUTES {
+            if let Some(values) = mesh.attribute(attribute.id) {
+                part.insert_attribute(attribute.clone(), select(values, original_vertices)?);
+            }
+        }
+        part.set_indices(Some(Indices::U32(std::mem::take(part_indices))));
+        parts.push(part);
+        original_vertices.clear();
+        Some(())
+    };
+    for triangle in indices.chunks_exact(3) {
+        let new = triangle
+            .iter()
+            .enumerate()
+            .filter(|(i, v)| !compact.contains_key(v) && !triangle[..*i].contains(v))
+            .count();
+        if compact.len() + new > max_vertices {
+            finish_part(&mut part_indices, &mut original_vertices)?;
+            compact.clear();
+        }
+        for vertex in triangle {
+            let index = *compact.entry(*vertex).or_insert_with(|| {
+                original_vertices.push(*vertex);
+                original_vertices.len() as u32 - 1
+            });
+            part_indices.push(index);
+        }
+    }
+    if !part_indices.is_empty() {
+        finish_part(&mut part_indices, &mut original_vertices)?;
+    }
+    Some(parts)
+}
@@ -1,2 +1,6 @@
+pub(crate) mod arena;
+pub(crate) mod binary;
 pub(crate) mod fbx_extend;
+pub(crate) mod layer_element;
+pub(crate) mod mesh;
 pub(crate) mod triangulate;
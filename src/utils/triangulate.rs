@@ -179,3 +179,266 @@ fn bounding_box<'a>(points: impl IntoIterator<Item = &'a DVec3>) -> Option<(DVec
         )
     })
 }
+
+/// Triangulate a polygon with holes.
+///
+/// The holes are bridged to the outer polygon, and the resulting
+/// polygon is triangulated by ear clipping. Triangles keep the winding
+/// of `outer_pvis`.
+pub fn triangulate_with_holes(
+    pvs: &PolygonVertices<'_>,
+    outer_pvis: &[PolygonVertexIndex],
+    hole_pvis: &[Vec<PolygonVertexIndex>],
+    results: &mut Vec<[PolygonVertexIndex; 3]>,
+) -> anyhow::Result<()> {
+    if outer_pvis.len() < 3 {
+        bail!(
+            "Not enough vertices in the polygon: length={}",
+            outer_pvis.len()
+        );
+    }
+    let all_pvis: Vec<_> = outer_pvis
+        .iter()
+        .chain(hole_pvis.iter().flatten())
+        .copied()
+        .collect();
+    let points = all_pvis
+        .iter()
+        .map(|pvi| get_vec(pvs, *pvi))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (min, max) = bounding_box(&points[..outer_pvis.len()])
+        .expect("Should never happen: there are 3 or more points");
+    let points_2d: Vec<_> = match smallest_direction(&(max - min)) {
+        Axis::X => points.iter().map(|v| DVec2::new(v[1], v[2])).collect(),
+        Axis::Y => points.iter().map(|v| DVec2::new(v[0], v[2])).collect(),
+        Axis::Z => points.iter().map(|v| DVec2::new(v[0], v[1])).collect(),
+    };
+    let mut start = outer_pvis.len();
+    let holes = hole_pvis
+        .iter()
+        .map(|hole| {
+            let indices = (start..start + hole.len()).collect();
+            start += hole.len();
+            indices
+        })
+        .collect::<Vec<Vec<usize>>>();
+    let outer = (0..outer_pvis.len()).collect();
+    for [a, b, c] in ear_clip(&points_2d, outer, holes) {
+        results.push([all_pvis[a], all_pvis[b], all_pvis[c]]);
+    }
+    Ok(())
+}
+
+/// Twice the signed area of the polygon `indices`, positive if counterclockwise.
+fn signed_area(points: &[DVec2], indices: &[usize]) -> f64 {
+    let next = indices.iter().cycle().skip(1);
+    indices
+        .iter()
+        .zip(next)
+        .map(|(a, b)| points[*a].perp_dot(points[*b]))
+        .sum()
+}
+
+/// Whether the segments `a0-a1` and `b0-b1` cross, ignoring shared end points.
+fn segments_cross(a0: DVec2, a1: DVec2, b0: DVec2, b1: DVec2) -> bool {
+    if a0 == b0 || a0 == b1 || a1 == b0 || a1 == b1 {
+        return false;
+    }
+    let side = |p: DVec2, q0: DVec2, q1: DVec2| (q1 - q0).perp_dot(p - q0);
+    let (d0, d1) = (side(b0, a0, a1), side(b1, a0, a1));
+    let (d2, d3) = (side(a0, b0, b1), side(a1, b0, b1));
+    d0 * d1 < 0.0 && d2 * d3 < 0.0
+}
+
+/// Whether `p` is inside or on the counterclockwise triangle `a b c`.
+fn in_triangle(p: DVec2, a: DVec2, b: DVec2, c: DVec2) -> bool {
+    (b - a).perp_dot(p - a) >= 0.0
+        && (c - b).perp_dot(p - b) >= 0.0
+        && (a - c).perp_dot(p - c) >= 0.0
+}
+
+/// Triangulate the polygon `outer` with `holes`, all indices into `points`.
+fn ear_clip(points: &[DVec2], outer: Vec<usize>, mut holes: Vec<Vec<usize>>) -> Vec<[usize; 3]> {
+    // Work on a counterclockwise outer polygon, mirroring the points
+    // rather than reordering them to keep the winding of the triangles.
+    let points: Vec<DVec2> = match signed_area(points, &outer) < 0.0 {
+        true => points.iter().map(|p| DVec2::new(-p.x, p.y)).collect(),
+        false => points.to_vec(),
+    };
+    // Holes go clockwise, so that bridging them keeps a consistent winding.
+    for hole in &mut holes {
+        if signed_area(&points, hole) > 0.0 {
+            hole.reverse();
+        }
+    }
+    let rightmost = |hole: &[usize]| {
+        (0..hole.len())
+            .max_by(|a, b| points[hole[*a]].x.total_cmp(&points[hole[*b]].x))
+            .unwrap_or(0)
+    };
+    holes.retain(|hole| hole.len() >= 3);
+    holes.sort_by(|a, b| {
+        let (a, b) = (points[a[rightmost(a)]].x, points[b[rightmost(b)]].x);
+        b.total_cmp(&a)
+    });
+
+    // Bridge each hole to the closest vertex it can see.
+    let mut polygon = outer;
+    for (i, hole) in holes.iter().enumerate() {
+        let m = rightmost(hole);
+        let hole_point = points[hole[m]];
+        let edges = |indices: &[usize]| -> Vec<(DVec2, DVec2)> {
+            let next = indices.iter().cycle().skip(1);
+            indices
+                .iter()
+                .zip(next)
+                .map(|(a, b)| (points[*a], points[*b]))
+                .collect()
+        };
+        let blockers: Vec<_> = std::iter::once(&polygon[..])
+            .chain(holes[i..].iter().map(Vec::as_slice))
+            .flat_map(edges)
+            .collect();
+        let visible = |vertex: &usize| {
+            let point = points[*vertex];
+            !blockers
+                .iter()
+                .any(|(e0, e1)| segments_cross(hole_point, point, *e0, *e1))
+        };
+        let bridge = (0..polygon.len())
+            .filter(|v| visible(&polygon[*v]))
+            .min_by(|a, b| {
+                let distance = |v: &usize| points[polygon[*v]].distance_squared(hole_point);
+                distance(a).total_cmp(&distance(b))
+            });
+        let bridge = match bridge {
+            Some(bridge) => bridge,
+            None => continue,
+        };
+        let hole_loop = hole[m..].iter().chain(&hole[..=m]).copied();
+        let spliced: Vec<_> = polygon[..=bridge]
+            .iter()
+            .copied()
+            .chain(hole_loop)
+            .chain(std::iter::once(polygon[bridge]))
+            .chain(polygon[bridge + 1..].iter().copied())
+            .collect();
+        polygon = spliced;
+    }
+
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let corners = |i: usize| (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+        let is_ear = |i: usize| {
+            let (a, b, c) = corners(i);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            let convex = (pb - pa).perp_dot(pc - pb) > 0.0;
+            convex
+                && polygon.iter().all(|v| {
+                    let p = points[*v];
+                    p == pa || p == pb || p == pc || !in_triangle(p, pa, pb, pc)
+                })
+        };
+        // Degenerate polygons may have no ear, clip any vertex to make progress.
+        let ear = (0..n).find(|i| is_ear(*i)).unwrap_or(0);
+        let (a, b, c) = corners(ear);
+        triangles.push([a, b, c]);
+        polygon.remove(ear);
+    }
+    if let [a, b, c] = polygon[..] {
+        triangles.push([a, b, c]);
+    }
+    triangles
+}
+
+/// How a polygon takes part in a polygon with holes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonRole {
+    /// A polygon without holes.
+    Solid,
+    /// A polygon followed by its holes.
+    Outer,
+    /// A hole of the closest preceding outer polygon.
+    Hole { last: bool },
+    /// A hole without outer polygon, not triangulated.
+    Orphan,
+}
+
+/// The role of each polygon, given whether it is flagged as a hole.
+///
+/// Holes belong to the closest polygon before them that is not a hole.
+pub fn polygon_roles(holes: &[bool]) -> Vec<PolygonRole> {
+    let mut roles = Vec::with_capacity(holes.len());
+    for (i, hole) in holes.iter().enumerate() {
+        let next_is_hole = holes.get(i + 1).copied().unwrap_or(false);
+        let role = match (hole, roles.last()) {
+            (false, _) if next_is_hole => PolygonRole::Outer,
+            (false, _) => PolygonRole::Solid,
+            (true, Some(PolygonRole::Outer | PolygonRole::Hole { .. })) => PolygonRole::Hole {
+                last: !next_is_hole,
+            },
+            (true, _) => PolygonRole::Orphan,
+        };
+        roles.push(role);
+    }
+    roles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x4 square with a 2x2 square hole in its middle.
+    fn square_with_hole() -> Vec<DVec2> {
+        let square = |min: f64, max: f64| {
+            [(min, min), (max, min), (max, max), (min, max)].map(|(x, y)| DVec2::new(x, y))
+        };
+        square(0.0, 4.0)
+            .into_iter()
+            .chain(square(1.0, 3.0))
+            .collect()
+    }
+
+    /// Twice the signed area of each triangle.
+    fn areas(points: &[DVec2], triangles: &[[usize; 3]]) -> Vec<f64> {
+        triangles.iter().map(|t| signed_area(points, t)).collect()
+    }
+
+    #[test]
+    fn hole_counterclockwise() {
+        let points = square_with_hole();
+        let triangles = ear_clip(&points, vec![0, 1, 2, 3], vec![vec![4, 5, 6, 7]]);
+        let areas = areas(&points, &triangles);
+        assert_eq!(triangles.len(), 8);
+        assert!(areas.iter().all(|area| *area > 0.0), "{areas:?}");
+        assert_eq!(areas.iter().sum::<f64>(), 2.0 * (16.0 - 4.0));
+    }
+
+    #[test]
+    fn hole_clockwise() {
+        let points = square_with_hole();
+        // Clockwise, with a hole going the same way as the outer polygon.
+        let triangles = ear_clip(&points, vec![3, 2, 1, 0], vec![vec![7, 6, 5, 4]]);
+        let areas = areas(&points, &triangles);
+        assert_eq!(triangles.len(), 8);
+        assert!(areas.iter().all(|area| *area < 0.0), "{areas:?}");
+        assert_eq!(areas.iter().sum::<f64>(), -2.0 * (16.0 - 4.0));
+    }
+
+    #[test]
+    fn roles() {
+        let roles = polygon_roles(&[true, false, true, true, false, false]);
+        assert_eq!(
+            roles,
+            [
+                PolygonRole::Orphan,
+                PolygonRole::Outer,
+                PolygonRole::Hole { last: false },
+                PolygonRole::Hole { last: true },
+                PolygonRole::Solid,
+                PolygonRole::Solid,
+            ]
+        );
+    }
+}
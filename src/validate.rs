@@ -0,0 +1,89 @@
+//! Checking FBX files without using them, see [`FbxLoader::validate_bytes`].
+//!
+//! [`FbxLoader::validate_bytes`]: crate::FbxLoader::validate_bytes
+
+use std::{
+    fmt::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use bevy::utils::tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Metadata, Subscriber,
+};
+
+/// The outcome of loading an FBX file, see [`FbxLoader::validate_bytes`].
+///
+/// [`FbxLoader::validate_bytes`]: crate::FbxLoader::validate_bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FbxValidationReport {
+    /// The error failing the load, with its causes, `None` if it loaded.
+    pub error: Option<String>,
+    /// The warnings of the load, such as malformed headers, skipped
+    /// connections, missing textures or exceeded budgets, in order.
+    pub warnings: Vec<String>,
+}
+impl FbxValidationReport {
+    /// Whether the file loaded, possibly with warnings.
+    ///
+    /// Set [`FbxLoaderSettings::strict_connections`] and a strict
+    /// [`FbxLoaderSettings::budget`] to fail on their warnings.
+    ///
+    /// [`FbxLoaderSettings::strict_connections`]: crate::FbxLoaderSettings::strict_connections
+    /// [`FbxLoaderSettings::budget`]: crate::FbxLoaderSettings::budget
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+impl fmt::Display for FbxValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for warning in &self.warnings {
+            writeln!(f, "warning: {warning}")?;
+        }
+        match &self.error {
+            Some(error) => write!(f, "error: {error}"),
+            None => write!(f, "ok, {} warnings", self.warnings.len()),
+        }
+    }
+}
+
+/// Collects the warnings and errors logged on the thread it is the
+/// default subscriber of, instead of printing them.
+#[derive(Clone, Default)]
+pub(crate) struct WarningCollector(Arc<Mutex<Vec<String>>>);
+impl WarningCollector {
+    pub(crate) fn warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+impl Subscriber for WarningCollector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_event() && *metadata.level() <= Level::WARN
+    }
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+    fn event(&self, event: &Event<'_>) {
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        self.0.lock().unwrap().push(message.0);
+    }
+    fn enter(&self, _: &span::Id) {}
+    fn exit(&self, _: &span::Id) {}
+}
+
+/// The text of an event, its message followed by its other fields.
+struct Message(String);
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        let _ = match field.name() {
+            "message" => write!(self.0, "{value:?}"),
+            name => write!(self.0, "{name}={value:?}"),
+        };
+    }
+}
@@ -0,0 +1,291 @@
+//! Vertex cache ("point cache") deformers.
+//!
+//! Simulations such as cloth or fluids are often exported as a cache
+//! of the positions of the vertices at each frame, stored in a separate
+//! file referenced by a `VertexCacheDeformer` of the geometry.
+//!
+//! Only the 3ds Max point cache format (`.pc2`) is loaded, as a
+//! [`FbxVertexCache`] played by [`FbxVertexCachePlayer`]. For other formats,
+//! the file reference is available in [`FbxMesh::vertex_cache_reference`].
+//!
+//! [`FbxMesh::vertex_cache_reference`]: crate::FbxMesh::vertex_cache_reference
+
+use anyhow::{bail, Context};
+use bevy::{
+    prelude::{Assets, Component, Handle, Mesh, Query, ReflectComponent, Res, ResMut, Time, Vec3},
+    reflect::{Reflect, TypeUuid},
+    render::{mesh::VertexAttributeValues, primitives::Aabb},
+};
+use fbxcel_dom::v7400::object::geometry::MeshHandle;
+
+use crate::utils::fbx_extend::ObjectPropertiesExt;
+
+/// The file format of a vertex cache, the `CacheFileType` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FbxVertexCacheFormat {
+    /// 3ds Max point cache (`.pc2`).
+    PointCache2,
+    /// Maya geometry cache (`.xml` and `.mcx`/`.mc`).
+    MayaCache,
+    Alembic,
+    Unknown,
+}
+impl FbxVertexCacheFormat {
+    fn from_i32(value: i32) -> Self {
+        match value {
+            1 => FbxVertexCacheFormat::PointCache2,
+            2 => FbxVertexCacheFormat::MayaCache,
+            3 => FbxVertexCacheFormat::Alembic,
+            _ => FbxVertexCacheFormat::Unknown,
+        }
+    }
+}
+
+/// The cache file of a `VertexCacheDeformer`.
+#[derive(Clone, Debug)]
+pub struct FbxVertexCacheReference {
+    /// The `CacheFile` property, relative to the FBX file.
+    pub relative_path: String,
+    /// The `CacheFileAbsolutePath` property, as saved on the author's machine.
+    pub absolute_path: String,
+    pub format: FbxVertexCacheFormat,
+    /// The channel of the cache file used by the deformer.
+    pub channel: Option<String>,
+}
+impl FbxVertexCacheReference {
+    /// The cache file of the active vertex cache deformer of `geometry`, if any.
+    pub(crate) fn from_geometry(geometry: MeshHandle) -> Option<Self> {
+        let deformer = geometry
+            .source_objects()
+            .filter_map(|obj| obj.object_handle())
+            .filter(|obj| obj.class() == "Deformer" && obj.subclass() == "VertexCacheDeformer")
+            .find(|obj| {
+                let p = obj.properties_by_native_typename("FbxVertexCacheDeformer");
+                p.get::<bool>("Active").unwrap_or(true)
+            })?;
+        let cache = deformer
+            .source_objects()
+            .filter_map(|obj| obj.object_handle())
+            .find(|obj| obj.class() == "Cache")?;
+        let p = cache.properties_by_native_typename("FbxCache");
+        let string = |name| -> Option<String> {
+            let property = p.get_property(name)?;
+            let value = property.value_part().first()?.get_string()?;
+            Some(value.to_owned())
+        };
+        let deformer_properties = deformer.properties_by_native_typename("FbxVertexCacheDeformer");
+        let channel = deformer_properties
+            .get_property("Channel")
+            .and_then(|p| Some(p.value_part().first()?.get_string()?.to_owned()))
+            .filter(|channel| !channel.is_empty());
+        Some(FbxVertexCacheReference {
+            relative_path: string("CacheFile").unwrap_or_default(),
+            absolute_path: string("CacheFileAbsolutePath").unwrap_or_default(),
+            format: FbxVertexCacheFormat::from_i32(p.get("CacheFileType").unwrap_or_default()),
+            channel,
+        })
+    }
+}
+
+/// Positions of the control points of a mesh at regular time intervals.
+#[derive(Clone, Debug, TypeUuid)]
+#[uuid = "f1d0f3a4-9f3c-4f5e-8c0a-3b6f6a3a2c11"]
+pub struct FbxVertexCache {
+    /// Time of the first sample, in seconds.
+    pub start: f32,
+    /// Time between two samples, in seconds.
+    pub sample_interval: f32,
+    /// The positions of all the control points, for each sample.
+    pub samples: Vec<Vec<Vec3>>,
+    /// The control point of each vertex of the bevy mesh.
+    pub vertex_control_points: Vec<u32>,
+}
+impl FbxVertexCache {
+    const PC2_SIGNATURE: &'static [u8] = b"POINTCACHE2\0";
+
+    /// Read a 3ds Max point cache, `frame_rate` is the scene frame rate.
+    pub(crate) fn from_pc2(
+        bytes: &[u8],
+        frame_rate: f64,
+        vertex_control_points: Vec<u32>,
+    ) -> anyhow::Result<Self> {
+        let header = bytes.get(..32).context("Truncated point cache header")?;
+        if &header[..12] != Self::PC2_SIGNATURE {
+            bail!("Not a point cache file");
+        }
+        let read = |offset: usize| <[u8; 4]>::try_from(&header[offset..offset + 4]).unwrap();
+        let point_count = u32::from_le_bytes(read(16)) as usize;
+        let start_frame = f32::from_le_bytes(read(20));
+        let frames_per_sample = f32::from_le_bytes(read(24));
+        let sample_count = u32::from_le_bytes(read(28)) as usize;
+
+        let data = &bytes[32..];
+        let size = sample_count
+            .checked_mul(point_count)
+            .and_then(|count| count.checked_mul(12));
+        let Some(size) = size else {
+            bail!("Point cache too large: {sample_count} samples of {point_count} points");
+        };
+        if data.len() < size {
+            bail!("Truncated point cache: expected {sample_count} samples of {point_count} points");
+        }
+        let floats: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        let samples = floats
+            .chunks_exact(3)
+            .map(Vec3::from_slice)
+            .collect::<Vec<_>>()
+            .chunks_exact(point_count.max(1))
+            .take(sample_count)
+            .map(<[Vec3]>::to_vec)
+            .collect();
+        Ok(FbxVertexCache {
+            start: start_frame / frame_rate as f32,
+            sample_interval: frames_per_sample / frame_rate as f32,
+            samples,
+            vertex_control_points,
+        })
+    }
+    /// Duration of the cache, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.samples.len().saturating_sub(1) as f32 * self.sample_interval
+    }
+    /// The positions of the vertices of the bevy mesh at `time` seconds.
+    pub fn sample(&self, time: f32) -> Option<Vec<[f32; 3]>> {
+        let last = self.samples.len().checked_sub(1)?;
+        let position = if self.sample_interval > 0.0 {
+            ((time - self.start) / self.sample_interval).clamp(0.0, last as f32)
+        } else {
+            0.0
+        };
+        let current = position.floor() as usize;
+        let next = (current + 1).min(last);
+        let lerp = position - current as f32;
+        let (current, next) = (&self.samples[current], &self.samples[next]);
+        let vertex = |control_point: &u32| {
+            let control_point = *control_point as usize;
+            let current = current.get(control_point).copied().unwrap_or_default();
+            let next = next.get(control_point).copied().unwrap_or(current);
+            current.lerp(next, lerp).to_array()
+        };
+        Some(self.vertex_control_points.iter().map(vertex).collect())
+    }
+}
+
+/// Plays a [`FbxVertexCache`] on the [`Mesh`] of the entity.
+///
+/// Added by the `FbxLoader` to the meshes with a loaded vertex cache.
+/// Only positions are updated, normals and tangents keep their rest value.
+///
+/// The mesh is shared by all the instances of the scene, so the player
+/// first replaces the entity's [`Handle<Mesh>`] with a copy of its own.
+/// Its [`Aabb`] follows the played positions.
+#[derive(Component, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct FbxVertexCachePlayer {
+    pub cache: Handle<FbxVertexCache>,
+    pub paused: bool,
+    pub repeat: bool,
+    pub speed: f32,
+    /// Time elapsed playing the cache, in seconds.
+    pub elapsed: f32,
+    /// The copy of the mesh owned by this player, once playing.
+    pub mesh: Option<Handle<Mesh>>,
+}
+impl FbxVertexCachePlayer {
+    pub fn new(cache: Handle<FbxVertexCache>) -> Self {
+        FbxVertexCachePlayer {
+            cache,
+            repeat: true,
+            speed: 1.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// System that advances the [`FbxVertexCachePlayer`]s and updates
+/// the positions of their [`Mesh`].
+pub fn play_vertex_caches(
+    time: Res<Time>,
+    caches: Res<Assets<FbxVertexCache>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut players: Query<(
+        &mut FbxVertexCachePlayer,
+        &mut Handle<Mesh>,
+        Option<&mut Aabb>,
+    )>,
+) {
+    for (mut player, mut mesh, aabb) in &mut players {
+        let cache = match caches.get(&player.cache) {
+            Some(cache) => cache,
+            None => continue,
+        };
+        if !player.paused {
+            player.elapsed += time.delta_seconds() * player.speed;
+        }
+        let mut elapsed = player.elapsed;
+        if player.repeat && cache.duration() > 0.0 {
+            elapsed = elapsed.rem_euclid(cache.duration());
+        }
+        let positions = match cache.sample(cache.start + elapsed) {
+            Some(positions) => positions,
+            None => continue,
+        };
+        if player.mesh.as_ref() != Some(&*mesh) {
+            let Some(copy) = meshes.get(&mesh).cloned() else {
+                continue;
+            };
+            *mesh = meshes.add(copy);
+            player.mesh = Some(mesh.clone());
+        }
+        if let Some(mesh) = meshes.get_mut(&mesh) {
+            let attribute = VertexAttributeValues::Float32x3(positions);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, attribute);
+            if let (Some(mut aabb), Some(bounds)) = (aabb, mesh.compute_aabb()) {
+                *aabb = bounds;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A point cache header of `sample_count` samples of `point_count` points.
+    fn header(point_count: u32, sample_count: u32) -> Vec<u8> {
+        let mut bytes = FbxVertexCache::PC2_SIGNATURE.to_vec();
+        bytes.extend(1_u32.to_le_bytes());
+        bytes.extend(point_count.to_le_bytes());
+        bytes.extend(0.0_f32.to_le_bytes());
+        bytes.extend(1.0_f32.to_le_bytes());
+        bytes.extend(sample_count.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn samples() {
+        let mut bytes = header(1, 2);
+        for value in [0.0_f32, 0.0, 0.0, 2.0, 4.0, 6.0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        let cache = FbxVertexCache::from_pc2(&bytes, 1.0, vec![0, 0]).unwrap();
+        assert_eq!(cache.duration(), 1.0);
+        assert_eq!(cache.sample(0.5), Some(vec![[1.0, 2.0, 3.0]; 2]));
+    }
+
+    #[test]
+    fn truncated() {
+        let error = FbxVertexCache::from_pc2(&header(1, 2), 1.0, vec![]).unwrap_err();
+        assert!(error.to_string().starts_with("Truncated point cache"));
+    }
+
+    #[test]
+    fn overflowing_size() {
+        let error = FbxVertexCache::from_pc2(&header(u32::MAX, u32::MAX), 1.0, vec![]);
+        let error = error.unwrap_err();
+        assert!(error.to_string().starts_with("Point cache too large"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Import problems reported to the app, see [`FbxLoadWarning`].
+
+use std::{fmt, path::PathBuf};
+
+/// What a [`FbxLoadWarning`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FbxWarningCategory {
+    /// The binary framing of the file, such as a malformed header or footer.
+    File,
+    /// A connection between objects, skipped because it is broken.
+    Connection,
+    /// Objects sharing their id, or using the id of the scene root,
+    /// given unique ids along with the connections that could be resolved.
+    ObjectId,
+    /// A node transform that bevy can't represent exactly, approximated.
+    Transform,
+    /// A mesh that couldn't be converted as the settings asked.
+    Mesh,
+    /// A texture map of a material that couldn't be loaded or applied.
+    Material,
+    /// An animation take or clip of the settings that couldn't be made.
+    Animation,
+    /// A vertex cache file that couldn't be loaded.
+    VertexCache,
+    /// A file of [`FbxLoaderSettings::cache_folder`] that couldn't be
+    /// read or written.
+    ///
+    /// [`FbxLoaderSettings::cache_folder`]: crate::FbxLoaderSettings::cache_folder
+    Cache,
+    /// An external FBX document that couldn't be found.
+    Reference,
+    /// A limit of [`FbxLoaderSettings::budget`] that the file exceeds.
+    ///
+    /// [`FbxLoaderSettings::budget`]: crate::FbxLoaderSettings::budget
+    Budget,
+}
+
+/// Event sent when the `FbxLoader` works around a problem of a file,
+/// for editors to show actionable import problems to artists.
+///
+/// The warnings are logged too. Files loaded with
+/// [`FbxLoader::load_from_bytes`] only log them.
+///
+/// [`FbxLoader::load_from_bytes`]: crate::FbxLoader::load_from_bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FbxLoadWarning {
+    /// Path of the FBX file, relative to the asset folder.
+    pub path: PathBuf,
+    pub category: FbxWarningCategory,
+    /// Name or label of the node, mesh, material, clip or document
+    /// the warning is about, if any.
+    pub object: Option<String>,
+    /// What went wrong, as logged.
+    pub message: String,
+    /// How to fix it, if there is an obvious way.
+    pub suggestion: Option<String>,
+}
+impl fmt::Display for FbxLoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        match &self.suggestion {
+            Some(suggestion) => write!(f, " ({suggestion})"),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! A minimal writer of binary FBX files, shared by the end-to-end tests.
+
+use std::io::Cursor;
+
+use fbxcel::{
+    low::FbxVersion,
+    writer::v7400::binary::{FbxFooter, Writer},
+};
+
+/// An attribute of a FBX node.
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F64(f64),
+    Str(String),
+    F64s(Vec<f64>),
+    I32s(Vec<i32>),
+}
+
+pub struct Node {
+    name: &'static str,
+    attributes: Vec<Value>,
+    children: Vec<Node>,
+}
+pub fn node(name: &'static str, attributes: Vec<Value>, children: Vec<Node>) -> Node {
+    Node {
+        name,
+        attributes,
+        children,
+    }
+}
+pub fn string(value: &str) -> Value {
+    Value::Str(value.to_owned())
+}
+/// A `P` node of a `Properties70`.
+pub fn property(name: &str, type_name: &str, values: Vec<Value>) -> Node {
+    let mut attributes = vec![string(name), string(type_name), string(""), string("A")];
+    attributes.extend(values);
+    node("P", attributes, vec![])
+}
+pub fn vector(name: &str, [x, y, z]: [f64; 3]) -> Node {
+    let values = vec![Value::F64(x), Value::F64(y), Value::F64(z)];
+    property(name, name, values)
+}
+/// The `UnitScaleFactor` global property: the file units are
+/// `unit_scale_factor` centimeters.
+pub fn unit_scale_factor(unit_scale_factor: f64) -> Node {
+    property(
+        "UnitScaleFactor",
+        "double",
+        vec![Value::F64(unit_scale_factor)],
+    )
+}
+/// A `Properties70` node holding `properties`.
+pub fn properties(properties: Vec<Node>) -> Vec<Node> {
+    vec![node("Properties70", vec![], properties)]
+}
+/// An object of class `class`, which is also the name of its FBX node.
+pub fn object(
+    class: &'static str,
+    id: i64,
+    name: &str,
+    subclass: &str,
+    children: Vec<Node>,
+) -> Node {
+    let name = string(&format!("{name}\u{0}\u{1}{class}"));
+    node(
+        class,
+        vec![Value::I64(id), name, string(subclass)],
+        children,
+    )
+}
+pub fn connection(child: i64, parent: i64) -> Node {
+    let attributes = vec![string("OO"), Value::I64(child), Value::I64(parent)];
+    node("C", attributes, vec![])
+}
+
+/// A `Geometry` of a single triangle, `vertices` being its three positions.
+pub fn triangle_geometry(id: i64, name: &str, vertices: Vec<f64>) -> Node {
+    // The loader needs normals and UVs, their values don't matter here.
+    let element = |element: &'static str, data: &'static str, values: Vec<f64>| {
+        let children = vec![
+            node("MappingInformationType", vec![string("AllSame")], vec![]),
+            node("ReferenceInformationType", vec![string("Direct")], vec![]),
+            node(data, vec![Value::F64s(values)], vec![]),
+        ];
+        let layer_element = vec![
+            node("Type", vec![string(element)], vec![]),
+            node("TypedIndex", vec![Value::I32(0)], vec![]),
+        ];
+        let layer_element = node("LayerElement", vec![], layer_element);
+        (node(element, vec![Value::I32(0)], children), layer_element)
+    };
+    let (normals, normals_layer) = element("LayerElementNormal", "Normals", vec![0.0, 0.0, 1.0]);
+    let (uvs, uvs_layer) = element("LayerElementUV", "UV", vec![0.0, 0.0]);
+    let layer = node("Layer", vec![Value::I32(0)], vec![normals_layer, uvs_layer]);
+    object(
+        "Geometry",
+        id,
+        name,
+        "Mesh",
+        vec![
+            node("Vertices", vec![Value::F64s(vertices)], vec![]),
+            node(
+                "PolygonVertexIndex",
+                vec![Value::I32s(vec![0, 1, !2])],
+                vec![],
+            ),
+            normals,
+            uvs,
+            layer,
+        ],
+    )
+}
+
+fn write(writer: &mut Writer<Cursor<Vec<u8>>>, node: &Node) {
+    let mut attributes = writer.new_node(node.name).unwrap();
+    for value in &node.attributes {
+        match value {
+            Value::I32(v) => attributes.append_i32(*v),
+            Value::I64(v) => attributes.append_i64(*v),
+            Value::F64(v) => attributes.append_f64(*v),
+            Value::Str(v) => attributes.append_string_direct(v),
+            Value::F64s(v) => attributes.append_arr_f64_from_iter(None, v.iter().copied()),
+            Value::I32s(v) => attributes.append_arr_i32_from_iter(None, v.iter().copied()),
+        }
+        .unwrap();
+    }
+    for child in &node.children {
+        write(writer, child);
+    }
+    writer.close_node().unwrap();
+}
+
+/// A binary FBX file of `objects` and their `connections`, with the
+/// `globals` properties of its `GlobalSettings`, such as its units and axes.
+pub fn fbx_file(
+    version: FbxVersion,
+    globals: Vec<Node>,
+    objects: Vec<Node>,
+    connections: Vec<Node>,
+) -> Vec<u8> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()), version).unwrap();
+    let (major, minor) = version.major_minor();
+    let version = node(
+        "FBXVersion",
+        vec![Value::I32((major * 1000 + minor * 100) as i32)],
+        vec![],
+    );
+    write(
+        &mut writer,
+        &node("FBXHeaderExtension", vec![], vec![version]),
+    );
+    let global_settings = vec![
+        node("Version", vec![Value::I32(1000)], vec![]),
+        node("Properties70", vec![], globals),
+    ];
+    write(
+        &mut writer,
+        &node("GlobalSettings", vec![], global_settings),
+    );
+    let enumeration = |name: &str| property(name, "enum", vec![Value::I32(0)]);
+    let node_template = vec![
+        enumeration("RotationOrder"),
+        enumeration("InheritType"),
+        vector("RotationPivot", [0.0; 3]),
+        vector("RotationOffset", [0.0; 3]),
+        vector("ScalingPivot", [0.0; 3]),
+        vector("ScalingOffset", [0.0; 3]),
+        vector("PreRotation", [0.0; 3]),
+        vector("PostRotation", [0.0; 3]),
+        vector("Lcl Translation", [0.0; 3]),
+        vector("Lcl Rotation", [0.0; 3]),
+        vector("Lcl Scaling", [1.0; 3]),
+    ];
+    let template = node(
+        "PropertyTemplate",
+        vec![string("FbxNode")],
+        properties(node_template),
+    );
+    let model_type = node("ObjectType", vec![string("Model")], vec![template]);
+    write(&mut writer, &node("Definitions", vec![], vec![model_type]));
+    let root_node = node("RootNode", vec![Value::I64(0)], vec![]);
+    let document = object("Document", 1, "Scene", "Scene", vec![root_node]);
+    let count = node("Count", vec![Value::I32(1)], vec![]);
+    write(
+        &mut writer,
+        &node("Documents", vec![], vec![count, document]),
+    );
+    write(&mut writer, &node("Objects", vec![], objects));
+    write(&mut writer, &node("Connections", vec![], connections));
+    let bytes = writer.finalize_and_flush(&FbxFooter::default()).unwrap();
+    bytes.into_inner()
+}
@@ -0,0 +1,253 @@
+//! End-to-end tests of the conversion of FBX transforms into bevy space.
+//!
+//! The same asset, a triangle under a `Root` node with a `Tip` locator
+//! at its apex, is written as different authoring tools export it: with
+//! their own units, the axes of their `GlobalSettings`, and their way of
+//! storing pivots and axis conversions. Once loaded, all of them must end
+//! up at the same place and orientation in bevy, Y-up and in meters.
+//!
+//! The loader doesn't convert the axes of the global settings yet, so the
+//! exports keeping their Z-up axes are ignored. Geometric transforms
+//! (`GeometricTranslation`...), which 3ds Max uses for object offsets, are
+//! not supported either, so they are not covered here.
+
+mod common;
+
+use bevy::prelude::{Mat4, Vec3};
+use bevy_mod_fbx::{FbxLoadedScene, FbxLoader, FbxLoaderSettings, FbxScene};
+use common::{
+    connection, fbx_file, object, properties, property, triangle_geometry, unit_scale_factor,
+    vector, Value,
+};
+use fbxcel::low::FbxVersion;
+
+/// The triangle in bevy space, in meters.
+const WORLD_TRIANGLE: [Vec3; 3] = [
+    Vec3::new(1.1, 0.0, 0.0),
+    Vec3::new(1.1, 2.0, 0.0),
+    Vec3::new(4.1, 0.0, 0.0),
+];
+/// The apex of the triangle, where the `Tip` locator is.
+const WORLD_TIP: Vec3 = Vec3::new(1.1, 2.0, 0.0);
+
+const EPSILON: f32 = 1e-4;
+
+/// The axes of the `GlobalSettings` of a file, as `(axis, sign)` pairs,
+/// `axis` being 0, 1 or 2 for `X`, `Y` or `Z`.
+struct Axes {
+    up: (i32, i32),
+    front: (i32, i32),
+    coord: (i32, i32),
+}
+impl Axes {
+    /// Y-up and right-handed, as Maya and the FBX SDK default to.
+    const Y_UP: Axes = Axes {
+        up: (1, 1),
+        front: (2, 1),
+        coord: (0, 1),
+    };
+    /// Z-up and right-handed, as 3ds Max exports when not converting to Y-up.
+    const Z_UP: Axes = Axes {
+        up: (2, 1),
+        front: (1, -1),
+        coord: (0, 1),
+    };
+}
+
+/// The asset as exported by an authoring tool.
+struct Export {
+    /// Centimeters per file unit.
+    unit_scale_factor: f64,
+    /// The axes of the file.
+    axes: Axes,
+    /// The transform properties of the `Root` node.
+    root: Vec<(&'static str, [f64; 3])>,
+    /// The `Lcl Translation` of `Tip`, relative to `Root`.
+    tip: [f64; 3],
+    /// The triangle, in the space of `Root`.
+    triangle: [[f64; 3]; 3],
+    /// The up and right axes of the authoring tool,
+    /// which must become bevy's `Y` and `X` axes.
+    up: Vec3,
+    right: Vec3,
+}
+impl Export {
+    /// The binary FBX file of the export.
+    fn to_fbx(&self, version: FbxVersion) -> Vec<u8> {
+        let (root, tip, tip_attribute, mesh, geometry) = (10, 11, 12, 13, 14);
+        let vertices = self.triangle.iter().flatten().copied().collect();
+        let root_properties = self.root.iter().map(|(name, v)| vector(name, *v)).collect();
+        let objects = vec![
+            object("Model", root, "Root", "Null", properties(root_properties)),
+            object(
+                "Model",
+                tip,
+                "Tip",
+                "Null",
+                properties(vec![vector("Lcl Translation", self.tip)]),
+            ),
+            object("NodeAttribute", tip_attribute, "Tip", "Null", vec![]),
+            object("Model", mesh, "Triangle", "Mesh", properties(vec![])),
+            triangle_geometry(geometry, "Triangle", vertices),
+        ];
+        let connections = vec![
+            connection(root, 0),
+            connection(tip, root),
+            connection(tip_attribute, tip),
+            connection(mesh, root),
+            connection(geometry, mesh),
+        ];
+        let int = |name: &str, value| property(name, "int", vec![Value::I32(value)]);
+        let Axes { up, front, coord } = self.axes;
+        let globals = vec![
+            int("UpAxis", up.0),
+            int("UpAxisSign", up.1),
+            int("FrontAxis", front.0),
+            int("FrontAxisSign", front.1),
+            int("CoordAxis", coord.0),
+            int("CoordAxisSign", coord.1),
+            unit_scale_factor(self.unit_scale_factor),
+        ];
+        fbx_file(version, globals, objects, connections)
+    }
+
+    fn load(&self, version: FbxVersion) -> FbxLoadedScene {
+        FbxLoader::load_from_bytes(&self.to_fbx(version), FbxLoaderSettings::default()).unwrap()
+    }
+
+    /// Check that the asset ends up at the same place as the reference,
+    /// both in FBX 7.4 files and in FBX 7.5 ones, with 64-bit node offsets.
+    fn check(&self) {
+        self.check_version(FbxVersion::V7_4);
+        self.check_version(FbxVersion::V7_5);
+    }
+
+    fn check_version(&self, version: FbxVersion) {
+        let loaded = self.load(version);
+        let scene = &loaded.scene;
+
+        let bounds = scene.bounds.expect("the triangle has bounds");
+        let (min, max) = WORLD_TRIANGLE.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), v| (min.min(*v), max.max(*v)),
+        );
+        assert_close(bounds.min().into(), min, "bounds min");
+        assert_close(bounds.max().into(), max, "bounds max");
+
+        let root = world_matrix(scene, self.unit_scale_factor, &["Root"]);
+        let tip = world_matrix(scene, self.unit_scale_factor, &["Root", "Tip"]);
+        assert_close(tip.transform_point3(Vec3::ZERO), WORLD_TIP, "tip");
+        assert_close(root.transform_vector3(self.up).normalize(), Vec3::Y, "up");
+        assert_close(
+            root.transform_vector3(self.right).normalize(),
+            Vec3::X,
+            "right",
+        );
+    }
+}
+
+/// The global transform of the node at `path` in the spawned scene.
+fn world_matrix(scene: &FbxScene, unit_scale_factor: f64, path: &[&str]) -> Mat4 {
+    // Bevy units are meters, FBX units are `unit_scale_factor` centimeters.
+    let mut matrix = Mat4::from_scale(Vec3::splat(0.01 * unit_scale_factor as f32));
+    let mut siblings = scene.roots.clone();
+    for name in path {
+        let (_, node) = siblings
+            .iter()
+            .filter_map(|id| Some((id, scene.hierarchy.get(id)?)))
+            .find(|(_, node)| node.name.as_deref() == Some(name))
+            .unwrap_or_else(|| panic!("no node {name}"));
+        matrix *= node.transform.compute_matrix();
+        siblings = node.children.clone();
+    }
+    matrix
+}
+
+fn assert_close(actual: Vec3, expected: Vec3, what: &str) {
+    assert!(
+        actual.abs_diff_eq(expected, EPSILON),
+        "{what}: expected {expected}, got {actual}"
+    );
+}
+
+/// Maya, Y-up in centimeters, rotated and scaled around a pivot.
+#[test]
+fn maya_y_up_centimeters() {
+    Export {
+        unit_scale_factor: 1.0,
+        axes: Axes::Y_UP,
+        root: vec![
+            ("Lcl Translation", [100.0, 0.0, 0.0]),
+            ("Lcl Rotation", [0.0, 90.0, 0.0]),
+            ("Lcl Scaling", [2.0, 2.0, 2.0]),
+            ("RotationPivot", [10.0, 0.0, 0.0]),
+            ("ScalingPivot", [10.0, 0.0, 0.0]),
+        ],
+        tip: [10.0, 100.0, 0.0],
+        triangle: [[10.0, 0.0, 0.0], [10.0, 100.0, 0.0], [10.0, 0.0, 150.0]],
+        up: Vec3::Y,
+        right: Vec3::Z,
+    }
+    .check();
+}
+
+/// 3ds Max, Z-up in meters, exported Y-up: the axes of the file are Y-up,
+/// and the root is converted by a pre-rotation.
+#[test]
+fn max_y_up_meters() {
+    Export {
+        unit_scale_factor: 100.0,
+        axes: Axes::Y_UP,
+        root: vec![
+            ("Lcl Translation", [1.1, 0.0, 0.0]),
+            ("PreRotation", [-90.0, 0.0, 0.0]),
+            ("Lcl Rotation", [0.0, 0.0, 90.0]),
+        ],
+        tip: [0.0, 0.0, 2.0],
+        triangle: [[0.0, 0.0, 0.0], [0.0, 0.0, 2.0], [0.0, -3.0, 0.0]],
+        up: Vec3::Z,
+        right: Vec3::NEG_Y,
+    }
+    .check();
+}
+
+/// 3ds Max, Z-up in meters, exported Z-up: the axes of the file are Z-up,
+/// and nothing is converted.
+#[test]
+#[ignore = "the loader doesn't convert the UpAxis of the global settings"]
+fn max_z_up_meters() {
+    Export {
+        unit_scale_factor: 100.0,
+        axes: Axes::Z_UP,
+        root: vec![
+            ("Lcl Translation", [1.1, 0.0, 0.0]),
+            ("Lcl Rotation", [0.0, 0.0, 90.0]),
+        ],
+        tip: [0.0, 0.0, 2.0],
+        triangle: [[0.0, 0.0, 0.0], [0.0, 0.0, 2.0], [0.0, -3.0, 0.0]],
+        up: Vec3::Z,
+        right: Vec3::NEG_Y,
+    }
+    .check();
+}
+
+/// Blender, Z-up in meters, exported with its default axis conversion:
+/// the axes of the file are Y-up, and the root is converted to Y-up
+/// centimeters by its rotation and scale.
+#[test]
+fn blender_z_up_scaled() {
+    Export {
+        unit_scale_factor: 1.0,
+        axes: Axes::Y_UP,
+        root: vec![
+            ("Lcl Translation", [110.0, 0.0, 0.0]),
+            ("Lcl Rotation", [-90.0, 90.0, 0.0]),
+            ("Lcl Scaling", [100.0, 100.0, 100.0]),
+        ],
+        tip: [0.0, 0.0, 2.0],
+        triangle: [[0.0, 0.0, 0.0], [0.0, 0.0, 2.0], [0.0, -3.0, 0.0]],
+        up: Vec3::Z,
+        right: Vec3::NEG_Y,
+    }
+    .check();
+}
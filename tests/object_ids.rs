@@ -0,0 +1,191 @@
+//! End-to-end tests of files whose objects share their ids, which
+//! exporters write by mistake and the FBX DOM can't load as is.
+//!
+//! Each file is a `Root` node holding a `Triangle` mesh, plus objects
+//! sharing ids. The objects get unique ids, the connections that can be
+//! told apart by the classes of their ends are kept and the others dropped,
+//! each with a warning.
+
+mod common;
+
+use bevy::math::{Quat, Vec3};
+use bevy_mod_fbx::{FbxKeyframes, FbxLoadedScene, FbxLoader, FbxLoaderSettings, FbxScene};
+use common::{
+    connection, fbx_file, node, object, string, triangle_geometry, unit_scale_factor, Node, Value,
+};
+use fbxcel::low::FbxVersion;
+
+const ROOT: i64 = 10;
+const TRIANGLE: i64 = 13;
+const SHARED: i64 = 20;
+
+/// The `Root` node and its `Triangle` mesh, with the geometry `geometry`.
+fn base(geometry: i64) -> (Vec<Node>, Vec<Node>) {
+    let vertices = vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0];
+    let objects = vec![
+        object("Model", ROOT, "Root", "Null", vec![]),
+        object("Model", TRIANGLE, "Triangle", "Mesh", vec![]),
+        triangle_geometry(geometry, "Triangle", vertices),
+    ];
+    let connections = vec![
+        connection(ROOT, 0),
+        connection(TRIANGLE, ROOT),
+        connection(geometry, TRIANGLE),
+    ];
+    (objects, connections)
+}
+
+/// Load the file of `objects`, returning the scene and the warnings.
+fn load(objects: Vec<Node>, connections: Vec<Node>) -> (FbxLoadedScene, Vec<String>) {
+    let bytes = fbx_file(
+        FbxVersion::V7_4,
+        vec![unit_scale_factor(1.0)],
+        objects,
+        connections,
+    );
+    let report = FbxLoader::validate_bytes(&bytes, FbxLoaderSettings::default());
+    assert_eq!(report.error, None);
+    let loaded = FbxLoader::load_from_bytes(&bytes, FbxLoaderSettings::default()).unwrap();
+    (loaded, report.warnings)
+}
+
+fn assert_warned(warnings: &[String], expected: &str) {
+    assert!(
+        warnings.iter().any(|warning| warning.contains(expected)),
+        "no warning containing {expected:?} in {warnings:#?}"
+    );
+}
+
+/// The names of the children of the node `name`, or of the roots.
+fn children(scene: &FbxScene, name: Option<&str>) -> Vec<String> {
+    let ids = match name {
+        Some(name) => {
+            let node = scene.hierarchy.values();
+            let mut node = node.filter(|node| node.name.as_deref() == Some(name));
+            node.next()
+                .unwrap_or_else(|| panic!("no node {name}"))
+                .children
+                .clone()
+        }
+        None => scene.roots.clone(),
+    };
+    let mut names: Vec<String> = ids
+        .iter()
+        .filter_map(|id| scene.hierarchy.get(id)?.name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn models_sharing_an_id() {
+    let (mut objects, mut connections) = base(14);
+    objects.push(object("Model", SHARED, "A", "LimbNode", vec![]));
+    objects.push(object("Model", SHARED, "B", "LimbNode", vec![]));
+    // One connection to the root per model, they can't be told apart.
+    connections.push(connection(SHARED, 0));
+    connections.push(connection(SHARED, 0));
+
+    let (loaded, warnings) = load(objects, connections);
+    assert_warned(&warnings, "have a duplicate id 20");
+    assert_eq!(children(&loaded.scene, None), ["A", "B", "Root"]);
+}
+
+#[test]
+fn geometry_with_the_root_id() {
+    let (objects, connections) = base(0);
+
+    let (loaded, warnings) = load(objects, connections);
+    assert_warned(&warnings, "have the id of the scene root 0");
+    assert_eq!(children(&loaded.scene, None), ["Root"]);
+    assert_eq!(loaded.scene.meshes.len(), 1);
+    assert!(loaded.scene.bounds.is_some());
+}
+
+#[test]
+fn repeated_connections() {
+    let (objects, mut connections) = base(14);
+    connections.push(connection(TRIANGLE, ROOT));
+    connections.push(connection(ROOT, 0));
+
+    let (loaded, warnings) = load(objects, connections);
+    assert_warned(&warnings, "Connection of 13 to 10 is repeated");
+    assert_warned(&warnings, "Connection of 10 to 0 is repeated");
+    assert_eq!(children(&loaded.scene, None), ["Root"]);
+    assert_eq!(children(&loaded.scene, Some("Root")), ["Triangle"]);
+}
+
+#[test]
+fn ambiguous_connection_is_dropped() {
+    let (mut objects, mut connections) = base(14);
+    objects.push(object("Model", SHARED, "A", "LimbNode", vec![]));
+    objects.push(object("Model", SHARED, "B", "LimbNode", vec![]));
+    objects.push(object("Model", 21, "Child", "LimbNode", vec![]));
+    connections.push(connection(SHARED, ROOT));
+    connections.push(connection(SHARED, ROOT));
+    // `Child` could be under `A` or `B`.
+    connections.push(connection(21, SHARED));
+
+    let (loaded, warnings) = load(objects, connections);
+    assert_warned(&warnings, "Connection of 21 to 20 is ambiguous");
+    assert_eq!(
+        children(&loaded.scene, Some("Root")),
+        ["A", "B", "Triangle"]
+    );
+    assert!(children(&loaded.scene, Some("A")).is_empty());
+    assert!(children(&loaded.scene, Some("B")).is_empty());
+}
+
+#[test]
+fn pose_node_of_a_shared_id() {
+    let (mut objects, mut connections) = base(14);
+    objects.push(object("Model", SHARED, "A", "LimbNode", vec![]));
+    objects.push(object("Model", SHARED, "B", "LimbNode", vec![]));
+    // A model and an attribute share 21, only the model can be posed.
+    objects.push(object("Model", 21, "C", "LimbNode", vec![]));
+    objects.push(object("NodeAttribute", 21, "C", "Null", vec![]));
+    connections.push(connection(SHARED, ROOT));
+    connections.push(connection(SHARED, ROOT));
+    connections.push(connection(21, 0));
+    let pose_node = |id| {
+        #[rustfmt::skip]
+        let identity = vec![
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+        let children = vec![
+            node("Node", vec![Value::I64(id)], vec![]),
+            node("Matrix", vec![Value::F64s(identity)], vec![]),
+        ];
+        node("PoseNode", vec![], children)
+    };
+    let pose = vec![
+        node("Type", vec![string("RestPose")], vec![]),
+        node("Version", vec![Value::I32(100)], vec![]),
+        node("NbPoseNodes", vec![Value::I32(3)], vec![]),
+        pose_node(ROOT),
+        pose_node(SHARED),
+        pose_node(21),
+    ];
+    objects.push(object("Pose", 30, "Rest", "RestPose", pose));
+
+    let (loaded, warnings) = load(objects, connections);
+    assert_warned(&warnings, "Pose node of 20 is ambiguous");
+    let pose = loaded.get(&loaded.scene.poses["Rest"]).unwrap();
+    let mut posed: Vec<&str> = pose
+        .curves
+        .keys()
+        .map(|path| path.parts.last().unwrap().as_str())
+        .collect();
+    posed.sort();
+    assert_eq!(posed, ["C", "Root"]);
+    for curve in pose.curves.values().flatten() {
+        match &curve.keyframes {
+            FbxKeyframes::Translation(values) => assert_eq!(values, &[Vec3::ZERO]),
+            FbxKeyframes::Rotation(values) => assert_eq!(values, &[Quat::IDENTITY]),
+            FbxKeyframes::Scale(values) => assert_eq!(values, &[Vec3::ONE]),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! End-to-end tests of skinned meshes.
+//!
+//! A triangle geometry is skinned to the `Hips` joint of rig `A`, and
+//! instanced by the `Body` node of `A`, of `B`, a copy of the rig, and of
+//! `C`, which has no joints. Each instance must be moved by its own joints.
+
+mod common;
+
+use bevy::{
+    ecs::world::EntityRef,
+    prelude::{Entity, Mat4, Mesh, Name, Parent, World},
+    render::mesh::{
+        skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+        VertexAttributeValues,
+    },
+};
+use bevy_mod_fbx::{FbxLoadedScene, FbxLoader, FbxLoaderSettings};
+use common::{connection, fbx_file, node, object, triangle_geometry, unit_scale_factor, Value};
+use fbxcel::low::FbxVersion;
+
+const GEOMETRY: i64 = 90;
+const SKIN: i64 = 91;
+const CLUSTER: i64 = 92;
+
+fn load(settings: FbxLoaderSettings) -> (FbxLoadedScene, Vec<String>) {
+    let mut objects = Vec::new();
+    let mut connections = Vec::new();
+    for (i, name) in ["A", "B", "C"].into_iter().enumerate() {
+        let (group, body) = (10 * (i as i64 + 1), 10 * (i as i64 + 1) + 1);
+        objects.push(object("Model", group, name, "Null", vec![]));
+        objects.push(object("Model", body, "Body", "Mesh", vec![]));
+        connections.push(connection(group, 0));
+        connections.push(connection(body, group));
+        connections.push(connection(GEOMETRY, body));
+        if name != "C" {
+            let hips = body + 1;
+            objects.push(object("Model", hips, "Hips", "LimbNode", vec![]));
+            connections.push(connection(hips, group));
+        }
+    }
+    let vertices = vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0];
+    objects.push(triangle_geometry(GEOMETRY, "Body", vertices));
+    #[rustfmt::skip]
+    let identity = vec![
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ];
+    let cluster = vec![
+        node("Indexes", vec![Value::I32s(vec![0, 1])], vec![]),
+        node("Weights", vec![Value::F64s(vec![1.0, 0.5])], vec![]),
+        node("Transform", vec![Value::F64s(identity.clone())], vec![]),
+        node("TransformLink", vec![Value::F64s(identity)], vec![]),
+    ];
+    objects.push(object("Deformer", SKIN, "Skin", "Skin", vec![]));
+    objects.push(object("SubDeformer", CLUSTER, "Hips", "Cluster", cluster));
+    connections.push(connection(SKIN, GEOMETRY));
+    connections.push(connection(CLUSTER, SKIN));
+    connections.push(connection(12, CLUSTER));
+
+    let bytes = fbx_file(
+        FbxVersion::V7_4,
+        vec![unit_scale_factor(1.0)],
+        objects,
+        connections,
+    );
+    let report = FbxLoader::validate_bytes(&bytes, settings.clone());
+    assert_eq!(report.error, None);
+    let loaded = FbxLoader::load_from_bytes(&bytes, settings).unwrap();
+    (loaded, report.warnings)
+}
+
+fn parent(entity: EntityRef) -> Entity {
+    entity.get::<Parent>().unwrap().get()
+}
+
+fn name(world: &World, entity: Entity) -> &str {
+    world.get::<Name>(entity).unwrap().as_str()
+}
+
+/// The skinned mesh entity of the `Body` of `group`.
+fn skinned_mesh<'w>(world: &'w World, group: &str) -> (Entity, &'w SkinnedMesh) {
+    let mut found = world.iter_entities().filter_map(|entity| {
+        let skinned = entity.get::<SkinnedMesh>()?;
+        let node = parent(entity);
+        let group_entity = world.get::<Parent>(node)?.get();
+        (name(world, group_entity) == group).then_some((node, skinned))
+    });
+    let skinned = found
+        .next()
+        .unwrap_or_else(|| panic!("no skinned mesh in {group}"));
+    assert!(found.next().is_none());
+    skinned
+}
+
+#[test]
+fn instances_are_moved_by_their_own_rig() {
+    let (loaded, warnings) = load(FbxLoaderSettings::default());
+    let scene = loaded.get(&loaded.scene.scenes[0]).unwrap();
+    let world = &scene.world;
+
+    let (node_a, skinned_a) = skinned_mesh(world, "A");
+    let (node_b, skinned_b) = skinned_mesh(world, "B");
+    for (node, skinned, group) in [(node_a, skinned_a, "A"), (node_b, skinned_b, "B")] {
+        let [hips, rest] = skinned.joints[..] else {
+            panic!("{group} has {} joints", skinned.joints.len());
+        };
+        assert_eq!(name(world, hips), "Hips");
+        assert_eq!(name(world, parent(world.entity(hips))), group);
+        assert_eq!(rest, node);
+    }
+    assert_ne!(skinned_a.joints[0], skinned_b.joints[0]);
+    assert_eq!(skinned_a.inverse_bindposes, skinned_b.inverse_bindposes);
+
+    // Without a copy of the rig, C is bound to its node only, at rest.
+    assert!(warnings
+        .iter()
+        .any(|w| w.contains("has no copy of its joints")));
+    let (node_c, skinned_c) = skinned_mesh(world, "C");
+    assert_eq!(skinned_c.joints, [node_c, node_c]);
+    let rest = loaded.get(&skinned_c.inverse_bindposes).unwrap();
+    assert!(rest.iter().all(|bindpose| *bindpose == Mat4::IDENTITY));
+}
+
+#[test]
+fn vertices_are_weighted() {
+    let (loaded, _) = load(FbxLoaderSettings::default());
+    let fbx_mesh = loaded.scene.meshes.values().next().unwrap();
+    let fbx_mesh = loaded.get(fbx_mesh).unwrap();
+    let skin = fbx_mesh.skin.as_ref().unwrap();
+    let bindposes: &SkinnedMeshInverseBindposes = loaded.get(&skin.inverse_bindposes).unwrap();
+    assert_eq!(bindposes.len(), 2);
+
+    let mesh: &Mesh = loaded.get(&fbx_mesh.bevy_mesh_handles[0]).unwrap();
+    let indices = mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX).unwrap();
+    let weights = mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT).unwrap();
+    let (VertexAttributeValues::Uint16x4(indices), VertexAttributeValues::Float32x4(weights)) =
+        (indices, weights)
+    else {
+        panic!("unexpected joint attribute formats");
+    };
+    // The weight of the second vertex is normalized, the third has none
+    // and is moved by the mesh node.
+    let mut joints: Vec<_> = indices
+        .iter()
+        .zip(weights)
+        .map(|(i, w)| (i[0], w[0]))
+        .collect();
+    joints.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    joints.dedup();
+    assert_eq!(joints, [(0, 1.0), (1, 1.0)]);
+}
+
+#[test]
+fn only_skinned_meshes_get_animated_bounds() {
+    let (loaded, _) = load(FbxLoaderSettings::default());
+    assert_eq!(loaded.scene.skinned_aabbs.len(), 2);
+
+    // Subdivided meshes are loaded unskinned, at rest.
+    let settings = FbxLoaderSettings {
+        subdivision_level: 1,
+        ..Default::default()
+    };
+    let (loaded, _) = load(settings);
+    let fbx_mesh = loaded.scene.meshes.values().next().unwrap();
+    assert!(loaded.get(fbx_mesh).unwrap().skin.is_none());
+    assert!(loaded.scene.skinned_aabbs.is_empty());
+    assert!(loaded.scene.skin_joints.is_empty());
+    let scene = loaded.get(&loaded.scene.scenes[0]).unwrap();
+    let mut entities = scene.world.iter_entities();
+    assert!(!entities.any(|entity| entity.contains::<SkinnedMesh>()));
+}